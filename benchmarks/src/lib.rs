@@ -18,11 +18,17 @@ pub mod benches;
 
 use std::{
     fs::OpenOptions,
+    ops::RangeInclusive,
     path::Path,
     time::{Duration, Instant},
 };
 
-use risc0_zkvm::{sha::Digest, ExecutorEnv, ExecutorImpl, Receipt, Segment, Session};
+use anyhow::Result;
+use risc0_zkvm::{
+    get_prover_server, sha::Digest, CalibrationSample, ExecutorEnv, ExecutorImpl, ProverOpts,
+    ProvingModel, Receipt, Segment, Session, VerifierContext,
+};
+use risc0_zkvm_methods::{multi_test::MultiTestSpec, MULTI_TEST_ELF};
 use serde::Serialize;
 use tracing::info;
 
@@ -37,6 +43,7 @@ pub struct Metrics {
     pub insn_cycles: u32,
     pub output_bytes: u32,
     pub proof_bytes: u32,
+    pub segment_po2s: Vec<u32>,
 }
 
 impl Metrics {
@@ -52,6 +59,7 @@ impl Metrics {
             insn_cycles: 0,
             output_bytes: 0,
             proof_bytes: 0,
+            segment_po2s: Vec::new(),
         }
     }
 
@@ -69,6 +77,11 @@ impl Metrics {
     }
 }
 
+pub struct MemoryMetrics {
+    pub po2: u32,
+    pub peak_memory: usize,
+}
+
 pub struct MetricsAverage {
     pub job_name: String,
     pub job_size: u32,
@@ -118,7 +131,7 @@ impl Job {
         self.input.len() as u32
     }
 
-    fn exec_compute(&self) -> (Session, u32, u32, Duration) {
+    fn exec_compute(&self) -> (Session, Vec<u32>, u32, u32, Duration) {
         let env = ExecutorEnv::builder()
             .write_slice(&self.input)
             .build()
@@ -128,8 +141,15 @@ impl Job {
         let session = exec.run().unwrap();
         let elapsed = start.elapsed();
         let segments = session.resolve().unwrap();
+        let segment_po2s = segments.iter().map(|segment| segment.po2).collect();
         let (exec_cycles, prove_cycles) = get_cycles(segments);
-        (session, prove_cycles as u32, exec_cycles as u32, elapsed)
+        (
+            session,
+            segment_po2s,
+            prove_cycles as u32,
+            exec_cycles as u32,
+            elapsed,
+        )
     }
 
     fn verify_proof(&self, receipt: &Receipt) -> bool {
@@ -145,8 +165,9 @@ impl Job {
     fn run(&self) -> Metrics {
         let mut metrics = Metrics::new(self.name.clone(), self.job_size());
 
-        let (session, cycles, insn_cycles, duration) = self.exec_compute();
+        let (session, segment_po2s, cycles, insn_cycles, duration) = self.exec_compute();
 
+        metrics.segment_po2s = segment_po2s;
         metrics.cycles = cycles;
         metrics.insn_cycles = insn_cycles;
         metrics.exec_duration = duration;
@@ -206,6 +227,26 @@ pub fn init_logging() {
     ();
 }
 
+/// Fit a [ProvingModel] for the default prover to the measured [Metrics].
+pub fn calibrate_model(metrics: &[Metrics]) -> Result<ProvingModel> {
+    let hal = if cfg!(feature = "cuda") {
+        "cuda"
+    } else if cfg!(feature = "metal") {
+        "metal"
+    } else {
+        "cpu"
+    };
+    let samples: Vec<_> = metrics
+        .iter()
+        .map(|metrics| CalibrationSample {
+            segment_po2s: metrics.segment_po2s.clone(),
+            proving_time: metrics.proof_duration,
+            seal_bytes: metrics.proof_bytes as u64,
+        })
+        .collect();
+    ProvingModel::calibrate(&ProverOpts::default().hashfn, hal, &samples)
+}
+
 #[derive(Serialize)]
 struct CsvRow<'a> {
     job_name: &'a str,
@@ -219,6 +260,12 @@ struct CsvRow<'a> {
     proof_bytes: u32,
 }
 
+#[derive(Serialize)]
+struct CsvRowMemory {
+    po2: u32,
+    peak_memory: usize,
+}
+
 #[derive(Serialize)]
 struct CsvRowAverage<'a> {
     job_name: &'a str,
@@ -281,3 +328,75 @@ pub fn run_jobs(out_path: &Path, jobs: Vec<Job>) -> Vec<Metrics> {
 
     all_metrics
 }
+
+/// Measure the peak memory used to prove a single segment of each po2,
+/// optionally in low-memory mode with the given budget.
+///
+/// The prover only reports the peak since the process started, so this must
+/// run before any other proving. Segments are proven from the smallest to the
+/// largest, so that each one sets a new peak.
+pub fn run_memory_profile(
+    out_path: &Path,
+    po2s: RangeInclusive<u32>,
+    memory_budget: Option<usize>,
+) -> Vec<MemoryMetrics> {
+    info!("");
+    info!(
+        "Measuring peak memory for po2 {po2s:?}; saving output to {}",
+        out_path.display()
+    );
+
+    let mut out = {
+        let out_file_exists = out_path.exists();
+        let out_file = OpenOptions::new()
+            .write(true)
+            .append(true)
+            .create(true)
+            .open(out_path)
+            .unwrap();
+        csv::WriterBuilder::new()
+            .has_headers(!out_file_exists)
+            .from_writer(out_file)
+    };
+
+    let opts = ProverOpts {
+        memory_budget,
+        ..Default::default()
+    };
+    let prover = get_prover_server(&opts).unwrap();
+    let ctx = VerifierContext::default();
+
+    let mut all_metrics = Vec::new();
+    for po2 in po2s {
+        println!("Measuring peak memory for po2 {po2}");
+        // Run for long enough to fill the first segment.
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::BusyLoop { cycles: 1 << po2 })
+            .unwrap()
+            .segment_limit_po2(po2)
+            .build()
+            .unwrap();
+        let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        let segment = session.resolve().unwrap().remove(0);
+        assert_eq!(segment.po2, po2);
+        prover.prove_segment(&ctx, &segment).unwrap();
+
+        let metrics = MemoryMetrics {
+            po2,
+            peak_memory: prover.get_peak_memory_usage(),
+        };
+        info!("+ po2: {po2}, peak_memory: {}", metrics.peak_memory);
+        out.serialize(CsvRowMemory {
+            po2: metrics.po2,
+            peak_memory: metrics.peak_memory,
+        })
+        .expect("Could not serialize");
+        all_metrics.push(metrics);
+    }
+
+    out.flush().expect("Could not flush");
+    all_metrics
+}
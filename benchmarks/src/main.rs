@@ -18,7 +18,10 @@ use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 use enum_iterator::Sequence;
-use risc0_benchmark::{benches::*, init_logging, run_jobs, Job};
+use risc0_benchmark::{
+    benches::*, calibrate_model, init_logging, run_jobs, run_memory_profile, Job,
+};
+use risc0_zkp::MIN_CYCLES_PO2;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -27,6 +30,24 @@ struct Cli {
     #[arg(long, value_name = "FILE")]
     out: PathBuf,
 
+    // Fit a proving model to the results and write it to this JSON file, for
+    // use with `r0vm --estimate-model`.
+    #[arg(long, value_name = "FILE")]
+    model: Option<PathBuf>,
+
+    // Measure the peak memory used to prove a segment of each po2 and write it
+    // to this CSV file, before running the jobs.
+    #[arg(long, value_name = "FILE")]
+    memory: Option<PathBuf>,
+
+    // The largest po2 to measure with `--memory`.
+    #[arg(long, default_value_t = 20)]
+    memory_max_po2: u32,
+
+    // Measure `--memory` in low-memory mode, with this budget in bytes.
+    #[arg(long, value_name = "BYTES")]
+    memory_budget: Option<usize>,
+
     #[command(subcommand)]
     command: Command,
 }
@@ -77,6 +98,16 @@ fn main() {
     init_logging();
 
     let cli = Cli::parse();
+    if let Some(memory_path) = cli.memory {
+        let po2s = MIN_CYCLES_PO2 as u32..=cli.memory_max_po2;
+        run_memory_profile(&memory_path, po2s, cli.memory_budget);
+    }
+
     let jobs = cli.command.get_jobs();
-    run_jobs(&cli.out, jobs);
+    let metrics = run_jobs(&cli.out, jobs);
+
+    if let Some(model_path) = cli.model {
+        let model = calibrate_model(&metrics).unwrap();
+        std::fs::write(model_path, serde_json::to_vec_pretty(&model).unwrap()).unwrap();
+    }
 }
@@ -0,0 +1,464 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate alloc;
+
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use anyhow::{bail, Context, Result};
+use risc0_zkp::core::{
+    digest::Digest,
+    hash::sha::{cpu::Impl, Sha256},
+};
+use risc0_zkvm_platform::{
+    memory::{BLOB_TABLE_PTR, GUEST_MAX_MEM},
+    WORD_SIZE,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    elf::{LoadedElf, Program},
+    MemoryImage,
+};
+
+/// A record of what was placed where by a [MemoryImageBuilder].
+///
+/// Together with the inputs named by the entries, the manifest is enough to
+/// rebuild the same [MemoryImage], and therefore the same image ID.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImageManifest {
+    /// The entrypoint of the image.
+    pub entry: u32,
+
+    /// The page size of the image.
+    pub page_size: u32,
+
+    /// The entries of the image, in the order they were added.
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// An entry of an [ImageManifest].
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// The name given to the entry when it was added.
+    pub name: String,
+
+    /// What kind of input the entry was loaded from.
+    pub kind: ManifestEntryKind,
+
+    /// SHA-256 digest of the input the entry was loaded from.
+    pub digest: Digest,
+
+    /// The `[start, end)` address ranges of memory occupied by the entry.
+    pub regions: Vec<(u32, u32)>,
+}
+
+/// The kind of a [ManifestEntry].
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ManifestEntryKind {
+    /// An executable ELF, loaded at the addresses it was linked at.
+    Executable,
+
+    /// A position-independent ELF, loaded at `base` with `relocations`
+    /// relocations applied.
+    PositionIndependent {
+        /// The address the ELF was loaded at.
+        base: u32,
+
+        /// The number of relocations applied.
+        relocations: u32,
+    },
+
+    /// A raw segment of bytes.
+    Segment,
+
+    /// A named blob preloaded at the top of guest memory.
+    Blob,
+}
+
+/// A builder for a [MemoryImage] made of one or more programs and extra
+/// segments.
+///
+/// The entrypoint of the image is that of the first program added. Every
+/// program and segment must occupy a range of memory that does not overlap
+/// any other, including the blobs preloaded at the top of guest memory.
+///
+/// ```ignore
+/// let (image, manifest) = MemoryImageBuilder::new(PAGE_SIZE)
+///     .program("main", MAIN_ELF)?
+///     .segment("table", 0x0800_0000, &table)?
+///     .preload("weights", &weights)?
+///     .build()?;
+/// ```
+pub struct MemoryImageBuilder {
+    page_size: u32,
+    max_mem: u32,
+    entry: Option<u32>,
+    image: BTreeMap<u32, u32>,
+    entries: Vec<ManifestEntry>,
+    blobs: BTreeMap<String, Vec<u8>>,
+}
+
+impl MemoryImageBuilder {
+    /// Construct a builder for a [MemoryImage] with the given `page_size`.
+    pub fn new(page_size: u32) -> Self {
+        Self {
+            page_size,
+            max_mem: GUEST_MAX_MEM as u32,
+            entry: None,
+            image: BTreeMap::new(),
+            entries: Vec::new(),
+            blobs: BTreeMap::new(),
+        }
+    }
+
+    /// Add an executable ELF, loaded at the addresses it was linked at.
+    pub fn program(&mut self, name: &str, elf: &[u8]) -> Result<&mut Self> {
+        let loaded = LoadedElf::new(elf, self.max_mem, None)?;
+        self.add_elf(name, elf, loaded, ManifestEntryKind::Executable)
+    }
+
+    /// Add a position-independent ELF, loaded at `base` with its dynamic
+    /// relocations applied.
+    pub fn program_at(&mut self, name: &str, elf: &[u8], base: u32) -> Result<&mut Self> {
+        let loaded = LoadedElf::new(elf, self.max_mem, Some(base))?;
+        let kind = ManifestEntryKind::PositionIndependent {
+            base,
+            relocations: loaded.relocations,
+        };
+        self.add_elf(name, elf, loaded, kind)
+    }
+
+    /// Add a segment holding `data` at `addr`, which must be word aligned.
+    ///
+    /// The last word of the segment is padded with zeros.
+    pub fn segment(&mut self, name: &str, addr: u32, data: &[u8]) -> Result<&mut Self> {
+        if addr % WORD_SIZE as u32 != 0 {
+            bail!("segment {name} address {addr:08x} is unaligned");
+        }
+        let end = u32::try_from(data.len())
+            .ok()
+            .and_then(|len| len.checked_next_multiple_of(WORD_SIZE as u32))
+            .and_then(|len| addr.checked_add(len))
+            .filter(|&end| end <= self.max_mem)
+            .with_context(|| {
+                alloc::format!("segment {name} exceeds maximum address for guest programs")
+            })?;
+        let regions = if addr < end {
+            Vec::from([(addr, end)])
+        } else {
+            Vec::new()
+        };
+        self.check_overlap(name, &regions)?;
+
+        write_bytes(&mut self.image, addr, data);
+        self.push_entry(name, ManifestEntryKind::Segment, data, regions);
+        Ok(self)
+    }
+
+    /// Preload `data` at the top of guest memory, where the guest can look it
+    /// up by `name`.
+    ///
+    /// Blobs are laid out in order of their names, so the resulting image does
+    /// not depend on the order they are added in. The blobs take only the space
+    /// they need, and the heap of the guest stops below them. An image without
+    /// preloaded blobs is the same as one built from its programs alone.
+    ///
+    /// See [BLOB_TABLE_PTR] for the layout of the blobs.
+    pub fn preload(&mut self, name: &str, data: &[u8]) -> Result<&mut Self> {
+        if self.blobs.contains_key(name) {
+            bail!("blob {name} is already preloaded");
+        }
+        self.blobs.insert(name.to_string(), data.to_vec());
+        Ok(self)
+    }
+
+    /// Build the [MemoryImage], along with the manifest of what was placed
+    /// where.
+    pub fn build(&self) -> Result<(MemoryImage, ImageManifest)> {
+        let entry = self
+            .entry
+            .context("A memory image requires at least one program")?;
+        let mut image = self.image.clone();
+        let mut entries = self.entries.clone();
+        if !self.blobs.is_empty() {
+            self.layout_blobs(&mut image, &mut entries)?;
+        }
+        let program = Program { entry, image };
+        let image = MemoryImage::new(&program, self.page_size)?;
+        let manifest = ImageManifest {
+            entry,
+            page_size: self.page_size,
+            entries,
+        };
+        Ok((image, manifest))
+    }
+
+    /// Write the table of blobs, followed by their names and data, at the top
+    /// of guest memory, and point [BLOB_TABLE_PTR] at the table.
+    fn layout_blobs(
+        &self,
+        image: &mut BTreeMap<u32, u32>,
+        entries: &mut Vec<ManifestEntry>,
+    ) -> Result<()> {
+        const TABLE_ENTRY_WORDS: usize = 4;
+        let padded_len = |bytes: &[u8]| bytes.len().next_multiple_of(WORD_SIZE);
+        let table_words = 1 + self.blobs.len() * TABLE_ENTRY_WORDS;
+        let size = self
+            .blobs
+            .iter()
+            .fold(table_words * WORD_SIZE, |size, (name, data)| {
+                size.saturating_add(padded_len(name.as_bytes()) + padded_len(data))
+            });
+        let start = u32::try_from(size)
+            .ok()
+            .and_then(|size| (BLOB_TABLE_PTR as u32).checked_sub(size))
+            .context("preloaded blobs do not fit in guest memory")?;
+        self.check_overlap("preloaded blobs", &[(start, GUEST_MAX_MEM as u32)])?;
+
+        let mut table = Vec::with_capacity(table_words);
+        table.push(self.blobs.len() as u32);
+        let mut next = start + (table_words * WORD_SIZE) as u32;
+        let mut place = |bytes: &[u8], image: &mut BTreeMap<u32, u32>| -> (u32, u32) {
+            let addr = next;
+            write_bytes(image, addr, bytes);
+            next += padded_len(bytes) as u32;
+            (addr, next)
+        };
+
+        let mut blob_entries = Vec::with_capacity(self.blobs.len());
+        for (name, data) in self.blobs.iter() {
+            let (name_addr, _) = place(name.as_bytes(), image);
+            let (data_addr, data_end) = place(data, image);
+            table.extend([name_addr, name.len() as u32, data_addr, data.len() as u32]);
+            blob_entries.push(ManifestEntry {
+                name: name.clone(),
+                kind: ManifestEntryKind::Blob,
+                digest: *Impl::hash_bytes(data),
+                regions: Vec::from([(data_addr, data_end)]),
+            });
+        }
+
+        for (i, word) in table.into_iter().enumerate() {
+            image.insert(start + (i * WORD_SIZE) as u32, word);
+        }
+        image.insert(BLOB_TABLE_PTR as u32, start);
+        entries.extend(blob_entries);
+        Ok(())
+    }
+
+    fn add_elf(
+        &mut self,
+        name: &str,
+        elf: &[u8],
+        loaded: LoadedElf,
+        kind: ManifestEntryKind,
+    ) -> Result<&mut Self> {
+        self.check_overlap(name, &loaded.regions)?;
+        self.entry.get_or_insert(loaded.entry);
+        self.image.extend(loaded.image);
+        self.push_entry(name, kind, elf, loaded.regions);
+        Ok(self)
+    }
+
+    fn check_overlap(&self, name: &str, regions: &[(u32, u32)]) -> Result<()> {
+        for entry in self.entries.iter() {
+            for &(start, end) in entry.regions.iter() {
+                if let Some((new_start, new_end)) = regions
+                    .iter()
+                    .find(|&&(new_start, new_end)| new_start < end && start < new_end)
+                {
+                    bail!(
+                        "{name} [0x{new_start:08x}, 0x{new_end:08x}) overlaps {} [0x{start:08x}, 0x{end:08x})",
+                        entry.name
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn push_entry(
+        &mut self,
+        name: &str,
+        kind: ManifestEntryKind,
+        input: &[u8],
+        regions: Vec<(u32, u32)>,
+    ) {
+        self.entries.push(ManifestEntry {
+            name: name.to_string(),
+            kind,
+            digest: *Impl::hash_bytes(input),
+            regions,
+        });
+    }
+}
+
+/// Write `bytes` into `image` at the word aligned `addr`, padding the last word
+/// with zeros.
+fn write_bytes(image: &mut BTreeMap<u32, u32>, addr: u32, bytes: &[u8]) {
+    for (i, chunk) in bytes.chunks(WORD_SIZE).enumerate() {
+        let mut word = [0_u8; WORD_SIZE];
+        word[..chunk.len()].copy_from_slice(chunk);
+        image.insert(addr + (i * WORD_SIZE) as u32, u32::from_le_bytes(word));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm_methods::{HELLO_COMMIT_ELF, MULTI_TEST_ELF};
+    use risc0_zkvm_platform::memory::{BLOB_TABLE_PTR, GUEST_MAX_MEM};
+
+    use super::{ManifestEntryKind, MemoryImageBuilder};
+    use crate::{
+        elf::{tests::pie_without_section_headers, Program},
+        MemoryImage,
+    };
+
+    const PAGE_SIZE: u32 = 1024;
+
+    #[test]
+    fn single_program() {
+        let program = Program::load_elf(MULTI_TEST_ELF, GUEST_MAX_MEM as u32).unwrap();
+        let expected = MemoryImage::new(&program, PAGE_SIZE).unwrap();
+
+        let (image, manifest) = MemoryImageBuilder::new(PAGE_SIZE)
+            .program("multi_test", MULTI_TEST_ELF)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(image.compute_id().unwrap(), expected.compute_id().unwrap());
+        assert_eq!(manifest.entry, program.entry);
+        assert_eq!(manifest.page_size, PAGE_SIZE);
+        assert_eq!(manifest.entries.len(), 1);
+        assert_eq!(manifest.entries[0].kind, ManifestEntryKind::Executable);
+    }
+
+    #[test]
+    fn extra_segment() {
+        const ADDR: u32 = 0x0800_0000;
+        let table: Vec<u8> = (0..=254).collect();
+        let mut builder = MemoryImageBuilder::new(PAGE_SIZE);
+        builder.program("multi_test", MULTI_TEST_ELF).unwrap();
+        builder.segment("table", ADDR, &table).unwrap();
+        let (image, manifest) = builder.build().unwrap();
+
+        let mut bytes = [0_u8; 256];
+        image.load_region_in_page(ADDR, &mut bytes).unwrap();
+        assert_eq!(&bytes[..255], table.as_slice());
+        assert_eq!(bytes[255], 0);
+        assert_eq!(manifest.entries[1].regions, vec![(ADDR, ADDR + 256)]);
+
+        // The same inputs always produce the same image.
+        let (other, _) = builder.build().unwrap();
+        assert_eq!(image.compute_id().unwrap(), other.compute_id().unwrap());
+    }
+
+    #[test]
+    fn position_independent() {
+        const BASE: u32 = 0x0800_0000;
+        let elf = pie_without_section_headers();
+        let mut builder = MemoryImageBuilder::new(PAGE_SIZE);
+        builder.program_at("pie", &elf, BASE).unwrap();
+        let (image, manifest) = builder.build().unwrap();
+
+        let mut words = [0_u8; 12];
+        image.load_region_in_page(BASE + 116, &mut words).unwrap();
+        let words: Vec<u32> = words
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+        assert_eq!(words, [BASE + 0x40, BASE + 0x104, 0x5008]);
+        assert_eq!(manifest.entry, BASE);
+        assert_eq!(
+            manifest.entries[0].kind,
+            ManifestEntryKind::PositionIndependent {
+                base: BASE,
+                relocations: 3
+            }
+        );
+        assert_eq!(
+            manifest.entries[0].regions,
+            vec![(BASE, BASE + elf.len() as u32)]
+        );
+
+        // The same ELF can be loaded again elsewhere, but not over itself, and
+        // an executable cannot be loaded as position-independent.
+        builder.program_at("pie2", &elf, BASE + 0x1000).unwrap();
+        assert!(builder.program_at("pie3", &elf, BASE + 4).is_err());
+        assert!(builder
+            .program_at("multi_test", MULTI_TEST_ELF, 0x1000_0000)
+            .is_err());
+    }
+
+    #[test]
+    fn overlap() {
+        let program = Program::load_elf(MULTI_TEST_ELF, GUEST_MAX_MEM as u32).unwrap();
+        let mut builder = MemoryImageBuilder::new(PAGE_SIZE);
+        builder.program("multi_test", MULTI_TEST_ELF).unwrap();
+        assert!(builder.segment("blob", program.entry, &[1, 2, 3]).is_err());
+        assert!(builder.program("hello_commit", HELLO_COMMIT_ELF).is_err());
+        assert!(builder.segment("unaligned", 0x0800_0001, &[1]).is_err());
+    }
+
+    #[test]
+    fn preload() {
+        let mut builder = MemoryImageBuilder::new(PAGE_SIZE);
+        builder.program("multi_test", MULTI_TEST_ELF).unwrap();
+        builder.preload("weights", &[1, 2, 3, 4, 5]).unwrap();
+        builder.preload("book", b"e4 e5").unwrap();
+        assert!(builder.preload("book", b"d4").is_err());
+        let (image, manifest) = builder.build().unwrap();
+
+        let load = |addr: u32, len: u32| {
+            let mut bytes = vec![0_u8; len as usize];
+            image.load_region_in_page(addr, &mut bytes).unwrap();
+            bytes
+        };
+        let load_word = |addr: u32| u32::from_le_bytes(load(addr, 4).try_into().unwrap());
+
+        // Blobs are packed at the top of guest memory, in order of their names.
+        let table = load_word(BLOB_TABLE_PTR as u32);
+        // The table takes 9 words, the names 12 bytes and the data 16 bytes.
+        assert_eq!(table, BLOB_TABLE_PTR as u32 - (9 * 4 + 12 + 16));
+        assert_eq!(load_word(table), 2);
+        let name = load(load_word(table + 4), load_word(table + 8));
+        let data = load(load_word(table + 12), load_word(table + 16));
+        assert_eq!(name, b"book");
+        assert_eq!(data, b"e4 e5");
+        let name = load(load_word(table + 20), load_word(table + 24));
+        let data = load(load_word(table + 28), load_word(table + 32));
+        assert_eq!(name, b"weights");
+        assert_eq!(data, [1, 2, 3, 4, 5]);
+
+        assert_eq!(manifest.entries[1].name, "book");
+        assert_eq!(manifest.entries[1].kind, ManifestEntryKind::Blob);
+        assert_eq!(manifest.entries[2].name, "weights");
+
+        // A segment cannot overlap the preloaded blobs.
+        builder.segment("table", table, &[1]).unwrap();
+        assert!(builder.build().is_err());
+    }
+
+    #[test]
+    fn no_program() {
+        let mut builder = MemoryImageBuilder::new(PAGE_SIZE);
+        builder.segment("table", 0x0800_0000, &[1, 2, 3]).unwrap();
+        assert!(builder.build().is_err());
+    }
+}
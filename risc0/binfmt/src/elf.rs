@@ -14,10 +14,13 @@
 
 extern crate alloc;
 
-use alloc::collections::BTreeMap;
+use alloc::{collections::BTreeMap, format, vec::Vec};
 
 use anyhow::{anyhow, bail, Context, Result};
-use elf::{endian::LittleEndian, file::Class, ElfBytes};
+use elf::{
+    dynamic::DynamicTable, endian::LittleEndian, file::Class, relocation::RelaIterator,
+    symbol::SymbolTable, ElfBytes,
+};
 use risc0_zkvm_platform::WORD_SIZE;
 
 /// A RISC Zero program
@@ -32,7 +35,48 @@ pub struct Program {
 impl Program {
     /// Initialize a RISC Zero Program from an appropriate ELF file
     pub fn load_elf(input: &[u8], max_mem: u32) -> Result<Program> {
+        let elf = LoadedElf::new(input, max_mem, None)?;
+        Ok(Program {
+            entry: elf.entry,
+            image: elf.image,
+        })
+    }
+}
+
+/// RISC-V relocation types applied when loading a position-independent ELF.
+const R_RISCV_NONE: u32 = 0;
+const R_RISCV_32: u32 = 1;
+const R_RISCV_RELATIVE: u32 = 3;
+
+/// The sizes of an `Elf32_Rela` and an `Elf32_Sym`.
+const RELAENT_SIZE: u64 = 12;
+const SYMENT_SIZE: u64 = 16;
+
+/// The `PT_LOAD` segments of an ELF file, loaded at their final addresses.
+#[cfg_attr(target_os = "zkvm", allow(dead_code))]
+pub(crate) struct LoadedElf {
+    /// The entrypoint of the program
+    pub entry: u32,
+
+    /// The loaded memory, as a map from word address to word
+    pub image: BTreeMap<u32, u32>,
+
+    /// The `[start, end)` address range of each loaded segment
+    pub regions: Vec<(u32, u32)>,
+
+    /// The number of relocations applied
+    pub relocations: u32,
+}
+
+impl LoadedElf {
+    /// Load the segments of `input`.
+    ///
+    /// If `base` is `None`, the ELF must be an executable. Otherwise, the ELF
+    /// must be position-independent: its segments are loaded at an offset of
+    /// `base` and its dynamic relocations are applied.
+    pub fn new(input: &[u8], max_mem: u32, base: Option<u32>) -> Result<Self> {
         let mut image: BTreeMap<u32, u32> = BTreeMap::new();
+        let mut regions = Vec::new();
         let elf = ElfBytes::<LittleEndian>::minimal_parse(input)
             .map_err(|err| anyhow!("Elf parse error: {err}"))?;
         if elf.ehdr.class != Class::ELF32 {
@@ -41,14 +85,25 @@ impl Program {
         if elf.ehdr.e_machine != elf::abi::EM_RISCV {
             bail!("Invalid machine type, must be RISC-V");
         }
-        if elf.ehdr.e_type != elf::abi::ET_EXEC {
-            bail!("Invalid ELF type, must be executable");
+        match base {
+            None if elf.ehdr.e_type != elf::abi::ET_EXEC => {
+                bail!("Invalid ELF type, must be executable")
+            }
+            Some(_) if elf.ehdr.e_type != elf::abi::ET_DYN => {
+                bail!("Invalid ELF type, must be position-independent")
+            }
+            _ => {}
+        }
+        let base = base.unwrap_or(0);
+        if base % WORD_SIZE as u32 != 0 {
+            bail!("base {base:08x} is unaligned");
         }
         let entry: u32 = elf
             .ehdr
             .e_entry
             .try_into()
             .map_err(|err| anyhow!("e_entry was larger than 32 bits. {err}"))?;
+        let entry = entry.checked_add(base).context("Invalid entrypoint")?;
         if entry >= max_mem || entry % WORD_SIZE as u32 != 0 {
             bail!("Invalid entrypoint");
         }
@@ -75,6 +130,7 @@ impl Program {
                 .p_vaddr
                 .try_into()
                 .map_err(|err| anyhow!("vaddr is larger than 32 bits. {err}"))?;
+            let vaddr = vaddr.checked_add(base).context("Invalid segment vaddr")?;
             if vaddr % WORD_SIZE as u32 != 0 {
                 bail!("vaddr {vaddr:08x} is unaligned");
             }
@@ -102,7 +158,210 @@ impl Program {
                     image.insert(addr, word);
                 }
             }
+            if mem_size > 0 {
+                let end = vaddr + mem_size.next_multiple_of(WORD_SIZE as u32);
+                regions.push((vaddr, end));
+            }
+        }
+
+        let relocations = if elf.ehdr.e_type == elf::abi::ET_DYN {
+            relocate(&elf, base, &mut image)?
+        } else {
+            0
+        };
+
+        Ok(Self {
+            entry,
+            image,
+            regions,
+            relocations,
+        })
+    }
+}
+
+/// Apply the dynamic relocations of a position-independent ELF loaded at
+/// `base`, returning the number of relocations applied.
+///
+/// The relocations are found through the `PT_DYNAMIC` segment, as a dynamic
+/// loader would, so that ELFs without section headers are supported.
+fn relocate(
+    elf: &ElfBytes<LittleEndian>,
+    base: u32,
+    image: &mut BTreeMap<u32, u32>,
+) -> Result<u32> {
+    let segments = elf.segments().ok_or(anyhow!("Missing segment table"))?;
+    let Some(dynamic) = segments
+        .iter()
+        .find(|phdr| phdr.p_type == elf::abi::PT_DYNAMIC)
+    else {
+        return Ok(0);
+    };
+    let dynamic = elf
+        .segment_data(&dynamic)
+        .map_err(|err| anyhow!("Invalid dynamic segment: {err}"))?;
+    let dynamic = DynamicTable::new(elf.ehdr.endianness, elf.ehdr.class, dynamic);
+
+    let (mut rela, mut rela_size, mut rela_ent, mut symtab) = (None, None, None, None);
+    for entry in dynamic.iter() {
+        let (tag, value) = (entry.d_tag, entry.d_val());
+        match tag {
+            elf::abi::DT_NULL => break,
+            elf::abi::DT_RELA => rela = Some(value),
+            elf::abi::DT_RELASZ => rela_size = Some(value),
+            elf::abi::DT_RELAENT => rela_ent = Some(value),
+            elf::abi::DT_SYMTAB => symtab = Some(value),
+            elf::abi::DT_SYMENT if value != SYMENT_SIZE => {
+                bail!("Unsupported dynamic symbol size {value}")
+            }
+            elf::abi::DT_REL => bail!("Unsupported DT_REL relocations"),
+            _ => {}
         }
-        Ok(Program { entry, image })
+    }
+    let Some(rela) = rela else {
+        return Ok(0);
+    };
+    let rela_size = rela_size.context("Missing DT_RELASZ")?;
+    if rela_ent != Some(RELAENT_SIZE) {
+        bail!("Missing or unsupported DT_RELAENT");
+    }
+    let relas = RelaIterator::new(
+        elf.ehdr.endianness,
+        elf.ehdr.class,
+        file_data(elf, rela, Some(rela_size))?,
+    );
+    // The dynamic symbol table has no size of its own, so it is bounded by the
+    // segment that contains it.
+    let symbols = symtab
+        .map(|symtab| file_data(elf, symtab, None))
+        .transpose()?
+        .map(|data| SymbolTable::new(elf.ehdr.endianness, elf.ehdr.class, data));
+
+    let mut count = 0;
+    for rela in relas {
+        let offset: u32 = rela
+            .r_offset
+            .try_into()
+            .map_err(|err| anyhow!("r_offset was larger than 32 bits. {err}"))?;
+        let addr = offset
+            .checked_add(base)
+            .context("Invalid relocation offset")?;
+        if addr % WORD_SIZE as u32 != 0 {
+            bail!("relocation at {addr:08x} is unaligned");
+        }
+        let addend = rela.r_addend as u32;
+        let value = match rela.r_type {
+            R_RISCV_NONE => continue,
+            R_RISCV_RELATIVE => base.wrapping_add(addend),
+            R_RISCV_32 => {
+                let symbol = symbols
+                    .as_ref()
+                    .context("Missing dynamic symbol table")?
+                    .get(rela.r_sym as usize)
+                    .map_err(|err| anyhow!("Invalid relocation symbol: {err}"))?;
+                match symbol.st_shndx {
+                    elf::abi::SHN_UNDEF => {
+                        bail!("relocation at {addr:08x} refers to an undefined symbol")
+                    }
+                    // Absolute symbols do not move with the ELF.
+                    elf::abi::SHN_ABS => (symbol.st_value as u32).wrapping_add(addend),
+                    _ => base
+                        .wrapping_add(symbol.st_value as u32)
+                        .wrapping_add(addend),
+                }
+            }
+            r_type => bail!("Unsupported relocation type {r_type} at {addr:08x}"),
+        };
+        let word = image.get_mut(&addr).with_context(|| {
+            format!("relocation at {addr:08x} is outside of the loaded segments")
+        })?;
+        *word = value;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Return the file contents at the unrelocated address `vaddr`, up to `size`
+/// bytes or else to the end of the `PT_LOAD` segment that contains it.
+fn file_data<'data>(
+    elf: &ElfBytes<'data, LittleEndian>,
+    vaddr: u64,
+    size: Option<u64>,
+) -> Result<&'data [u8]> {
+    let segments = elf.segments().ok_or(anyhow!("Missing segment table"))?;
+    let segment = segments
+        .iter()
+        .find(|phdr| {
+            phdr.p_type == elf::abi::PT_LOAD
+                && phdr.p_vaddr <= vaddr
+                && vaddr - phdr.p_vaddr < phdr.p_filesz
+        })
+        .with_context(|| format!("Address {vaddr:08x} is not in a loaded segment"))?;
+    let data = elf
+        .segment_data(&segment)
+        .map_err(|err| anyhow!("Invalid segment data: {err}"))?;
+    let start = (vaddr - segment.p_vaddr) as usize;
+    let end = match size {
+        Some(size) => start.checked_add(size as usize).context("Invalid size")?,
+        None => data.len(),
+    };
+    data.get(start..end)
+        .with_context(|| format!("Data at {vaddr:08x} extends past its segment"))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{LoadedElf, R_RISCV_32, R_RISCV_RELATIVE};
+
+    /// Build a position-independent ELF without section headers, whose
+    /// relocations patch the three words at offset 116.
+    pub(crate) fn pie_without_section_headers() -> Vec<u8> {
+        const PHDRS: u32 = 52;
+        const DATA: u32 = PHDRS + 2 * 32;
+        const DYNAMIC: u32 = DATA + 3 * 4;
+        const RELA: u32 = DYNAMIC + 6 * 8;
+        const SYMTAB: u32 = RELA + 3 * 12;
+        const END: u32 = SYMTAB + 3 * 16;
+
+        let mut elf = Vec::new();
+        let mut put = |words: &[u32]| words.iter().for_each(|w| elf.extend(w.to_le_bytes()));
+        // ELF header: ELFCLASS32, little-endian, ET_DYN, EM_RISCV.
+        put(&[0x464c457f, 0x00010101, 0, 0]);
+        put(&[(243 << 16) | 3, 1, 0, PHDRS, 0, 0, (32 << 16) | 52, 2, 0]);
+        // Program headers: a PT_LOAD of the whole file, and its PT_DYNAMIC.
+        put(&[1, 0, 0, 0, END, END, 7, 4]);
+        put(&[
+            2,
+            DYNAMIC,
+            DYNAMIC,
+            DYNAMIC,
+            RELA - DYNAMIC,
+            RELA - DYNAMIC,
+            6,
+            4,
+        ]);
+        // The words to relocate.
+        put(&[0, 0, 0]);
+        // DT_RELA, DT_RELASZ, DT_RELAENT, DT_SYMTAB, DT_SYMENT, DT_NULL.
+        put(&[7, RELA, 8, 3 * 12, 9, 12, 6, SYMTAB, 11, 16, 0, 0]);
+        // Relocations: a relative one, then one to each symbol.
+        put(&[DATA, R_RISCV_RELATIVE, 0x40]);
+        put(&[DATA + 4, (1 << 8) | R_RISCV_32, 4]);
+        put(&[DATA + 8, (2 << 8) | R_RISCV_32, 8]);
+        // Symbols: the null symbol, one in section 1, and an absolute one.
+        put(&[0, 0, 0, 0]);
+        put(&[0, 0x100, 0, 1 << 16]);
+        put(&[0, 0x5000, 0, (elf::abi::SHN_ABS as u32) << 16]);
+        elf
+    }
+
+    #[test]
+    fn relocate_from_dynamic_segment() {
+        let base = 0x1000;
+        let loaded = LoadedElf::new(&pie_without_section_headers(), 0x10000, Some(base)).unwrap();
+        assert_eq!(loaded.relocations, 3);
+        assert_eq!(loaded.image[&(base + 116)], base + 0x40);
+        assert_eq!(loaded.image[&(base + 120)], base + 0x104);
+        // Absolute symbols are not moved by the base.
+        assert_eq!(loaded.image[&(base + 124)], 0x5008);
     }
 }
@@ -14,9 +14,15 @@
 
 extern crate alloc;
 
-use alloc::{collections::BTreeMap, vec, vec::Vec};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use core::ops::Range;
 
-use anyhow::{ensure, Result};
+use anyhow::{anyhow, bail, ensure, Result};
 use risc0_zkp::core::{
     digest::Digest,
     hash::sha::{cpu::Impl, Sha256, BLOCK_BYTES, SHA256_INIT},
@@ -24,20 +30,31 @@ use risc0_zkp::core::{
 use risc0_zkvm_platform::{
     memory::{GUEST_MAX_MEM, MEM_SIZE, PAGE_TABLE},
     syscall::DIGEST_BYTES,
+    PAGE_SIZE,
 };
 use serde::{Deserialize, Serialize};
 
 use crate::{elf::Program, Digestible, SystemState};
 
+/// A page of a [MemoryImage], shared between the images it belongs to.
+pub type Page = Arc<Vec<u8>>;
+
 /// An image of a zkVM guest's memory
 ///
 /// This is an image of the full memory state of the zkVM, including the data,
 /// text, inputs, page table, and system memory. In addition to the memory image
 /// proper, this includes some metadata about the page table.
+///
+/// Pages are reference counted and copied on write, so cloning an image is
+/// cheap: a clone shares every page with the original until either of them
+/// writes to it. A base image can therefore be cached in a
+/// [MemoryImageCache] and cloned for each execution, and the images of
+/// consecutive segments only hold copies of the pages that changed between
+/// them.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct MemoryImage {
     /// Sparse memory memory image as a map from page index to page.
-    pub pages: BTreeMap<u32, Vec<u8>>,
+    pages: BTreeMap<u32, Page>,
 
     /// Metadata about the structure of the page table
     pub info: PageTableInfo,
@@ -113,6 +130,12 @@ impl PageTableInfo {
             page_size > 0 && (page_size & (page_size - 1)) == 0,
             "Page size must be a power of 2"
         );
+        // Each page must hold more than one entry, or the page table never
+        // shrinks to a single root page.
+        ensure!(
+            page_size as usize > DIGEST_BYTES,
+            "Page size must be larger than {DIGEST_BYTES} bytes"
+        );
 
         let mut layers = Vec::new();
         let mut page_table_size = 0u32;
@@ -161,6 +184,164 @@ impl PageTableInfo {
     pub fn get_page_entry_addr(&self, page_idx: u32) -> u32 {
         self.page_table_addr + page_idx * DIGEST_BYTES as u32
     }
+
+    /// Returns the index of the page holding the page table entry for
+    /// `page_idx`, or `None` if `page_idx` is the root page.
+    fn get_parent_index(&self, page_idx: u32) -> Option<u32> {
+        (page_idx < self.root_idx).then(|| self.get_page_index(self.get_page_entry_addr(page_idx)))
+    }
+
+    /// Returns the index of the page whose page table entry is at
+    /// `entry_addr`.
+    fn get_entry_page_index(&self, entry_addr: u32) -> u32 {
+        (entry_addr - self.page_table_addr) / DIGEST_BYTES as u32
+    }
+
+    /// Returns the range of addresses of the page table entries held by the
+    /// page table page `page_idx`.
+    fn get_entry_range(&self, page_idx: u32) -> Range<u32> {
+        let page_addr = self.get_page_addr(page_idx);
+        if page_idx == self.root_idx {
+            page_addr..self.root_addr
+        } else {
+            page_addr..page_addr + self.page_size
+        }
+    }
+
+    /// Returns the end address of the region of `len` bytes at `addr`, if the
+    /// region lies entirely below the page table.
+    fn get_region_end(&self, addr: u32, len: u32) -> Result<u32> {
+        addr.checked_add(len)
+            .filter(|&end| end <= self.page_table_addr)
+            .ok_or_else(|| anyhow!("region 0x{addr:08x}+{len} is outside of guest memory"))
+    }
+
+    /// Returns the page table pages on the paths from the pages covering the
+    /// region `[addr, end)` up to the root page.
+    fn region_path(&self, addr: u32, end: u32) -> BTreeSet<u32> {
+        let mut path = BTreeSet::new();
+        for page_idx in region_page_indices(self, addr, end) {
+            let mut page_idx = page_idx;
+            while let Some(parent_idx) = self.get_parent_index(page_idx) {
+                if !path.insert(parent_idx) {
+                    break;
+                }
+                page_idx = parent_idx;
+            }
+        }
+        path
+    }
+}
+
+/// An inclusion proof for a region of guest memory.
+///
+/// The proof holds the pages covering the region, along with the digests of
+/// the sibling pages whose page table entries share a page table page with
+/// the path from the region to the root of the page table Merkle tree. Pages
+/// of the region that are not present are taken to contain all zeros, as in
+/// [MemoryImage]. It is checked against the `merkle_root` of a
+/// [SystemState], such as the post state of a receipt, with
+/// [RegionProof::verify_region].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegionProof {
+    /// Start address of the region.
+    pub addr: u32,
+
+    /// Length of the region in bytes.
+    pub len: u32,
+
+    /// Page size of the memory image the proof was created from.
+    pub page_size: u32,
+
+    /// The pages covering the region, as a map from page index to page.
+    pub pages: BTreeMap<u32, Vec<u8>>,
+
+    /// The digests of the pages off the path to the root whose entries are
+    /// needed to rebuild the page table pages on it, as a map from page index
+    /// to digest.
+    pub siblings: BTreeMap<u32, Digest>,
+}
+
+impl RegionProof {
+    /// Verify this proof against the given page table `merkle_root`, and
+    /// return the contents of the region.
+    pub fn verify_region(&self, merkle_root: &Digest) -> Result<Vec<u8>> {
+        // The page size is read from the untrusted proof, so only accept the
+        // one used by the zkVM.
+        ensure!(
+            self.page_size as usize == PAGE_SIZE,
+            "Invalid page size in region proof: {}",
+            self.page_size
+        );
+        let info = PageTableInfo::new(PAGE_TABLE.start() as u32, self.page_size)?;
+        let end = info.get_region_end(self.addr, self.len)?;
+        let region = region_page_indices(&info, self.addr, end);
+        ensure!(
+            self.pages.iter().all(|(page_idx, page)| {
+                region.contains(page_idx) && page.len() == self.page_size as usize
+            }),
+            "Invalid page in region proof"
+        );
+
+        let zero_page = vec![0_u8; self.page_size as usize];
+        let load_page = |page_idx: u32| {
+            self.pages
+                .get(&page_idx)
+                .map_or(zero_page.as_slice(), |page| page.as_slice())
+        };
+
+        let mut digests = BTreeMap::new();
+        for page_idx in region.clone() {
+            digests.insert(page_idx, hash_page_bytes(load_page(page_idx))?);
+        }
+
+        // Rebuild each page table page on the path from the digests of its
+        // children, which always have lower indices than their parent.
+        let path = info.region_path(self.addr, end);
+        for &page_idx in &path {
+            let entries = info.get_entry_range(page_idx);
+            let mut page = Vec::with_capacity(entries.len());
+            for entry_addr in entries.step_by(DIGEST_BYTES) {
+                let child_idx = info.get_entry_page_index(entry_addr);
+                let digest = if region.contains(&child_idx) || path.contains(&child_idx) {
+                    digests.get(&child_idx)
+                } else {
+                    self.siblings.get(&child_idx)
+                };
+                let digest = digest
+                    .ok_or_else(|| anyhow!("Missing digest of page {child_idx} in region proof"))?;
+                page.extend_from_slice(digest.as_bytes());
+            }
+            digests.insert(page_idx, hash_page_bytes(&page)?);
+        }
+
+        let root = digests[&info.root_idx];
+        if root != *merkle_root {
+            bail!("Invalid root hash: {} != {}", root, merkle_root);
+        }
+
+        let mut bytes = Vec::with_capacity(self.len as usize);
+        let mut addr = self.addr;
+        while addr < end {
+            let page_idx = info.get_page_index(addr);
+            let page_addr = info.get_page_addr(page_idx);
+            let next = end.min(page_addr + self.page_size);
+            bytes.extend_from_slice(
+                &load_page(page_idx)[(addr - page_addr) as usize..(next - page_addr) as usize],
+            );
+            addr = next;
+        }
+        Ok(bytes)
+    }
+}
+
+/// Returns the indices of the pages covering the region `[addr, end)`.
+fn region_page_indices(info: &PageTableInfo, addr: u32, end: u32) -> Range<u32> {
+    if addr < end {
+        info.get_page_index(addr)..info.get_page_index(end - 1) + 1
+    } else {
+        0..0
+    }
 }
 
 impl MemoryImage {
@@ -190,15 +371,70 @@ impl MemoryImage {
         Ok(img)
     }
 
+    /// Construct a [MemoryImage] from pages that were taken from another
+    /// image, e.g. with [MemoryImage::pages].
+    ///
+    /// The page table is not recomputed, so it must already be held by the
+    /// pages.
+    pub fn from_pages<I>(info: PageTableInfo, pc: u32, pages: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (u32, Page)>,
+    {
+        let pages: BTreeMap<u32, Page> = pages.into_iter().collect();
+        ensure!(
+            pages
+                .values()
+                .all(|page| page.len() == info.page_size as usize),
+            "Invalid page size in memory image"
+        );
+        Ok(Self { pages, info, pc })
+    }
+
+    /// Return the pages held by this image, as a map from page index to page.
+    /// Pages that are not held are all zeros.
+    ///
+    /// This replaces the `pages` field, which is no longer public now that
+    /// pages are shared between images.
+    pub fn pages(&self) -> &BTreeMap<u32, Page> {
+        &self.pages
+    }
+
+    /// Return the page specified by page_idx, if it is held by this image.
+    pub fn page(&self, page_idx: u32) -> Option<&Page> {
+        self.pages.get(&page_idx)
+    }
+
     /// Load a page specified by page_idx. If no page is found, a zero page is
     /// returned.
     pub fn load_page(&self, page_idx: u32) -> Vec<u8> {
         self.pages
             .get(&page_idx)
-            .cloned()
+            .map(|page| page.as_ref().clone())
             .unwrap_or_else(|| vec![0; self.info.page_size as usize])
     }
 
+    /// Return the page specified by page_idx, shared with this image. If no
+    /// page is found, a new zero page is returned.
+    pub fn get_page(&self, page_idx: u32) -> Page {
+        self.pages
+            .get(&page_idx)
+            .cloned()
+            .unwrap_or_else(|| Arc::new(vec![0; self.info.page_size as usize]))
+    }
+
+    /// Replace the page specified by page_idx with `page`.
+    ///
+    /// The page table is not updated until the page is hashed, e.g. with
+    /// [MemoryImage::hash_pages_iter].
+    pub fn store_page(&mut self, page_idx: u32, page: Page) {
+        assert_eq!(
+            page.len(),
+            self.info.page_size as usize,
+            "invalid page size"
+        );
+        self.pages.insert(page_idx, page);
+    }
+
     /// Writes the given byte array in this memory image at the given
     /// address.  The caller is responsible for ensuring the bytes do
     /// not overlap a page boundary.
@@ -208,8 +444,9 @@ impl MemoryImage {
             if addr as usize >= MEM_SIZE {
                 panic!("address {addr:08X} outside MEM_SIZE")
             }
-            vec![0_u8; self.info.page_size as usize]
+            Arc::new(vec![0_u8; self.info.page_size as usize])
         });
+        let page = Arc::make_mut(page);
         let page_start = self.info.get_page_addr(page_idx);
         page[(addr - page_start) as usize..(addr - page_start) as usize + bytes.len()]
             .clone_from_slice(bytes);
@@ -310,6 +547,91 @@ impl MemoryImage {
     pub fn compute_id(&self) -> Result<Digest> {
         Ok(compute_image_id(&self.compute_root_hash()?, self.pc))
     }
+
+    /// Produce an inclusion proof for the `len` bytes of memory at `addr`
+    /// against the root returned by [MemoryImage::compute_root_hash].
+    ///
+    /// The caller is responsible for ensuring the page table is up to date,
+    /// e.g. by calling [MemoryImage::hash_pages].
+    pub fn prove_region(&self, addr: u32, len: u32) -> Result<RegionProof> {
+        let end = self.info.get_region_end(addr, len)?;
+        let region = region_page_indices(&self.info, addr, end);
+        let pages = region
+            .clone()
+            .filter_map(|page_idx| Some((page_idx, self.pages.get(&page_idx)?.to_vec())))
+            .collect();
+
+        let zero_page = vec![0_u8; self.info.page_size as usize];
+        let path = self.info.region_path(addr, end);
+        let mut siblings = BTreeMap::new();
+        for &page_idx in &path {
+            let page = self
+                .pages
+                .get(&page_idx)
+                .map_or(zero_page.as_slice(), |page| page.as_slice());
+            let page_addr = self.info.get_page_addr(page_idx);
+            for entry_addr in self.info.get_entry_range(page_idx).step_by(DIGEST_BYTES) {
+                let child_idx = self.info.get_entry_page_index(entry_addr);
+                if !region.contains(&child_idx) && !path.contains(&child_idx) {
+                    let offset = (entry_addr - page_addr) as usize;
+                    let digest = Digest::try_from(&page[offset..offset + DIGEST_BYTES]).unwrap();
+                    siblings.insert(child_idx, digest);
+                }
+            }
+        }
+
+        Ok(RegionProof {
+            addr,
+            len,
+            page_size: self.info.page_size,
+            pages,
+            siblings,
+        })
+    }
+}
+
+/// A cache of base [MemoryImage]s by image ID.
+///
+/// Instantiating a cached image clones it, which shares all of its pages, so
+/// a service that runs the same guest many times only needs to load and hash
+/// its image once. Each instance copies only the pages that it writes to.
+#[derive(Clone, Default)]
+pub struct MemoryImageCache {
+    images: BTreeMap<Digest, MemoryImage>,
+}
+
+impl MemoryImageCache {
+    /// Construct an empty [MemoryImageCache].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cache `image` as the base image for its image ID, and return that ID.
+    pub fn insert(&mut self, image: MemoryImage) -> Result<Digest> {
+        let image_id = image.compute_id()?;
+        self.images.insert(image_id, image);
+        Ok(image_id)
+    }
+
+    /// Instantiate the base image with the given image ID, if it is cached.
+    pub fn get(&self, image_id: &Digest) -> Option<MemoryImage> {
+        self.images.get(image_id).cloned()
+    }
+
+    /// Remove the base image with the given image ID from the cache.
+    pub fn remove(&mut self, image_id: &Digest) -> Option<MemoryImage> {
+        self.images.remove(image_id)
+    }
+
+    /// Return the number of cached images.
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    /// Return true if no images are cached.
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
 }
 
 fn hash_page_bytes(page: &[u8]) -> Result<Digest> {
@@ -328,14 +650,18 @@ fn hash_page_bytes(page: &[u8]) -> Result<Digest> {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use risc0_zkp::core::digest::Digest;
     use risc0_zkvm_methods::MULTI_TEST_ELF;
     use risc0_zkvm_platform::{
         memory::{GUEST_MAX_MEM, PAGE_TABLE, STACK_TOP, SYSTEM, TEXT_START},
         syscall::DIGEST_BYTES,
+        PAGE_SIZE,
     };
     use test_log::test;
 
-    use crate::{elf::Program, image::PageTableInfo, MemoryImage};
+    use crate::{elf::Program, image::PageTableInfo, MemoryImage, MemoryImageCache};
 
     fn page_table_size(max_mem: u32, page_size: u32) -> u32 {
         PageTableInfo::new(max_mem, page_size)
@@ -360,6 +686,151 @@ mod tests {
         image.check(image.info.root_page_addr).unwrap();
     }
 
+    #[test]
+    fn copy_on_write() {
+        const PAGE_SIZE: u32 = 1024;
+        let program = Program::load_elf(MULTI_TEST_ELF, GUEST_MAX_MEM as u32).unwrap();
+        let image = MemoryImage::new(&program, PAGE_SIZE).unwrap();
+        let id = image.compute_id().unwrap();
+
+        let mut other = image.clone();
+        let page_idx = image.info.get_page_index(TEXT_START);
+        assert!(Arc::ptr_eq(
+            &image.pages[&page_idx],
+            &other.pages[&page_idx]
+        ));
+
+        // Writing to the clone copies the page, leaving the original untouched.
+        other.store_region_in_page(TEXT_START, &[0xff; 4]);
+        other.hash_pages_iter([page_idx].into_iter()).unwrap();
+        assert!(!Arc::ptr_eq(
+            &image.pages[&page_idx],
+            &other.pages[&page_idx]
+        ));
+        assert_eq!(image.compute_id().unwrap(), id);
+        assert_ne!(other.compute_id().unwrap(), id);
+        image.check(TEXT_START).unwrap();
+        other.check(TEXT_START).unwrap();
+
+        // Pages that were not written to are still shared.
+        let stack_idx = image.info.get_page_index(STACK_TOP - 4);
+        let next_idx = page_idx + 1;
+        for idx in [stack_idx, next_idx] {
+            if let Some(page) = image.pages.get(&idx) {
+                assert!(Arc::ptr_eq(page, &other.pages[&idx]));
+            }
+        }
+    }
+
+    #[test]
+    fn image_cache() {
+        const PAGE_SIZE: u32 = 1024;
+        let program = Program::load_elf(MULTI_TEST_ELF, GUEST_MAX_MEM as u32).unwrap();
+        let image = MemoryImage::new(&program, PAGE_SIZE).unwrap();
+
+        let mut cache = MemoryImageCache::new();
+        let id = cache.insert(image.clone()).unwrap();
+        assert_eq!(id, image.compute_id().unwrap());
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(&Digest::ZERO).is_none());
+
+        // Each instance shares the pages of the cached image until written to.
+        let mut first = cache.get(&id).unwrap();
+        let second = cache.get(&id).unwrap();
+        let page_idx = image.info.get_page_index(TEXT_START);
+        assert!(Arc::ptr_eq(
+            first.page(page_idx).unwrap(),
+            second.page(page_idx).unwrap()
+        ));
+        first.store_region_in_page(TEXT_START, &[0xff; 4]);
+        assert!(!Arc::ptr_eq(
+            first.page(page_idx).unwrap(),
+            second.page(page_idx).unwrap()
+        ));
+        assert_eq!(cache.get(&id).unwrap().compute_id().unwrap(), id);
+
+        // An image rebuilt from the pages of another has the same ID.
+        let rebuilt =
+            MemoryImage::from_pages(second.info.clone(), second.pc, second.pages().clone())
+                .unwrap();
+        assert_eq!(rebuilt.compute_id().unwrap(), id);
+
+        assert!(cache.remove(&id).is_some());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn prove_region() {
+        const PAGE_SIZE: u32 = 1024;
+        let program = Program::load_elf(MULTI_TEST_ELF, GUEST_MAX_MEM as u32).unwrap();
+        let image = MemoryImage::new(&program, PAGE_SIZE).unwrap();
+        let root = image.compute_root_hash().unwrap();
+
+        // A region spanning two pages of the program text.
+        let addr = TEXT_START + PAGE_SIZE - 8;
+        let proof = image.prove_region(addr, 16).unwrap();
+        let mut expected = [0_u8; 16];
+        image.load_region_in_page(addr, &mut expected[..8]).unwrap();
+        image
+            .load_region_in_page(addr + 8, &mut expected[8..])
+            .unwrap();
+        assert_eq!(proof.verify_region(&root).unwrap(), expected);
+
+        // A region in a page that was never written reads as zeros.
+        let proof = image.prove_region(STACK_TOP - 32, 32).unwrap();
+        assert_eq!(proof.verify_region(&root).unwrap(), vec![0_u8; 32]);
+
+        // Only the pages of the region are carried in full.
+        let proof = image.prove_region(addr, 16).unwrap();
+        let page_idx = image.info.get_page_index(addr);
+        assert_eq!(
+            proof.pages.keys().copied().collect::<Vec<_>>(),
+            vec![page_idx, page_idx + 1]
+        );
+
+        // Tampering with the region contents is detected.
+        let mut proof = image.prove_region(addr, 16).unwrap();
+        proof.pages.get_mut(&page_idx).unwrap()[0] ^= 1;
+        assert!(proof.verify_region(&root).is_err());
+
+        // As is tampering with, or dropping, a sibling digest.
+        let mut proof = image.prove_region(addr, 16).unwrap();
+        let sibling_idx = *proof.siblings.keys().next().unwrap();
+        proof.siblings.get_mut(&sibling_idx).unwrap().as_mut_bytes()[0] ^= 1;
+        assert!(proof.verify_region(&root).is_err());
+        let mut proof = image.prove_region(addr, 16).unwrap();
+        proof.siblings.remove(&sibling_idx);
+        assert!(proof.verify_region(&root).is_err());
+
+        // Regions within the page table cannot be proven.
+        assert!(image.prove_region(PAGE_TABLE.start() as u32, 16).is_err());
+
+        // So is verifying against a different root.
+        let proof = image.prove_region(addr, 16).unwrap();
+        assert!(proof.verify_region(&Digest::ZERO).is_err());
+
+        assert!(image.prove_region(u32::MAX, 2).is_err());
+    }
+
+    #[test]
+    fn verify_region_bad_page_size() {
+        let program = Program::load_elf(MULTI_TEST_ELF, GUEST_MAX_MEM as u32).unwrap();
+        let image = MemoryImage::new(&program, PAGE_SIZE as u32).unwrap();
+        let root = image.compute_root_hash().unwrap();
+        let proof = image.prove_region(TEXT_START, 16).unwrap();
+        assert!(proof.verify_region(&root).is_ok());
+
+        // A malformed page size must be rejected rather than hang or panic.
+        for page_size in [0, 16, 32, 2048] {
+            let mut proof = proof.clone();
+            proof.page_size = page_size;
+            assert!(proof.verify_region(&root).is_err());
+        }
+        for page_size in [0, 16, 32] {
+            assert!(PageTableInfo::new(PAGE_TABLE.start() as u32, page_size).is_err());
+        }
+    }
+
     #[test]
     fn page_table_info() {
         const PAGE_SIZE_1K: u32 = 1024;
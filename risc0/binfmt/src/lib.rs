@@ -16,6 +16,8 @@
 
 #![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
 
+#[cfg(not(target_os = "zkvm"))]
+mod builder;
 mod elf;
 mod hash;
 #[cfg(not(target_os = "zkvm"))]
@@ -23,7 +25,11 @@ mod image;
 mod sys_state;
 
 #[cfg(not(target_os = "zkvm"))]
-pub use crate::image::{compute_image_id, MemoryImage, PageTableInfo};
+pub use crate::builder::{ImageManifest, ManifestEntry, ManifestEntryKind, MemoryImageBuilder};
+#[cfg(not(target_os = "zkvm"))]
+pub use crate::image::{
+    compute_image_id, MemoryImage, MemoryImageCache, Page, PageTableInfo, RegionProof,
+};
 pub use crate::{
     elf::Program,
     hash::{tagged_list, tagged_list_cons, tagged_struct, Digestible},
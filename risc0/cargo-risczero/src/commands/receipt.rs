@@ -0,0 +1,90 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, Subcommand};
+use hex::FromHex;
+use risc0_zkvm::{sha::Digest, AnyReceipt, ReceiptFile, VerifierContext};
+
+/// `cargo risczero receipt`
+#[derive(Parser)]
+pub struct ReceiptCommand {
+    #[command(subcommand)]
+    command: ReceiptSubcommand,
+}
+
+#[derive(Subcommand)]
+enum ReceiptSubcommand {
+    /// Print the header of a receipt file and a summary of its receipt.
+    Inspect {
+        /// Path to the receipt file.
+        path: PathBuf,
+    },
+    /// Verify the receipt in a receipt file.
+    Verify {
+        /// Path to the receipt file.
+        path: PathBuf,
+
+        /// The expected image ID, as hex.
+        ///
+        /// This is required, since the image ID recorded in the header of the
+        /// file is chosen by whoever wrote it. Use `inspect` to print it.
+        #[arg(long)]
+        image_id: String,
+    },
+}
+
+impl ReceiptCommand {
+    /// Execute this command
+    pub fn run(&self) -> Result<()> {
+        match &self.command {
+            ReceiptSubcommand::Inspect { path } => inspect(&ReceiptFile::load(path)?),
+            ReceiptSubcommand::Verify { path, image_id } => {
+                let file = ReceiptFile::load(path)?;
+                let image_id = Digest::from_hex(image_id.trim_start_matches("0x"))
+                    .context("invalid image ID")?;
+                file.receipt
+                    .verify_with_context(&VerifierContext::default(), image_id)
+                    .map_err(|err| anyhow!("receipt verification failed: {err}"))?;
+                println!("Receipt verified for image ID {image_id}.");
+                Ok(())
+            }
+        }
+    }
+}
+
+fn inspect(file: &ReceiptFile) -> Result<()> {
+    let header = &file.header;
+    println!("format version: {}", file.format_version);
+    println!("kind:           {}", header.kind);
+    println!(
+        "hash function:  {}",
+        header.hashfn.as_deref().unwrap_or("n/a")
+    );
+    println!(
+        "zkvm version:   {}",
+        header.zkvm_version.as_deref().unwrap_or("unknown")
+    );
+    println!("image ID:       {}", header.image_id);
+    println!("claim digest:   {}", header.claim_digest);
+
+    let claim = file.receipt.get_claim()?;
+    println!("exit code:      {:?}", claim.exit_code);
+    if let AnyReceipt::Receipt(receipt) = &file.receipt {
+        println!("journal:        {} bytes", receipt.journal.bytes.len());
+    }
+    Ok(())
+}
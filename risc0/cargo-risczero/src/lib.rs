@@ -26,6 +26,8 @@ use clap::{Parser, Subcommand};
 
 #[cfg(feature = "experimental")]
 use self::commands::build::BuildCommand;
+#[cfg(feature = "receipt")]
+use self::commands::receipt::ReceiptCommand;
 use self::commands::{
     build_guest::BuildGuest, build_toolchain::BuildToolchain, install::Install, new::NewCommand,
 };
@@ -58,6 +60,9 @@ pub enum RisczeroCmd {
     Install(Install),
     /// Creates a new risczero starter project.
     New(NewCommand),
+    /// Inspect or verify a receipt file.
+    #[cfg(feature = "receipt")]
+    Receipt(ReceiptCommand),
     /// Build a crate for RISC Zero.
     #[cfg(feature = "experimental")]
     BuildCrate(BuildCommand),
@@ -22,7 +22,10 @@ use risc0_core::field::{
 use risc0_zkp::{
     core::log2_ceil,
     hal::{
-        cuda::{BufferImpl as CudaBuffer, CudaHal, CudaHash, CudaHashPoseidon, CudaHashSha256},
+        cuda::{
+            BufferImpl as CudaBuffer, CudaHal, CudaHash, CudaHashPoseidon, CudaHashPoseidon2,
+            CudaHashSha256,
+        },
         Buffer, CircuitHal, Hal,
     },
     INV_RATE,
@@ -111,6 +114,7 @@ impl<'a, CH: CudaHash> CircuitHal<CudaHal<CH>> for CudaCircuitHal<CH> {
 
 pub type CudaCircuitHalSha256 = CudaCircuitHal<CudaHashSha256>;
 pub type CudaCircuitHalPoseidon = CudaCircuitHal<CudaHashPoseidon>;
+pub type CudaCircuitHalPoseidon2 = CudaCircuitHal<CudaHashPoseidon2>;
 
 #[cfg(test)]
 mod tests {
@@ -118,8 +122,11 @@ mod tests {
 
     use risc0_core::field::baby_bear::BabyBear;
     use risc0_zkp::{
-        core::hash::sha::Sha256HashSuite,
-        hal::{cpu::CpuHal, cuda::CudaHalSha256},
+        core::hash::{poseidon2::Poseidon2HashSuite, sha::Sha256HashSuite},
+        hal::{
+            cpu::CpuHal,
+            cuda::{CudaHalPoseidon2, CudaHalSha256},
+        },
     };
     use test_log::test;
 
@@ -135,4 +142,15 @@ mod tests {
         let gpu_eval = super::CudaCircuitHal::new(gpu_hal.clone());
         crate::testutil::eval_check(&cpu_hal, cpu_eval, gpu_hal.as_ref(), gpu_eval, PO2);
     }
+
+    #[test]
+    fn eval_check_poseidon2() {
+        const PO2: usize = 4;
+        let circuit = crate::CircuitImpl::new();
+        let cpu_hal: CpuHal<BabyBear> = CpuHal::new(Poseidon2HashSuite::new_suite());
+        let cpu_eval = CpuCircuitHal::new(&circuit);
+        let gpu_hal = Rc::new(CudaHalPoseidon2::new());
+        let gpu_eval = super::CudaCircuitHalPoseidon2::new(gpu_hal.clone());
+        crate::testutil::eval_check(&cpu_hal, cpu_eval, gpu_hal.as_ref(), gpu_eval, PO2);
+    }
 }
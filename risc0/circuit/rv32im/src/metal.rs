@@ -97,8 +97,11 @@ mod tests {
 
     use risc0_core::field::baby_bear::BabyBear;
     use risc0_zkp::{
-        core::hash::sha::Sha256HashSuite,
-        hal::{cpu::CpuHal, metal::MetalHalSha256},
+        core::hash::{poseidon2::Poseidon2HashSuite, sha::Sha256HashSuite},
+        hal::{
+            cpu::CpuHal,
+            metal::{MetalHalPoseidon2, MetalHalSha256},
+        },
     };
     use test_log::test;
 
@@ -118,6 +121,18 @@ mod tests {
         crate::testutil::eval_check(&cpu_hal, cpu_eval, gpu_hal.as_ref(), gpu_eval, PO2);
     }
 
+    #[test]
+    #[ignore]
+    fn eval_check_poseidon2() {
+        const PO2: usize = 4;
+        let circuit = crate::CircuitImpl::new();
+        let cpu_hal = CpuHal::new(Poseidon2HashSuite::new_suite());
+        let cpu_eval = CpuCircuitHal::new(&circuit);
+        let gpu_hal = Rc::new(MetalHalPoseidon2::new());
+        let gpu_eval = super::MetalCircuitHal::new(gpu_hal.clone());
+        crate::testutil::eval_check(&cpu_hal, cpu_eval, gpu_hal.as_ref(), gpu_eval, PO2);
+    }
+
     #[test]
     #[ignore]
     fn memory_usage() {
@@ -153,6 +153,10 @@ impl field::Elem for Elem {
     fn is_valid(&self) -> bool {
         self.0 != Self::INVALID.0
     }
+
+    fn is_reduced(&self) -> bool {
+        self.0 < P
+    }
 }
 
 macro_rules! rou_array {
@@ -484,6 +488,10 @@ impl field::Elem for ExtElem {
     fn is_valid(&self) -> bool {
         self.0[0].is_valid()
     }
+
+    fn is_reduced(&self) -> bool {
+        self.0.iter().all(|x| x.is_reduced())
+    }
 }
 
 impl field::ExtElem for ExtElem {
@@ -105,6 +105,13 @@ pub trait Elem:
     /// methods, this may be called on an INVALID element.
     fn is_valid(&self) -> bool;
 
+    /// Returns true if this element is in its canonical, fully reduced
+    /// form.  Elements read from untrusted sources (such as a seal)
+    /// must be checked with this before any arithmetic is done on
+    /// them.  Unlike most methods, this may be called on an INVALID
+    /// element.
+    fn is_reduced(&self) -> bool;
+
     /// Returns 0 if this element is INVALID, else the value of this
     /// element.  Unlike most methods, this may be called on an
     /// INVALID element.
@@ -12,12 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{fs, io, path::PathBuf, rc::Rc};
+use std::{fs, io, net::TcpListener, path::PathBuf, rc::Rc};
 
 use clap::{Args, Parser, ValueEnum};
 use risc0_zkvm::{
-    get_prover_server, ApiServer, ExecutorEnv, ExecutorImpl, ProverOpts, ProverServer,
-    VerifierContext,
+    estimate, get_prover_server, ApiServer, EstimateOpts, ExecutorEnv, ExecutorImpl, ProfileFormat,
+    ProveHandle, ProverOpts, ProverServer, ProvingModel, ReceiptFile, ReceiptKind, VerifierContext,
 };
 
 /// Runs a RISC-V ELF binary within the RISC Zero ZKVM.
@@ -44,6 +44,10 @@ struct Cli {
     #[arg(long)]
     prove_guest_errors: bool,
 
+    /// The kind of receipt to produce.
+    #[arg(long, value_enum, default_value_t = ReceiptKindArg::Composite)]
+    receipt_kind: ReceiptKindArg,
+
     /// File to read initial input from.
     ///
     /// Reads input from stdin if an initial input file is not provided.
@@ -63,6 +67,43 @@ struct Cli {
     /// to read it.
     #[arg(long, env = "RISC0_PPROF_OUT")]
     pprof_out: Option<PathBuf>,
+
+    /// The format of the profile written to `--pprof-out`.
+    ///
+    /// Besides pprof, the profile can be written as folded stacks for
+    /// `inferno` or `flamegraph.pl`, as an SVG flamegraph, or as a table of
+    /// the cycles spent in each function and source line.
+    #[arg(long, value_enum, default_value_t = ProfileFormatArg::Pprof)]
+    profile_format: ProfileFormatArg,
+
+    /// Wait for a GDB client to connect on this port and run the guest under
+    /// its control, using the GDB remote serial protocol.
+    ///
+    /// The execution is not proven in this mode.
+    #[arg(long, conflicts_with = "port")]
+    gdb: Option<u16>,
+
+    /// Estimate the number of segments and cycles instead of proving the
+    /// execution.
+    ///
+    /// The proof size and the proving time are also estimated if a proving
+    /// model is given with `--estimate-model`.
+    #[arg(long, conflicts_with_all = ["port", "gdb"])]
+    estimate: bool,
+
+    /// Stop executing after this many cycles when estimating.
+    #[arg(long, requires = "estimate")]
+    estimate_cycle_limit: Option<u64>,
+
+    /// The number of user cycles expected, used to extrapolate the estimate
+    /// when execution is stopped by `--estimate-cycle-limit`.
+    #[arg(long, requires = "estimate_cycle_limit")]
+    estimate_user_cycles: Option<u64>,
+
+    /// A JSON file containing a proving model calibrated with the
+    /// `risc0-benchmark` crate.
+    #[arg(long, requires = "estimate")]
+    estimate_model: Option<PathBuf>,
 }
 
 #[derive(Args)]
@@ -86,6 +127,30 @@ enum HashFn {
     Sha256,
     #[value(name = "poseidon")]
     Poseidon,
+    #[value(name = "poseidon2")]
+    Poseidon2,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum ReceiptKindArg {
+    #[value(name = "composite")]
+    Composite,
+    #[value(name = "succinct")]
+    Succinct,
+    #[value(name = "compact")]
+    Compact,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum ProfileFormatArg {
+    #[value(name = "pprof")]
+    Pprof,
+    #[value(name = "folded")]
+    Folded,
+    #[value(name = "flamegraph")]
+    Flamegraph,
+    #[value(name = "table")]
+    Table,
 }
 
 pub fn main() {
@@ -118,33 +183,51 @@ pub fn main() {
 
         if let Some(pprof_out) = args.pprof_out.as_ref() {
             builder.enable_profiler(pprof_out);
+            builder.profile_format(args.get_profile_format());
         }
 
         builder.build().unwrap()
     };
 
-    let session = {
-        let mut exec = if let Some(ref elf_path) = args.mode.elf {
-            let elf_contents = fs::read(elf_path).unwrap();
-            ExecutorImpl::from_elf(env, &elf_contents).unwrap()
-        } else if let Some(ref image_path) = args.mode.image {
-            let image_contents = fs::read(image_path).unwrap();
-            let image = bincode::deserialize(&image_contents).unwrap();
-            ExecutorImpl::new(env, image).unwrap()
-        } else {
-            unreachable!()
-        };
-        exec.run().unwrap()
+    if args.estimate {
+        let elf_path = args
+            .mode
+            .elf
+            .as_ref()
+            .expect("--estimate requires an ELF to be specified with --elf");
+        let elf_contents = fs::read(elf_path).unwrap();
+        run_estimate(&args, env, &elf_contents);
+        return;
+    }
+
+    let mut exec = if let Some(ref elf_path) = args.mode.elf {
+        let elf_contents = fs::read(elf_path).unwrap();
+        ExecutorImpl::from_elf(env, &elf_contents).unwrap()
+    } else if let Some(ref image_path) = args.mode.image {
+        let image_contents = fs::read(image_path).unwrap();
+        let image = bincode::deserialize(&image_contents).unwrap();
+        ExecutorImpl::new(env, image).unwrap()
+    } else {
+        unreachable!()
     };
 
+    if let Some(port) = args.gdb {
+        run_gdb(&mut exec, port);
+        return;
+    }
+    let session = exec.run().unwrap();
+
     let prover = args.get_prover();
     let ctx = VerifierContext::default();
-    let receipt = prover.prove_session(&ctx, &session).unwrap();
+    let handle = ProveHandle::default();
+    let receipt = prover.prove_session(&ctx, &session, &handle).unwrap();
+    let receipt = prover
+        .compress(&ctx, &args.get_prover_opts(), &receipt, &handle)
+        .unwrap();
 
-    let receipt_data = bincode::serialize(&receipt).unwrap();
-    let receipt_bytes = bytemuck::cast_slice(&receipt_data);
+    let receipt_data = ReceiptFile::new(receipt).unwrap().encode().unwrap();
     if let Some(receipt_file) = args.receipt.as_ref() {
-        fs::write(receipt_file, receipt_bytes).expect("Unable to write receipt file");
+        fs::write(receipt_file, &receipt_data).expect("Unable to write receipt file");
         if args.verbose > 0 {
             eprintln!(
                 "Wrote {} bytes of receipt to {}",
@@ -156,17 +239,72 @@ pub fn main() {
 }
 
 impl Cli {
-    fn get_prover(&self) -> Rc<dyn ProverServer> {
+    fn get_prover_opts(&self) -> ProverOpts {
         let hashfn = match self.hashfn {
             HashFn::Sha256 => "sha-256",
             HashFn::Poseidon => "poseidon",
+            HashFn::Poseidon2 => "poseidon2",
+        };
+        let receipt_kind = match self.receipt_kind {
+            ReceiptKindArg::Composite => ReceiptKind::Composite,
+            ReceiptKindArg::Succinct => ReceiptKind::Succinct,
+            ReceiptKindArg::Compact => ReceiptKind::Compact,
         };
-        let opts = ProverOpts {
+        ProverOpts {
             hashfn: hashfn.to_string(),
             prove_guest_errors: self.prove_guest_errors,
-        };
+            receipt_kind,
+            ..Default::default()
+        }
+    }
+
+    fn get_profile_format(&self) -> ProfileFormat {
+        match self.profile_format {
+            ProfileFormatArg::Pprof => ProfileFormat::Pprof,
+            ProfileFormatArg::Folded => ProfileFormat::Folded,
+            ProfileFormatArg::Flamegraph => ProfileFormat::Flamegraph,
+            ProfileFormatArg::Table => ProfileFormat::Table,
+        }
+    }
+
+    fn get_prover(&self) -> Rc<dyn ProverServer> {
+        get_prover_server(&self.get_prover_opts()).unwrap()
+    }
+}
 
-        get_prover_server(&opts).unwrap()
+fn run_gdb(exec: &mut ExecutorImpl, port: u16) {
+    let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+    eprintln!("Waiting for GDB to connect on 127.0.0.1:{port}");
+    let (stream, addr) = listener.accept().unwrap();
+    eprintln!("GDB connected from {addr}");
+    let session = exec.run_with_gdb(stream).unwrap();
+    eprintln!("Execution ended with {:?}", session.exit_code);
+}
+
+fn run_estimate(args: &Cli, env: ExecutorEnv, elf: &[u8]) {
+    let model: Option<ProvingModel> = args
+        .estimate_model
+        .as_ref()
+        .map(|path| serde_json::from_slice(&fs::read(path).unwrap()).unwrap());
+    let opts = EstimateOpts {
+        cycle_limit: args.estimate_cycle_limit,
+        expected_user_cycles: args.estimate_user_cycles,
+        model,
+    };
+    let estimate = estimate(env, elf, &opts).unwrap();
+    println!("complete:      {}", estimate.complete);
+    println!("segments:      {}", estimate.segments);
+    println!("user_cycles:   {}", estimate.user_cycles);
+    println!("total_cycles:  {}", estimate.total_cycles);
+    if let Some(model) = opts.model {
+        println!("hashfn:        {}", model.hashfn);
+        println!("hal:           {}", model.hal);
+    }
+    if let Some(proof_bytes) = estimate.proof_bytes {
+        println!("proof_bytes:   {proof_bytes}");
+    }
+    if let Some(proving_time) = estimate.proving_time {
+        println!("proving_time:  {proving_time:?}");
     }
 }
 
@@ -14,7 +14,7 @@
 
 use assert_cmd::Command;
 use assert_fs::{fixture::PathChild, TempDir};
-use risc0_zkvm::{serde::to_vec, Receipt};
+use risc0_zkvm::{serde::to_vec, AnyReceipt, Receipt, ReceiptFile};
 use risc0_zkvm_methods::{multi_test::MultiTestSpec, MULTI_TEST_PATH};
 
 fn run_dev_mode() -> Receipt {
@@ -32,8 +32,10 @@ fn run_dev_mode() -> Receipt {
 
     cmd.assert().success();
 
-    let data = std::fs::read(receipt_file).unwrap();
-    bincode::deserialize(&data).unwrap()
+    match ReceiptFile::load(&*receipt_file).unwrap().receipt {
+        AnyReceipt::Receipt(receipt) => receipt,
+        _ => panic!("expected a receipt"),
+    }
 }
 
 #[test]
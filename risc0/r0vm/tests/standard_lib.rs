@@ -32,8 +32,7 @@ fn load_receipt(path: &Path) -> Receipt {
     bincode::deserialize(&data).unwrap()
 }
 
-#[test]
-fn stdio_outputs_in_receipt() {
+fn prove_stdio(hashfn: &str) -> Receipt {
     let temp = TempDir::new().unwrap();
     let receipt_file = temp.child("receipt.dat");
 
@@ -43,6 +42,8 @@ fn stdio_outputs_in_receipt() {
         .arg(risc0_zkvm_methods::STANDARD_LIB_PATH)
         .arg("--receipt")
         .arg(&*receipt_file)
+        .arg("--hashfn")
+        .arg(hashfn)
         .arg("--env")
         .arg("TEST_MODE=STDIO")
         .write_stdin(STDIN_MSG);
@@ -52,9 +53,41 @@ fn stdio_outputs_in_receipt() {
         .stdout(expected_stdout())
         .success();
 
-    let receipt = load_receipt(&receipt_file);
+    load_receipt(&receipt_file)
+}
+
+#[test]
+fn stdio_outputs_in_receipt() {
+    let receipt = prove_stdio("poseidon");
     let segments = &receipt.inner.composite().unwrap().segments;
     assert_eq!(segments.len(), 1);
     assert!(segments[0].get_seal_bytes().len() > 0);
     receipt.verify(STANDARD_LIB_ID).unwrap();
 }
+
+#[test]
+fn hashfn_poseidon2() {
+    let receipt = prove_stdio("poseidon2");
+    let segments = &receipt.inner.composite().unwrap().segments;
+    assert_eq!(segments.len(), 1);
+    assert_eq!(segments[0].hashfn, "poseidon2");
+    receipt.verify(STANDARD_LIB_ID).unwrap();
+}
+
+#[test]
+fn estimate() {
+    let mut cmd = Command::cargo_bin("r0vm").unwrap();
+
+    cmd.arg("--elf")
+        .arg(risc0_zkvm_methods::STANDARD_LIB_PATH)
+        .arg("--estimate")
+        .arg("--env")
+        .arg("TEST_MODE=STDIO")
+        .write_stdin(STDIN_MSG);
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.starts_with(&expected_stdout()));
+    assert!(stdout.contains("complete:      true\n"));
+    assert!(stdout.contains("segments:      1\n"));
+}
@@ -0,0 +1,28 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verifies arbitrary words as the seal of a segment receipt, proven with an
+//! arbitrary number of FRI queries.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use risc0_verifier::{verify_segment_seal, FriParams, VerifierContext};
+
+fuzz_target!(|data: (u8, u16, Vec<u32>)| {
+    let (hashfn, queries, seal) = data;
+    let hashfn = ["sha-256", "poseidon", "poseidon2", "blake2b"][hashfn as usize % 4];
+    let params = FriParams::new(queries as usize);
+    let _ = verify_segment_seal(&VerifierContext::default(), hashfn, params, &seal);
+});
@@ -0,0 +1,24 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verifies arbitrary words as the seal of a succinct receipt.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use risc0_verifier::{verify_succinct_seal, VerifierContext};
+
+fuzz_target!(|seal: Vec<u32>| {
+    let _ = verify_succinct_seal(&VerifierContext::default(), &seal);
+});
@@ -22,25 +22,23 @@
 use alloc::{collections::VecDeque, vec::Vec};
 use core::{fmt, ops::Deref};
 
+#[cfg(not(target_os = "zkvm"))]
 use anyhow::{anyhow, ensure};
-use risc0_binfmt::{
-    read_sha_halfs, tagged_list, tagged_list_cons, tagged_struct, write_sha_halfs, Digestible,
-};
+use risc0_binfmt::{read_sha_halfs, tagged_list, tagged_struct, Digestible, SystemState};
+#[cfg(not(target_os = "zkvm"))]
+use risc0_binfmt::{tagged_list_cons, write_sha_halfs};
+#[cfg(not(target_os = "zkvm"))]
+use risc0_zkp::core::hash::sha::cpu::Impl;
+use risc0_zkp::core::{digest::Digest, hash::sha::Sha256};
 use serde::{Deserialize, Serialize};
 
-use crate::{
-    sha::{self, Digest, Sha256},
-    SystemState,
-};
-
 /// Public claims about a zkVM guest execution, such as the journal committed to by the guest.
 ///
 /// Also includes important information such as the exit code and the starting and ending system
 /// state (i.e. the state of memory). [ReceiptClaim] is a "Merkle-ized struct" supporting
 /// partial openings of the underlying fields from a hash commitment to the full structure. Also
 /// see [MaybePruned].
-#[derive(Clone, Debug, Serialize, Deserialize)]
-#[cfg_attr(test, derive(PartialEq))]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct ReceiptClaim {
     /// The [SystemState] of a segment just before execution has begun.
     pub pre: MaybePruned<SystemState>,
@@ -95,6 +93,7 @@ impl ReceiptClaim {
     }
 
     /// Encode a [ReceiptClaim] to a list of [u32]'s
+    #[cfg(not(target_os = "zkvm"))]
     pub fn encode(&self, flat: &mut Vec<u32>) -> Result<(), PrunedValueError> {
         write_sha_halfs(flat, &self.input);
         self.pre.as_value()?.encode(flat);
@@ -102,7 +101,7 @@ impl ReceiptClaim {
         let (sys_exit, user_exit) = self.exit_code.into_pair();
         flat.push(sys_exit);
         flat.push(user_exit);
-        write_sha_halfs(flat, &self.output.digest::<sha::Impl>());
+        write_sha_halfs(flat, &self.output.digest::<Impl>());
         Ok(())
     }
 }
@@ -189,7 +188,9 @@ pub enum ExitCode {
 }
 
 impl ExitCode {
-    pub(crate) fn into_pair(self) -> (u32, u32) {
+    /// Convert this exit code into the (system, user) pair committed to by the
+    /// rv32im circuit.
+    pub fn into_pair(self) -> (u32, u32) {
         match self {
             ExitCode::Halted(user_exit) => (0, user_exit),
             ExitCode::Paused(user_exit) => (1, user_exit),
@@ -202,10 +203,9 @@ impl ExitCode {
         }
     }
 
-    pub(crate) fn from_pair(
-        sys_exit: u32,
-        user_exit: u32,
-    ) -> Result<ExitCode, InvalidExitCodeError> {
+    /// Convert a (system, user) pair committed to by the rv32im circuit into an
+    /// exit code.
+    pub fn from_pair(sys_exit: u32, user_exit: u32) -> Result<ExitCode, InvalidExitCodeError> {
         match sys_exit {
             0 => Ok(ExitCode::Halted(user_exit)),
             1 => Ok(ExitCode::Paused(user_exit)),
@@ -214,8 +214,9 @@ impl ExitCode {
         }
     }
 
-    #[cfg(not(target_os = "zkvm"))]
-    pub(crate) fn expects_output(&self) -> bool {
+    /// Returns true if an execution with this exit code commits to an
+    /// [Output].
+    pub fn expects_output(&self) -> bool {
         match self {
             ExitCode::Halted(_) | ExitCode::Paused(_) => true,
             ExitCode::SystemSplit | ExitCode::SessionLimit | ExitCode::Fault => false,
@@ -240,8 +241,7 @@ impl fmt::Display for InvalidExitCodeError {
 impl std::error::Error for InvalidExitCodeError {}
 
 /// Output field in the [ReceiptClaim], committing to a claimed journal and assumptions list.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-#[cfg_attr(test, derive(PartialEq))]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Output {
     /// The journal committed to by the guest execution.
     pub journal: MaybePruned<Vec<u8>>,
@@ -269,8 +269,7 @@ impl Digestible for Output {
 }
 
 /// A list of assumptions, each a [Digest] of a [ReceiptClaim].
-#[derive(Clone, Default, Debug, Serialize, Deserialize)]
-#[cfg_attr(test, derive(PartialEq))]
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Assumptions(pub Vec<MaybePruned<ReceiptClaim>>);
 
 impl Assumptions {
@@ -282,6 +281,7 @@ impl Assumptions {
     /// Mark an assumption as resolved and remove it from the list.
     ///
     /// Assumptions can only be removed from the head of the list.
+    #[cfg(not(target_os = "zkvm"))]
     pub fn resolve(&mut self, resolved: &Digest) -> anyhow::Result<()> {
         let head = self
             .0
@@ -289,10 +289,10 @@ impl Assumptions {
             .ok_or_else(|| anyhow!("cannot resolve assumption from empty list"))?;
 
         ensure!(
-            &head.digest::<sha::Impl>() == resolved,
+            &head.digest::<Impl>() == resolved,
             "resolved assumption is not equal to the head of the list: {} != {}",
             resolved,
-            head.digest::<sha::Impl>()
+            head.digest::<Impl>()
         );
 
         // Drop the head of the assumptions list.
@@ -331,13 +331,14 @@ impl MaybePruned<Assumptions> {
     /// Add an assumption to the head of the assumptions list.
     ///
     /// If this value is pruned, then the result will also be a pruned value.
+    #[cfg(not(target_os = "zkvm"))]
     pub fn add(&mut self, assumption: MaybePruned<ReceiptClaim>) {
         match self {
             MaybePruned::Value(list) => list.add(assumption),
             MaybePruned::Pruned(list_digest) => {
-                *list_digest = tagged_list_cons::<sha::Impl>(
+                *list_digest = tagged_list_cons::<Impl>(
                     "risc0.Assumptions",
-                    &assumption.digest::<sha::Impl>(),
+                    &assumption.digest::<Impl>(),
                     &*list_digest,
                 );
             }
@@ -350,12 +351,12 @@ impl MaybePruned<Assumptions> {
     /// is pruned, then the result will also be a pruned value. The `tail`
     /// parameter should be equal to the digest of the list after the
     /// resolved assumption is removed.
+    #[cfg(not(target_os = "zkvm"))]
     pub fn resolve(&mut self, resolved: &Digest, tail: &Digest) -> anyhow::Result<()> {
         match self {
             MaybePruned::Value(list) => list.resolve(resolved),
             MaybePruned::Pruned(list_digest) => {
-                let reconstructed =
-                    tagged_list_cons::<sha::Impl>("risc0.Assumptions", resolved, tail);
+                let reconstructed = tagged_list_cons::<Impl>("risc0.Assumptions", resolved, tail);
                 ensure!(
                     &reconstructed == list_digest,
                     "reconstructed list digest does not match; expected {}, reconstructed {}",
@@ -470,7 +471,6 @@ where
     }
 }
 
-#[cfg(test)]
 impl<T> PartialEq for MaybePruned<T>
 where
     T: Clone + Serialize + PartialEq,
@@ -496,9 +496,15 @@ where
         if let MaybePruned::Value(value) = self {
             builder.field("value", value);
         }
-        builder
-            .field("digest", &self.digest::<sha::Impl>())
-            .finish()
+        // Within the zkVM, there is no hash implementation to compute the digest
+        // of an unpruned value with.
+        #[cfg(not(target_os = "zkvm"))]
+        builder.field("digest", &self.digest::<Impl>());
+        #[cfg(target_os = "zkvm")]
+        if let MaybePruned::Pruned(digest) = self {
+            builder.field("digest", digest);
+        }
+        builder.finish()
     }
 }
 
@@ -514,263 +520,3 @@ impl fmt::Display for PrunedValueError {
 
 #[cfg(feature = "std")]
 impl std::error::Error for PrunedValueError {}
-
-/// Merge two structured containing [MaybePruned] fields to produce a resulting structure with
-/// populated fields equal to the union of the two.
-///
-/// Viewing the two structs as Merkle trees, in which subtrees may be pruned, the result of this
-/// operation is a tree with a set of nodes equal to the union of the set of nodes for each input.
-pub(crate) trait Merge: Digestible + Sized {
-    /// Merge two structs to produce an output with a union of the fields populated in the inputs.
-    fn merge(&self, other: &Self) -> Result<Self, MergeInequalityError>;
-}
-
-/// Error returned when a merge it attempted with two values with unequal digests.
-#[derive(Debug, Clone)]
-pub(crate) struct MergeInequalityError(pub Digest, pub Digest);
-
-impl fmt::Display for MergeInequalityError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "cannot merge values; left and right are not diegst equal: left {}, right {}",
-            hex::encode(&self.0),
-            hex::encode(&self.1)
-        )
-    }
-}
-
-#[cfg(feature = "std")]
-impl std::error::Error for MergeInequalityError {}
-
-/// Private marker trait providing an implementation of merge to values which implement PartialEq and clone and do not contain Merge fields.
-trait MergeLeaf: Digestible + PartialEq + Clone + Sized {}
-
-impl MergeLeaf for SystemState {}
-impl MergeLeaf for Vec<u8> {}
-
-impl<T: MergeLeaf> Merge for T {
-    fn merge(&self, other: &Self) -> Result<Self, MergeInequalityError> {
-        if self != other {
-            return Err(MergeInequalityError(
-                self.digest::<sha::Impl>(),
-                other.digest::<sha::Impl>(),
-            ));
-        }
-
-        Ok(self.clone())
-    }
-}
-
-impl<T> Merge for MaybePruned<T>
-where
-    T: Merge + Serialize + Clone,
-{
-    fn merge(&self, other: &Self) -> Result<Self, MergeInequalityError> {
-        let check_eq = || {
-            if self.digest::<sha::Impl>() != other.digest::<sha::Impl>() {
-                Err(MergeInequalityError(
-                    self.digest::<sha::Impl>(),
-                    other.digest::<sha::Impl>(),
-                ))
-            } else {
-                Ok(())
-            }
-        };
-
-        Ok(match (self, other) {
-            (MaybePruned::Value(left), MaybePruned::Value(right)) => {
-                MaybePruned::Value(left.merge(&right)?)
-            }
-            (MaybePruned::Value(_), MaybePruned::Pruned(_)) => {
-                check_eq()?;
-                self.clone()
-            }
-            (MaybePruned::Pruned(_), MaybePruned::Value(_)) => {
-                check_eq()?;
-                other.clone()
-            }
-            (MaybePruned::Pruned(_), MaybePruned::Pruned(_)) => {
-                check_eq()?;
-                self.clone()
-            }
-        })
-    }
-}
-
-impl Merge for Assumptions {
-    fn merge(&self, other: &Self) -> Result<Self, MergeInequalityError> {
-        if self.0.len() != other.0.len() {
-            return Err(MergeInequalityError(
-                self.digest::<sha::Impl>(),
-                other.digest::<sha::Impl>(),
-            ));
-        }
-        Ok(Assumptions(
-            self.0
-                .iter()
-                .zip(other.0.iter())
-                .map(|(left, right)| left.merge(right))
-                .collect::<Result<Vec<_>, _>>()?,
-        ))
-    }
-}
-
-impl Merge for Output {
-    fn merge(&self, other: &Self) -> Result<Self, MergeInequalityError> {
-        Ok(Self {
-            journal: self.journal.merge(&other.journal)?,
-            assumptions: self.assumptions.merge(&other.assumptions)?,
-        })
-    }
-}
-
-impl Merge for Option<Output> {
-    fn merge(&self, other: &Self) -> Result<Self, MergeInequalityError> {
-        match (self, other) {
-            (Some(left), Some(right)) => Some(left.merge(right)).transpose(),
-            (None, None) => Ok(None),
-            _ => Err(MergeInequalityError(
-                self.digest::<sha::Impl>(),
-                other.digest::<sha::Impl>(),
-            )),
-        }
-    }
-}
-
-impl Merge for ReceiptClaim {
-    fn merge(&self, other: &Self) -> Result<Self, MergeInequalityError> {
-        if self.exit_code != other.exit_code || self.input != other.input {
-            return Err(MergeInequalityError(
-                self.digest::<sha::Impl>(),
-                other.digest::<sha::Impl>(),
-            ));
-        }
-        Ok(Self {
-            pre: self.pre.merge(&other.pre)?,
-            post: self.post.merge(&other.post)?,
-            exit_code: self.exit_code,
-            input: self.input,
-            output: self.output.merge(&other.output)?,
-        })
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use hex::FromHex;
-
-    use super::{Assumptions, ExitCode, MaybePruned, Merge, Output, ReceiptClaim, SystemState};
-    use crate::sha::{Digest, Digestible};
-
-    /// Testing utility for randomly pruning structs.
-    trait RandPrune {
-        fn rand_prune(&self) -> Self;
-    }
-
-    impl RandPrune for MaybePruned<ReceiptClaim> {
-        fn rand_prune(&self) -> Self {
-            match (self, rand::random::<bool>()) {
-                (Self::Value(x), true) => Self::Pruned(x.digest()),
-                (Self::Value(x), false) => ReceiptClaim {
-                    pre: x.pre.rand_prune(),
-                    post: x.post.rand_prune(),
-                    exit_code: x.exit_code,
-                    input: x.input,
-                    output: x.output.rand_prune(),
-                }
-                .into(),
-                (Self::Pruned(x), _) => Self::Pruned(x.clone()),
-            }
-        }
-    }
-
-    impl RandPrune for MaybePruned<SystemState> {
-        fn rand_prune(&self) -> Self {
-            match (self, rand::random::<bool>()) {
-                (Self::Value(x), true) => Self::Pruned(x.digest()),
-                (Self::Value(x), false) => SystemState {
-                    pc: x.pc,
-                    merkle_root: x.merkle_root,
-                }
-                .into(),
-                (Self::Pruned(x), _) => Self::Pruned(x.clone()),
-            }
-        }
-    }
-
-    impl RandPrune for MaybePruned<Option<Output>> {
-        fn rand_prune(&self) -> Self {
-            match (self, rand::random::<bool>()) {
-                (Self::Value(x), true) => Self::Pruned(x.digest()),
-                (Self::Value(x), false) => x
-                    .as_ref()
-                    .map(|o| Output {
-                        journal: o.journal.rand_prune(),
-                        assumptions: o.assumptions.rand_prune(),
-                    })
-                    .into(),
-                (Self::Pruned(x), _) => Self::Pruned(x.clone()),
-            }
-        }
-    }
-
-    impl RandPrune for MaybePruned<Vec<u8>> {
-        fn rand_prune(&self) -> Self {
-            match (self, rand::random::<bool>()) {
-                (Self::Value(x), true) => Self::Pruned(x.digest()),
-                (Self::Value(x), false) => x.clone().into(),
-                (Self::Pruned(x), _) => Self::Pruned(x.clone()),
-            }
-        }
-    }
-
-    impl RandPrune for MaybePruned<Assumptions> {
-        fn rand_prune(&self) -> Self {
-            match (self, rand::random::<bool>()) {
-                (Self::Value(x), true) => Self::Pruned(x.digest()),
-                (Self::Value(x), false) => x.clone().into(),
-                (Self::Pruned(x), _) => Self::Pruned(x.clone()),
-            }
-        }
-    }
-
-    #[test]
-    fn merge_receipt_claim() {
-        let claim = MaybePruned::Value(ReceiptClaim {
-            pre: SystemState {
-                pc: 2100484,
-                merkle_root: Digest::from_hex(
-                    "9095da07d84ccc170c5113e3dafdf0531700f0b3f0c627acc9f0329440d984fa",
-                )
-                .unwrap(),
-            }
-            .into(),
-            post: SystemState {
-                pc: 2297164,
-                merkle_root: Digest::from_hex(
-                    "223651656250c0cf2f1c3f8923ef3d2c8624a361830492ffec6450e1930fb07d",
-                )
-                .unwrap(),
-            }
-            .into(),
-            exit_code: ExitCode::Halted(0),
-            input: Digest::ZERO,
-            output: MaybePruned::Value(Some(Output {
-                journal: MaybePruned::Value(b"hello world".to_vec()),
-                assumptions: MaybePruned::Value(Assumptions(vec![
-                    MaybePruned::Pruned(Digest::ZERO),
-                    MaybePruned::Pruned(Digest::ZERO),
-                ])),
-            })),
-        });
-
-        // Run the test to 10k times to reach every combination with high probability.
-        for _ in 0..10000 {
-            let left = claim.rand_prune();
-            let right = claim.rand_prune();
-
-            assert_eq!(left.merge(&right).unwrap().digest(), claim.digest());
-        }
-    }
-}
@@ -12,6 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! Verification of the Groth16 proofs over BN254 that wrap a RISC Zero
+//! receipt.
+
 use alloc::{format, vec, vec::Vec};
 use core::str::FromStr;
 
@@ -23,10 +26,10 @@ use ark_groth16::{
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use hex::FromHex;
 use num_bigint::BigInt;
+use risc0_circuit_recursion::control_id::ALLOWED_IDS_ROOT;
+use risc0_zkp::core::digest::Digest;
 use serde::{Deserialize, Serialize};
 
-use crate::{sha::Digest, ALLOWED_IDS_ROOT};
-
 const ALPHA_X: &str =
     "20491192805390485299153009773594534940189261866228447918068658471970481763042";
 const ALPHA_Y: &str =
@@ -114,32 +117,15 @@ impl Groth16Seal {
             bail!("Data length mismatch");
         }
 
-        let mut offset = 0;
-        let mut a = Vec::with_capacity(2);
-        let mut b = Vec::with_capacity(2);
-        let mut c = Vec::with_capacity(2);
+        // The length check above guarantees exactly eight elements.
+        let mut elems = data
+            .chunks_exact(Self::ELEMENT_SIZE)
+            .map(|elem| elem.to_vec());
+        let mut next = || elems.next().ok_or_else(|| anyhow!("Data length mismatch"));
 
-        // Deserialize 'a'
-        for _ in 0..2 {
-            a.push(data[offset..offset + Self::ELEMENT_SIZE].to_vec());
-            offset += Self::ELEMENT_SIZE;
-        }
-
-        // Deserialize 'b'
-        for _ in 0..2 {
-            let mut sub_vec = Vec::with_capacity(2);
-            for _ in 0..2 {
-                sub_vec.push(data[offset..offset + Self::ELEMENT_SIZE].to_vec());
-                offset += Self::ELEMENT_SIZE;
-            }
-            b.push(sub_vec);
-        }
-
-        // Deserialize 'c'
-        for _ in 0..2 {
-            c.push(data[offset..offset + Self::ELEMENT_SIZE].to_vec());
-            offset += Self::ELEMENT_SIZE;
-        }
+        let a = vec![next()?, next()?];
+        let b = vec![vec![next()?, next()?], vec![next()?, next()?]];
+        let c = vec![next()?, next()?];
 
         Ok(Groth16Seal { a, b, c })
     }
@@ -250,7 +236,7 @@ fn fr_from_bytes(scalar: &Vec<u8>) -> Result<Fr, Error> {
 
 // Deserialize an element over the G1 group from bytes in big-endian format
 fn g1_from_bytes(elem: &[Vec<u8>]) -> Result<G1Affine, Error> {
-    if elem.len() != 2 {
+    if elem.len() != 2 || elem.iter().any(|x| x.len() != Groth16Seal::ELEMENT_SIZE) {
         bail!("Malformed G1 field element");
     }
     let g1_affine: Vec<u8> = elem[0]
@@ -265,7 +251,13 @@ fn g1_from_bytes(elem: &[Vec<u8>]) -> Result<G1Affine, Error> {
 
 // Deserialize an element over the G2 group from bytes in big-endian format
 fn g2_from_bytes(elem: &Vec<Vec<Vec<u8>>>) -> Result<G2Affine, Error> {
-    if elem.len() != 2 || elem[0].len() != 2 || elem[1].len() != 2 {
+    if elem.len() != 2
+        || elem.iter().any(|x| x.len() != 2)
+        || elem
+            .iter()
+            .flatten()
+            .any(|x| x.len() != Groth16Seal::ELEMENT_SIZE)
+    {
         bail!("Malformed G2 field element");
     }
     let g2_affine: Vec<u8> = elem[0][1]
@@ -346,4 +338,21 @@ mod tests {
                 .unwrap();
         groth16.verify().unwrap();
     }
+
+    #[test]
+    fn malformed_seal() {
+        let seal: Groth16Seal = serde_json::from_str(RISC0_GROTH16_SEAL).unwrap();
+        let bytes = seal.to_vec();
+        assert_eq!(Groth16Seal::from_vec(&bytes).unwrap(), seal);
+        assert!(Groth16Seal::from_vec(&bytes[1..]).is_err());
+        assert!(Groth16Seal::from_vec(&[bytes.as_slice(), &[0]].concat()).is_err());
+
+        let claim = Digest::from_hex(RISC0_RECEIPT_CLAIM_DIGEST).unwrap();
+        let mut short = seal.clone();
+        short.a[0].pop();
+        assert!(Groth16Proof::from_seal(&short, claim).is_err());
+        let mut missing = seal;
+        missing.b[1].pop();
+        assert!(Groth16Proof::from_seal(&missing, claim).is_err());
+    }
 }
@@ -0,0 +1,64 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![doc = include_str!("../README.md")]
+#![cfg_attr(not(feature = "std"), no_std)]
+#![deny(rustdoc::broken_intra_doc_links)]
+#![deny(missing_docs)]
+
+extern crate alloc;
+
+mod claim;
+#[cfg(not(target_os = "zkvm"))]
+mod control_id;
+#[cfg(not(target_os = "zkvm"))]
+pub mod groth16;
+#[cfg(not(target_os = "zkvm"))]
+mod merge;
+#[cfg(not(target_os = "zkvm"))]
+mod receipt;
+#[cfg(not(target_os = "zkvm"))]
+mod seal;
+
+pub use risc0_binfmt::SystemState;
+pub use risc0_zkp::core::digest::Digest;
+
+pub use self::claim::{
+    Assumptions, DecodeError, ExitCode, InvalidExitCodeError, MaybePruned, Output,
+    PrunedValueError, ReceiptClaim,
+};
+
+#[cfg(not(target_os = "zkvm"))]
+pub use risc0_binfmt::RegionProof;
+#[cfg(not(target_os = "zkvm"))]
+pub use risc0_circuit_recursion::control_id::ALLOWED_IDS_ROOT;
+#[cfg(not(target_os = "zkvm"))]
+pub use risc0_zkp::{params::FriParams, verify::VerificationError};
+
+#[cfg(not(target_os = "zkvm"))]
+pub use self::{
+    control_id::{
+        RawControlId, BLAKE2B_CONTROL_ID, POSEIDON2_CONTROL_ID, POSEIDON_CONTROL_ID,
+        SHA256_CONTROL_ID,
+    },
+    merge::{Merge, MergeInequalityError},
+    receipt::{
+        AssumptionReceipt, CompositeReceipt, Groth16Receipt, InnerReceipt, Journal, Receipt,
+        SegmentReceipt, SuccinctReceipt,
+    },
+    seal::{
+        decode_segment_claim, decode_segment_globals, valid_control_ids, verify_segment_seal,
+        verify_succinct_seal, SegmentGlobals, VerifierContext,
+    },
+};
@@ -0,0 +1,591 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Receipts and their verification.
+//!
+//! `risc0-zkvm` re-exports the [InnerReceipt] and its variants, so these are
+//! the receipts produced by the prover. [Receipt] has the same serialized
+//! representation as the receipt of `risc0-zkvm`.
+
+use alloc::{string::String, vec, vec::Vec};
+
+use risc0_binfmt::Digestible;
+use risc0_zkp::{
+    core::{digest::Digest, hash::sha::cpu::Impl},
+    params::FriParams,
+    verify::VerificationError,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    claim::{Assumptions, ExitCode, MaybePruned, Output, ReceiptClaim},
+    groth16::{Groth16Proof, Groth16Seal},
+    seal::{decode_segment_claim, verify_segment_seal, verify_succinct_seal, VerifierContext},
+};
+
+/// The size of a word in the zkVM, in bytes.
+const WORD_SIZE: u32 = 4;
+
+/// A receipt attesting to the execution of a guest, along with its journal.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Receipt {
+    /// The polymorphic [InnerReceipt].
+    pub inner: InnerReceipt,
+
+    /// The public commitment written by the guest.
+    pub journal: Journal,
+}
+
+impl Receipt {
+    /// Verify that this receipt proves a successful execution of the zkVM from
+    /// the given `image_id`.
+    ///
+    /// This checks the seal, that the guest exited with a successful status
+    /// code (e.g. `Halted(0)` or `Paused(0)`), that the image ID is as
+    /// expected, and that the journal has not been tampered with.
+    pub fn verify(&self, image_id: impl Into<Digest>) -> Result<(), VerificationError> {
+        self.verify_with_context(&VerifierContext::default(), image_id)
+    }
+
+    /// Verify that this receipt proves a successful execution of the zkVM from
+    /// the given `image_id`, with the hash functions in the given context.
+    pub fn verify_with_context(
+        &self,
+        ctx: &VerifierContext,
+        image_id: impl Into<Digest>,
+    ) -> Result<(), VerificationError> {
+        self.inner.verify_integrity_with_context(ctx)?;
+        let claim = self.inner.get_claim()?;
+        claim.verify_success(image_id.into())?;
+        // It is expected that there are no (unresolved) assumptions.
+        claim.verify_journal(&self.journal.bytes, Assumptions(vec![]).into())
+    }
+
+    /// Verify that this receipt proves a successful execution of the zkVM from
+    /// the given `image_id`, conditioned on the returned [Assumptions].
+    ///
+    /// The receipt is only valid if each of the returned assumptions is
+    /// accepted by the verifier.
+    pub fn verify_conditional_with_context(
+        &self,
+        ctx: &VerifierContext,
+        image_id: impl Into<Digest>,
+    ) -> Result<Assumptions, VerificationError> {
+        let assumptions = self.verify_conditional_integrity_with_context(ctx)?;
+        self.inner.get_claim()?.verify_success(image_id.into())?;
+        Ok(assumptions)
+    }
+
+    /// Verify the integrity of this receipt, ensuring the claim and journal
+    /// are attested to by the seal, and that the claim carries no
+    /// assumptions.
+    ///
+    /// This does not check the exit code or the image ID.
+    pub fn verify_integrity_with_context(
+        &self,
+        ctx: &VerifierContext,
+    ) -> Result<(), VerificationError> {
+        self.inner.verify_integrity_with_context(ctx)?;
+        self.inner
+            .get_claim()?
+            .verify_journal(&self.journal.bytes, Assumptions(vec![]).into())
+    }
+
+    /// Verify the integrity of this receipt like
+    /// [Receipt::verify_integrity_with_context], but also accept a
+    /// conditional receipt, returning the [Assumptions] its claim carries.
+    pub fn verify_conditional_integrity_with_context(
+        &self,
+        ctx: &VerifierContext,
+    ) -> Result<Assumptions, VerificationError> {
+        self.inner.verify_integrity_with_context(ctx)?;
+        let claim = self.inner.get_claim()?;
+        let assumptions = claim.open_assumptions()?;
+        claim.verify_journal(&self.journal.bytes, assumptions.clone().into())?;
+        Ok(assumptions)
+    }
+
+    /// Extract the [ReceiptClaim] from this receipt.
+    pub fn get_claim(&self) -> Result<ReceiptClaim, VerificationError> {
+        self.inner.get_claim()
+    }
+}
+
+/// A journal is a record of all public commitments for a given proof session.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Journal {
+    /// The raw bytes of the journal.
+    pub bytes: Vec<u8>,
+}
+
+impl ReceiptClaim {
+    /// Check that this claim is of a successful execution of the zkVM from the
+    /// given `image_id`, i.e. that the guest exited with `Halted(0)` or
+    /// `Paused(0)`.
+    ///
+    /// The post-state digest and the input digest are unconstrained by this
+    /// check.
+    pub fn verify_success(&self, image_id: Digest) -> Result<(), VerificationError> {
+        if self.pre.digest::<Impl>() != image_id {
+            return Err(VerificationError::ImageVerificationError);
+        }
+        let (ExitCode::Halted(0) | ExitCode::Paused(0)) = self.exit_code else {
+            return Err(VerificationError::UnexpectedExitCode);
+        };
+        Ok(())
+    }
+
+    /// Check that the output of this claim commits to the given `journal` and
+    /// `assumptions`.
+    ///
+    /// A claim without output is accepted for an empty journal.
+    pub fn verify_journal(
+        &self,
+        journal: &[u8],
+        assumptions: MaybePruned<Assumptions>,
+    ) -> Result<(), VerificationError> {
+        let expected_output = self.exit_code.expects_output().then(|| Output {
+            journal: MaybePruned::Pruned(journal.digest::<Impl>()),
+            assumptions,
+        });
+        if self.output.digest::<Impl>() != expected_output.digest::<Impl>() {
+            let empty_output = self.output.is_none() && journal.is_empty();
+            if !empty_output {
+                return Err(VerificationError::JournalDigestMismatch);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the assumptions carried by the output of this claim.
+    ///
+    /// A claim without output carries no assumptions. The assumptions list
+    /// must be opened in the claim; a pruned, non-empty list is rejected with
+    /// [VerificationError::ReceiptFormatError].
+    pub fn open_assumptions(&self) -> Result<Assumptions, VerificationError> {
+        let Ok(Some(output)) = self.output.as_value() else {
+            return Ok(Assumptions(vec![]));
+        };
+        match &output.assumptions {
+            MaybePruned::Value(assumptions) => Ok(assumptions.clone()),
+            MaybePruned::Pruned(digest) if *digest == Assumptions(vec![]).digest::<Impl>() => {
+                Ok(Assumptions(vec![]))
+            }
+            MaybePruned::Pruned(_) => Err(VerificationError::ReceiptFormatError),
+        }
+    }
+}
+
+/// An inner receipt can take the form of a [CompositeReceipt], a
+/// [SuccinctReceipt] or a [Groth16Receipt].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum InnerReceipt {
+    /// A non-succinct [CompositeReceipt].
+    Composite(CompositeReceipt),
+
+    /// The [SuccinctReceipt].
+    Succinct(SuccinctReceipt),
+
+    /// The [Groth16Receipt].
+    Groth16(Groth16Receipt),
+
+    /// A fake receipt for testing and development.
+    ///
+    /// This receipt is not valid and always fails verification with
+    /// [InnerReceipt::verify_integrity_with_context]. `risc0-zkvm` accepts it
+    /// when the environment variable `RISC0_DEV_MODE` is set to `true`, in
+    /// which case a pass-through 'verification' is performed, but it *does not*
+    /// represent any meaningful attestation of receipt's integrity.
+    ///
+    /// This type solely exists to improve development experience, for further
+    /// information about development-only mode see our [dev-mode
+    /// documentation](https://dev.risczero.com/zkvm/dev-mode).
+    Fake {
+        /// [ReceiptClaim] for this fake receipt.
+        claim: ReceiptClaim,
+    },
+}
+
+impl InnerReceipt {
+    /// Verify the integrity of this receipt, ensuring the claim is attested
+    /// to by the seal.
+    pub fn verify_integrity_with_context(
+        &self,
+        ctx: &VerifierContext,
+    ) -> Result<(), VerificationError> {
+        match self {
+            InnerReceipt::Composite(x) => x.verify_integrity_with_context(ctx),
+            InnerReceipt::Groth16(x) => x.verify_integrity(),
+            InnerReceipt::Succinct(x) => x.verify_integrity_with_context(ctx),
+            InnerReceipt::Fake { .. } => Err(VerificationError::InvalidProof),
+        }
+    }
+
+    /// Returns the [InnerReceipt::Composite] arm.
+    pub fn composite(&self) -> Result<&CompositeReceipt, VerificationError> {
+        if let InnerReceipt::Composite(x) = self {
+            Ok(x)
+        } else {
+            Err(VerificationError::ReceiptFormatError)
+        }
+    }
+
+    /// Returns the [InnerReceipt::Groth16] arm.
+    pub fn groth16(&self) -> Result<&Groth16Receipt, VerificationError> {
+        if let InnerReceipt::Groth16(x) = self {
+            Ok(x)
+        } else {
+            Err(VerificationError::ReceiptFormatError)
+        }
+    }
+
+    /// Returns the [InnerReceipt::Succinct] arm.
+    pub fn succinct(&self) -> Result<&SuccinctReceipt, VerificationError> {
+        if let InnerReceipt::Succinct(x) = self {
+            Ok(x)
+        } else {
+            Err(VerificationError::ReceiptFormatError)
+        }
+    }
+
+    /// Extract the [ReceiptClaim] from this receipt.
+    pub fn get_claim(&self) -> Result<ReceiptClaim, VerificationError> {
+        match self {
+            InnerReceipt::Composite(receipt) => receipt.get_claim(),
+            InnerReceipt::Groth16(receipt) => Ok(receipt.claim.clone()),
+            InnerReceipt::Succinct(receipt) => Ok(receipt.claim.clone()),
+            InnerReceipt::Fake { claim } => Ok(claim.clone()),
+        }
+    }
+}
+
+/// A receipt composed of a Groth16 over the BN_254 curve
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Groth16Receipt {
+    /// A Groth16 proof of a zkVM execution with the associated claim.
+    pub seal: Vec<u8>,
+
+    /// [ReceiptClaim] containing information about the execution that this
+    /// receipt proves.
+    pub claim: ReceiptClaim,
+}
+
+impl Groth16Receipt {
+    /// Verify the integrity of this receipt, ensuring the claim is attested
+    /// to by the seal.
+    pub fn verify_integrity(&self) -> Result<(), VerificationError> {
+        Groth16Proof::from_seal(
+            &Groth16Seal::from_vec(&self.seal).map_err(|_| VerificationError::InvalidProof)?,
+            self.claim.digest::<Impl>(),
+        )
+        .map_err(|_| VerificationError::InvalidProof)?
+        .verify()
+        .map_err(|_| VerificationError::InvalidProof)
+    }
+}
+
+/// A succinct receipt, produced via recursion, proving the execution of the
+/// zkVM.
+///
+/// Using recursion, a [CompositeReceipt] can be compressed to form a
+/// [SuccinctReceipt]. In this way, a constant sized proof can be generated for
+/// arbitrarily long computations, and with an arbitrary number of segments
+/// linked via composition.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SuccinctReceipt {
+    /// The cryptographic seal of this receipt. This seal is a STARK proving an
+    /// execution of the recursion circuit.
+    pub seal: Vec<u32>,
+
+    /// The control ID of this receipt, identifying the recursion program that
+    /// was run (e.g. lift, join, or resolve).
+    pub control_id: Digest,
+
+    /// [ReceiptClaim] containing information about the execution that this
+    /// receipt proves.
+    pub claim: ReceiptClaim,
+}
+
+impl SuccinctReceipt {
+    /// Verify the integrity of this receipt, ensuring the claim is attested
+    /// to by the seal.
+    pub fn verify_integrity_with_context(
+        &self,
+        ctx: &VerifierContext,
+    ) -> Result<(), VerificationError> {
+        // Verify the seal, and that the claim on the struct is the one proven by it.
+        if verify_succinct_seal(ctx, &self.seal)? != self.claim.digest::<Impl>() {
+            return Err(VerificationError::JournalDigestMismatch);
+        }
+        Ok(())
+    }
+
+    /// Return the seal for this receipt, as a vector of bytes.
+    pub fn get_seal_bytes(&self) -> Vec<u8> {
+        self.seal.iter().flat_map(|x| x.to_le_bytes()).collect()
+    }
+}
+
+/// A receipt composed of one or more [SegmentReceipt] structs proving a single
+/// execution with continuations, and zero or more [AssumptionReceipt] structs
+/// either proving or leaving open the assumptions made by the guest.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CompositeReceipt {
+    /// Segment receipts forming the proof of a execution with continuations.
+    pub segments: Vec<SegmentReceipt>,
+
+    /// An ordered list of assumptions, either proven or unresolved, made within
+    /// the continuation represented by the segment receipts. If any
+    /// assumptions are unresolved, this receipt is only _conditionally_
+    /// valid. See [CompositeReceipt::unresolved_assumptions].
+    pub assumptions: Vec<AssumptionReceipt>,
+
+    /// Digest of journal included in the final output of the continuation. Will
+    /// be `None` if the continuation has no output (e.g. it ended in
+    /// `Fault`).
+    // NOTE: This field is needed in order to open the assumptions digest from the output digest.
+    // TODO(1.0): This field can potentially be removed since it can be included in the claim on
+    // the last segment receipt instead.
+    pub journal_digest: Option<Digest>,
+}
+
+impl CompositeReceipt {
+    /// Verify the integrity of this receipt, ensuring the claim is attested
+    /// to by the seal.
+    pub fn verify_integrity_with_context(
+        &self,
+        ctx: &VerifierContext,
+    ) -> Result<(), VerificationError> {
+        // Verify the continuation, by verifying every segment receipt in order.
+        let (final_receipt, receipts) = self
+            .segments
+            .split_last()
+            .ok_or(VerificationError::ReceiptFormatError)?;
+
+        // Verify each segment and its chaining to the next.
+        let mut expected_pre_state_digest = None;
+        for receipt in receipts {
+            receipt.verify_integrity_with_context(ctx)?;
+            if let Some(id) = expected_pre_state_digest {
+                if id != receipt.claim.pre.digest::<Impl>() {
+                    return Err(VerificationError::ImageVerificationError);
+                }
+            }
+            if receipt.claim.exit_code != ExitCode::SystemSplit {
+                return Err(VerificationError::UnexpectedExitCode);
+            }
+            if !receipt.claim.output.is_none() {
+                return Err(VerificationError::ReceiptFormatError);
+            }
+            // Post state PC is stored as the "actual" value plus 4. This matches the join
+            // predicate implementation. See [ReceiptClaim] for more detail.
+            let mut post = receipt
+                .claim
+                .post
+                .as_value()
+                .map_err(|_| VerificationError::ReceiptFormatError)?
+                .clone();
+            post.pc = post
+                .pc
+                .checked_sub(WORD_SIZE)
+                .ok_or(VerificationError::ReceiptFormatError)?;
+            expected_pre_state_digest = Some(post.digest::<Impl>());
+        }
+
+        // Verify the last receipt in the continuation.
+        final_receipt.verify_integrity_with_context(ctx)?;
+        if let Some(id) = expected_pre_state_digest {
+            if id != final_receipt.claim.pre.digest::<Impl>() {
+                return Err(VerificationError::ImageVerificationError);
+            }
+        }
+
+        // Verify all corroborating receipts attached to this composite receipt.
+        for assumption in self.assumptions.iter() {
+            if let AssumptionReceipt::Proven(receipt) = assumption {
+                receipt.verify_integrity_with_context(ctx)?;
+            }
+        }
+
+        // Verify decoded output digest is consistent with the journal_digest and
+        // assumptions.
+        self.verify_output_consistency(&final_receipt.claim)
+    }
+
+    /// Returns the [ReceiptClaim] for this [CompositeReceipt].
+    pub fn get_claim(&self) -> Result<ReceiptClaim, VerificationError> {
+        let first_claim = &self
+            .segments
+            .first()
+            .ok_or(VerificationError::ReceiptFormatError)?
+            .claim;
+        let last_claim = &self
+            .segments
+            .last()
+            .ok_or(VerificationError::ReceiptFormatError)?
+            .claim;
+
+        // After verifying the internally consistency of this receipt, we can use
+        // self.assumptions and self.journal_digest in place of
+        // last_claim.output, which is equal.
+        self.verify_output_consistency(last_claim)?;
+        let output = if last_claim.output.is_some() {
+            Some(Output {
+                journal: MaybePruned::Pruned(
+                    self.journal_digest
+                        .ok_or(VerificationError::ReceiptFormatError)?,
+                ),
+                // NOTE: Proven assumptions are not included in the CompositeReceipt claim.
+                assumptions: Assumptions(self.unresolved_assumptions()).into(),
+            })
+        } else {
+            None
+        };
+
+        Ok(ReceiptClaim {
+            pre: first_claim.pre.clone(),
+            post: last_claim.post.clone(),
+            exit_code: last_claim.exit_code,
+            input: first_claim.input,
+            output: output.into(),
+        })
+    }
+
+    /// Returns the claims of the assumptions on this receipt that are not
+    /// proven by an attached receipt, in order.
+    ///
+    /// If this list is non-empty, the receipt is only _conditionally_ valid.
+    /// These are the assumptions reported by
+    /// [Receipt::verify_conditional_with_context].
+    pub fn unresolved_assumptions(&self) -> Vec<MaybePruned<ReceiptClaim>> {
+        self.assumptions
+            .iter()
+            .filter_map(|a| match a {
+                AssumptionReceipt::Proven(_) => None,
+                AssumptionReceipt::Unresolved(claim) => Some(claim.clone()),
+            })
+            .collect()
+    }
+
+    /// Check that the output fields in the given receipt claim are consistent
+    /// with the exit code, and with the journal_digest and assumptions encoded
+    /// on self.
+    fn verify_output_consistency(&self, claim: &ReceiptClaim) -> Result<(), VerificationError> {
+        if claim.exit_code.expects_output() && claim.output.is_some() {
+            let journal_digest = self
+                .journal_digest
+                .ok_or(VerificationError::ReceiptFormatError)?;
+            let assumptions = self
+                .assumptions
+                .iter()
+                .map(|a| a.get_claim())
+                .collect::<Result<Vec<_>, _>>()?;
+            let self_output = Output {
+                journal: MaybePruned::Pruned(journal_digest),
+                assumptions: Assumptions(assumptions).into(),
+            };
+
+            // If these digests do not match, this receipt is internally inconsistent.
+            if self_output.digest::<Impl>() != claim.output.digest::<Impl>() {
+                return Err(VerificationError::ReceiptFormatError);
+            }
+        } else if claim.output.is_some()
+            || !self.assumptions.is_empty()
+            || self.journal_digest.is_some()
+        {
+            // All output fields must be empty. If not, this receipt is internally inconsistent.
+            return Err(VerificationError::ReceiptFormatError);
+        }
+        Ok(())
+    }
+}
+
+/// An assumption attached to a [CompositeReceipt], along with the receipt
+/// proving it, if any.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum AssumptionReceipt {
+    /// An [InnerReceipt] proving the assumption.
+    Proven(InnerReceipt),
+
+    /// [ReceiptClaim] for an assumption that is not proven by this receipt.
+    ///
+    /// A [CompositeReceipt] with unresolved assumptions is conditional. It
+    /// can be made unconditional by lifting it into a [SuccinctReceipt] and
+    /// resolving each assumption, in order, with a receipt proving it.
+    Unresolved(MaybePruned<ReceiptClaim>),
+}
+
+impl AssumptionReceipt {
+    /// Returns the [ReceiptClaim] for this [AssumptionReceipt].
+    pub fn get_claim(&self) -> Result<MaybePruned<ReceiptClaim>, VerificationError> {
+        match self {
+            Self::Proven(receipt) => Ok(receipt.get_claim()?.into()),
+            Self::Unresolved(claim) => Ok(claim.clone()),
+        }
+    }
+}
+
+/// A receipt attesting to the execution of a Segment.
+///
+/// A SegmentReceipt attests that a segment was executed in a manner consistent
+/// with the [ReceiptClaim] included in the receipt.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct SegmentReceipt {
+    /// The cryptographic data attesting to the validity of the code execution.
+    ///
+    /// This data is used by the ZKP Verifier (as called by
+    /// [SegmentReceipt::verify_integrity_with_context]) to cryptographically prove that this
+    /// Segment was faithfully executed. It is largely opaque cryptographic data, but contains a
+    /// non-opaque claim component, which can be conveniently accessed with
+    /// [SegmentReceipt::claim].
+    pub seal: Vec<u32>,
+
+    /// Segment index within the [Receipt]
+    pub index: u32,
+
+    /// Name of the hash function used to create this receipt.
+    pub hashfn: String,
+
+    /// [ReceiptClaim] containing information about the execution that this
+    /// receipt proves.
+    pub claim: ReceiptClaim,
+
+    /// The [FriParams] used to create this receipt.
+    #[serde(default)]
+    pub fri_params: FriParams,
+}
+
+impl SegmentReceipt {
+    /// Verify the integrity of this receipt, ensuring the claim is attested
+    /// to by the seal.
+    pub fn verify_integrity_with_context(
+        &self,
+        ctx: &VerifierContext,
+    ) -> Result<(), VerificationError> {
+        verify_segment_seal(ctx, &self.hashfn, self.fri_params, &self.seal)?;
+
+        // Receipt is consistent with the claim encoded on the seal. Now check against the
+        // claim on the struct.
+        let decoded_claim = decode_segment_claim(&self.seal)?;
+        if decoded_claim.digest::<Impl>() != self.claim.digest::<Impl>() {
+            return Err(VerificationError::ReceiptFormatError);
+        }
+        Ok(())
+    }
+
+    /// Return the seal for this receipt, as a vector of bytes.
+    pub fn get_seal_bytes(&self) -> Vec<u8> {
+        self.seal.iter().flat_map(|x| x.to_le_bytes()).collect()
+    }
+}
@@ -0,0 +1,259 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of the STARK seals produced by the rv32im and recursion
+//! circuits.
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    string::String,
+    vec::Vec,
+};
+
+use hex::FromHex;
+use risc0_binfmt::{read_sha_halfs, SystemState};
+use risc0_circuit_recursion::control_id::RECURSION_CONTROL_IDS;
+use risc0_circuit_rv32im::layout;
+use risc0_core::field::{
+    baby_bear::{BabyBear, BabyBearElem},
+    Elem,
+};
+use risc0_zkp::{
+    adapter::CircuitInfo,
+    core::{
+        digest::Digest,
+        hash::{
+            blake2b::Blake2bCpuHashSuite, poseidon::PoseidonHashSuite,
+            poseidon2::Poseidon2HashSuite, sha::Sha256HashSuite, HashSuite,
+        },
+    },
+    layout::Buffer,
+    params::FriParams,
+    verify::VerificationError,
+};
+
+use crate::{
+    claim::{ExitCode, MaybePruned, ReceiptClaim},
+    control_id::{
+        BLAKE2B_CONTROL_ID, POSEIDON2_CONTROL_ID, POSEIDON_CONTROL_ID, SHA256_CONTROL_ID,
+    },
+};
+
+const RV32IM_CIRCUIT: risc0_circuit_rv32im::CircuitImpl = risc0_circuit_rv32im::CircuitImpl::new();
+
+const RECURSION_CIRCUIT: risc0_circuit_recursion::CircuitImpl =
+    risc0_circuit_recursion::CircuitImpl::new();
+
+/// Context available to the verification process.
+pub struct VerifierContext {
+    /// A registry of hash functions to be used by the verification process.
+    pub suites: BTreeMap<String, HashSuite<BabyBear>>,
+
+    /// The minimum conjectured security, in bits, of the [FriParams] that a
+    /// segment receipt may be proven with.
+    pub min_security_bits: usize,
+}
+
+impl Default for VerifierContext {
+    fn default() -> Self {
+        Self {
+            suites: BTreeMap::from([
+                ("blake2b".into(), Blake2bCpuHashSuite::new_suite()),
+                ("poseidon".into(), PoseidonHashSuite::new_suite()),
+                ("poseidon2".into(), Poseidon2HashSuite::new_suite()),
+                ("sha-256".into(), Sha256HashSuite::new_suite()),
+            ]),
+            min_security_bits: FriParams::DEFAULT.conjectured_security_bits(),
+        }
+    }
+}
+
+/// Returns the control IDs of the recursion programs accepted in a succinct
+/// receipt, including the programs that lift a segment receipt for each
+/// cycle count.
+pub fn valid_control_ids() -> Vec<Digest> {
+    let mut all_ids = Vec::<Digest>::new();
+    for digest_str in POSEIDON_CONTROL_ID {
+        all_ids.push(Digest::from_hex(digest_str).unwrap());
+    }
+    for (_, digest_str) in RECURSION_CONTROL_IDS {
+        all_ids.push(Digest::from_hex(digest_str).unwrap());
+    }
+    all_ids
+}
+
+/// Verify the seal of a segment receipt, created with the hash function named
+/// `hashfn` and the given [FriParams], against the rv32im circuit.
+///
+/// Seals proven with parameters that provide less conjectured security than
+/// [VerifierContext::min_security_bits] are rejected.
+pub fn verify_segment_seal(
+    ctx: &VerifierContext,
+    hashfn: &str,
+    params: FriParams,
+    seal: &[u32],
+) -> Result<(), VerificationError> {
+    if params.conjectured_security_bits() < ctx.min_security_bits {
+        return Err(VerificationError::InvalidFriParams);
+    }
+    let check_code = |_, control_id: &Digest| -> Result<(), VerificationError> {
+        POSEIDON_CONTROL_ID
+            .into_iter()
+            .chain(SHA256_CONTROL_ID)
+            .chain(BLAKE2B_CONTROL_ID)
+            .chain(POSEIDON2_CONTROL_ID)
+            .find(|x| Digest::from_hex(x).unwrap() == *control_id)
+            .map(|_| ())
+            .ok_or(VerificationError::ControlVerificationError)
+    };
+    let suite = ctx
+        .suites
+        .get(hashfn)
+        .ok_or(VerificationError::InvalidHashSuite)?;
+    risc0_zkp::verify::verify_with_params(&RV32IM_CIRCUIT, suite, params, seal, check_code)
+}
+
+/// Verify the seal of a succinct receipt against the recursion circuit, and
+/// return the digest of the claim it proves.
+pub fn verify_succinct_seal(
+    ctx: &VerifierContext,
+    seal: &[u32],
+) -> Result<Digest, VerificationError> {
+    // Assemble the list of control IDs, and therefore circuit variants, we will
+    // accept.
+    let valid_ids = valid_control_ids();
+    let check_code = |_, control_id: &Digest| -> Result<(), VerificationError> {
+        valid_ids
+            .iter()
+            .find(|x| *x == control_id)
+            .map(|_| ())
+            .ok_or(VerificationError::ControlVerificationError)
+    };
+
+    // All receipts from the recursion circuit use Poseidon as the FRI hash
+    // function.
+    let suite = ctx
+        .suites
+        .get("poseidon")
+        .ok_or(VerificationError::InvalidHashSuite)?;
+
+    // Verify the receipt itself is correct, and therefore the encoded globals are
+    // reliable.
+    risc0_zkp::verify::verify(&RECURSION_CIRCUIT, suite, seal, check_code)?;
+
+    // Extract the globals from the seal
+    let output_size = risc0_circuit_recursion::CircuitImpl::OUTPUT_SIZE;
+    let output_elems: &[BabyBearElem] = bytemuck::cast_slice(
+        seal.get(..output_size)
+            .ok_or(VerificationError::ReceiptFormatError)?,
+    );
+    let mut seal_claim: VecDeque<u32> = output_elems.iter().map(|elem| elem.as_u32()).collect();
+
+    // TODO: Read root hash
+    seal_claim.drain(0..16);
+    read_sha_halfs(&mut seal_claim).map_err(|_| VerificationError::ReceiptFormatError)
+}
+
+/// The values committed to in the globals of a segment seal.
+#[derive(Clone, Debug)]
+pub struct SegmentGlobals {
+    /// The [SystemState] of the segment just before execution has begun.
+    pub pre: SystemState,
+
+    /// The [SystemState] of the segment just after execution has completed.
+    pub post: SystemState,
+
+    /// The digest of the input to the guest.
+    pub input: Digest,
+
+    /// The digest of the output of the guest.
+    pub output: Digest,
+
+    /// The system part of the exit code.
+    pub sys_exit: u32,
+
+    /// The user part of the exit code.
+    pub user_exit: u32,
+}
+
+fn decode_system_state_from_io(
+    io: layout::OutBuffer,
+    sys_state: &layout::SystemState,
+) -> Result<SystemState, VerificationError> {
+    let bytes: Vec<u8> = io
+        .tree(sys_state.image_id)
+        .get_bytes()
+        .or(Err(VerificationError::ReceiptFormatError))?;
+    let pc = io
+        .tree(sys_state.pc)
+        .get_u32()
+        .or(Err(VerificationError::ReceiptFormatError))?;
+    let merkle_root = Digest::try_from(bytes).or(Err(VerificationError::ReceiptFormatError))?;
+    Ok(SystemState { pc, merkle_root })
+}
+
+/// Decode the globals of a segment seal.
+///
+/// The seal may come from an untrusted source, so this checks that the globals
+/// are present and hold canonical field elements before decoding them. It does
+/// not verify the seal.
+pub fn decode_segment_globals(seal: &[u32]) -> Result<SegmentGlobals, VerificationError> {
+    let elems: &[BabyBearElem] = bytemuck::cast_slice(
+        seal.get(..risc0_circuit_rv32im::CircuitImpl::OUTPUT_SIZE)
+            .ok_or(VerificationError::ReceiptFormatError)?,
+    );
+    if !elems.iter().all(BabyBearElem::is_reduced) {
+        return Err(VerificationError::ReceiptFormatError);
+    }
+    let io = layout::OutBuffer(elems);
+    let body = layout::LAYOUT.mux.body;
+    let pre = decode_system_state_from_io(io, body.global.pre)?;
+    let post = decode_system_state_from_io(io, body.global.post)?;
+
+    let input_bytes: Vec<u8> = io
+        .tree(body.global.input)
+        .get_bytes()
+        .or(Err(VerificationError::ReceiptFormatError))?;
+    let input = Digest::try_from(input_bytes).or(Err(VerificationError::ReceiptFormatError))?;
+
+    let output_bytes: Vec<u8> = io
+        .tree(body.global.output)
+        .get_bytes()
+        .or(Err(VerificationError::ReceiptFormatError))?;
+    let output = Digest::try_from(output_bytes).or(Err(VerificationError::ReceiptFormatError))?;
+
+    Ok(SegmentGlobals {
+        pre,
+        post,
+        input,
+        output,
+        sys_exit: io.get_u64(body.global.sys_exit_code) as u32,
+        user_exit: io.get_u64(body.global.user_exit_code) as u32,
+    })
+}
+
+/// Decode the claim encoded in the globals of a segment seal.
+///
+/// Like [decode_segment_globals], this does not verify the seal.
+pub fn decode_segment_claim(seal: &[u32]) -> Result<ReceiptClaim, VerificationError> {
+    let globals = decode_segment_globals(seal)?;
+    Ok(ReceiptClaim {
+        pre: globals.pre.into(),
+        post: globals.post.into(),
+        exit_code: ExitCode::from_pair(globals.sys_exit, globals.user_exit)
+            .map_err(|_| VerificationError::ReceiptFormatError)?,
+        input: globals.input,
+        output: MaybePruned::Pruned(globals.output),
+    })
+}
@@ -0,0 +1,52 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks that the verifier builds for targets without the Rust stdlib.
+//!
+//! These tests need the `thumbv7em-none-eabihf` and `wasm32-unknown-unknown`
+//! targets to be installed, so they are ignored by default and run explicitly
+//! by the `verifier-no-std` CI job.
+
+use std::{path::Path, process::Command};
+
+fn build_for(target: &str) {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+    // Use a separate target directory, since the one of this test is locked by
+    // the running cargo.
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("no_std");
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--no-default-features", "--target", target])
+        .arg("--manifest-path")
+        .arg(manifest)
+        .arg("--target-dir")
+        .arg(target_dir)
+        .status()
+        .unwrap();
+    assert!(
+        status.success(),
+        "risc0-verifier failed to build for {target}"
+    );
+}
+
+#[test]
+#[ignore = "requires the wasm32-unknown-unknown target"]
+fn wasm32_unknown_unknown() {
+    build_for("wasm32-unknown-unknown");
+}
+
+#[test]
+#[ignore = "requires the thumbv7em-none-eabihf target"]
+fn thumbv7em_none_eabihf() {
+    build_for("thumbv7em-none-eabihf");
+}
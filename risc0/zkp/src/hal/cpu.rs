@@ -20,12 +20,13 @@ use core::{
 };
 use std::{cell::RefCell, fmt::Debug, rc::Rc};
 
+use anyhow::Result;
 use bytemuck::Pod;
 use ndarray::{ArrayView, ArrayViewMut, Axis};
 use rayon::prelude::*;
 use risc0_core::field::{Elem, ExtElem, Field};
 
-use super::{Buffer, Hal, TRACKER};
+use super::{Buffer, Hal, MemoryTracker, TRACKER};
 use crate::{
     core::{
         digest::Digest,
@@ -38,14 +39,34 @@ use crate::{
 
 pub struct CpuHal<F: Field> {
     suite: HashSuite<F>,
+    budget: Option<Rc<RefCell<MemoryBudget>>>,
 }
 
 impl<F: Field> CpuHal<F> {
     pub fn new(suite: HashSuite<F>) -> Self {
-        Self { suite }
+        Self {
+            suite,
+            budget: None,
+        }
+    }
+
+    /// Prove in low-memory mode, limiting the buffers allocated by this HAL to
+    /// `budget` bytes. See [Hal::get_memory_budget] and [Hal::check_alloc].
+    pub fn with_memory_budget(mut self, budget: usize) -> Self {
+        self.budget = Some(Rc::new(RefCell::new(MemoryBudget {
+            limit: budget,
+            tracker: MemoryTracker::new(),
+        })));
+        self
     }
 }
 
+/// The buffers allocated by a single [CpuHal] with a memory budget.
+struct MemoryBudget {
+    limit: usize,
+    tracker: MemoryTracker,
+}
+
 #[derive(Debug, Clone)]
 struct Region(usize, usize);
 
@@ -66,7 +87,7 @@ impl Region {
     }
 }
 
-struct TrackedVec<T>(Vec<T>);
+struct TrackedVec<T>(Vec<T>, Option<Rc<RefCell<MemoryBudget>>>);
 
 impl<T> TrackedVec<T> {
     pub fn new(vec: Vec<T>) -> Self {
@@ -74,16 +95,33 @@ impl<T> TrackedVec<T> {
             .lock()
             .unwrap()
             .alloc(vec.capacity() * std::mem::size_of::<T>());
-        Self(vec)
+        Self(vec, None)
+    }
+
+    /// Track a vector of `len` elements built by `f`, also counting it
+    /// against `budget` if there is one.
+    fn new_within<B>(len: usize, budget: Option<&Rc<RefCell<MemoryBudget>>>, f: B) -> Self
+    where
+        B: FnOnce() -> Vec<T>,
+    {
+        let size = len * std::mem::size_of::<T>();
+        TRACKER.lock().unwrap().alloc(size);
+        if let Some(budget) = budget {
+            budget.borrow_mut().tracker.alloc(size);
+        }
+        let vec = f();
+        assert_eq!(vec.capacity(), len);
+        Self(vec, budget.cloned())
     }
 }
 
 impl<T> Drop for TrackedVec<T> {
     fn drop(&mut self) {
-        TRACKER
-            .lock()
-            .unwrap()
-            .free(self.0.capacity() * std::mem::size_of::<T>());
+        let size = self.0.capacity() * std::mem::size_of::<T>();
+        TRACKER.lock().unwrap().free(size);
+        if let Some(budget) = &self.1 {
+            budget.borrow_mut().tracker.free(size);
+        }
     }
 }
 
@@ -159,10 +197,10 @@ impl<'a, T: Default + Clone + Pod> SyncSlice<'a, T> {
 }
 
 impl<T: Default + Clone + Pod> CpuBuffer<T> {
-    fn new(size: usize) -> Self {
-        let buf = vec![T::default(); size];
+    fn new(size: usize, budget: Option<&Rc<RefCell<MemoryBudget>>>) -> Self {
+        let buf = TrackedVec::new_within(size, budget, || vec![T::default(); size]);
         CpuBuffer {
-            buf: Rc::new(RefCell::new(TrackedVec::new(buf))),
+            buf: Rc::new(RefCell::new(buf)),
             region: Region(0, size),
         }
     }
@@ -171,10 +209,11 @@ impl<T: Default + Clone + Pod> CpuBuffer<T> {
         self.as_slice_sync().get_ptr()
     }
 
-    fn copy_from(slice: &[T]) -> Self {
+    fn copy_from(slice: &[T], budget: Option<&Rc<RefCell<MemoryBudget>>>) -> Self {
         let bytes = bytemuck::cast_slice(slice);
+        let buf = TrackedVec::new_within(slice.len(), budget, || Vec::from(bytes));
         CpuBuffer {
-            buf: Rc::new(RefCell::new(TrackedVec::new(Vec::from(bytes)))),
+            buf: Rc::new(RefCell::new(buf)),
             region: Region(0, slice.len()),
         }
     }
@@ -255,7 +294,7 @@ impl<F: Field> Hal for CpuHal<F> {
     type Buffer<T: Clone + Debug + PartialEq + Pod> = CpuBuffer<T>;
 
     fn alloc_elem(&self, _name: &'static str, size: usize) -> Self::Buffer<Self::Elem> {
-        CpuBuffer::new(size)
+        CpuBuffer::new(size, self.budget.as_ref())
     }
 
     fn copy_from_elem(
@@ -263,11 +302,11 @@ impl<F: Field> Hal for CpuHal<F> {
         _name: &'static str,
         slice: &[Self::Elem],
     ) -> Self::Buffer<Self::Elem> {
-        CpuBuffer::copy_from(slice)
+        CpuBuffer::copy_from(slice, self.budget.as_ref())
     }
 
     fn alloc_extelem(&self, _name: &'static str, size: usize) -> Self::Buffer<Self::ExtElem> {
-        CpuBuffer::new(size)
+        CpuBuffer::new(size, self.budget.as_ref())
     }
 
     fn copy_from_extelem(
@@ -275,23 +314,23 @@ impl<F: Field> Hal for CpuHal<F> {
         _name: &'static str,
         slice: &[Self::ExtElem],
     ) -> Self::Buffer<Self::ExtElem> {
-        CpuBuffer::copy_from(slice)
+        CpuBuffer::copy_from(slice, self.budget.as_ref())
     }
 
     fn alloc_digest(&self, _name: &'static str, size: usize) -> Self::Buffer<Digest> {
-        CpuBuffer::new(size)
+        CpuBuffer::new(size, self.budget.as_ref())
     }
 
     fn copy_from_digest(&self, _name: &'static str, slice: &[Digest]) -> Self::Buffer<Digest> {
-        CpuBuffer::copy_from(slice)
+        CpuBuffer::copy_from(slice, self.budget.as_ref())
     }
 
     fn alloc_u32(&self, _name: &'static str, size: usize) -> Self::Buffer<u32> {
-        CpuBuffer::new(size)
+        CpuBuffer::new(size, self.budget.as_ref())
     }
 
     fn copy_from_u32(&self, _name: &'static str, slice: &[u32]) -> Self::Buffer<u32> {
-        CpuBuffer::copy_from(slice)
+        CpuBuffer::copy_from(slice, self.budget.as_ref())
     }
 
     #[tracing::instrument(skip_all)]
@@ -302,34 +341,21 @@ impl<F: Field> Hal for CpuHal<F> {
         count: usize,
         expand_bits: usize,
     ) {
-        // batch_expand
-        {
-            let out_size = output.size() / count;
-            let in_size = input.size() / count;
-            let expand_bits = log2_ceil(out_size / in_size);
-            assert_eq!(out_size, in_size * (1 << expand_bits));
-            assert_eq!(out_size * count, output.size());
-            assert_eq!(in_size * count, input.size());
-            output
-                .as_slice_mut()
-                .par_chunks_exact_mut(out_size)
-                .zip(input.as_slice().par_chunks_exact(in_size))
-                .for_each(|(output, input)| {
-                    expand(output, input, expand_bits);
-                });
-        }
-
-        // batch_evaluate_ntt
-        {
-            let row_size = output.size() / count;
-            assert_eq!(row_size * count, output.size());
-            output
-                .as_slice_mut()
-                .par_chunks_exact_mut(row_size)
-                .for_each(|row| {
-                    evaluate_ntt::<Self::Elem, Self::Elem>(row, expand_bits);
-                });
-        }
+        let out_size = output.size() / count;
+        let in_size = input.size() / count;
+        assert_eq!(out_size, in_size * (1 << expand_bits));
+        assert_eq!(out_size * count, output.size());
+        assert_eq!(in_size * count, input.size());
+        // Evaluate each row as soon as it has been expanded, so that it passes
+        // through the cache once rather than twice.
+        output
+            .as_slice_mut()
+            .par_chunks_exact_mut(out_size)
+            .zip(input.as_slice().par_chunks_exact(in_size))
+            .for_each(|(output, input)| {
+                expand(output, input, expand_bits);
+                evaluate_ntt::<Self::Elem, Self::Elem>(output, expand_bits);
+            });
     }
 
     #[tracing::instrument(skip_all)]
@@ -555,11 +581,16 @@ impl<F: Field> Hal for CpuHal<F> {
         let mut output = output.as_slice_mut();
         let matrix = &*matrix.as_slice();
         let hashfn = self.suite.hashfn.as_ref();
-        output.par_iter_mut().enumerate().for_each(|(idx, output)| {
-            let column: Vec<Self::Elem> =
-                (0..col_size).map(|i| matrix[i * row_size + idx]).collect();
-            *output = *hashfn.hash_elem_slice(column.as_slice());
-        });
+        // Gather each row into a scratch buffer that is reused by each worker,
+        // rather than allocating one per row.
+        output.par_iter_mut().enumerate().for_each_init(
+            || Vec::with_capacity(col_size),
+            |column: &mut Vec<Self::Elem>, (idx, output)| {
+                column.clear();
+                column.extend((0..col_size).map(|i| matrix[i * row_size + idx]));
+                *output = *hashfn.hash_elem_slice(column.as_slice());
+            },
+        );
     }
 
     fn hash_fold(&self, io: &Self::Buffer<Digest>, input_size: usize, output_size: usize) {
@@ -595,6 +626,26 @@ impl<F: Field> Hal for CpuHal<F> {
         true
     }
 
+    fn get_memory_usage(&self) -> usize {
+        // A HAL with a budget reports the peak of its own buffers.
+        match &self.budget {
+            Some(budget) => budget.borrow().tracker.peak,
+            None => TRACKER.lock().unwrap().peak,
+        }
+    }
+
+    fn get_memory_budget(&self) -> Option<usize> {
+        self.budget.as_ref().map(|budget| budget.borrow().limit)
+    }
+
+    fn check_alloc(&self, size: usize) -> Result<()> {
+        if let Some(budget) = &self.budget {
+            let budget = budget.borrow();
+            budget.tracker.check_within(size, budget.limit)?;
+        }
+        Ok(())
+    }
+
     fn get_hash_suite(&self) -> &HashSuite<Self::Field> {
         &self.suite
     }
@@ -618,6 +669,33 @@ mod tests {
         hal.eltwise_add_elem(&a, &b, &b);
     }
 
+    #[test]
+    fn memory_budget() {
+        let hal: CpuHal<BabyBear> =
+            CpuHal::new(Sha256HashSuite::new_suite()).with_memory_budget(64);
+        assert_eq!(hal.get_memory_budget(), Some(64));
+
+        // Freed buffers no longer count against the budget.
+        for _ in 0..2 {
+            hal.check_alloc(64).unwrap();
+            let a = hal.alloc_elem("a", 16);
+            drop(a);
+        }
+
+        let _a = hal.alloc_elem("a", 8);
+        hal.check_alloc(32).unwrap();
+        let err = hal.check_alloc(33).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Memory budget of 64 bytes exceeded"));
+        assert_eq!(hal.get_memory_usage(), 64);
+
+        // Other HALs are not limited by this one's budget.
+        let hal: CpuHal<BabyBear> = CpuHal::new(Sha256HashSuite::new_suite());
+        let _c = hal.alloc_elem("c", 1024);
+        hal.check_alloc(1 << 20).unwrap();
+    }
+
     #[test]
     fn fp() {
         let hal: CpuHal<BabyBear> = CpuHal::new(Sha256HashSuite::new_suite());
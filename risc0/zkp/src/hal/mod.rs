@@ -23,6 +23,7 @@ pub mod metal;
 
 use std::{fmt::Debug, sync::Mutex};
 
+use anyhow::{ensure, Result};
 use bytemuck::Pod;
 use lazy_static::lazy_static;
 use risc0_core::field::{Elem, ExtElem, Field, RootsOfUnity};
@@ -60,6 +61,28 @@ pub trait Hal {
         TRACKER.lock().unwrap().peak
     }
 
+    /// The maximum number of bytes that the buffers allocated by this HAL
+    /// may occupy, if it is limited.
+    ///
+    /// A HAL with a memory budget and unified memory proves in low-memory
+    /// mode: each polynomial group is committed one coset of its evaluation
+    /// domain at a time, and the lower layers of each Merkle tree are not
+    /// retained. The groups are only expanded over their full evaluation
+    /// domain together while the check polynomial is evaluated, so the budget
+    /// must still cover those.
+    fn get_memory_budget(&self) -> Option<usize> {
+        None
+    }
+
+    /// Return an error if allocating another `size` bytes would exceed the
+    /// memory budget of this HAL.
+    ///
+    /// Allocation itself cannot fail, so provers call this before each large
+    /// allocation to stop before the budget is exceeded.
+    fn check_alloc(&self, _size: usize) -> Result<()> {
+        Ok(())
+    }
+
     fn get_hash_suite(&self) -> &HashSuite<Self::Field>;
 
     fn alloc_digest(&self, name: &'static str, size: usize) -> Self::Buffer<Digest>;
@@ -181,6 +204,17 @@ impl MemoryTracker {
         self.peak = self.peak.max(self.total);
     }
 
+    /// Fail if an allocation of `size` bytes would take the total above
+    /// `budget`.
+    pub fn check_within(&self, size: usize, budget: usize) -> Result<()> {
+        let total = self.total;
+        ensure!(
+            total + size <= budget,
+            "Memory budget of {budget} bytes exceeded: {total} bytes in use, {size} bytes requested"
+        );
+        Ok(())
+    }
+
     pub fn free(&mut self, size: usize) {
         self.total = self.total.saturating_sub(size);
     }
@@ -27,6 +27,7 @@ pub mod hal;
 pub mod layout;
 #[cfg(not(target_os = "zkvm"))]
 mod merkle;
+pub mod params;
 #[cfg(feature = "prove")]
 pub mod prove;
 pub mod taps;
@@ -50,6 +51,8 @@ pub const MAX_CYCLES_PO2: usize = 24;
 pub const MAX_CYCLES: usize = 1 << MAX_CYCLES_PO2; // 16M
 
 /// 50 FRI queries gives ~100 bits of conjectured security
+///
+/// This is the number of queries used by [params::FriParams::DEFAULT].
 pub const QUERIES: usize = 50;
 pub const ZK_CYCLES: usize = QUERIES;
 pub const MIN_PO2: usize = core::log2_ceil(1 + ZK_CYCLES);
@@ -0,0 +1,222 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parameters of the FRI protocol, and the security level they provide.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{core::digest::Digest, core::log2_ceil, FRI_FOLD, INV_RATE, QUERIES, ZK_CYCLES};
+
+/// The number of bits in the order of the degree 4 extension of BabyBear,
+/// from which all challenges are drawn, rounded down.
+const EXT_FIELD_BITS: usize = 123;
+
+/// `log2(1 + 1 / 2m)` for the multiplicity `m = 3` used by
+/// [FriParams::proven_security_bits], in thousandths of a bit, rounded up.
+const JOHNSON_SLACK_MILLIBITS: usize = 223;
+
+/// `log2((m + 1/2)^7 / 3)` for `m = 3`, in thousandths of a bit, rounded up.
+const COMMIT_FACTOR_MILLIBITS: usize = 11_067;
+
+/// The largest number of FRI queries accepted by the prover and verifier.
+///
+/// Each query opens a row of the trace, and only the last [ZK_CYCLES] rows
+/// are randomized, so more queries would leak information about the witness.
+pub const MAX_QUERIES: usize = ZK_CYCLES;
+
+/// A set of parameters for the FRI protocol.
+///
+/// The parameters travel next to the seal (for example in a segment receipt)
+/// and must be known to the verifier, which checks them against its own policy
+/// before verifying the seal. Parameters other than [FriParams::DEFAULT] are
+/// also committed to the Fiat-Shamir transcript of the seal; see
+/// [FriParams::transcript_digest].
+///
+/// The rv32im and recursion circuits, and the GPU kernels that fold the FRI
+/// polynomial, are built for a rate of 1/[INV_RATE] and a folding factor of
+/// [FRI_FOLD], so only the number of queries may currently differ from
+/// [FriParams::DEFAULT], and it may not exceed [MAX_QUERIES].
+///
+/// As a result, these parameters can only trade security for smaller and
+/// faster proofs, and [FriParams::DEFAULT] is the strongest supported set.
+/// More queries would need a longer randomized tail to stay zero-knowledge,
+/// and [ZK_CYCLES] determines where the control columns of every circuit end,
+/// so a stronger set needs new control IDs rather than a new value here.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FriParams {
+    /// The number of FRI queries.
+    pub queries: usize,
+
+    /// Inverse of the Reed-Solomon expansion rate.
+    pub inv_rate: usize,
+
+    /// The FRI folding factor.
+    pub fri_fold: usize,
+}
+
+impl FriParams {
+    /// The parameters used by default, giving ~100 bits of conjectured
+    /// security.
+    pub const DEFAULT: Self = Self::new(QUERIES);
+
+    /// Construct a [FriParams] with the given number of queries.
+    pub const fn new(queries: usize) -> Self {
+        Self {
+            queries,
+            inv_rate: INV_RATE,
+            fri_fold: FRI_FOLD,
+        }
+    }
+
+    /// Construct the [FriParams] with the fewest queries that provide at
+    /// least `bits` of conjectured security, if there is one.
+    pub fn for_security_bits(bits: usize) -> Option<Self> {
+        let bits_per_query = log2_ceil(INV_RATE);
+        let queries = bits.saturating_add(bits_per_query - 1) / bits_per_query;
+        let params = Self::new(queries.max(1));
+        (params.is_supported() && params.conjectured_security_bits() >= bits).then_some(params)
+    }
+
+    /// Returns true if these parameters can be used to prove and verify seals.
+    pub fn is_supported(&self) -> bool {
+        self.inv_rate == INV_RATE
+            && self.fri_fold == FRI_FOLD
+            && (1..=MAX_QUERIES).contains(&self.queries)
+    }
+
+    /// The bits of security provided by these parameters under the ethSTARK
+    /// conjecture, where each query contributes `log2(inv_rate)` bits.
+    pub fn conjectured_security_bits(&self) -> usize {
+        let query_bits = self.queries.saturating_mul(log2_ceil(self.inv_rate));
+        query_bits.min(EXT_FIELD_BITS)
+    }
+
+    /// The bits of security that FRI is proven to provide with these
+    /// parameters when proving a segment of `2^po2` cycles.
+    ///
+    /// This is the soundness bound of FRI in the list-decoding regime from
+    /// Theorem 1.5 of Ben-Sasson, Carmon, Ishai, Kopparty and Saraf,
+    /// "Proximity Gaps for Reed-Solomon Codes" (ePrint 2020/654), in the form
+    /// stated by Theorem 8 of the ethSTARK documentation (ePrint 2021/582),
+    /// with `m = 3`. For a rate `rho = 1 / inv_rate`, an evaluation domain of
+    /// size `n` and an extension field `F`, the soundness error is the larger
+    /// of the query error `(sqrt(rho) * (1 + 1 / 2m))^queries` and the commit
+    /// error `(m + 1/2)^7 / (3 * rho^(3/2)) * n^2 / |F|`. The remaining terms
+    /// of the bound are smaller by a factor of about `n`, and are omitted.
+    pub fn proven_security_bits(&self, po2: usize) -> usize {
+        const MILLIBITS: usize = 1000;
+        let rate_bits = log2_ceil(self.inv_rate);
+        let query_millibits = self
+            .queries
+            .saturating_mul((rate_bits * MILLIBITS / 2).saturating_sub(JOHNSON_SLACK_MILLIBITS));
+        let domain_po2 = po2.saturating_add(rate_bits);
+        let commit_millibits = (EXT_FIELD_BITS * MILLIBITS)
+            .saturating_sub(domain_po2.saturating_mul(2 * MILLIBITS))
+            .saturating_sub(COMMIT_FACTOR_MILLIBITS)
+            .saturating_sub(rate_bits * MILLIBITS * 3 / 2);
+        query_millibits.min(commit_millibits) / MILLIBITS
+    }
+
+    /// The digest that a seal proven with these parameters commits to its
+    /// Fiat-Shamir transcript before any challenge is drawn, so that the seal
+    /// can only be verified with the parameters it was proven with.
+    ///
+    /// Seals proven with [FriParams::DEFAULT] commit nothing, so that they are
+    /// unchanged from before the parameters were configurable, and can still
+    /// be verified by the recursion circuit.
+    pub fn transcript_digest(&self) -> Option<Digest> {
+        (*self != Self::DEFAULT).then(|| {
+            Digest::new([
+                self.queries as u32,
+                self.inv_rate as u32,
+                self.fri_fold as u32,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ])
+        })
+    }
+}
+
+impl Default for FriParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FriParams, MAX_QUERIES};
+    use crate::{FRI_FOLD, INV_RATE, MAX_CYCLES_PO2, MIN_CYCLES_PO2, ZK_CYCLES};
+
+    #[test]
+    fn default_security() {
+        let params = FriParams::default();
+        assert!(params.is_supported());
+        assert_eq!(params.conjectured_security_bits(), 100);
+
+        // Each query contributes 1 - log2(7/6) bits, and the commit error is
+        // 123 - 2 * (po2 + 2) - log2(3.5^7 / 3) - 3 bits.
+        assert_eq!(params.proven_security_bits(MIN_CYCLES_PO2), 38);
+        assert_eq!(params.proven_security_bits(MAX_CYCLES_PO2), 38);
+        assert_eq!(params.proven_security_bits(40), 24);
+        for po2 in MIN_CYCLES_PO2..=MAX_CYCLES_PO2 {
+            assert!(params.proven_security_bits(po2) < params.conjectured_security_bits());
+        }
+        assert!(FriParams::new(25).proven_security_bits(20) < params.proven_security_bits(20));
+    }
+
+    #[test]
+    fn default_is_strongest() {
+        assert_eq!(MAX_QUERIES, FriParams::DEFAULT.queries);
+        assert_eq!(FriParams::for_security_bits(101), None);
+    }
+
+    #[test]
+    fn transcript_digest() {
+        assert_eq!(FriParams::DEFAULT.transcript_digest(), None);
+        let digest = FriParams::new(25).transcript_digest().unwrap();
+        assert_ne!(FriParams::new(26).transcript_digest(), Some(digest));
+    }
+
+    #[test]
+    fn for_security_bits() {
+        assert_eq!(FriParams::for_security_bits(100), Some(FriParams::DEFAULT));
+        assert_eq!(FriParams::for_security_bits(0), Some(FriParams::new(1)));
+        let params = FriParams::for_security_bits(61).unwrap();
+        assert_eq!(params.queries, 31);
+        assert_eq!(params.conjectured_security_bits(), 62);
+        assert_eq!(FriParams::for_security_bits(200), None);
+    }
+
+    #[test]
+    fn unsupported() {
+        assert!(!FriParams::new(0).is_supported());
+        assert!(FriParams::new(ZK_CYCLES).is_supported());
+        assert!(!FriParams::new(ZK_CYCLES + 1).is_supported());
+        assert!(!FriParams::new(MAX_QUERIES + 1).is_supported());
+        let params = FriParams {
+            inv_rate: INV_RATE * 2,
+            ..Default::default()
+        };
+        assert!(!params.is_supported());
+        let params = FriParams {
+            fri_fold: FRI_FOLD / 2,
+            ..Default::default()
+        };
+        assert!(!params.is_supported());
+    }
+}
@@ -13,7 +13,9 @@
 // limitations under the License.
 
 use alloc::vec::Vec;
+use core::mem::size_of;
 
+use anyhow::Result;
 use risc0_core::field::ExtElem;
 use tracing::debug;
 
@@ -21,7 +23,7 @@ use crate::{
     core::log2_ceil,
     hal::{Buffer, Hal},
     prove::{merkle::MerkleTreeProver, write_iop::WriteIOP},
-    FRI_FOLD, FRI_MIN_DEGREE, INV_RATE, QUERIES,
+    FRI_FOLD, FRI_MIN_DEGREE, INV_RATE,
 };
 
 struct ProveRoundInfo<H: Hal> {
@@ -36,7 +38,12 @@ impl<H: Hal> ProveRoundInfo<H> {
     /// produce the evaluations of the polynomial, the merkle tree
     /// committing to the evaluation, and the coefficients of the folded
     /// polynomial.
-    pub fn new(hal: &H, iop: &mut WriteIOP<H::Field>, coeffs: &H::Buffer<H::Elem>) -> Self {
+    pub fn new(
+        hal: &H,
+        iop: &mut WriteIOP<H::Field>,
+        coeffs: &H::Buffer<H::Elem>,
+        queries: usize,
+    ) -> Result<Self> {
         debug!("Doing FRI folding");
         let ext_size = H::ExtElem::EXT_SIZE;
         // Get the number of coefficients of the polynomial over the extension field.
@@ -44,6 +51,7 @@ impl<H: Hal> ProveRoundInfo<H> {
         // Get a larger domain to interpolate over.
         let domain = size * INV_RATE;
         // Allocate space in which to put the interpolated values.
+        hal.check_alloc((domain + size / FRI_FOLD) * ext_size * size_of::<H::Elem>())?;
         let evaluated = hal.alloc_elem("evaluated", domain * ext_size);
         // Put in the coefficients, padding out with zeros so that we are left with the
         // same polynomial represented by a larger coefficient list
@@ -56,8 +64,8 @@ impl<H: Hal> ProveRoundInfo<H> {
             &evaluated,
             domain / FRI_FOLD,
             FRI_FOLD * ext_size,
-            QUERIES,
-        );
+            queries,
+        )?;
         // Send the merkle tree (as a commitment) to the virtual IOP verifier
         merkle.commit(iop);
         // Retrieve from the IOP verifier a random value to mix the polynomial slices.
@@ -66,11 +74,11 @@ impl<H: Hal> ProveRoundInfo<H> {
         let out_coeffs = hal.alloc_elem("out_coeffs", size / FRI_FOLD * ext_size);
         // Compute the folded polynomial
         hal.fri_fold(&out_coeffs, coeffs, &fold_mix);
-        ProveRoundInfo {
+        Ok(ProveRoundInfo {
             domain,
             coeffs: out_coeffs,
             merkle,
-        }
+        })
     }
 
     pub fn prove_query(&mut self, hal: &H, iop: &mut WriteIOP<H::Field>, pos: &mut usize) {
@@ -83,21 +91,28 @@ impl<H: Hal> ProveRoundInfo<H> {
     }
 }
 
+/// Prove that `coeffs` are those of a low-degree polynomial.
+///
+/// Once every query position has been drawn, `open` is given all of them, and
+/// returns the 'inner' proof that is then written for each query.
 #[tracing::instrument(skip_all)]
-pub fn fri_prove<H: Hal, F>(
+pub fn fri_prove<H: Hal, F, G>(
     hal: &H,
     iop: &mut WriteIOP<H::Field>,
     coeffs: &H::Buffer<H::Elem>,
-    inner: F,
-) where
-    F: Fn(&mut WriteIOP<H::Field>, usize),
+    queries: usize,
+    open: F,
+) -> Result<()>
+where
+    F: FnOnce(&[usize]) -> Result<G>,
+    G: Fn(&mut WriteIOP<H::Field>, usize),
 {
     let ext_size = H::ExtElem::EXT_SIZE;
     let orig_domain = coeffs.size() / ext_size * INV_RATE;
     let mut rounds = Vec::new();
     let mut coeffs = coeffs.clone();
     while coeffs.size() / ext_size > FRI_MIN_DEGREE {
-        let round = ProveRoundInfo::new(hal, iop, &coeffs);
+        let round = ProveRoundInfo::new(hal, iop, &coeffs, queries)?;
         coeffs = round.coeffs.clone();
         rounds.push(round);
     }
@@ -113,9 +128,13 @@ pub fn fri_prove<H: Hal, F>(
     });
     // Do queries
     debug!("Doing Queries");
-    for _ in 0..QUERIES {
-        // Get a 'random' index.
-        let mut pos = iop.random_bits(log2_ceil(orig_domain)) as usize;
+    // Get a 'random' index for each query. Writing the proofs does not touch
+    // the rng, so these are drawn up front.
+    let positions: Vec<usize> = (0..queries)
+        .map(|_| iop.random_bits(log2_ceil(orig_domain)) as usize)
+        .collect();
+    let inner = open(&positions)?;
+    for mut pos in positions {
         // Do the 'inner' proof for this index
         inner(iop, pos);
         // Write the per-round proofs
@@ -123,4 +142,5 @@ pub fn fri_prove<H: Hal, F>(
             round.prove_query(hal, iop, &mut pos);
         }
     }
+    Ok(())
 }
@@ -13,7 +13,9 @@
 // limitations under the License.
 
 use alloc::vec::Vec;
+use core::mem::size_of;
 
+use anyhow::Result;
 #[allow(unused_imports)]
 use tracing::debug;
 
@@ -21,20 +23,29 @@ use crate::{
     core::digest::Digest,
     hal::{Buffer, Hal},
     merkle::MerkleTreeParams,
-    prove::write_iop::WriteIOP,
+    prove::{is_low_memory, write_iop::WriteIOP},
 };
 
+const LOW_MEMORY_CHUNK_PO2: usize = 10;
+
 pub struct MerkleTreeProver<H: Hal> {
     params: MerkleTreeParams,
 
-    // The retained matrix of values
-    matrix: H::Buffer<H::Elem>,
+    // The retained matrix of values, or None if the tree was built from the
+    // hashes of its rows, in which case the rows are opened with prove_row.
+    matrix: Option<H::Buffer<H::Elem>>,
 
     // A heap style array where node N has children 2*N and 2*N+1.  The size of
     // this buffer is (1 << (layers + 1)) and begins at offset 1 (zero is unused
-    // to make indexing nicer).
+    // to make indexing nicer). In low-memory mode, the layers below the roots
+    // of each chunk of (1 << chunk_po2) rows are not retained, so the size is
+    // (1 << (layers - chunk_po2 + 1)).
     nodes: Vec<Digest>,
 
+    // The log2 of the number of rows in each chunk, or zero if every layer of
+    // the tree is retained.
+    chunk_po2: usize,
+
     // The root value
     root: Digest,
 }
@@ -59,16 +70,111 @@ impl<H: Hal> MerkleTreeProver<H> {
         rows: usize,
         cols: usize,
         queries: usize,
-    ) -> Self {
+    ) -> Result<Self> {
         assert_eq!(matrix.size(), rows * cols);
         let params = MerkleTreeParams::new(rows, cols, queries);
+        // In low-memory mode, the rows are hashed a chunk at a time, and only
+        // the layers from the root of each chunk up are retained.
+        let chunk_po2 = select_chunk_po2(hal, &params);
+        let leaves = rows >> chunk_po2;
         // Allocate nodes
-        let nodes = hal.alloc_digest("nodes", rows * 2);
-        // SHA-256 hash each column
-        hal.hash_rows(&nodes.slice(rows, rows), matrix);
+        hal.check_alloc(leaves * 2 * size_of::<Digest>())?;
+        let nodes = hal.alloc_digest("nodes", leaves * 2);
+        if chunk_po2 == 0 {
+            // SHA-256 hash each column
+            hal.hash_rows(&nodes.slice(rows, rows), matrix);
+        } else {
+            tracing::info_span!("hash_chunks").in_scope(|| -> Result<()> {
+                hal.check_alloc(
+                    (cols << chunk_po2) * size_of::<H::Elem>()
+                        + (2 << chunk_po2) * size_of::<Digest>(),
+                )?;
+                let scratch = hal.alloc_elem("chunk", cols << chunk_po2);
+                let chunk_nodes = hal.alloc_digest("chunk_nodes", 2 << chunk_po2);
+                let mut roots = Vec::with_capacity(leaves);
+                for chunk in 0..leaves {
+                    hash_chunk(
+                        hal,
+                        matrix,
+                        &params,
+                        chunk_po2,
+                        chunk,
+                        &scratch,
+                        &chunk_nodes,
+                    );
+                    chunk_nodes.view(|view| roots.push(view[1]));
+                }
+                nodes
+                    .slice(leaves, leaves)
+                    .view_mut(|view| view.copy_from_slice(&roots));
+                Ok(())
+            })?;
+        }
+        Ok(Self::fold(
+            hal,
+            params,
+            nodes,
+            chunk_po2,
+            Some(matrix.clone()),
+        ))
+    }
+
+    /// Generate a merkle tree from the hashes of the rows of a matrix with
+    /// `cols` columns, without retaining the matrix itself.
+    ///
+    /// The rows of such a tree are opened with
+    /// [MerkleTreeProver::prove_row].
+    #[tracing::instrument(name = "MerkleTreeProver", skip_all)]
+    pub fn from_leaves(
+        hal: &H,
+        leaves: &H::Buffer<Digest>,
+        cols: usize,
+        queries: usize,
+    ) -> Result<Self> {
+        let rows = leaves.size();
+        let params = MerkleTreeParams::new(rows, cols, queries);
+        let chunk_po2 = select_chunk_po2(hal, &params);
+        let chunks = rows >> chunk_po2;
+        hal.check_alloc((chunks * 2 + (2 << chunk_po2)) * size_of::<Digest>())?;
+        let nodes = hal.alloc_digest("nodes", chunks * 2);
+        if chunk_po2 == 0 {
+            leaves.view(|leaves| {
+                nodes
+                    .slice(rows, rows)
+                    .view_mut(|view| view.copy_from_slice(leaves))
+            });
+        } else {
+            tracing::info_span!("fold_chunks").in_scope(|| {
+                let chunk_rows = 1 << chunk_po2;
+                let chunk_nodes = hal.alloc_digest("chunk_nodes", 2 << chunk_po2);
+                let mut roots = Vec::with_capacity(chunks);
+                for chunk in 0..chunks {
+                    leaves.slice(chunk * chunk_rows, chunk_rows).view(|leaves| {
+                        chunk_nodes
+                            .slice(chunk_rows, chunk_rows)
+                            .view_mut(|view| view.copy_from_slice(leaves))
+                    });
+                    fold_chunk(hal, &chunk_nodes, chunk_po2);
+                    chunk_nodes.view(|view| roots.push(view[1]));
+                }
+                nodes
+                    .slice(chunks, chunks)
+                    .view_mut(|view| view.copy_from_slice(&roots));
+            });
+        }
+        Ok(Self::fold(hal, params, nodes, chunk_po2, None))
+    }
+
+    fn fold(
+        hal: &H,
+        params: MerkleTreeParams,
+        nodes: H::Buffer<Digest>,
+        chunk_po2: usize,
+        matrix: Option<H::Buffer<H::Elem>>,
+    ) -> Self {
         // For each layer, sha up the layer below
         tracing::info_span!("hash_fold").in_scope(|| {
-            for i in (0..params.layers).rev() {
+            for i in (0..params.layers - chunk_po2).rev() {
                 let layer_size = 1 << i;
                 hal.hash_fold(&nodes, layer_size * 2, layer_size);
             }
@@ -80,8 +186,9 @@ impl<H: Hal> MerkleTreeProver<H> {
         let root = nodes_host[1];
         MerkleTreeProver {
             params,
-            matrix: matrix.clone(),
+            matrix,
             nodes: nodes_host,
+            chunk_po2,
             root,
         }
     }
@@ -98,6 +205,12 @@ impl<H: Hal> MerkleTreeProver<H> {
         &self.root
     }
 
+    /// The log2 of the number of rows whose hashes are needed to open a row
+    /// with [MerkleTreeProver::prove_row].
+    pub fn chunk_po2(&self) -> usize {
+        self.chunk_po2
+    }
+
     /// Generate a proof at a given index, and return the values at that column.
     ///
     /// The format of the proof is always:
@@ -109,9 +222,13 @@ impl<H: Hal> MerkleTreeProver<H> {
     /// wrong row is specified.
     pub fn prove(&self, hal: &H, iop: &mut WriteIOP<H::Field>, idx: usize) -> Vec<H::Elem> {
         assert!(idx < self.params.row_size);
+        let matrix = self
+            .matrix
+            .as_ref()
+            .expect("A tree built from leaves is opened with prove_row");
         let mut out = Vec::with_capacity(self.params.col_size);
         if hal.has_unified_memory() {
-            self.matrix.view(|view| {
+            matrix.view(|view| {
                 for i in 0..self.params.col_size {
                     out.push(view[idx + i * self.params.row_size]);
                 }
@@ -120,7 +237,7 @@ impl<H: Hal> MerkleTreeProver<H> {
             let sample = hal.alloc_elem("sample", self.params.col_size);
             hal.gather_sample(
                 &sample,
-                &self.matrix,
+                matrix,
                 idx,
                 self.params.col_size,
                 self.params.row_size,
@@ -129,15 +246,127 @@ impl<H: Hal> MerkleTreeProver<H> {
                 out.extend_from_slice(view);
             });
         }
-        iop.write_field_elem_slice::<H::Elem>(out.as_slice());
+        // In low-memory mode, rehash the chunk containing this row to recover
+        // the layers of its subtree that were not retained.
+        let chunk_nodes = if self.chunk_po2 == 0 {
+            Vec::new()
+        } else {
+            let scratch = hal.alloc_elem("chunk", self.params.col_size << self.chunk_po2);
+            let chunk_nodes = hal.alloc_digest("chunk_nodes", 2 << self.chunk_po2);
+            let chunk = idx >> self.chunk_po2;
+            hash_chunk(
+                hal,
+                matrix,
+                &self.params,
+                self.chunk_po2,
+                chunk,
+                &scratch,
+                &chunk_nodes,
+            );
+            let mut nodes = Vec::with_capacity(chunk_nodes.size());
+            chunk_nodes.view(|view| nodes.extend_from_slice(view));
+            nodes
+        };
+        self.write_proof(iop, idx, &out, &chunk_nodes);
+        out
+    }
+
+    /// Generate a proof at a given index of a tree built with
+    /// [MerkleTreeProver::from_leaves].
+    ///
+    /// `row` holds the values at that row, and `chunk_leaves` the hashes of
+    /// the `1 << chunk_po2` rows of the chunk that contains it.
+    pub fn prove_row(
+        &self,
+        hal: &H,
+        iop: &mut WriteIOP<H::Field>,
+        idx: usize,
+        row: &[H::Elem],
+        chunk_leaves: &[Digest],
+    ) {
+        assert!(idx < self.params.row_size);
+        assert_eq!(row.len(), self.params.col_size);
+        assert_eq!(chunk_leaves.len(), 1 << self.chunk_po2);
+        let chunk_nodes = if self.chunk_po2 == 0 {
+            Vec::new()
+        } else {
+            let chunk_rows = chunk_leaves.len();
+            let chunk_nodes = hal.alloc_digest("chunk_nodes", 2 * chunk_rows);
+            chunk_nodes
+                .slice(chunk_rows, chunk_rows)
+                .view_mut(|view| view.copy_from_slice(chunk_leaves));
+            fold_chunk(hal, &chunk_nodes, self.chunk_po2);
+            let mut nodes = Vec::with_capacity(chunk_nodes.size());
+            chunk_nodes.view(|view| nodes.extend_from_slice(view));
+            nodes
+        };
+        self.write_proof(iop, idx, row, &chunk_nodes);
+    }
+
+    fn write_proof(
+        &self,
+        iop: &mut WriteIOP<H::Field>,
+        idx: usize,
+        row: &[H::Elem],
+        chunk_nodes: &[Digest],
+    ) {
+        iop.write_field_elem_slice::<H::Elem>(row);
+        let mut chunk_idx = (1 << self.chunk_po2) + idx % (1 << self.chunk_po2);
         let mut idx = idx + self.params.row_size;
         while idx >= 2 * self.params.top_size {
             let low_bit = idx % 2;
             idx /= 2;
+            chunk_idx /= 2;
             let other_idx = 2 * idx + (1 - low_bit);
-            iop.write_pod_slice(&[self.nodes[other_idx]]);
+            if other_idx < self.nodes.len() {
+                iop.write_pod_slice(&[self.nodes[other_idx]]);
+            } else {
+                iop.write_pod_slice(&[chunk_nodes[2 * chunk_idx + (1 - low_bit)]]);
+            }
         }
-        out
+    }
+}
+
+// In low-memory mode, the chunks are never larger than the subtrees below the
+// top layer.
+fn select_chunk_po2<H: Hal>(hal: &H, params: &MerkleTreeParams) -> usize {
+    if is_low_memory(hal) {
+        LOW_MEMORY_CHUNK_PO2.min(params.layers - params.top_layer)
+    } else {
+        0
+    }
+}
+
+fn hash_chunk<H: Hal>(
+    hal: &H,
+    matrix: &H::Buffer<H::Elem>,
+    params: &MerkleTreeParams,
+    chunk_po2: usize,
+    chunk: usize,
+    scratch: &H::Buffer<H::Elem>,
+    nodes: &H::Buffer<Digest>,
+) {
+    let chunk_rows = 1 << chunk_po2;
+    let start = chunk * chunk_rows;
+    matrix.view(|matrix| {
+        scratch.view_mut(|scratch| {
+            for col in 0..params.col_size {
+                let offset = col * params.row_size + start;
+                scratch[col * chunk_rows..(col + 1) * chunk_rows]
+                    .copy_from_slice(&matrix[offset..offset + chunk_rows]);
+            }
+        });
+    });
+    hal.hash_rows(&nodes.slice(chunk_rows, chunk_rows), scratch);
+    fold_chunk(hal, nodes, chunk_po2);
+}
+
+// Fold the leaves of a chunk, held in the upper half of `nodes`, up to its
+// root.
+fn fold_chunk<H: Hal>(hal: &H, nodes: &H::Buffer<Digest>, chunk_po2: usize) {
+    for i in (0..chunk_po2).rev() {
+        let layer_size = 1 << i;
+        hal.hash_fold(nodes, layer_size * 2, layer_size);
     }
 }
 
@@ -176,7 +405,7 @@ mod tests {
         }
         let matrix = hal.copy_from_elem("matrix", data.as_slice());
 
-        MerkleTreeProver::new(hal, &matrix, rows, cols, queries)
+        MerkleTreeProver::new(hal, &matrix, rows, cols, queries).unwrap()
     }
 
     fn bad_row_access(suite: HashSuite<BabyBear>, rows: usize, cols: usize, queries: usize) {
@@ -223,7 +452,7 @@ mod tests {
             iop.proof[manip_idx] ^= 1;
         }
         let mut r_iop = ReadIOP::new(&iop.proof, rng);
-        let verifier = MerkleTreeVerifier::new(&mut r_iop, hashfn, rows, cols, queries);
+        let verifier = MerkleTreeVerifier::new(&mut r_iop, hashfn, rows, cols, queries).unwrap();
         assert_eq!(verifier.root(), prover.root());
         let mut err = false;
         for query in 0..queries {
@@ -257,7 +486,7 @@ mod tests {
             }
         }
         if !err {
-            r_iop.verify_complete();
+            r_iop.verify_complete().unwrap();
         }
     }
 
@@ -305,6 +534,76 @@ mod tests {
         (rows, cols, queries)
     }
 
+    fn low_memory(suite: HashSuite<BabyBear>, rows: usize, cols: usize, queries: usize) {
+        let hal = CpuHal::new(suite.clone());
+        let low_memory_hal = CpuHal::new(suite).with_memory_budget(usize::MAX);
+        let prover = init_prover(&hal, rows, cols, queries);
+        let low_memory_prover = init_prover(&low_memory_hal, rows, cols, queries);
+        assert_eq!(prover.root(), low_memory_prover.root());
+        assert!(low_memory_prover.nodes.len() < prover.nodes.len());
+
+        let rng = hal.get_hash_suite().rng.as_ref();
+        let mut iop = WriteIOP::new(rng);
+        let mut low_memory_iop = WriteIOP::new(rng);
+        prover.commit(&mut iop);
+        low_memory_prover.commit(&mut low_memory_iop);
+        for idx in [0, 1, rows / 2 - 1, rows / 2, rows - 1] {
+            prover.prove(&hal, &mut iop, idx);
+            low_memory_prover.prove(&low_memory_hal, &mut low_memory_iop, idx);
+        }
+        assert_eq!(iop.proof, low_memory_iop.proof);
+    }
+
+    fn from_leaves_matches(
+        suite: HashSuite<BabyBear>,
+        low_memory: bool,
+        rows: usize,
+        cols: usize,
+        queries: usize,
+    ) {
+        let hal = CpuHal::new(suite);
+        let hal = if low_memory {
+            hal.with_memory_budget(usize::MAX)
+        } else {
+            hal
+        };
+        let prover = init_prover(&hal, rows, cols, queries);
+        let leaves = hal.alloc_digest("leaves", rows);
+        hal.hash_rows(&leaves, prover.matrix.as_ref().unwrap());
+        let leaf_prover = MerkleTreeProver::from_leaves(&hal, &leaves, cols, queries).unwrap();
+        assert_eq!(prover.root(), leaf_prover.root());
+        assert_eq!(prover.nodes, leaf_prover.nodes);
+
+        let rng = hal.get_hash_suite().rng.as_ref();
+        let mut iop = WriteIOP::new(rng);
+        let mut leaf_iop = WriteIOP::new(rng);
+        prover.commit(&mut iop);
+        leaf_prover.commit(&mut leaf_iop);
+        let chunk_rows = 1 << leaf_prover.chunk_po2();
+        for idx in [0, 1, rows / 2 - 1, rows / 2, rows - 1] {
+            let row = prover.prove(&hal, &mut iop, idx);
+            let start = idx / chunk_rows * chunk_rows;
+            leaves.slice(start, chunk_rows).view(|chunk_leaves| {
+                leaf_prover.prove_row(&hal, &mut leaf_iop, idx, &row, chunk_leaves)
+            });
+        }
+        assert_eq!(iop.proof, leaf_iop.proof);
+    }
+
+    #[test]
+    fn merkle_cpu_from_leaves() {
+        for low_memory in [false, true] {
+            from_leaves_matches(Sha256HashSuite::new_suite(), low_memory, 1 << 14, 3, 50);
+            from_leaves_matches(Poseidon2HashSuite::new_suite(), low_memory, 1 << 8, 5, 2);
+        }
+    }
+
+    #[test]
+    fn merkle_cpu_low_memory() {
+        low_memory(Sha256HashSuite::new_suite(), 1 << 14, 3, 50);
+        low_memory(Poseidon2HashSuite::new_suite(), 1 << 8, 5, 2);
+    }
+
     #[test]
     #[should_panic(expected = "assertion failed: idx < self.params.row_size")]
     fn merkle_cpu_1_1_1_bad_row_access() {
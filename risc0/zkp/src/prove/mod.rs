@@ -29,3 +29,11 @@ pub mod prover;
 pub mod write_iop;
 
 pub use prover::Prover;
+
+use crate::hal::Hal;
+
+/// Whether the prover runs in low-memory mode on `hal`. See
+/// [Hal::get_memory_budget].
+pub(crate) fn is_low_memory<H: Hal>(hal: &H) -> bool {
+    hal.get_memory_budget().is_some() && hal.has_unified_memory()
+}
@@ -12,70 +12,300 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use core::mem::size_of;
+
+use anyhow::Result;
+use rayon::prelude::*;
+use risc0_core::field::{Elem, RootsOfUnity};
+
 use crate::{
-    core::log2_ceil,
+    core::{
+        digest::Digest,
+        log2_ceil,
+        ntt::{bit_reverse, evaluate_ntt},
+    },
     hal::{Buffer, Hal},
-    prove::merkle::MerkleTreeProver,
-    INV_RATE, QUERIES,
+    prove::{is_low_memory, merkle::MerkleTreeProver, write_iop::WriteIOP},
+    INV_RATE,
 };
 
-/// A PolyGroup represents a group of polynomials, all of the same maximum
-/// degree, as well as the evaluation of those polynomials over some domain that
-/// is larger than that degree by some invRate. Additionally, it includes a
-/// dense Merkle tree, where each entry is a single point of the domain, and the
-/// leaf hash is a simple linear hash of all of the values at that point.  That
-/// is, if we have 100 polynomials evaluated on 2^16 points, the merkle tree has
-/// 2^16 entries, each being a hash of 100 values.  The size of the domain is
-/// always a power of 2 so that we can use NTTs.
-///
-/// The primary purpose of the PolyGroup is for use in the DEEP-ALI protocol,
-/// which basically needs 4 methods during proof generation, specifically we
-/// need to: 1) Resolve queries (i.e. make MerkleColProofs)
-/// 2) Do evaluation of the polynomials at 'randomly' chosen points
-/// 3) Mix the polynomials via a random set of linear coefficients
-/// 4) Access the raw values in the evaluation domain to 'evaluate' the
-/// constraint polynomial
-///
-/// The poly group holds 3 buffers:
-/// 1) The per-polynomial coefficients, used for evaluation + mixing
-/// 2) The points evaluated on the domain in question (for the 'col' part of
-/// merkle proofs) 3) The Merkle tree itself.
-///
-/// PolyGroups are constructed from two basic sources: steps of a computations,
-/// and a single higher degree polynomial that has been split into lower degree
-/// parts.  In the case of computations, the resulting steps must be padded
-/// (possibly with randomized data), which is presumed to be done by the caller.
-/// The constructor additionally 'shifts' the polynomial so that f(x) -> f(3*x),
-/// which means that the normal NTT evaluation domain does not reveal anything
-/// about the original datapoints (i.e. is zero knowledge) so long as the number
-/// of queries is less than the randomized padding.
 pub struct PolyGroup<H: Hal> {
     pub coeffs: H::Buffer<H::Elem>,
     pub count: usize,
-    pub evaluated: H::Buffer<H::Elem>,
+    /// The evaluations of the polynomials over the whole evaluation domain.
+    ///
+    /// This is `None` in low-memory mode, where the domain is evaluated and
+    /// hashed one coset at a time instead. See [PolyGroup::expand].
+    pub evaluated: Option<H::Buffer<H::Elem>>,
     pub merkle: MerkleTreeProver<H>,
 }
 
+/// The rows of a [PolyGroup] committed in low-memory mode that are opened by
+/// the queries of a proof.
+pub(crate) struct Openings<E> {
+    // For each row, its values and the hashes of every row in its chunk of the
+    // Merkle tree.
+    rows: BTreeMap<usize, (Vec<E>, Vec<Digest>)>,
+}
+
 impl<H: Hal> PolyGroup<H> {
-    #[tracing::instrument(name = "PolyGroup", skip_all, fields(name = _name))]
+    /// Commit to the `count` polynomials of `size` coefficients each in
+    /// `coeffs`.
+    ///
+    /// Panics if the memory budget of `hal` would be exceeded; see
+    /// [PolyGroup::try_new].
     pub fn new(
         hal: &H,
         coeffs: H::Buffer<H::Elem>,
         count: usize,
         size: usize,
-        _name: &'static str,
+        queries: usize,
+        name: &'static str,
     ) -> Self {
+        Self::try_new(hal, coeffs, count, size, queries, name).unwrap()
+    }
+
+    /// Commit to the polynomials in `coeffs` like [PolyGroup::new], failing
+    /// if the memory budget of `hal` would be exceeded.
+    #[tracing::instrument(name = "PolyGroup", skip_all, fields(name = _name))]
+    pub fn try_new(
+        hal: &H,
+        coeffs: H::Buffer<H::Elem>,
+        count: usize,
+        size: usize,
+        queries: usize,
+        _name: &'static str,
+    ) -> Result<Self> {
         assert_eq!(coeffs.size(), count * size);
         let domain = size * INV_RATE;
+        if is_low_memory(hal) {
+            hal.batch_bit_reverse(&coeffs, count);
+            hal.check_alloc(domain * size_of::<Digest>())?;
+            let leaves = hal.alloc_digest("leaves", domain);
+            for_each_coset(hal, &coeffs, count, |coset, _, hashes| {
+                leaves.view_mut(|leaves| {
+                    for (row, hash) in hashes.iter().enumerate() {
+                        leaves[coset + row * INV_RATE] = *hash;
+                    }
+                })
+            })?;
+            let merkle = MerkleTreeProver::from_leaves(hal, &leaves, count, queries)?;
+            return Ok(PolyGroup {
+                coeffs,
+                count,
+                evaluated: None,
+                merkle,
+            });
+        }
+        hal.check_alloc(count * domain * size_of::<H::Elem>())?;
         let evaluated = hal.alloc_elem("evaluated", count * domain);
         hal.batch_expand_into_evaluate_ntt(&evaluated, &coeffs, count, log2_ceil(INV_RATE));
         hal.batch_bit_reverse(&coeffs, count);
-        let merkle = MerkleTreeProver::new(hal, &evaluated, domain, count, QUERIES);
-        PolyGroup {
+        let merkle = MerkleTreeProver::new(hal, &evaluated, domain, count, queries)?;
+        Ok(PolyGroup {
             coeffs,
             count,
-            evaluated,
+            evaluated: Some(evaluated),
             merkle,
+        })
+    }
+
+    /// Get the evaluations of the polynomials over the whole evaluation
+    /// domain, expanding them again from the coefficients in low-memory mode.
+    pub fn expand(&self, hal: &H) -> Result<H::Buffer<H::Elem>> {
+        if let Some(evaluated) = &self.evaluated {
+            return Ok(evaluated.clone());
+        }
+        let domain = self.coeffs.size() / self.count * INV_RATE;
+        hal.check_alloc(self.count * domain * size_of::<H::Elem>())?;
+        let evaluated = hal.alloc_elem("evaluated", self.count * domain);
+        // The coefficients are kept in natural order, but are expanded from
+        // bit-reversed order.
+        hal.batch_bit_reverse(&self.coeffs, self.count);
+        hal.batch_expand_into_evaluate_ntt(
+            &evaluated,
+            &self.coeffs,
+            self.count,
+            log2_ceil(INV_RATE),
+        );
+        hal.batch_bit_reverse(&self.coeffs, self.count);
+        Ok(evaluated)
+    }
+
+    /// Recover the rows at `positions` in low-memory mode, where they are not
+    /// retained, so that they can be proven with [PolyGroup::prove].
+    pub(crate) fn open(&self, hal: &H, positions: &[usize]) -> Result<Openings<H::Elem>> {
+        let mut rows = BTreeMap::new();
+        if self.evaluated.is_some() {
+            return Ok(Openings { rows });
         }
+        let size = self.coeffs.size() / self.count;
+        let chunk_rows = 1 << self.merkle.chunk_po2();
+        for pos in positions {
+            let opening = (
+                vec![H::Elem::ZERO; self.count],
+                vec![Digest::ZERO; chunk_rows],
+            );
+            rows.insert(*pos, opening);
+        }
+        for_each_coset(hal, &self.coeffs, self.count, |coset, evaluated, hashes| {
+            for (pos, (row, leaves)) in rows.iter_mut() {
+                if pos % INV_RATE == coset {
+                    for (col, value) in row.iter_mut().enumerate() {
+                        *value = evaluated[col * size + pos / INV_RATE];
+                    }
+                }
+                let start = pos / chunk_rows * chunk_rows;
+                for (i, leaf) in leaves.iter_mut().enumerate() {
+                    if (start + i) % INV_RATE == coset {
+                        *leaf = hashes[(start + i) / INV_RATE];
+                    }
+                }
+            }
+        })?;
+        Ok(Openings { rows })
+    }
+
+    /// Generate the Merkle proof of the row at `idx`, using `openings` if the
+    /// group was committed in low-memory mode.
+    pub(crate) fn prove(
+        &self,
+        hal: &H,
+        iop: &mut WriteIOP<H::Field>,
+        idx: usize,
+        openings: &Openings<H::Elem>,
+    ) {
+        match openings.rows.get(&idx) {
+            Some((row, leaves)) => self.merkle.prove_row(hal, iop, idx, row, leaves),
+            None => {
+                self.merkle.prove(hal, iop, idx);
+            }
+        }
+    }
+}
+
+// Evaluate the polynomials given by `coeffs`, in natural order, over each coset
+// of rows `coset + INV_RATE * k` of the evaluation domain in turn. `f` is given
+// the coset, its evaluations and the hashes of its rows.
+fn for_each_coset<H, F>(hal: &H, coeffs: &H::Buffer<H::Elem>, count: usize, mut f: F) -> Result<()>
+where
+    H: Hal,
+    F: FnMut(usize, &[H::Elem], &[Digest]),
+{
+    let size = coeffs.size() / count;
+    let domain = size * INV_RATE;
+    hal.check_alloc(count * size * size_of::<H::Elem>() + size * size_of::<Digest>())?;
+    let evaluated = hal.alloc_elem("coset", count * size);
+    let hashes = hal.alloc_digest("coset_hashes", size);
+    for coset in 0..INV_RATE {
+        // Scaling the coefficient of x^i by shift^i moves the evaluation domain
+        // of a size-point NTT onto this coset.
+        let shift = H::Elem::ROU_FWD[log2_ceil(domain)].pow(coset);
+        coeffs.view(|coeffs| {
+            evaluated.view_mut(|evaluated| {
+                evaluated
+                    .par_chunks_exact_mut(size)
+                    .zip(coeffs.par_chunks_exact(size))
+                    .for_each(|(evaluated, coeffs)| {
+                        let mut x = H::Elem::ONE;
+                        for (value, coeff) in evaluated.iter_mut().zip(coeffs) {
+                            *value = *coeff * x;
+                            x *= shift;
+                        }
+                        bit_reverse(evaluated);
+                        evaluate_ntt::<H::Elem, H::Elem>(evaluated, 0);
+                    });
+            })
+        });
+        hal.hash_rows(&hashes, &evaluated);
+        evaluated.view(|evaluated| hashes.view(|hashes| f(coset, evaluated, hashes)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::thread_rng;
+    use risc0_core::field::baby_bear::{BabyBear, BabyBearElem};
+
+    use super::*;
+    use crate::{core::hash::sha::Sha256HashSuite, hal::cpu::CpuHal, prove::write_iop::WriteIOP};
+
+    const COUNT: usize = 5;
+    const SIZE: usize = 1 << 12;
+    const QUERIES: usize = 50;
+
+    fn random_coeffs() -> Vec<BabyBearElem> {
+        let mut rng = thread_rng();
+        (0..COUNT * SIZE)
+            .map(|_| BabyBearElem::random(&mut rng))
+            .collect()
+    }
+
+    #[test]
+    fn low_memory() {
+        let coeffs = random_coeffs();
+        let hal: CpuHal<BabyBear> = CpuHal::new(Sha256HashSuite::new_suite());
+        let low_memory_hal: CpuHal<BabyBear> =
+            CpuHal::new(Sha256HashSuite::new_suite()).with_memory_budget(usize::MAX);
+        let group = PolyGroup::new(
+            &hal,
+            hal.copy_from_elem("coeffs", &coeffs),
+            COUNT,
+            SIZE,
+            QUERIES,
+            "test",
+        );
+        let low_memory_group = PolyGroup::new(
+            &low_memory_hal,
+            low_memory_hal.copy_from_elem("coeffs", &coeffs),
+            COUNT,
+            SIZE,
+            QUERIES,
+            "test",
+        );
+        assert!(low_memory_group.evaluated.is_none());
+        assert_eq!(group.merkle.root(), low_memory_group.merkle.root());
+        group
+            .coeffs
+            .view(|a| low_memory_group.coeffs.view(|b| assert_eq!(a, b)));
+        let evaluated = group.expand(&hal).unwrap();
+        let low_memory_evaluated = low_memory_group.expand(&low_memory_hal).unwrap();
+        evaluated.view(|a| low_memory_evaluated.view(|b| assert_eq!(a, b)));
+
+        // The rows opened from the coefficients prove the same as the rows of
+        // the retained evaluations.
+        let positions = [0, 1, 3, 1234, SIZE * INV_RATE - 1, 1234];
+        let openings = group.open(&hal, &positions).unwrap();
+        let low_memory_openings = low_memory_group.open(&low_memory_hal, &positions).unwrap();
+        let rng = hal.get_hash_suite().rng.as_ref();
+        let mut iop = WriteIOP::new(rng);
+        let mut low_memory_iop = WriteIOP::new(rng);
+        for idx in positions {
+            group.prove(&hal, &mut iop, idx, &openings);
+            low_memory_group.prove(
+                &low_memory_hal,
+                &mut low_memory_iop,
+                idx,
+                &low_memory_openings,
+            );
+        }
+        assert_eq!(iop.proof, low_memory_iop.proof);
+    }
+
+    #[test]
+    fn memory_budget() {
+        let coeffs = random_coeffs();
+        let size = coeffs.len() * size_of::<BabyBearElem>();
+        let hal: CpuHal<BabyBear> =
+            CpuHal::new(Sha256HashSuite::new_suite()).with_memory_budget(2 * size);
+        let coeffs = hal.copy_from_elem("coeffs", &coeffs);
+        let err = PolyGroup::try_new(&hal, coeffs, COUNT, SIZE, QUERIES, "test")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Memory budget"));
+        // The commitment failed before allocating anything more.
+        assert_eq!(hal.get_memory_usage(), size);
     }
 }
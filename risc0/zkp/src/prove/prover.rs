@@ -12,12 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use core::mem::size_of;
+
+use anyhow::Result;
 use rayon::prelude::*;
 use risc0_core::field::{Elem, ExtElem, RootsOfUnity};
 
 use crate::{
     core::poly::{poly_divide, poly_interpolate},
     hal::{Buffer, CircuitHal, Hal},
+    params::FriParams,
     prove::{fri::fri_prove, poly_group::PolyGroup, write_iop::WriteIOP},
     taps::TapSet,
     INV_RATE,
@@ -27,6 +31,7 @@ use crate::{
 pub struct Prover<'a, H: Hal> {
     hal: &'a H,
     taps: &'a TapSet<'a>,
+    params: FriParams,
     iop: WriteIOP<H::Field>,
     groups: Vec<Option<PolyGroup<H>>>,
     cycles: usize,
@@ -45,10 +50,24 @@ fn make_coeffs<H: Hal>(hal: &H, buf: H::Buffer<H::Elem>, count: usize) -> H::Buf
 impl<'a, H: Hal> Prover<'a, H> {
     /// Creates a new prover.
     pub fn new(hal: &'a H, taps: &'a TapSet) -> Self {
+        Self::new_with_params(hal, taps, FriParams::DEFAULT)
+    }
+
+    /// Creates a new prover that uses the given [FriParams].
+    pub fn new_with_params(hal: &'a H, taps: &'a TapSet, params: FriParams) -> Self {
+        assert!(
+            params.is_supported(),
+            "Unsupported FRI parameters: {params:?}"
+        );
+        let mut iop = WriteIOP::new(hal.get_hash_suite().rng.as_ref());
+        if let Some(digest) = params.transcript_digest() {
+            iop.commit(&digest);
+        }
         Self {
             hal,
             taps,
-            iop: WriteIOP::new(hal.get_hash_suite().rng.as_ref()),
+            params,
+            iop,
             groups: std::iter::repeat_with(|| None)
                 .take(taps.num_groups())
                 .collect(),
@@ -73,8 +92,21 @@ impl<'a, H: Hal> Prover<'a, H> {
 
     /// Commits a given buffer to the IOP; the values must not subsequently
     /// change.
-    #[tracing::instrument(skip_all)]
+    ///
+    /// Panics if the memory budget of the HAL would be exceeded; see
+    /// [Prover::try_commit_group].
     pub fn commit_group(&mut self, tap_group_index: usize, buf: H::Buffer<H::Elem>) {
+        self.try_commit_group(tap_group_index, buf).unwrap()
+    }
+
+    /// Commits a given buffer to the IOP like [Prover::commit_group], failing
+    /// if the memory budget of the HAL would be exceeded.
+    #[tracing::instrument(skip_all)]
+    pub fn try_commit_group(
+        &mut self,
+        tap_group_index: usize,
+        buf: H::Buffer<H::Elem>,
+    ) -> Result<()> {
         let group_size = self.taps.group_size(tap_group_index);
         assert_eq!(buf.size() % group_size, 0);
         assert_eq!(buf.size() / group_size, self.cycles);
@@ -85,13 +117,14 @@ impl<'a, H: Hal> Prover<'a, H> {
         );
 
         let coeffs = make_coeffs(self.hal, buf, group_size);
-        let group_ref = self.groups[tap_group_index].insert(PolyGroup::new(
+        let group_ref = self.groups[tap_group_index].insert(PolyGroup::try_new(
             self.hal,
             coeffs,
             group_size,
             self.cycles,
+            self.params.queries,
             "data",
-        ));
+        )?);
 
         group_ref.merkle.commit(&mut self.iop);
 
@@ -100,11 +133,28 @@ impl<'a, H: Hal> Prover<'a, H> {
             self.taps.group_name(tap_group_index),
             group_ref.merkle.root()
         );
+        Ok(())
     }
 
     /// Generates the proof and returns the seal.
+    ///
+    /// Panics if the memory budget of the HAL would be exceeded; see
+    /// [Prover::try_finalize].
+    pub fn finalize<C>(self, globals: &[&H::Buffer<H::Elem>], circuit_hal: &C) -> Vec<u32>
+    where
+        C: CircuitHal<H>,
+    {
+        self.try_finalize(globals, circuit_hal).unwrap()
+    }
+
+    /// Generates the proof like [Prover::finalize], failing if the memory
+    /// budget of the HAL would be exceeded.
     #[tracing::instrument(skip_all)]
-    pub fn finalize<C>(mut self, globals: &[&H::Buffer<H::Elem>], circuit_hal: &C) -> Vec<u32>
+    pub fn try_finalize<C>(
+        mut self,
+        globals: &[&H::Buffer<H::Elem>],
+        circuit_hal: &C,
+    ) -> Result<Vec<u32>>
     where
         C: CircuitHal<H>,
     {
@@ -118,13 +168,18 @@ impl<'a, H: Hal> Prover<'a, H> {
         // The check polynomial is the core of the STARK: if the constraints are
         // satisfied, the check polynomial will be a low-degree polynomial. See
         // DEEP-ALI paper for details on the construction of the check_poly.
+        self.hal
+            .check_alloc(ext_size * domain * size_of::<H::Elem>())?;
         let check_poly = self.hal.alloc_elem("check_poly", ext_size * domain);
 
-        let groups: Vec<&_> = self
+        // In low-memory mode, the groups are only expanded over the whole
+        // evaluation domain while the check polynomial is evaluated.
+        let evaluated = self
             .groups
             .iter()
-            .map(|pg| &pg.as_ref().unwrap().evaluated)
-            .collect();
+            .map(|pg| pg.as_ref().unwrap().expand(self.hal))
+            .collect::<Result<Vec<_>>>()?;
+        let groups: Vec<&_> = evaluated.iter().collect();
         circuit_hal.eval_check(
             &check_poly,
             groups.as_slice(),
@@ -133,6 +188,8 @@ impl<'a, H: Hal> Prover<'a, H> {
             self.po2,
             self.cycles,
         );
+        drop(groups);
+        drop(evaluated);
 
         #[cfg(feature = "circuit_debug")]
         check_poly.view(|check_out| {
@@ -162,7 +219,14 @@ impl<'a, H: Hal> Prover<'a, H> {
         // invRate*size to 16 polys of size, without actually doing anything.
 
         // Make the PolyGroup + add it to the IOP;
-        let check_group = PolyGroup::new(self.hal, check_poly, H::CHECK_SIZE, self.cycles, "check");
+        let check_group = PolyGroup::try_new(
+            self.hal,
+            check_poly,
+            H::CHECK_SIZE,
+            self.cycles,
+            self.params.queries,
+            "check",
+        )?;
         check_group.merkle.commit(&mut self.iop);
         tracing::debug!("checkGroup: {}", check_group.merkle.root());
 
@@ -254,6 +318,8 @@ impl<'a, H: Hal> Prover<'a, H> {
         // Do the coefficent mixing
         // Begin by making a zeroed output buffer
         let combo_count = self.taps.combos_size();
+        self.hal
+            .check_alloc(self.cycles * (combo_count + 1) * size_of::<H::ExtElem>())?;
         let combos = vec![H::ExtElem::ZERO; self.cycles * (combo_count + 1)];
         let combos = self.hal.copy_from_extelem("combos", combos.as_slice());
         tracing::info_span!("mix_poly_coeffs").in_scope(|| {
@@ -338,26 +404,48 @@ impl<'a, H: Hal> Prover<'a, H> {
         });
         // Sum the combos up into one final polynomial + make it into 4 Fp polys.
         // Additionally, it needs to be bit reversed to make everyone happy
+        self.hal
+            .check_alloc(self.cycles * ext_size * size_of::<H::Elem>())?;
         let final_poly_coeffs = self
             .hal
             .alloc_elem("final_poly_coeffs", self.cycles * ext_size);
         self.hal.eltwise_sum_extelem(&final_poly_coeffs, &combos);
 
+        // Only the groups are needed from here on, so release the combos
+        // before FRI allocates its own buffers.
+        drop(combos);
+        let groups: Vec<_> = std::mem::take(&mut self.groups)
+            .into_iter()
+            .map(Option::unwrap)
+            .chain(std::iter::once(check_group))
+            .collect();
+
         // Finally do the FRI protocol to prove the degree of the polynomial
         self.hal.batch_bit_reverse(&final_poly_coeffs, ext_size);
         tracing::debug!("FRI-proof, size = {}", final_poly_coeffs.size() / ext_size);
 
-        fri_prove(self.hal, &mut self.iop, &final_poly_coeffs, |iop, idx| {
-            for pg in self.groups.iter() {
-                let pg = pg.as_ref().unwrap();
-                pg.merkle.prove(self.hal, iop, idx);
-            }
-            check_group.merkle.prove(self.hal, iop, idx);
-        });
+        let hal = self.hal;
+        fri_prove(
+            hal,
+            &mut self.iop,
+            &final_poly_coeffs,
+            self.params.queries,
+            |positions| {
+                let openings = groups
+                    .iter()
+                    .map(|pg| pg.open(hal, positions))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(move |iop: &mut WriteIOP<H::Field>, idx| {
+                    for (pg, openings) in groups.iter().zip(openings.iter()) {
+                        pg.prove(hal, iop, idx, openings);
+                    }
+                })
+            },
+        )?;
 
         // Return final proof
         let proof = self.iop.proof;
         tracing::debug!("Proof size = {}", proof.len());
-        proof
+        Ok(proof)
     }
 }
@@ -25,7 +25,7 @@ use crate::{
         ntt::{bit_reverse, interpolate_ntt},
     },
     verify::{merkle::MerkleTreeVerifier, read_iop::ReadIOP, VerificationError},
-    FRI_FOLD, FRI_FOLD_PO2, FRI_MIN_DEGREE, INV_RATE, QUERIES,
+    FRI_FOLD, FRI_FOLD_PO2, FRI_MIN_DEGREE, INV_RATE,
 };
 
 /// VerifyRoundInfo contains the data against which the queries for a particular
@@ -38,19 +38,24 @@ struct VerifyRoundInfo<'a, F: Field> {
 }
 
 impl<'a, F: Field> VerifyRoundInfo<'a, F> {
-    pub fn new(iop: &mut ReadIOP<'a, F>, hashfn: &dyn HashFn<F>, in_domain: usize) -> Self {
+    pub fn new(
+        iop: &mut ReadIOP<'a, F>,
+        hashfn: &dyn HashFn<F>,
+        in_domain: usize,
+        queries: usize,
+    ) -> Result<Self, VerificationError> {
         let domain = in_domain / FRI_FOLD;
-        VerifyRoundInfo {
+        Ok(VerifyRoundInfo {
             domain,
             merkle: MerkleTreeVerifier::new(
                 iop,
                 hashfn,
                 domain,
                 FRI_FOLD * F::ExtElem::EXT_SIZE,
-                QUERIES,
-            ),
+                queries,
+            )?,
             mix: iop.random_ext_elem(),
-        }
+        })
     }
 }
 
@@ -109,33 +114,30 @@ where
         let hashfn = self.suite.hashfn.as_ref();
         let orig_domain = INV_RATE * degree;
         let mut domain = orig_domain;
-        // Prep the folding verfiers
+        // Prep the folding verfiers. The capacity is enough for every degree
+        // that verify accepts, so we don't reallocate here.
         let rounds_capacity =
             (log2_ceil((degree + FRI_FOLD - 1) / FRI_FOLD) + FRI_FOLD_PO2 - 1) / FRI_FOLD_PO2;
         let mut rounds = Vec::with_capacity(rounds_capacity);
         while degree > FRI_MIN_DEGREE {
-            rounds.push(VerifyRoundInfo::new(iop, hashfn, domain));
+            rounds.push(VerifyRoundInfo::new(
+                iop,
+                hashfn,
+                domain,
+                self.params.queries,
+            )?);
             domain /= FRI_FOLD;
             degree /= FRI_FOLD;
         }
-        // We want to minimize reallocation in verify, so make sure we
-        // didn't have to reallocate.
-        assert!(
-            rounds.len() < rounds_capacity,
-            "Did not allocate enough rounds; needed {} for degree {} but only allocated {}",
-            rounds.len(),
-            degree,
-            rounds_capacity
-        );
         // Grab the final coeffs + commit
-        let final_coeffs = iop.read_field_elem_slice(F::ExtElem::EXT_SIZE * degree);
+        let final_coeffs = iop.read_field_elem_slice(F::ExtElem::EXT_SIZE * degree)?;
         let final_digest = hashfn.hash_elem_slice(final_coeffs);
         iop.commit(&final_digest);
         // Get the generator for the final polynomial evaluations
         let gen = <F::Elem as RootsOfUnity>::ROU_FWD[log2_ceil(domain)];
         // Do queries
         let mut poly_buf: Vec<F::ExtElem> = Vec::with_capacity(degree);
-        for _ in 0..QUERIES {
+        for _ in 0..self.params.queries {
             let mut pos = iop.random_bits(log2_ceil(orig_domain)) as usize;
             // Do the 'inner' verification for this index
             let mut goal = inner(iop, pos)?;
@@ -28,8 +28,9 @@ use risc0_core::field::{Elem, ExtElem, Field, RootsOfUnity};
 use crate::{
     adapter::{CircuitCoreDef, REGISTER_GROUP_ACCUM, REGISTER_GROUP_CODE, REGISTER_GROUP_DATA},
     core::{digest::Digest, hash::HashSuite, log2_ceil},
+    params::FriParams,
     taps::TapSet,
-    INV_RATE, MAX_CYCLES_PO2, QUERIES,
+    INV_RATE, MAX_CYCLES_PO2, MIN_CYCLES_PO2,
 };
 
 #[derive(PartialEq)]
@@ -43,6 +44,7 @@ pub enum VerificationError {
     JournalDigestMismatch,
     UnexpectedExitCode,
     InvalidHashSuite,
+    InvalidFriParams,
     FaultStateMismatch,
     ValidFaultReceipt,
 }
@@ -69,6 +71,12 @@ impl fmt::Display for VerificationError {
             }
             VerificationError::UnexpectedExitCode => write!(f, "Unexpected exit_code"),
             VerificationError::InvalidHashSuite => write!(f, "Invalid hash suite"),
+            VerificationError::InvalidFriParams => {
+                write!(
+                    f,
+                    "FRI parameters are unsupported or below the required security"
+                )
+            }
             VerificationError::FaultStateMismatch => {
                 write!(f, "Fault checker generated incorrect guest state")
             }
@@ -99,6 +107,7 @@ where
 {
     circuit: &'a C,
     suite: &'a HashSuite<F>,
+    params: FriParams,
     po2: u32,
     steps: usize,
     out: Option<&'a [F::Elem]>,
@@ -113,10 +122,11 @@ where
     F: Field,
     C: CircuitCoreDef<F>,
 {
-    fn new(circuit: &'a C, suite: &'a HashSuite<F>) -> Self {
+    fn new(circuit: &'a C, suite: &'a HashSuite<F>, params: FriParams) -> Self {
         Self {
             circuit,
             suite,
+            params,
             po2: 0,
             steps: 0,
             out: None,
@@ -209,21 +219,27 @@ where
     where
         CheckCodeFn: Fn(u32, &Digest) -> Result<(), VerificationError>,
     {
+        if !self.params.is_supported() {
+            return Err(VerificationError::InvalidFriParams);
+        }
         if seal.is_empty() {
             return Err(VerificationError::ReceiptFormatError);
         }
+        let queries = self.params.queries;
 
         let taps = self.circuit.get_taps();
         let hashfn = self.suite.hashfn.as_ref();
 
-        // Make IOP
+        // Make IOP, bound to the FRI parameters
         let mut iop = ReadIOP::new(seal, self.suite.rng.as_ref());
+        if let Some(digest) = self.params.transcript_digest() {
+            iop.commit(&digest);
+        }
 
         // Read any execution state
-        self.execute(&mut iop);
+        self.execute(&mut iop)?;
 
         // Get the size
-        assert!(self.po2 as usize <= MAX_CYCLES_PO2);
         let size = 1 << self.po2;
         let domain = INV_RATE * size;
         // tracing::debug!("size = {size}, po2 = {po2}");
@@ -237,7 +253,7 @@ where
         // The code merkle tree contains the control instructions for the zkVM.
         #[cfg(not(target_os = "zkvm"))]
         tracing::debug!("code_merkle");
-        let code_merkle = MerkleTreeVerifier::new(&mut iop, hashfn, domain, code_size, QUERIES);
+        let code_merkle = MerkleTreeVerifier::new(&mut iop, hashfn, domain, code_size, queries)?;
         // tracing::debug!("codeRoot = {}", code_merkle.root());
         check_code(self.po2, code_merkle.root())?;
 
@@ -247,7 +263,7 @@ where
         // accesses sorted by location used by PLONK.
         #[cfg(not(target_os = "zkvm"))]
         tracing::debug!("data_merkle");
-        let data_merkle = MerkleTreeVerifier::new(&mut iop, hashfn, domain, data_size, QUERIES);
+        let data_merkle = MerkleTreeVerifier::new(&mut iop, hashfn, domain, data_size, queries)?;
         // tracing::debug!("dataRoot = {}", data_merkle.root());
 
         // Prep accumulation
@@ -267,7 +283,7 @@ where
         // implement a look-up table.
         #[cfg(not(target_os = "zkvm"))]
         tracing::debug!("accum_merkle");
-        let accum_merkle = MerkleTreeVerifier::new(&mut iop, hashfn, domain, accum_size, QUERIES);
+        let accum_merkle = MerkleTreeVerifier::new(&mut iop, hashfn, domain, accum_size, queries)?;
         // tracing::debug!("accumRoot = {}", accum_merkle.root());
 
         // Get a pseudorandom value with which to mix the constraint polynomials.
@@ -277,7 +293,7 @@ where
         #[cfg(not(target_os = "zkvm"))]
         tracing::debug!("check_merkle");
         let check_merkle =
-            MerkleTreeVerifier::new(&mut iop, hashfn, domain, Self::CHECK_SIZE, QUERIES);
+            MerkleTreeVerifier::new(&mut iop, hashfn, domain, Self::CHECK_SIZE, queries)?;
         // tracing::debug!("checkRoot = {}", check_merkle.root());
 
         // Get a pseudorandom DEEP query point
@@ -288,7 +304,7 @@ where
 
         // Read the U coeffs (the interpolations of the taps) + commit their hash.
         let num_taps = taps.tap_size();
-        let coeff_u = iop.read_field_elem_slice(num_taps + Self::CHECK_SIZE);
+        let coeff_u = iop.read_field_elem_slice(num_taps + Self::CHECK_SIZE)?;
         let hash_u = self.suite.hashfn.hash_ext_elem_slice(coeff_u);
         iop.commit(&hash_u);
 
@@ -407,15 +423,19 @@ where
             let ret = self.fri_eval_taps(taps, mix, &combo_u, check_row, back_one, x, z, rows);
             Ok(ret)
         })?;
-        iop.verify_complete();
+        iop.verify_complete()?;
         Ok(())
     }
 
-    fn execute(&mut self, iop: &mut ReadIOP<'a, F>) {
+    fn execute(&mut self, iop: &mut ReadIOP<'a, F>) -> Result<(), VerificationError> {
         // Read the outputs + size
-        self.out = Some(iop.read_field_elem_slice(C::OUTPUT_SIZE));
-        self.po2 = *iop.read_u32s(1).first().unwrap();
+        self.out = Some(iop.read_field_elem_slice(C::OUTPUT_SIZE)?);
+        self.po2 = match iop.read_u32s(1)? {
+            &[po2] if (MIN_CYCLES_PO2..=MAX_CYCLES_PO2).contains(&(po2 as usize)) => po2,
+            _ => return Err(VerificationError::ReceiptFormatError),
+        };
         self.steps = 1 << self.po2;
+        Ok(())
     }
 
     /// Evaluate a polynomial whose coefficients are in the extension field at a
@@ -445,5 +465,24 @@ where
     C: CircuitCoreDef<F>,
     CheckCode: Fn(u32, &Digest) -> Result<(), VerificationError>,
 {
-    Verifier::<F, C>::new(circuit, suite).verify(seal, check_code)
+    verify_with_params(circuit, suite, FriParams::DEFAULT, seal, check_code)
+}
+
+/// Verify a seal is valid for the given circuit, and code checking function,
+/// when proven with the given [FriParams].
+#[must_use]
+#[tracing::instrument(skip_all)]
+pub fn verify_with_params<F, C, CheckCode>(
+    circuit: &C,
+    suite: &HashSuite<F>,
+    params: FriParams,
+    seal: &[u32],
+    check_code: CheckCode,
+) -> Result<(), VerificationError>
+where
+    F: Field,
+    C: CircuitCoreDef<F>,
+    CheckCode: Fn(u32, &Digest) -> Result<(), VerificationError>,
+{
+    Verifier::<F, C>::new(circuit, suite, params).verify(seal, check_code)
 }
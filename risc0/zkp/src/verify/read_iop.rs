@@ -16,11 +16,18 @@ use alloc::boxed::Box;
 
 use risc0_core::field::{Elem, Field};
 
-use crate::core::{
-    digest::Digest,
-    hash::{Rng, RngFactory},
+use crate::{
+    core::{
+        digest::Digest,
+        hash::{Rng, RngFactory},
+    },
+    verify::VerificationError,
 };
 
+/// Reads the contents of a seal during verification.
+///
+/// The seal is untrusted, so every read checks that there is enough data left
+/// and returns a [VerificationError::ReceiptFormatError] if there is not.
 pub struct ReadIOP<'a, F: Field> {
     proof: &'a [u32],
     rng: Box<dyn Rng<F>>,
@@ -34,28 +41,42 @@ impl<'a, F: Field> ReadIOP<'a, F> {
         }
     }
 
-    pub fn read_u32s(&mut self, n: usize) -> &'a [u32] {
+    pub fn read_u32s(&mut self, n: usize) -> Result<&'a [u32], VerificationError> {
+        if n > self.proof.len() {
+            return Err(VerificationError::ReceiptFormatError);
+        }
         let u32s;
         (u32s, self.proof) = self.proof.split_at(n);
-        u32s
+        Ok(u32s)
     }
 
     /// Read some field elements from this IOP, and check to make sure
-    /// they're not INVALID.
-    pub fn read_field_elem_slice<T: Elem>(&mut self, n: usize) -> &'a [T] {
-        let u32s = self.read_u32s(n * T::WORDS);
-        T::from_u32_slice(u32s)
+    /// they're fully reduced and therefore not INVALID.
+    pub fn read_field_elem_slice<T: Elem>(
+        &mut self,
+        n: usize,
+    ) -> Result<&'a [T], VerificationError> {
+        let len = n
+            .checked_mul(T::WORDS)
+            .ok_or(VerificationError::ReceiptFormatError)?;
+        let elems = T::from_u32_slice_unchecked(self.read_u32s(len)?);
+        if !elems.iter().all(T::is_reduced) {
+            return Err(VerificationError::ReceiptFormatError);
+        }
+        Ok(elems)
     }
 
     /// Read some plain old data from this IOP without doing any
     /// validation.  Prefer to use read_field_elem_slice if reading
     /// field elements.
-    pub fn read_pod_slice<T: bytemuck::Pod>(&mut self, n: usize) -> &'a [T] {
-        let u32s;
-        (u32s, self.proof) = self
-            .proof
-            .split_at(n * core::mem::size_of::<T>() / core::mem::size_of::<u32>());
-        bytemuck::cast_slice(u32s)
+    pub fn read_pod_slice<T: bytemuck::Pod>(
+        &mut self,
+        n: usize,
+    ) -> Result<&'a [T], VerificationError> {
+        let len = n
+            .checked_mul(core::mem::size_of::<T>() / core::mem::size_of::<u32>())
+            .ok_or(VerificationError::ReceiptFormatError)?;
+        Ok(bytemuck::cast_slice(self.read_u32s(len)?))
     }
 
     pub fn commit(&mut self, digest: &Digest) {
@@ -63,8 +84,11 @@ impl<'a, F: Field> ReadIOP<'a, F> {
     }
 
     /// Checks that the entire data of the IOP has been read.
-    pub fn verify_complete(&self) {
-        assert_eq!(self.proof.len(), 0);
+    pub fn verify_complete(&self) -> Result<(), VerificationError> {
+        if !self.proof.is_empty() {
+            return Err(VerificationError::ReceiptFormatError);
+        }
+        Ok(())
     }
 
     /// Get a cryptographically uniform u32
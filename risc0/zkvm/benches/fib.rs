@@ -15,7 +15,9 @@
 use criterion::{
     black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
 };
-use risc0_zkvm::{get_prover_server, ExecutorEnv, ExecutorImpl, ProverOpts, VerifierContext};
+use risc0_zkvm::{
+    get_prover_server, ExecutorEnv, ExecutorImpl, ProveHandle, ProverOpts, VerifierContext,
+};
 use risc0_zkvm_methods::FIB_ELF;
 
 fn setup(iterations: u32) -> ExecutorImpl<'static> {
@@ -82,7 +84,13 @@ pub fn bench(c: &mut Criterion) {
                                 let mut exec = setup(iterations);
                                 exec.run().unwrap()
                             },
-                            |session| black_box(prover.prove_session(&ctx, &session).unwrap()),
+                            |session| {
+                                black_box(
+                                    prover
+                                        .prove_session(&ctx, &session, &ProveHandle::default())
+                                        .unwrap(),
+                                )
+                            },
                             BatchSize::SmallInput,
                         )
                     });
@@ -96,7 +104,9 @@ pub fn bench(c: &mut Criterion) {
                             |mut exec| {
                                 black_box({
                                     let session = exec.run().unwrap();
-                                    prover.prove_session(&ctx, &session).unwrap()
+                                    prover
+                                        .prove_session(&ctx, &session, &ProveHandle::default())
+                                        .unwrap()
                                 })
                             },
                             BatchSize::SmallInput,
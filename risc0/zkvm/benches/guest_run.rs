@@ -52,6 +52,26 @@ fn guest_iter(b: &mut Bencher, spec: BenchmarkSpec) {
     b.iter_custom(|iters| run_guest(SpecWithIters(spec.clone(), iters)))
 }
 
+// Compare a full run, which materializes every segment, with an execute only
+// run that skips building segments.
+fn run_guest_segmented(spec: SpecWithIters, execute_only: bool) -> Duration {
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(16)
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, BENCH_ELF).unwrap();
+
+    let start = Instant::now();
+    if execute_only {
+        black_box(exec.execute_only().unwrap());
+    } else {
+        black_box(exec.run().unwrap());
+    }
+    start.elapsed()
+}
+
 fn rand_buffer<T>(len: usize) -> Vec<T>
 where
     Standard: Distribution<T>,
@@ -67,6 +87,21 @@ pub fn bench(c: &mut Criterion) {
         guest_iter(b, BenchmarkSpec::SimpleLoop)
     });
 
+    let mut execute_only_group = c.benchmark_group("execute_only");
+    execute_only_group.sampling_mode(SamplingMode::Flat);
+    for execute_only in [false, true] {
+        let name = if execute_only { "execute_only" } else { "run" };
+        execute_only_group.bench_function(name, |b| {
+            b.iter_custom(|iters| {
+                run_guest_segmented(
+                    SpecWithIters(BenchmarkSpec::SimpleLoop, iters * 100_000),
+                    execute_only,
+                )
+            })
+        });
+    }
+    execute_only_group.finish();
+
     let mut hash_bytes_group = c.benchmark_group("hash_bytes");
     hash_bytes_group
         .sampling_mode(SamplingMode::Flat)
@@ -16,7 +16,8 @@ use std::rc::Rc;
 
 use clap::Parser;
 use risc0_zkvm::{
-    get_prover_server, ExecutorEnv, ExecutorImpl, ProverOpts, ProverServer, VerifierContext,
+    get_prover_server, ExecutorEnv, ExecutorImpl, ProveHandle, ProverOpts, ProverServer,
+    VerifierContext,
 };
 use risc0_zkvm_methods::FIB_ELF;
 use tracing_subscriber::{prelude::*, EnvFilter};
@@ -75,7 +76,7 @@ fn top(prover: Rc<dyn ProverServer>, iterations: u32, skip_prover: bool) -> Metr
     } else {
         let ctx = VerifierContext::default();
         prover
-            .prove_session(&ctx, &session)
+            .prove_session(&ctx, &session, &ProveHandle::default())
             .unwrap()
             .inner
             .composite()
@@ -0,0 +1,27 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deserializes arbitrary bytes as a [Receipt] and verifies it.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use risc0_zkvm::{sha::Digest, Receipt, VerifierContext};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(receipt) = bincode::deserialize::<Receipt>(data) else {
+        return;
+    };
+    let _ = receipt.verify_with_context(&VerifierContext::default(), Digest::ZERO);
+});
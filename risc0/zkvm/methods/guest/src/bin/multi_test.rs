@@ -19,7 +19,12 @@
 
 extern crate alloc;
 
-use alloc::{format, vec};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
 use core::arch::asm;
 
 use getrandom::getrandom;
@@ -29,7 +34,7 @@ use risc0_zkvm::{
     sha::{Digest, Sha256},
     ReceiptClaim,
 };
-use risc0_zkvm_methods::multi_test::{MultiTestSpec, SYS_MULTI_TEST};
+use risc0_zkvm_methods::multi_test::{MultiTestSpec, SquareRequest, SYS_MULTI_TEST};
 use risc0_zkvm_platform::{
     fileno,
     memory::{self, SYSTEM},
@@ -50,6 +55,28 @@ fn profile_test_func2() {
     unsafe { asm!("nop") }
 }
 
+fn busy_loop(cycles: u32) {
+    let mut last_cycles = env::get_cycle_count();
+
+    // Count all the cycles that have happened so far before we got to this point.
+    env::log("Busy loop starting!");
+    let mut tot_cycles = last_cycles;
+
+    while tot_cycles < cycles as usize {
+        let now_cycles = env::get_cycle_count();
+        if now_cycles <= last_cycles {
+            // Cycle count may have reset or wrapped around.
+            // Since we don't know which, just start counting
+            // from zero.
+            tot_cycles += now_cycles;
+        } else {
+            tot_cycles += now_cycles - last_cycles;
+        }
+        last_cycles = now_cycles;
+    }
+    env::log("Busy loop complete");
+}
+
 pub fn main() {
     let impl_select: MultiTestSpec = env::read();
     match impl_select {
@@ -142,6 +169,33 @@ pub fn main() {
                 input_len = input.len();
             }
         }
+        MultiTestSpec::RpcCall { values } => {
+            let results: Vec<Result<u64, String>> = values
+                .iter()
+                .map(|&value| {
+                    env::call(SYS_MULTI_TEST, &SquareRequest(value)).map_err(|err| err.to_string())
+                })
+                .collect();
+            env::commit(&results);
+        }
+        MultiTestSpec::CycleRegions { iterations } => {
+            for i in 0..iterations {
+                env::cycle_region("outer", || {
+                    let digest =
+                        env::cycle_region("sha", || *sha::Impl::hash_bytes(&i.to_le_bytes()));
+                    env::commit(&digest);
+                });
+            }
+        }
+        MultiTestSpec::ReadBorrowed => {
+            let (text, len): (&str, u32) = env::read_borrowed();
+            let prefix = &text[..len as usize];
+            env::commit(&prefix);
+        }
+        MultiTestSpec::Preloaded { name } => {
+            let blob = env::preloaded(&name).map(|blob| blob.to_vec());
+            env::commit(&blob);
+        }
         MultiTestSpec::DoRandom => {
             // Test random number generation in the zkvm
             // Test for a combination of lengths and data alignments to make sure all cases
@@ -175,6 +229,14 @@ pub fn main() {
         MultiTestSpec::SysVerify { image_id, journal } => {
             env::verify(image_id, &journal).unwrap();
         }
+        MultiTestSpec::SysVerifyInRegion {
+            image_id,
+            journal,
+            cycles,
+        } => {
+            env::verify(image_id, &journal).unwrap();
+            env::cycle_region("busy", || busy_loop(cycles));
+        }
         MultiTestSpec::SysVerifyIntegrity { claim_words } => {
             let claim: ReceiptClaim = risc0_zkvm::serde::from_slice(&claim_words).unwrap();
             env::verify_integrity(&claim).unwrap();
@@ -196,27 +258,7 @@ pub fn main() {
             unsafe { sys_read_words(fd, buf.as_mut_ptr(), nwords) };
             env::commit_slice(&buf);
         }
-        MultiTestSpec::BusyLoop { cycles } => {
-            let mut last_cycles = env::get_cycle_count();
-
-            // Count all the cycles that have happened so far before we got to this point.
-            env::log("Busy loop starting!");
-            let mut tot_cycles = last_cycles;
-
-            while tot_cycles < cycles as usize {
-                let now_cycles = env::get_cycle_count();
-                if now_cycles <= last_cycles {
-                    // Cycle count may have reset or wrapped around.
-                    // Since we don't know which, just start counting
-                    // from zero.
-                    tot_cycles += now_cycles;
-                } else {
-                    tot_cycles += now_cycles - last_cycles;
-                }
-                last_cycles = now_cycles;
-            }
-            env::log("Busy loop complete");
-        }
+        MultiTestSpec::BusyLoop { cycles } => busy_loop(cycles),
         MultiTestSpec::BigInt { x, y, modulus } => {
             let mut result = [0u32; bigint::WIDTH_WORDS];
             unsafe {
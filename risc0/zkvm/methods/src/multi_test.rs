@@ -17,7 +17,7 @@ extern crate alloc;
 
 use alloc::vec::Vec;
 
-use risc0_zkvm::{declare_syscall, sha::Digest};
+use risc0_zkvm::{declare_syscall, rpc::RpcRequest, sha::Digest};
 use risc0_zkvm_platform::syscall::bigint;
 use serde::{Deserialize, Serialize};
 
@@ -47,7 +47,23 @@ pub enum MultiTestSpec {
     Syscall {
         count: u32,
     },
+    RpcCall {
+        /// Each value is sent to the host as a separate [SquareRequest].
+        values: Vec<u32>,
+    },
+    CycleRegions {
+        /// The number of times each region is entered.
+        iterations: u32,
+    },
     DoRandom,
+    /// Reads a `(&str, u32)` written with `write_frame` and commits that
+    /// many bytes of the string.
+    ReadBorrowed,
+    /// Commits the blob preloaded under `name`, if any, as an
+    /// `Option<Vec<u8>>`.
+    Preloaded {
+        name: String,
+    },
     SysRead {
         // Buffer to read to
         buf: Vec<u8>,
@@ -59,6 +75,13 @@ pub enum MultiTestSpec {
         image_id: Digest,
         journal: Vec<u8>,
     },
+    /// Verifies a receipt for `image_id` and `journal`, then busy loops for
+    /// `cycles` inside the cycle region `busy`.
+    SysVerifyInRegion {
+        image_id: Digest,
+        journal: Vec<u8>,
+        cycles: u32,
+    },
     SysVerifyIntegrity {
         // Define this field as a serialized vector to avoid circular dependency issues.
         claim_words: Vec<u32>,
@@ -90,3 +113,16 @@ pub enum MultiTestSpec {
 }
 
 declare_syscall!(pub SYS_MULTI_TEST);
+
+/// The [RpcRequest::SCHEMA] of [SquareRequest].
+pub const SQUARE_REQUEST_SCHEMA: &str = "multi_test::SquareRequest/1";
+
+/// Asks the host to square a value.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SquareRequest(pub u32);
+
+impl RpcRequest for SquareRequest {
+    type Response = u64;
+
+    const SCHEMA: &'static str = SQUARE_REQUEST_SCHEMA;
+}
@@ -18,6 +18,15 @@ pub const MEM_BITS: usize = 28;
 pub const MEM_SIZE: usize = 1 << MEM_BITS;
 pub const GUEST_MIN_MEM: usize = 0x0000_0400;
 pub const GUEST_MAX_MEM: usize = SYSTEM.start;
+/// The last word of guest memory, which holds the address of the table of
+/// blobs preloaded by the host, or zero if nothing is preloaded.
+///
+/// Preloaded blobs are packed at the top of guest memory, just below this word,
+/// and the heap stops at the start of their table. The table holds a word with
+/// the number of blobs, followed by four words per blob holding the address and
+/// length in bytes of its name, then the address and length in bytes of its
+/// data.
+pub const BLOB_TABLE_PTR: usize = GUEST_MAX_MEM - WORD_SIZE;
 
 /// Top of stack; stack grows down from this location.
 pub const STACK_TOP: u32 = 0x0020_0400;
@@ -26,6 +35,13 @@ pub const STACK_TOP: u32 = 0x0020_0400;
 pub const TEXT_START: u32 = 0x0020_0800;
 pub const SYSTEM: Region = Region::new(0x0C00_0000, mb(16));
 pub const PAGE_TABLE: Region = Region::new(0x0D00_0000, mb(16));
+/// Memory initialized by the prover's loader with constants used by the
+/// circuit, such as the SHA-256 round constants.
+///
+/// This region lies above [GUEST_MAX_MEM], so the guest cannot load from it,
+/// and above the root of the page table, so its contents are not part of the
+/// memory image or its image ID. Data that the host preloads for the guest is
+/// therefore placed at the top of guest memory instead; see [BLOB_TABLE_PTR].
 pub const PRE_LOAD: Region = Region::new(0x0D70_0000, mb(9));
 
 pub struct Region {
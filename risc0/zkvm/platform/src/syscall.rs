@@ -32,6 +32,11 @@ pub mod halt {
     pub const SPLIT: u32 = 2;
 }
 
+pub mod cycle_region {
+    pub const ENTER: u32 = 0;
+    pub const EXIT: u32 = 1;
+}
+
 pub mod reg_abi {
     pub const REG_ZERO: usize = 0; // zero constant
     pub const REG_RA: usize = 1; // return address
@@ -124,6 +129,7 @@ macro_rules! declare_syscall {
 
 pub mod nr {
     declare_syscall!(pub SYS_CYCLE_COUNT);
+    declare_syscall!(pub SYS_CYCLE_REGION);
     declare_syscall!(pub SYS_GETENV);
     declare_syscall!(pub SYS_ARGC);
     declare_syscall!(pub SYS_ARGV);
@@ -411,6 +417,24 @@ pub extern "C" fn sys_cycle_count() -> usize {
     a0 as usize
 }
 
+/// Enters or exits the named cycle region, depending on whether `op` is
+/// [cycle_region::ENTER] or [cycle_region::EXIT].
+///
+/// # Safety
+///
+/// `name_ptr` must be aligned and dereferenceable.
+#[cfg_attr(feature = "export-syscalls", no_mangle)]
+pub unsafe extern "C" fn sys_cycle_region(op: u32, name_ptr: *const u8, len: usize) {
+    syscall_3(
+        nr::SYS_CYCLE_REGION,
+        null_mut(),
+        0,
+        name_ptr as u32,
+        len as u32,
+        op,
+    );
+}
+
 /// Reads the given number of bytes into the given buffer, posix-style.  Returns
 /// the number of bytes actually read.  On end of file, returns 0.
 ///
@@ -614,6 +638,10 @@ pub unsafe extern "C" fn sys_getenv(
 ///
 /// NOTE: Repeated calls to sys_argc are not guaranteed to result in the same
 /// data being returned. Returned data is entirely in the control of the host.
+///
+/// # Safety
+///
+/// This syscall takes no pointers, and is always safe to call.
 #[cfg_attr(feature = "export-syscalls", no_mangle)]
 pub unsafe extern "C" fn sys_argc() -> usize {
     let Return(a0, _) = syscall_0(nr::SYS_ARGC, null_mut(), 0);
@@ -633,6 +661,10 @@ pub unsafe extern "C" fn sys_argc() -> usize {
 ///
 /// NOTE: Repeated calls to sys_argv are not guaranteed to result in the same
 /// data being returned. Returned data is entirely in the control of the host.
+///
+/// # Safety
+///
+/// `out_words` must be aligned and dereferenceable for `out_nwords` words.
 #[cfg_attr(feature = "export-syscalls", no_mangle)]
 pub unsafe extern "C" fn sys_argv(
     out_words: *mut u32,
@@ -648,6 +680,12 @@ pub extern "C" fn sys_alloc_words(nwords: usize) -> *mut u32 {
     unsafe { sys_alloc_aligned(WORD_SIZE * nwords, WORD_SIZE) as *mut u32 }
 }
 
+/// Allocates `bytes` bytes aligned to `align` from the guest heap, which is
+/// never freed.
+///
+/// # Safety
+///
+/// `align` must be a power of two.
 #[cfg(feature = "export-syscalls")]
 #[no_mangle]
 pub unsafe extern "C" fn sys_alloc_aligned(bytes: usize, align: usize) -> *mut u8 {
@@ -665,11 +703,25 @@ pub unsafe extern "C" fn sys_alloc_aligned(bytes: usize, align: usize) -> *mut u
     // initialized.
     static mut HEAP_POS: usize = 0;
 
+    // End of the heap, set when the heap is initialized.
+    static mut HEAP_END: usize = 0;
+
     // SAFETY: Single threaded, so nothing else can touch this while we're working.
     let mut heap_pos = unsafe { HEAP_POS };
 
     if heap_pos == 0 {
         heap_pos = unsafe { (&_end) as *const u8 as usize };
+        // The heap stops below the blobs preloaded at the top of guest memory,
+        // or else below the word pointing at their table.
+        // SAFETY: the word is part of guest memory, and is zero unless the host
+        // preloaded any blobs.
+        let blob_table = unsafe { *(crate::memory::BLOB_TABLE_PTR as *const usize) };
+        let heap_end = if blob_table == 0 {
+            crate::memory::BLOB_TABLE_PTR
+        } else {
+            blob_table
+        };
+        unsafe { HEAP_END = heap_end };
     }
 
     let offset = heap_pos & (align - 1);
@@ -683,8 +735,9 @@ pub unsafe extern "C" fn sys_alloc_aligned(bytes: usize, align: usize) -> *mut u
     let ptr = heap_pos as *mut u8;
     heap_pos += bytes;
 
-    // Check to make sure heap doesn't collide with SYSTEM memory.
-    if crate::memory::SYSTEM.start() < heap_pos {
+    // Check to make sure heap doesn't collide with the preloaded blobs, or with
+    // SYSTEM memory.
+    if unsafe { HEAP_END } < heap_pos {
         const MSG: &[u8] = "Out of memory!".as_bytes();
         unsafe { sys_panic(MSG.as_ptr(), MSG.len()) };
     }
@@ -701,6 +754,10 @@ pub unsafe extern "C" fn sys_alloc_aligned(bytes: usize, align: usize) -> *mut u
 /// 0 to register a0. The caller must calculate the ReceiptClaim digest, using the provided post
 /// state digest and encode the digest into a public assumptions list for inclusion in the guest
 /// output.
+///
+/// # Safety
+///
+/// `image_id`, `journal_digest`, and `from_host_buf` must be aligned and dereferenceable.
 #[cfg(feature = "export-syscalls")]
 #[no_mangle]
 pub unsafe extern "C" fn sys_verify(
@@ -740,6 +797,10 @@ pub unsafe extern "C" fn sys_verify(
 /// associated with that claim digest, and will always return a result code
 /// of 0 to register a0. The caller must encode the metadata_digest into a
 /// public assumptions list for inclusion in the guest output.
+///
+/// # Safety
+///
+/// `metadata_digest` must be aligned and dereferenceable.
 #[cfg(feature = "export-syscalls")]
 #[no_mangle]
 pub unsafe extern "C" fn sys_verify_integrity(metadata_digest: *const [u32; DIGEST_WORDS]) {
@@ -17,32 +17,35 @@
 use core::{fmt, mem::MaybeUninit};
 
 use bytemuck::Pod;
+use risc0_binfmt::tagged_list_cons;
 use risc0_zkvm_platform::{
-    fileno,
+    fileno, memory,
     syscall::{
-        self, sys_alloc_words, sys_cycle_count, sys_halt, sys_log, sys_pause, sys_read,
-        sys_read_words, sys_verify, sys_verify_integrity, sys_write, syscall_2, SyscallName,
+        self, sys_alloc_words, sys_cycle_count, sys_cycle_region, sys_halt, sys_log, sys_pause,
+        sys_read, sys_read_words, sys_verify, sys_verify_integrity, sys_write, syscall_2,
+        SyscallName,
     },
     WORD_SIZE,
 };
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::{
     align_up,
-    serde::{Deserializer, Serializer, WordRead, WordWrite},
+    rpc::{self, RpcError, RpcRequest},
+    serde::{from_slice_borrowed, Deserializer, Serializer, WordRead, WordWrite},
     sha::{
+        self,
         rust_crypto::{Digest as _, Sha256},
         Digest, Digestible, DIGEST_WORDS,
     },
-    Assumptions, ExitCode, InvalidExitCodeError, MaybePruned, Output, PrunedValueError,
-    ReceiptClaim,
+    ExitCode, InvalidExitCodeError, MaybePruned, Output, PrunedValueError, ReceiptClaim,
 };
 
 static mut HASHER: Option<Sha256> = None;
 
-/// Digest of the running list of [Assumptions], generated by the [verify] and
+/// Digest of the running list of [crate::Assumptions], generated by the [verify] and
 /// [verify_integrity] calls made by the guest.
-static mut ASSUMPTIONS_DIGEST: MaybePruned<Assumptions> = MaybePruned::Pruned(Digest::ZERO);
+static mut ASSUMPTIONS_DIGEST: Digest = Digest::ZERO;
 
 /// A random 16 byte value initialized to random data, provided by the host, on
 /// guest start and upon resuming from a pause. Setting this value ensures that
@@ -66,7 +69,7 @@ pub(crate) fn finalize(halt: bool, user_exit: u8) {
         let journal_digest: Digest = hasher.unwrap().finalize().as_slice().try_into().unwrap();
         let output = Output {
             journal: MaybePruned::Pruned(journal_digest),
-            assumptions: MaybePruned::Pruned(ASSUMPTIONS_DIGEST.digest()),
+            assumptions: MaybePruned::Pruned(ASSUMPTIONS_DIGEST),
         };
         let output_words: [u32; 8] = output.digest().into();
 
@@ -158,11 +161,18 @@ pub fn verify(image_id: Digest, journal: &[u8]) -> Result<(), VerifyError> {
         })
         .into(),
     };
-    unsafe { ASSUMPTIONS_DIGEST.add(assumption_claim.into()) };
+    unsafe { add_assumption(assumption_claim.digest()) };
 
     Ok(())
 }
 
+/// Add the assumption with the given claim digest to the head of the running
+/// list of [crate::Assumptions].
+unsafe fn add_assumption(claim_digest: Digest) {
+    ASSUMPTIONS_DIGEST =
+        tagged_list_cons::<sha::Impl>("risc0.Assumptions", &claim_digest, &ASSUMPTIONS_DIGEST);
+}
+
 /// Error encountered during a call to [verify].
 ///
 /// Note that an error is only returned for "provable" errors. In particular, if
@@ -219,7 +229,7 @@ pub fn verify_integrity(claim: &ReceiptClaim) -> Result<(), VerifyIntegrityError
 
     unsafe {
         sys_verify_integrity(claim_digest.as_ref());
-        ASSUMPTIONS_DIGEST.add(MaybePruned::Pruned(claim_digest));
+        add_assumption(claim_digest);
     }
 
     Ok(())
@@ -283,11 +293,74 @@ pub fn send_recv_slice<T: Pod, U: Pod>(syscall_name: SyscallName, to_host: &[T])
     &bytemuck::cast_slice(from_host_buf)[..nelem as usize]
 }
 
+/// Makes a typed request to the host on the given channel and returns the
+/// response.
+///
+/// The request and the response are encoded with [crate::serde], along with a
+/// header that lets the host check that it agrees with the guest on the
+/// [RpcRequest::SCHEMA] of the request. On the host side, register a handler
+/// for the channel with `ExecutorEnvBuilder::rpc_handler`.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use risc0_zkvm::{declare_syscall, guest::env};
+///
+/// declare_syscall!(pub SYS_PRICE_ORACLE);
+///
+/// // `GetPrice` implements `RpcRequest` with a `Response` of `u64`.
+/// let request = GetPrice { symbol: "ETH".into() };
+/// let price: u64 = env::call(SYS_PRICE_ORACLE, &request).unwrap();
+/// ```
+pub fn call<Req: RpcRequest>(channel: SyscallName, req: &Req) -> Result<Req::Response, RpcError> {
+    let request = rpc::encode_request(req)?;
+    let reply: &[u8] = send_recv_slice(channel, bytemuck::cast_slice::<u32, u8>(&request));
+    // The reply is written into a buffer allocated in words, so it is aligned.
+    let reply = bytemuck::try_cast_slice(reply)
+        .map_err(|_| RpcError::Serde("RPC reply is not a whole number of words".into()))?;
+    rpc::decode_reply(reply)
+}
+
 /// Read private data from the host and deserializes it.
 pub fn read<T: DeserializeOwned>() -> T {
     stdin().read()
 }
 
+/// Read private data from the host and deserialize it without copying.
+///
+/// The data must have been written by the host with
+/// `ExecutorEnvBuilder::write_frame`. Its serialized form is read into a
+/// buffer that is kept alive for the rest of execution, so `&str` and `&[u8]`
+/// fields of `T` point into that buffer instead of being copied. This saves
+/// cycles and paging when reading large inputs. See
+/// [crate::serde::from_slice_borrowed] for how to serialize borrowed bytes.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use risc0_zkvm::guest::env;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Document<'a> {
+///     title: &'a str,
+///     body: &'a str,
+/// }
+///
+/// let doc: Document = env::read_borrowed();
+/// ```
+pub fn read_borrowed<T: Deserialize<'static>>() -> T {
+    let mut stdin = stdin();
+    let mut nwords = 0u32;
+    stdin
+        .read_words(core::slice::from_mut(&mut nwords))
+        .unwrap();
+    let nwords = nwords as usize;
+    // The buffer is never freed, which lets the value borrow from it.
+    let words = unsafe { core::slice::from_raw_parts_mut(sys_alloc_words(nwords), nwords) };
+    stdin.read_words(words).unwrap();
+    from_slice_borrowed(words).unwrap()
+}
+
 /// Read a slice from the host.
 pub fn read_slice<T: Pod>(slice: &mut [T]) {
     stdin().read_slice(slice)
@@ -333,6 +406,71 @@ pub fn get_cycle_count() -> usize {
     sys_cycle_count()
 }
 
+/// Run `f` in the cycle region called `name` and return its result.
+///
+/// The host adds up the cycles spent in each region, including the cycles
+/// spent paging memory in and out, and reports them as the `cycle_regions`
+/// of the `Session` or `SessionInfo`. Regions can be nested. The host can also
+/// set a budget for a region with `ExecutorEnvBuilder::cycle_budget`, in which
+/// case execution fails when the region uses more cycles than allowed.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use risc0_zkvm::guest::env;
+///
+/// let input: Vec<u8> = env::read();
+/// let parsed = env::cycle_region("parse", || parse(&input));
+/// ```
+pub fn cycle_region<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    unsafe { sys_cycle_region(syscall::cycle_region::ENTER, name.as_ptr(), name.len()) };
+    let result = f();
+    unsafe { sys_cycle_region(syscall::cycle_region::EXIT, name.as_ptr(), name.len()) };
+    result
+}
+
+/// Return the blob preloaded by the host under `name`, or `None` if there is
+/// no such blob.
+///
+/// Blobs are preloaded with `ExecutorEnvBuilder::preload` and are part of the
+/// memory image, so their contents are covered by the image ID.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use risc0_zkvm::guest::env;
+///
+/// let weights = env::preloaded("weights").expect("missing weights");
+/// ```
+pub fn preloaded(name: &str) -> Option<&'static [u8]> {
+    const TABLE_ENTRY_WORDS: usize = 4;
+    // SAFETY: the word is part of guest memory, and is zero unless the host
+    // preloaded any blobs.
+    let start = unsafe { *(memory::BLOB_TABLE_PTR as *const u32) } as usize;
+    if start == 0 {
+        return None;
+    }
+    let end = memory::BLOB_TABLE_PTR;
+    let region = |addr: u32, len: u32| -> Option<&'static [u8]> {
+        let (addr, len) = (addr as usize, len as usize);
+        let in_bounds = addr >= start && addr <= end && len <= end - addr;
+        // SAFETY: the blobs are part of guest memory, initialized by the host
+        // when building the memory image, and the heap stops below them.
+        in_bounds.then(|| unsafe { core::slice::from_raw_parts(addr as *const u8, len) })
+    };
+
+    let count = u32::from_le_bytes(region(start as u32, WORD_SIZE as u32)?.try_into().unwrap());
+    let table_len = (count as usize).checked_mul(TABLE_ENTRY_WORDS * WORD_SIZE)?;
+    let table: &[u32] = bytemuck::cast_slice(region(
+        (start + WORD_SIZE) as u32,
+        u32::try_from(table_len).ok()?,
+    )?);
+    table
+        .chunks_exact(TABLE_ENTRY_WORDS)
+        .find(|entry| region(entry[0], entry[1]) == Some(name.as_bytes()))
+        .and_then(|entry| region(entry[2], entry[3]))
+}
+
 /// Print a message to the debug console.
 pub fn log(msg: &str) {
     let msg = msg.as_bytes();
@@ -26,10 +26,10 @@ use crate::{
     get_version,
     host::{
         api::SegmentInfo,
-        client::prove::get_r0vm_path,
+        client::{prove::get_r0vm_path, syscall::SyscallContext},
         receipt::{SegmentReceipt, SuccinctReceipt},
     },
-    ExecutorEnv, Journal, ProverOpts, Receipt,
+    ExecutorEnv, Journal, ProveHandle, ProverOpts, Receipt,
 };
 
 /// A client implementation for interacting with a zkVM server.
@@ -68,7 +68,17 @@ impl Client {
     }
 
     /// Prove the specified ELF binary.
-    pub fn prove(&self, env: &ExecutorEnv<'_>, opts: ProverOpts, binary: Asset) -> Result<Receipt> {
+    ///
+    /// The progress reported by the server is forwarded to the given
+    /// [ProveHandle]. Once the handle is cancelled, the server is asked to
+    /// stop proving at the start of the next phase.
+    pub fn prove(
+        &self,
+        env: &ExecutorEnv<'_>,
+        opts: ProverOpts,
+        binary: Asset,
+        handle: &ProveHandle,
+    ) -> Result<Receipt> {
         let mut conn = self.connect()?;
 
         let request = pb::api::ServerRequest {
@@ -84,7 +94,7 @@ impl Client {
         };
         conn.send(request)?;
 
-        let asset = self.prove_handler(&mut conn, env)?;
+        let asset = self.prove_handler(&mut conn, env, handle)?;
 
         let code = conn.close()?;
         if code != 0 {
@@ -333,6 +343,7 @@ impl Client {
             env_vars: env.env_vars.clone(),
             args: env.args.clone(),
             slice_ios: env.slice_io.borrow().inner.keys().cloned().collect(),
+            syscalls: env.syscalls.borrow().inner.keys().cloned().collect(),
             read_fds: env.posix_io.borrow().read_fds.keys().cloned().collect(),
             write_fds: env.posix_io.borrow().write_fds.keys().cloned().collect(),
             segment_limit_po2: env.segment_limit_po2,
@@ -343,6 +354,17 @@ impl Client {
                 .as_ref()
                 .map(|x| x.to_string_lossy().into())
                 .unwrap_or_default(),
+            profile_format: env.profile_format.to_string(),
+            cycle_budgets: env
+                .cycle_budgets
+                .iter()
+                .map(|(name, max_cycles)| (name.clone(), *max_cycles))
+                .collect(),
+            preloads: env
+                .preloads
+                .iter()
+                .map(|(name, data)| (name.clone(), data.clone()))
+                .collect(),
         }
     }
 
@@ -369,6 +391,9 @@ impl Client {
                             tracing::trace!("tx: {msg:?}");
                             conn.send(msg)?;
                         }
+                        pb::api::client_callback::Kind::Syscall(request) => {
+                            self.on_syscall(conn, env, request)?;
+                        }
                         pb::api::client_callback::Kind::SegmentDone(segment) => {
                             let reply: pb::api::GenericReply = segment
                                 .segment
@@ -398,11 +423,17 @@ impl Client {
                                         .exit_code
                                         .ok_or(malformed_err())?
                                         .try_into()?,
+                                    cycle_regions: session
+                                        .cycle_regions
+                                        .into_iter()
+                                        .map(|(name, region)| (name, region.into()))
+                                        .collect(),
                                 }),
                                 None => Err(malformed_err()),
                             }
                         }
-                        pb::api::client_callback::Kind::ProveDone(_) => {
+                        pb::api::client_callback::Kind::ProveDone(_)
+                        | pb::api::client_callback::Kind::Progress(_) => {
                             return Err(anyhow!("Illegal client callback"))
                         }
                     }
@@ -416,6 +447,7 @@ impl Client {
         &self,
         conn: &mut ConnectionWrapper,
         env: &ExecutorEnv<'_>,
+        handle: &ProveHandle,
     ) -> Result<pb::api::Asset> {
         loop {
             let reply: pb::api::ServerReply = conn.recv()?;
@@ -429,12 +461,21 @@ impl Client {
                             tracing::trace!("tx: {msg:?}");
                             conn.send(msg)?;
                         }
+                        pb::api::client_callback::Kind::Syscall(request) => {
+                            self.on_syscall(conn, env, request)?;
+                        }
                         pb::api::client_callback::Kind::SegmentDone(_) => {
                             return Err(anyhow!("Illegal client callback"))
                         }
                         pb::api::client_callback::Kind::SessionDone(_) => {
                             return Err(anyhow!("Illegal client callback"))
                         }
+                        pb::api::client_callback::Kind::Progress(progress) => {
+                            let reply: pb::api::GenericReply =
+                                self.on_progress(handle, progress).into();
+                            tracing::trace!("tx: {reply:?}");
+                            conn.send(reply)?;
+                        }
                         pb::api::client_callback::Kind::ProveDone(done) => {
                             return Ok(done.receipt.ok_or(malformed_err())?)
                         }
@@ -469,6 +510,10 @@ impl Client {
         }
     }
 
+    fn on_progress(&self, handle: &ProveHandle, progress: pb::api::OnProgress) -> Result<()> {
+        handle.enter(progress.try_into()?)
+    }
+
     fn on_posix_read(&self, env: &ExecutorEnv<'_>, fd: u32, nread: usize) -> Result<Bytes> {
         tracing::debug!("on_posix_read: {fd}, {nread}");
         let mut from_host = vec![0; nread];
@@ -503,6 +548,43 @@ impl Client {
         Ok(result)
     }
 
+    fn on_syscall(
+        &self,
+        conn: &mut ConnectionWrapper,
+        env: &ExecutorEnv<'_>,
+        request: pb::api::OnSyscallRequest,
+    ) -> Result<()> {
+        let name = request.name;
+        let handler = env
+            .syscalls
+            .borrow()
+            .inner
+            .get(&name)
+            .cloned()
+            .ok_or(anyhow!("Unknown syscall: {name}"));
+        let mut ctx = RemoteSyscallContext {
+            conn,
+            registers: request.registers,
+            cycle: request.cycle as usize,
+        };
+        let mut to_guest = vec![0; request.to_guest_words as usize];
+        let result = handler
+            .and_then(|handler| handler.borrow_mut().syscall(&name, &mut ctx, &mut to_guest));
+
+        let msg = pb::api::OnSyscallReply {
+            kind: Some(match result {
+                Ok((a0, a1)) => {
+                    pb::api::on_syscall_reply::Kind::Ok(pb::api::SyscallResult { to_guest, a0, a1 })
+                }
+                Err(err) => pb::api::on_syscall_reply::Kind::Error(pb::api::GenericError {
+                    reason: err.to_string(),
+                }),
+            }),
+        };
+        tracing::trace!("tx: {msg:?}");
+        ctx.conn.send(msg)
+    }
+
     fn on_trace(&self, env: &ExecutorEnv<'_>, event: pb::api::TraceEvent) -> Result<()> {
         for trace_callback in env.trace.iter() {
             trace_callback
@@ -513,6 +595,54 @@ impl Client {
     }
 }
 
+/// A [SyscallContext] for a syscall handled by the client, which reads the
+/// memory of the guest from the server on demand.
+struct RemoteSyscallContext<'c> {
+    conn: &'c mut ConnectionWrapper,
+    registers: Vec<u32>,
+    cycle: usize,
+}
+
+impl SyscallContext for RemoteSyscallContext<'_> {
+    fn get_cycle(&self) -> usize {
+        self.cycle
+    }
+
+    fn load_register(&mut self, idx: usize) -> Result<u32> {
+        self.registers
+            .get(idx)
+            .copied()
+            .ok_or_else(|| anyhow!("Invalid register: {idx}"))
+    }
+
+    fn load_region(&mut self, addr: u32, size: u32) -> Result<Vec<u8>> {
+        let msg = pb::api::OnSyscallReply {
+            kind: Some(pb::api::on_syscall_reply::Kind::Load(
+                pb::api::SyscallLoad { addr, size },
+            )),
+        };
+        tracing::trace!("tx: {msg:?}");
+        self.conn.send(msg)?;
+
+        let reply: pb::api::SyscallLoadReply = self.conn.recv()?;
+        tracing::trace!("rx: {reply:?}");
+        match reply.kind.ok_or(malformed_err())? {
+            pb::api::syscall_load_reply::Kind::Ok(bytes) => Ok(bytes),
+            pb::api::syscall_load_reply::Kind::Error(err) => bail!(err.reason),
+        }
+    }
+
+    fn load_u32(&mut self, addr: u32) -> Result<u32> {
+        let bytes = self.load_region(addr, 4)?;
+        Ok(u32::from_le_bytes(bytes.as_slice().try_into()?))
+    }
+
+    fn load_u8(&mut self, addr: u32) -> Result<u8> {
+        let bytes = self.load_region(addr, 1)?;
+        bytes.first().copied().ok_or(malformed_err())
+    }
+}
+
 impl From<Result<Bytes, anyhow::Error>> for pb::api::OnIoReply {
     fn from(result: Result<Bytes, anyhow::Error>) -> Self {
         Self {
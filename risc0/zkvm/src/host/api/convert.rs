@@ -12,21 +12,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::BTreeMap, path::PathBuf};
+use std::{path::PathBuf, sync::Arc};
 
 use anyhow::{anyhow, bail, Result};
 use prost::{Message, Name};
 use risc0_binfmt::{MemoryImage, PageTableInfo, SystemState};
-use risc0_zkp::core::digest::Digest;
+use risc0_verifier::decode_segment_claim;
+use risc0_zkp::{core::digest::Digest, params::FriParams};
 
 use super::{malformed_err, path_to_string, pb, Asset, AssetRequest};
 use crate::{
     host::{
-        receipt::{decode_receipt_claim_from_seal, CompositeReceipt, InnerReceipt, SegmentReceipt},
+        receipt::{AssumptionReceipt, CompositeReceipt, InnerReceipt, SegmentReceipt},
         recursion::SuccinctReceipt,
     },
-    Assumptions, ExitCode, Journal, MaybePruned, Output, ProverOpts, Receipt, ReceiptClaim,
-    TraceEvent,
+    Assumptions, CycleRegion, ExitCode, Journal, MaybePruned, Output, ParallelOpts, ProvePhase,
+    ProverOpts, Receipt, ReceiptClaim, ReceiptKind, TraceEvent,
 };
 
 mod ver {
@@ -140,6 +141,36 @@ impl TryFrom<pb::api::TraceEvent> for TraceEvent {
     }
 }
 
+impl From<ProvePhase> for pb::api::OnProgress {
+    fn from(phase: ProvePhase) -> Self {
+        Self {
+            phase: Some(match phase {
+                ProvePhase::Execute => pb::api::on_progress::Phase::Execute(()),
+                ProvePhase::Witness { segment } => pb::api::on_progress::Phase::Witness(segment),
+                ProvePhase::Commit { segment } => pb::api::on_progress::Phase::Commit(segment),
+                ProvePhase::Fri { segment } => pb::api::on_progress::Phase::Fri(segment),
+                ProvePhase::Lift { segment } => pb::api::on_progress::Phase::Lift(segment),
+                ProvePhase::Join => pb::api::on_progress::Phase::Join(()),
+            }),
+        }
+    }
+}
+
+impl TryFrom<pb::api::OnProgress> for ProvePhase {
+    type Error = anyhow::Error;
+
+    fn try_from(progress: pb::api::OnProgress) -> Result<Self> {
+        Ok(match progress.phase.ok_or(malformed_err())? {
+            pb::api::on_progress::Phase::Execute(()) => ProvePhase::Execute,
+            pb::api::on_progress::Phase::Witness(segment) => ProvePhase::Witness { segment },
+            pb::api::on_progress::Phase::Commit(segment) => ProvePhase::Commit { segment },
+            pb::api::on_progress::Phase::Fri(segment) => ProvePhase::Fri { segment },
+            pb::api::on_progress::Phase::Lift(segment) => ProvePhase::Lift { segment },
+            pb::api::on_progress::Phase::Join(()) => ProvePhase::Join,
+        })
+    }
+}
+
 impl From<ExitCode> for pb::base::ExitCode {
     fn from(value: ExitCode) -> Self {
         Self {
@@ -168,6 +199,46 @@ impl TryFrom<pb::base::ExitCode> for ExitCode {
     }
 }
 
+impl From<FriParams> for pb::base::FriParams {
+    fn from(value: FriParams) -> Self {
+        Self {
+            queries: value.queries as u32,
+            inv_rate: value.inv_rate as u32,
+            fri_fold: value.fri_fold as u32,
+        }
+    }
+}
+
+impl From<pb::base::FriParams> for FriParams {
+    fn from(value: pb::base::FriParams) -> Self {
+        Self {
+            queries: value.queries as usize,
+            inv_rate: value.inv_rate as usize,
+            fri_fold: value.fri_fold as usize,
+        }
+    }
+}
+
+impl From<CycleRegion> for pb::api::CycleRegion {
+    fn from(value: CycleRegion) -> Self {
+        Self {
+            cycles: value.cycles,
+            paging_cycles: value.paging_cycles,
+            count: value.count,
+        }
+    }
+}
+
+impl From<pb::api::CycleRegion> for CycleRegion {
+    fn from(value: pb::api::CycleRegion) -> Self {
+        Self {
+            cycles: value.cycles,
+            paging_cycles: value.paging_cycles,
+            count: value.count,
+        }
+    }
+}
+
 impl From<Result<(), anyhow::Error>> for pb::api::GenericReply {
     fn from(result: Result<(), anyhow::Error>) -> Self {
         Self {
@@ -196,8 +267,12 @@ impl From<pb::api::GenericError> for anyhow::Error {
 impl From<pb::api::ProverOpts> for ProverOpts {
     fn from(opts: pb::api::ProverOpts) -> Self {
         Self {
+            receipt_kind: opts.receipt_kind().into(),
+            fri_params: opts.fri_params.map(Into::into).unwrap_or_default(),
             hashfn: opts.hashfn,
             prove_guest_errors: opts.prove_guest_errors,
+            memory_budget: opts.memory_budget.map(|budget| budget as usize),
+            parallel: opts.parallel.map(Into::into),
         }
     }
 }
@@ -207,6 +282,48 @@ impl From<ProverOpts> for pb::api::ProverOpts {
         Self {
             hashfn: opts.hashfn,
             prove_guest_errors: opts.prove_guest_errors,
+            receipt_kind: pb::api::ReceiptKind::from(opts.receipt_kind).into(),
+            fri_params: Some(opts.fri_params.into()),
+            memory_budget: opts.memory_budget.map(|budget| budget as u64),
+            parallel: opts.parallel.map(Into::into),
+        }
+    }
+}
+
+impl From<pb::api::ParallelOpts> for ParallelOpts {
+    fn from(opts: pb::api::ParallelOpts) -> Self {
+        Self {
+            max_workers: opts.max_workers as usize,
+            memory_limit: opts.memory_limit.map(|limit| limit as usize),
+        }
+    }
+}
+
+impl From<ParallelOpts> for pb::api::ParallelOpts {
+    fn from(opts: ParallelOpts) -> Self {
+        Self {
+            max_workers: opts.max_workers as u64,
+            memory_limit: opts.memory_limit.map(|limit| limit as u64),
+        }
+    }
+}
+
+impl From<pb::api::ReceiptKind> for ReceiptKind {
+    fn from(kind: pb::api::ReceiptKind) -> Self {
+        match kind {
+            pb::api::ReceiptKind::Composite => Self::Composite,
+            pb::api::ReceiptKind::Succinct => Self::Succinct,
+            pb::api::ReceiptKind::Compact => Self::Compact,
+        }
+    }
+}
+
+impl From<ReceiptKind> for pb::api::ReceiptKind {
+    fn from(kind: ReceiptKind) -> Self {
+        match kind {
+            ReceiptKind::Composite => Self::Composite,
+            ReceiptKind::Succinct => Self::Succinct,
+            ReceiptKind::Compact => Self::Compact,
         }
     }
 }
@@ -214,11 +331,11 @@ impl From<ProverOpts> for pb::api::ProverOpts {
 impl From<MemoryImage> for pb::core::MemoryImage {
     fn from(value: MemoryImage) -> Self {
         let pages = value
-            .pages
+            .pages()
             .iter()
-            .map(|(addr, data)| pb::core::PageEntry {
-                addr: *addr,
-                data: data.clone(),
+            .map(|(&addr, data)| pb::core::PageEntry {
+                addr,
+                data: data.to_vec(),
             })
             .collect();
         Self {
@@ -238,17 +355,15 @@ impl TryFrom<pb::core::MemoryImage> for MemoryImage {
         if version > ver::MEMORY_IMAGE.value {
             bail!("Incompatible MemoryImage version: {version}");
         }
-        let pages = BTreeMap::from_iter(
-            value
-                .pages
-                .into_iter()
-                .map(|entry| (entry.addr, entry.data)),
-        );
-        Ok(Self {
+        let pages = value
+            .pages
+            .into_iter()
+            .map(|entry| (entry.addr, Arc::new(entry.data)));
+        Self::from_pages(
+            value.info.ok_or(malformed_err())?.try_into()?,
+            value.pc,
             pages,
-            info: value.info.ok_or(malformed_err())?.try_into()?,
-            pc: value.pc,
-        })
+        )
     }
 }
 
@@ -328,6 +443,7 @@ impl From<SegmentReceipt> for pb::core::SegmentReceipt {
             index: value.index,
             hashfn: value.hashfn,
             claim: Some(value.claim.into()),
+            fri_params: Some(value.fri_params.into()),
         }
     }
 }
@@ -353,13 +469,14 @@ impl TryFrom<pb::core::SegmentReceipt> for SegmentReceipt {
         let claim = value
             .claim
             .map(|m| m.try_into())
-            .unwrap_or_else(|| Ok(decode_receipt_claim_from_seal(&seal)?))?;
+            .unwrap_or_else(|| Ok(decode_segment_claim(&seal)?))?;
 
         Ok(Self {
             claim,
             seal,
             index: value.index,
             hashfn: value.hashfn,
+            fri_params: value.fri_params.map(Into::into).unwrap_or_default(),
         })
     }
 }
@@ -437,10 +554,24 @@ impl TryFrom<pb::core::InnerReceipt> for InnerReceipt {
 
 impl From<CompositeReceipt> for pb::core::CompositeReceipt {
     fn from(value: CompositeReceipt) -> Self {
+        let mut assumptions = Vec::new();
+        let mut unresolved_assumptions = Vec::new();
+        for (index, assumption) in value.assumptions.into_iter().enumerate() {
+            match assumption {
+                AssumptionReceipt::Proven(inner) => assumptions.push(inner.into()),
+                AssumptionReceipt::Unresolved(claim) => {
+                    unresolved_assumptions.push(pb::core::UnresolvedAssumption {
+                        index: index as u32,
+                        claim: Some(claim.into()),
+                    })
+                }
+            }
+        }
         Self {
             segments: value.segments.into_iter().map(|s| s.into()).collect(),
-            assumptions: value.assumptions.into_iter().map(|a| a.into()).collect(),
+            assumptions,
             journal_digest: value.journal_digest.map(|d| d.into()),
+            unresolved_assumptions,
         }
     }
 }
@@ -449,17 +580,31 @@ impl TryFrom<pb::core::CompositeReceipt> for CompositeReceipt {
     type Error = anyhow::Error;
 
     fn try_from(value: pb::core::CompositeReceipt) -> Result<Self> {
+        let len = value.assumptions.len() + value.unresolved_assumptions.len();
+        let mut proven = value.assumptions.into_iter();
+        let mut unresolved = value.unresolved_assumptions.into_iter().peekable();
+        let mut assumptions = Vec::with_capacity(len);
+        for index in 0..len {
+            let assumption = match unresolved.next_if(|x| x.index as usize == index) {
+                Some(x) => {
+                    AssumptionReceipt::Unresolved(x.claim.ok_or(malformed_err())?.try_into()?)
+                }
+                None => {
+                    AssumptionReceipt::Proven(proven.next().ok_or(malformed_err())?.try_into()?)
+                }
+            };
+            assumptions.push(assumption);
+        }
+        if unresolved.next().is_some() {
+            return Err(malformed_err());
+        }
         Ok(Self {
             segments: value
                 .segments
                 .into_iter()
                 .map(|s| s.try_into())
                 .collect::<Result<Vec<_>>>()?,
-            assumptions: value
-                .assumptions
-                .into_iter()
-                .map(|a| a.try_into())
-                .collect::<Result<Vec<_>>>()?,
+            assumptions,
             journal_digest: value.journal_digest.map(|d| d.try_into()).transpose()?,
         })
     }
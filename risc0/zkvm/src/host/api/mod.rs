@@ -21,6 +21,7 @@ pub(crate) mod server;
 mod tests;
 
 use std::{
+    collections::BTreeMap,
     io::{Read, Write},
     net::{TcpListener, TcpStream},
     path::{Path, PathBuf},
@@ -38,7 +39,7 @@ use anyhow::{anyhow, Context, Result};
 use bytes::{Buf, BufMut, Bytes};
 use prost::Message;
 
-use crate::{ExitCode, Journal};
+use crate::{CycleRegion, ExitCode, Journal};
 
 mod pb {
     pub(crate) mod api {
@@ -56,7 +57,7 @@ const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
 
 trait RootMessage: Message {}
 
-pub trait Connection {
+pub trait Connection: Send {
     fn stream(&self) -> &TcpStream;
     fn close(&mut self) -> Result<i32>;
     fn try_clone(&self) -> Result<Box<dyn Connection>>;
@@ -73,6 +74,8 @@ impl RootMessage for pb::api::ServerRequest {}
 impl RootMessage for pb::api::ServerReply {}
 impl RootMessage for pb::api::GenericReply {}
 impl RootMessage for pb::api::OnIoReply {}
+impl RootMessage for pb::api::OnSyscallReply {}
+impl RootMessage for pb::api::SyscallLoadReply {}
 impl RootMessage for pb::api::ProveSegmentReply {}
 impl RootMessage for pb::api::LiftRequest {}
 impl RootMessage for pb::api::LiftReply {}
@@ -298,6 +301,9 @@ pub struct SessionInfo {
 
     /// The [ExitCode] of the session.
     pub exit_code: ExitCode,
+
+    /// The cycles spent in each cycle region opened by the guest, by name.
+    pub cycle_regions: BTreeMap<String, CycleRegion>,
 }
 
 /// Provides information about a segment of execution.
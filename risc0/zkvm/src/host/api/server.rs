@@ -16,22 +16,28 @@ use std::{
     error::Error as StdError,
     io::{BufReader, Error as IoError, ErrorKind as IoErrorKind, Read, Write},
     path::{Path, PathBuf},
+    sync::Mutex,
 };
 
 use anyhow::{anyhow, bail, Result};
 use bytes::Bytes;
 use prost::Message;
+use risc0_zkvm_platform::syscall::reg_abi::REG_MAX;
 use serde::{Deserialize, Serialize};
 
 use super::{malformed_err, path_to_string, pb, ConnectionWrapper, Connector, TcpConnector};
 use crate::{
     get_prover_server, get_version,
     host::{
-        client::{env::TraceCallback, slice_io::SliceIo},
+        client::{
+            env::TraceCallback,
+            slice_io::SliceIo,
+            syscall::{Syscall, SyscallContext},
+        },
         recursion::SuccinctReceipt,
     },
-    ExecutorEnv, ExecutorImpl, ProverOpts, Segment, SegmentReceipt, SegmentRef, TraceEvent,
-    VerifierContext,
+    ExecutorEnv, ExecutorImpl, ProveHandle, ProvePhase, ProverOpts, Segment, SegmentReceipt,
+    SegmentRef, TraceEvent, VerifierContext,
 };
 
 /// A server implementation for handling requests by clients of the zkVM.
@@ -169,6 +175,84 @@ impl SliceIo for SliceIoProxy {
     }
 }
 
+struct SyscallProxy {
+    conn: ConnectionWrapper,
+}
+
+impl SyscallProxy {
+    fn new(conn: ConnectionWrapper) -> Self {
+        Self { conn }
+    }
+
+    fn try_clone(&self) -> Result<Self> {
+        Ok(SyscallProxy {
+            conn: self.conn.try_clone()?,
+        })
+    }
+}
+
+impl Syscall for SyscallProxy {
+    fn syscall(
+        &mut self,
+        syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        let request = pb::api::ServerReply {
+            kind: Some(pb::api::server_reply::Kind::Ok(pb::api::ClientCallback {
+                kind: Some(pb::api::client_callback::Kind::Syscall(
+                    pb::api::OnSyscallRequest {
+                        name: syscall.to_string(),
+                        registers: (0..REG_MAX)
+                            .map(|idx| ctx.load_register(idx))
+                            .collect::<Result<_>>()?,
+                        cycle: ctx.get_cycle() as u64,
+                        to_guest_words: to_guest.len() as u32,
+                    },
+                )),
+            })),
+        };
+        tracing::trace!("tx: {request:?}");
+        self.conn.send(request)?;
+
+        // The client may read guest memory any number of times before
+        // returning the result of the syscall.
+        loop {
+            let reply: pb::api::OnSyscallReply = self.conn.recv()?;
+            tracing::trace!("rx: {reply:?}");
+
+            match reply.kind.ok_or(malformed_err())? {
+                pb::api::on_syscall_reply::Kind::Load(load) => {
+                    let msg = pb::api::SyscallLoadReply {
+                        kind: Some(match ctx.load_region(load.addr, load.size) {
+                            Ok(bytes) => pb::api::syscall_load_reply::Kind::Ok(bytes),
+                            Err(err) => {
+                                pb::api::syscall_load_reply::Kind::Error(pb::api::GenericError {
+                                    reason: err.to_string(),
+                                })
+                            }
+                        }),
+                    };
+                    tracing::trace!("tx: {msg:?}");
+                    self.conn.send(msg)?;
+                }
+                pb::api::on_syscall_reply::Kind::Ok(result) => {
+                    if result.to_guest.len() != to_guest.len() {
+                        bail!(
+                            "syscall {syscall} returned {} words, expected {}",
+                            result.to_guest.len(),
+                            to_guest.len()
+                        );
+                    }
+                    to_guest.copy_from_slice(&result.to_guest);
+                    return Ok((result.a0, result.a1));
+                }
+                pb::api::on_syscall_reply::Kind::Error(err) => bail!(err.reason),
+            }
+        }
+    }
+}
+
 struct TraceProxy {
     conn: ConnectionWrapper,
 }
@@ -202,6 +286,47 @@ impl TraceCallback for TraceProxy {
     }
 }
 
+struct ProgressProxy {
+    conn: Mutex<ConnectionWrapper>,
+    handle: ProveHandle,
+}
+
+impl ProgressProxy {
+    fn new(conn: ConnectionWrapper, handle: ProveHandle) -> Self {
+        Self {
+            conn: Mutex::new(conn),
+            handle,
+        }
+    }
+
+    fn on_progress(&self, phase: &ProvePhase) {
+        // The client cancels proving by replying with an error.
+        if let Err(err) = self.send(phase) {
+            tracing::debug!("cancel: {err}");
+            self.handle.cancel();
+        }
+    }
+
+    fn send(&self, phase: &ProvePhase) -> Result<()> {
+        let request = pb::api::ServerReply {
+            kind: Some(pb::api::server_reply::Kind::Ok(pb::api::ClientCallback {
+                kind: Some(pb::api::client_callback::Kind::Progress((*phase).into())),
+            })),
+        };
+        tracing::trace!("tx: {request:?}");
+        let mut conn = self.conn.lock().unwrap();
+        conn.send(request)?;
+
+        let reply: pb::api::GenericReply = conn.recv()?;
+        tracing::trace!("rx: {reply:?}");
+        let kind = reply.kind.ok_or(malformed_err())?;
+        if let pb::api::generic_reply::Kind::Error(err) = kind {
+            bail!(err)
+        }
+        Ok(())
+    }
+}
+
 impl Server {
     /// Construct a new [Server] with the specified [Connector].
     pub fn new(connector: Box<dyn Connector>) -> Self {
@@ -322,6 +447,11 @@ impl Server {
                                 segments: session.segments.len().try_into()?,
                                 journal: session.journal.unwrap_or_default().bytes,
                                 exit_code: Some(session.exit_code.into()),
+                                cycle_regions: session
+                                    .cycle_regions
+                                    .into_iter()
+                                    .map(|(name, region)| (name, region.into()))
+                                    .collect(),
                             }),
                         },
                     )),
@@ -351,9 +481,14 @@ impl Server {
             let bytes = binary.as_bytes()?;
 
             let opts: ProverOpts = request.opts.ok_or(malformed_err())?.into();
+            let handle = ProveHandle::new();
+            let progress = ProgressProxy::new(conn.try_clone()?, handle.clone());
+            let handle = handle.with_progress(move |phase| progress.on_progress(phase));
+
             let prover = get_prover_server(&opts)?;
             let ctx = VerifierContext::default();
-            let receipt = prover.prove_with_ctx(env, &ctx, &bytes)?;
+            let receipt = prover.prove_with_ctx(env, &ctx, &bytes, &handle)?;
+            let receipt = prover.compress(&ctx, &opts, &receipt, &handle)?;
 
             let receipt_pb: pb::core::Receipt = receipt.into();
             let receipt_bytes = receipt_pb.encode_to_vec();
@@ -561,10 +696,20 @@ fn build_env<'a>(
     for name in request.slice_ios.iter() {
         env_builder.slice_io(&name, proxy.try_clone()?);
     }
+    let proxy = SyscallProxy::new(conn.try_clone()?);
+    for name in request.syscalls.iter() {
+        env_builder.syscall(name, proxy.try_clone()?);
+    }
     if let Some(segment_limit_po2) = request.segment_limit_po2 {
         env_builder.segment_limit_po2(segment_limit_po2);
     }
     env_builder.session_limit(request.session_limit);
+    for (name, max_cycles) in request.cycle_budgets.iter() {
+        env_builder.cycle_budget(name, *max_cycles);
+    }
+    for (name, data) in request.preloads.iter() {
+        env_builder.preload(name, data);
+    }
     if let Some(_) = request.trace_events {
         let proxy = TraceProxy::new(conn.try_clone()?);
         env_builder.trace_callback(proxy);
@@ -572,6 +717,9 @@ fn build_env<'a>(
     if !request.pprof_out.is_empty() {
         env_builder.enable_profiler(Path::new(&request.pprof_out));
     }
+    if !request.profile_format.is_empty() {
+        env_builder.profile_format(request.profile_format.parse()?);
+    }
     env_builder.build()
 }
 
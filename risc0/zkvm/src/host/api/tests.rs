@@ -15,20 +15,24 @@
 use std::{
     net::{SocketAddr, TcpListener},
     path::PathBuf,
+    sync::{Arc, Mutex},
     thread,
 };
 
 use anyhow::Result;
 use risc0_zkvm_methods::{
-    multi_test::MultiTestSpec, MULTI_TEST_ELF, MULTI_TEST_ID, MULTI_TEST_PATH,
+    multi_test::{MultiTestSpec, SYS_MULTI_TEST},
+    MULTI_TEST_ELF, MULTI_TEST_ID, MULTI_TEST_PATH,
 };
+use risc0_zkvm_platform::syscall::reg_abi::{REG_A3, REG_A4};
 use tempfile::{tempdir, TempDir};
 use test_log::test;
 
 use super::{Asset, AssetRequest, ConnectionWrapper, Connector, TcpConnection};
 use crate::{
-    recursion::SuccinctReceipt, ApiClient, ApiServer, ExecutorEnv, InnerReceipt, ProverOpts,
-    Receipt, SegmentReceipt, SessionInfo, VerifierContext,
+    recursion::SuccinctReceipt, ApiClient, ApiServer, ExecutorEnv, ExitCode, InnerReceipt,
+    ProveHandle, ProvePhase, ProverOpts, Receipt, ReceiptKind, SegmentReceipt, SessionInfo,
+    Syscall, SyscallContext, VerifierContext,
 };
 
 struct TestClientConnector {
@@ -86,7 +90,20 @@ impl TestClient {
     }
 
     fn prove(&self, env: ExecutorEnv<'_>, opts: ProverOpts, binary: Asset) -> Receipt {
-        with_server(self.addr, || self.client.prove(&env, opts, binary))
+        let handle = ProveHandle::default();
+        with_server(self.addr, || self.client.prove(&env, opts, binary, &handle))
+    }
+
+    fn prove_with_handle(
+        &self,
+        env: ExecutorEnv<'_>,
+        opts: ProverOpts,
+        binary: Asset,
+        handle: &ProveHandle,
+    ) -> Result<Receipt> {
+        with_server(self.addr, || {
+            Ok(self.client.prove(&env, opts, binary, handle))
+        })
     }
 
     fn prove_segment(&self, opts: ProverOpts, segment: Asset) -> SegmentReceipt {
@@ -145,6 +162,50 @@ fn execute() {
     TestClient::new().execute(env, binary);
 }
 
+#[test]
+fn execute_syscall() {
+    // Replies to each message with its reverse, reading the message from guest
+    // memory through the server.
+    struct ReverseSyscall(Option<Vec<u8>>);
+
+    impl Syscall for ReverseSyscall {
+        fn syscall(
+            &mut self,
+            _syscall: &str,
+            ctx: &mut dyn SyscallContext,
+            to_guest: &mut [u32],
+        ) -> Result<(u32, u32)> {
+            match self.0.take() {
+                None => {
+                    let buf_ptr = ctx.load_register(REG_A3)?;
+                    let buf_len = ctx.load_register(REG_A4)?;
+                    let mut reply = ctx.load_region(buf_ptr, buf_len)?;
+                    reply.reverse();
+                    reply.push(b'x');
+                    let len = reply.len() as u32;
+                    self.0 = Some(reply);
+                    Ok((len, 0))
+                }
+                Some(reply) => {
+                    let to_guest_bytes: &mut [u8] = bytemuck::cast_slice_mut(to_guest);
+                    to_guest_bytes[..reply.len()].copy_from_slice(&reply);
+                    Ok((0, 0))
+                }
+            }
+        }
+    }
+
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::Syscall { count: 3 })
+        .unwrap()
+        .syscall(SYS_MULTI_TEST, ReverseSyscall(None))
+        .build()
+        .unwrap();
+    let binary = Asset::Inline(MULTI_TEST_ELF.into());
+    let session = TestClient::new().execute(env, binary);
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+}
+
 #[test]
 fn prove() {
     let env = ExecutorEnv::builder()
@@ -158,6 +219,78 @@ fn prove() {
     receipt.verify(MULTI_TEST_ID).unwrap();
 }
 
+#[test]
+fn prove_progress() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let binary = Asset::Path(MULTI_TEST_PATH.into());
+    let opts = ProverOpts {
+        receipt_kind: ReceiptKind::Succinct,
+        ..Default::default()
+    };
+    let phases = Arc::new(Mutex::new(Vec::new()));
+    let handle = ProveHandle::new().with_progress({
+        let phases = phases.clone();
+        move |phase| phases.lock().unwrap().push(*phase)
+    });
+    let receipt = TestClient::new()
+        .prove_with_handle(env, opts, binary, &handle)
+        .unwrap();
+    receipt.verify(MULTI_TEST_ID).unwrap();
+    assert_eq!(
+        phases.lock().unwrap().as_slice(),
+        &[
+            ProvePhase::Execute,
+            ProvePhase::Witness { segment: 0 },
+            ProvePhase::Commit { segment: 0 },
+            ProvePhase::Fri { segment: 0 },
+            ProvePhase::Lift { segment: 0 },
+        ]
+    );
+}
+
+#[test]
+fn prove_cancel() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let binary = Asset::Path(MULTI_TEST_PATH.into());
+    let handle = ProveHandle::new();
+    let canceller = handle.clone();
+    let handle = handle.with_progress(move |phase| {
+        if let ProvePhase::Witness { .. } = phase {
+            canceller.cancel();
+        }
+    });
+    let err = TestClient::new()
+        .prove_with_handle(env, ProverOpts::default(), binary, &handle)
+        .unwrap_err();
+    assert!(err.to_string().contains("cancelled"));
+}
+
+#[test]
+fn prove_poseidon2() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let binary = Asset::Path(MULTI_TEST_PATH.into());
+    let opts = ProverOpts {
+        hashfn: "poseidon2".to_string(),
+        ..Default::default()
+    };
+    let receipt = TestClient::new().prove(env, opts, binary);
+    let segments = &receipt.inner.composite().unwrap().segments;
+    assert!(segments.iter().all(|segment| segment.hashfn == "poseidon2"));
+    receipt.verify(MULTI_TEST_ID).unwrap();
+}
+
 #[test]
 fn prove_segment_elf() {
     let env = ExecutorEnv::builder()
@@ -16,14 +16,16 @@
 
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
+    fmt,
     io::{BufRead, BufReader, Cursor, Read, Write},
     mem,
     path::{Path, PathBuf},
     rc::Rc,
+    str::FromStr,
 };
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use bytemuck::Pod;
 use bytes::Bytes;
 use risc0_zkvm_platform::{self, fileno};
@@ -35,7 +37,9 @@ use crate::{
         exec::TraceEvent,
         posix_io::PosixIo,
         slice_io::{slice_io_from_fn, SliceIo, SliceIoTable},
+        syscall::{Syscall, SyscallHandlerTable},
     },
+    rpc::{self, RpcRequest},
     Assumption,
 };
 
@@ -59,6 +63,62 @@ where
     }
 }
 
+/// The format of the report written by the guest profiler.
+///
+/// See [ExecutorEnvBuilder::enable_profiler].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProfileFormat {
+    /// pprof protobuf, which can be read with
+    /// [pprof](https://github.com/google/pprof).
+    #[default]
+    Pprof,
+
+    /// Collapsed stacks, one per line, as read by `inferno` and
+    /// `flamegraph.pl`.
+    Folded,
+
+    /// An SVG flamegraph.
+    Flamegraph,
+
+    /// A text table of the cycles spent in each function and on each source
+    /// line, sorted from most to least expensive.
+    Table,
+}
+
+impl ProfileFormat {
+    /// Returns the name of this format, which is also accepted when parsing a
+    /// [ProfileFormat] from a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pprof => "pprof",
+            Self::Folded => "folded",
+            Self::Flamegraph => "flamegraph",
+            Self::Table => "table",
+        }
+    }
+}
+
+impl FromStr for ProfileFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "pprof" => Self::Pprof,
+            "folded" => Self::Folded,
+            "flamegraph" => Self::Flamegraph,
+            "table" => Self::Table,
+            _ => bail!("unknown profile format: {s}"),
+        })
+    }
+}
+
+impl fmt::Display for ProfileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Container for assumptions in the executor environment.
 #[derive(Debug, Default)]
 pub(crate) struct Assumptions {
@@ -77,13 +137,20 @@ pub struct ExecutorEnv<'a> {
     pub(crate) args: Vec<String>,
     pub(crate) segment_limit_po2: Option<u32>,
     pub(crate) session_limit: Option<u64>,
+    pub(crate) cycle_budgets: BTreeMap<String, u64>,
+    pub(crate) preloads: BTreeMap<String, Vec<u8>>,
     pub(crate) posix_io: Rc<RefCell<PosixIo<'a>>>,
     pub(crate) slice_io: Rc<RefCell<SliceIoTable<'a>>>,
+    pub(crate) syscalls: Rc<RefCell<SyscallHandlerTable<'a>>>,
     pub(crate) input: Vec<u8>,
     pub(crate) trace: Vec<Rc<RefCell<dyn TraceCallback + 'a>>>,
     pub(crate) assumptions: Rc<RefCell<Assumptions>>,
     pub(crate) segment_path: Option<PathBuf>,
+    pub(crate) snapshot_path: Option<PathBuf>,
+    pub(crate) syscall_record_path: Option<PathBuf>,
+    pub(crate) syscall_replay_path: Option<PathBuf>,
     pub(crate) pprof_out: Option<PathBuf>,
+    pub(crate) profile_format: ProfileFormat,
 }
 
 impl<'a> ExecutorEnv<'a> {
@@ -160,6 +227,52 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Set the maximum number of cycles that the guest may spend in the
+    /// named cycle region, summed over every time the region is entered.
+    ///
+    /// Guests open regions with `env::cycle_region`. Execution fails as soon
+    /// as a region exits with a total above its budget, which makes budgets a
+    /// convenient way to catch performance regressions in tests.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .cycle_budget("parse", 100_000)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn cycle_budget<C: AsRef<str>>(&mut self, region: C, max_cycles: u64) -> &mut Self {
+        self.inner
+            .cycle_budgets
+            .insert(region.as_ref().to_string(), max_cycles);
+        self
+    }
+
+    /// Preload a named blob into the memory image of the guest.
+    ///
+    /// Preloaded blobs are part of the memory image, and so covered by the
+    /// image ID, which must then be computed with
+    /// [crate::compute_image_id_with_preloads]. Guests look them up by name
+    /// with `env::preloaded`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .preload("weights", &[1, 2, 3, 4])
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn preload(&mut self, name: &str, data: &[u8]) -> &mut Self {
+        self.inner.preloads.insert(name.to_string(), data.to_vec());
+        self
+    }
+
     /// Add environment variables to the guest environment.
     ///
     /// # Example
@@ -247,6 +360,28 @@ impl<'a> ExecutorEnvBuilder<'a> {
         Ok(self.write_slice(&to_vec(data)?))
     }
 
+    /// Write input data to the zkVM guest stdin, preceded by its length.
+    ///
+    /// This function serializes `data` like [Self::write], but prefixes it
+    /// with its length in words so that the guest can read it into a single
+    /// buffer with `env::read_borrowed` and deserialize it without copying.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::ExecutorEnv;
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .write_frame(&"a large document")
+    ///     .unwrap()
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn write_frame<T: Serialize>(&mut self, data: &T) -> Result<&mut Self> {
+        let words = to_vec(data)?;
+        Ok(self.write_slice(&[words.len() as u32]).write_slice(&words))
+    }
+
     /// Write input data to the zkVM guest stdin.
     ///
     /// This function writes a slice directly to the underlying buffer. A
@@ -322,6 +457,101 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Add a handler for typed requests made by the guest with `env::call` on
+    /// the given channel.
+    ///
+    /// Requests and responses are encoded with [crate::serde]. Requests whose
+    /// header does not match the [RPC_VERSION](crate::rpc::RPC_VERSION) or the
+    /// [RpcRequest::SCHEMA] of `Req` are rejected, and errors returned by
+    /// `handler` are returned to the guest as an
+    /// [RpcError](crate::rpc::RpcError).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{declare_syscall, rpc::RpcRequest, ExecutorEnv};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// declare_syscall!(SYS_SQUARE);
+    ///
+    /// #[derive(Serialize, Deserialize)]
+    /// struct Square(u32);
+    ///
+    /// impl RpcRequest for Square {
+    ///     type Response = Option<u32>;
+    ///
+    ///     const SCHEMA: &'static str = "example::Square/1";
+    /// }
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .rpc_handler(SYS_SQUARE, |Square(x)| anyhow::Ok(x.checked_mul(x)))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn rpc_handler<C, Req>(
+        &mut self,
+        channel: C,
+        handler: impl Fn(Req) -> Result<Req::Response> + 'a,
+    ) -> &mut Self
+    where
+        C: AsRef<str>,
+        Req: RpcRequest,
+    {
+        self.io_callback(channel, move |from_guest| {
+            let request: Vec<u32> = from_guest
+                .chunks(4)
+                .map(|word| {
+                    let mut buf = [0u8; 4];
+                    buf[..word.len()].copy_from_slice(word);
+                    u32::from_le_bytes(buf)
+                })
+                .collect();
+            let reply = rpc::handle_request(&request, |req| handler(req));
+            Ok(Bytes::copy_from_slice(bytemuck::cast_slice(&reply)))
+        })
+    }
+
+    /// Add a handler for the syscall with the given name.
+    ///
+    /// The handler is invoked whenever the guest makes the syscall, with
+    /// read-only access to the registers and memory of the guest through its
+    /// [SyscallContext](crate::SyscallContext). A handler registered for the
+    /// name of a built-in syscall, such as `SYS_RANDOM` or `SYS_GETENV`,
+    /// replaces the built-in implementation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use risc0_zkvm::{ExecutorEnv, Syscall, SyscallContext};
+    /// use risc0_zkvm_platform::syscall::nr::SYS_RANDOM;
+    ///
+    /// struct ZeroRandom;
+    ///
+    /// impl Syscall for ZeroRandom {
+    ///     fn syscall(
+    ///         &mut self,
+    ///         _syscall: &str,
+    ///         _ctx: &mut dyn SyscallContext,
+    ///         to_guest: &mut [u32],
+    ///     ) -> anyhow::Result<(u32, u32)> {
+    ///         to_guest.fill(0);
+    ///         Ok((0, 0))
+    ///     }
+    /// }
+    ///
+    /// let env = ExecutorEnv::builder()
+    ///     .syscall(SYS_RANDOM, ZeroRandom)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn syscall<C: AsRef<str>>(&mut self, name: C, handler: impl Syscall + 'a) -> &mut Self {
+        self.inner
+            .syscalls
+            .borrow_mut()
+            .with_handler(name.as_ref(), handler);
+        self
+    }
+
     /// Add an [Assumption] to the [ExecutorEnv] associated assumptions.
     ///
     /// During execution, when the guest calls `env::verify` or
@@ -344,9 +574,56 @@ impl<'a> ExecutorEnvBuilder<'a> {
         self
     }
 
+    /// Set the path of a file where executor snapshots will be written.
+    ///
+    /// When set, an [ExecutorSnapshot](crate::ExecutorSnapshot) is written to
+    /// this file at every segment boundary and whenever the guest pauses,
+    /// replacing the previous snapshot. Execution can be resumed from the
+    /// latest snapshot with
+    /// [ExecutorImpl::from_snapshot](crate::ExecutorImpl::from_snapshot).
+    pub fn snapshot_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.inner.snapshot_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Record every syscall made by the guest, along with the response of the
+    /// host, to a [SyscallTranscript](crate::SyscallTranscript) file at the
+    /// specified path.
+    ///
+    /// The transcript is written at the end of each call to
+    /// [ExecutorImpl::run](crate::ExecutorImpl::run), including when
+    /// execution fails.
+    pub fn record_syscalls<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.inner.syscall_record_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Replay the host responses recorded in the
+    /// [SyscallTranscript](crate::SyscallTranscript) file at the specified
+    /// path instead of calling the syscall handlers of this environment.
+    ///
+    /// Execution fails if the guest makes a syscall that differs from the
+    /// recorded one in its name or arguments, or if it halts before the
+    /// transcript is exhausted. Writes to the journal and calls to
+    /// `env::verify` are still handled by the executor, so the assumptions
+    /// used by the recorded execution must be added to this environment.
+    pub fn replay_syscalls<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.inner.syscall_replay_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
     /// Enable the profiler and output results to the specified path.
+    ///
+    /// The report is written in pprof format unless another format is
+    /// selected with [ExecutorEnvBuilder::profile_format].
     pub fn enable_profiler<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
         self.inner.pprof_out = Some(path.as_ref().to_path_buf());
         self
     }
+
+    /// Set the format of the report written by the profiler.
+    pub fn profile_format(&mut self, format: ProfileFormat) -> &mut Self {
+        self.inner.profile_format = format;
+        self
+    }
 }
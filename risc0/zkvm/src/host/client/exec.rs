@@ -55,3 +55,18 @@ impl std::fmt::Debug for TraceEvent {
         }
     }
 }
+
+/// The cycles spent by the guest in a cycle region, as opened with
+/// `env::cycle_region`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CycleRegion {
+    /// The number of cycles spent in the region, including paging.
+    pub cycles: u64,
+
+    /// The number of cycles spent paging memory in and out while in the
+    /// region.
+    pub paging_cycles: u64,
+
+    /// The number of times the region was exited.
+    pub count: u64,
+}
@@ -17,3 +17,4 @@ pub(crate) mod exec;
 pub(crate) mod posix_io;
 pub(crate) mod prove;
 pub(crate) mod slice_io;
+pub(crate) mod syscall;
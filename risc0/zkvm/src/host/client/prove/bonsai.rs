@@ -17,8 +17,10 @@ use std::time::Duration;
 use anyhow::{anyhow, bail, ensure, Result};
 use bonsai_sdk::alpha::Client;
 
-use super::Prover;
-use crate::{compute_image_id, sha::Digestible, ExecutorEnv, ProverOpts, Receipt, VerifierContext};
+use super::{ProveHandle, Prover, ReceiptKind};
+use crate::{
+    compute_image_id, sha::Digestible, ExecutorEnv, FriParams, ProverOpts, Receipt, VerifierContext,
+};
 
 /// An implementation of a [Prover] that runs proof workloads via Bonsai.
 ///
@@ -48,7 +50,16 @@ impl Prover for BonsaiProver {
         ctx: &VerifierContext,
         elf: &[u8],
         opts: &ProverOpts,
+        handle: &ProveHandle,
     ) -> Result<Receipt> {
+        ensure!(
+            env.preloads.is_empty(),
+            "Preloaded blobs are not supported by Bonsai"
+        );
+        ensure!(
+            opts.fri_params == FriParams::DEFAULT,
+            "Only the default FRI parameters are supported by Bonsai"
+        );
         let client = Client::from_env(crate::VERSION)?;
 
         // Compute the ImageID and upload the ELF binary
@@ -70,6 +81,15 @@ impl Prover for BonsaiProver {
             // the proof request succeeded.
             let res = session.status(&client)?;
             if res.status == "RUNNING" {
+                // Bonsai does not report the phases of a session, but polling
+                // stops if proving is cancelled. The session itself keeps
+                // running on Bonsai.
+                if handle.is_cancelled() {
+                    bail!(
+                        "Proving was cancelled while waiting on Bonsai session {}",
+                        session.uuid
+                    );
+                }
                 std::thread::sleep(Duration::from_secs(5));
                 continue;
             }
@@ -93,10 +113,35 @@ impl Prover for BonsaiProver {
                 } else {
                     receipt.verify_with_context(ctx, image_id)?;
                 }
-                return Ok(receipt);
+                return compress(ctx, opts, receipt, handle);
             } else {
                 bail!("Bonsai prover workflow exited: {}", res.status);
             }
         }
     }
 }
+
+/// Convert a receipt returned by Bonsai into the [ReceiptKind] selected by the
+/// given [ProverOpts], using the local prover.
+fn compress(
+    ctx: &VerifierContext,
+    opts: &ProverOpts,
+    receipt: Receipt,
+    handle: &ProveHandle,
+) -> Result<Receipt> {
+    if opts.receipt_kind == ReceiptKind::Composite {
+        return Ok(receipt);
+    }
+
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "prove")] {
+            crate::get_prover_server(opts)?.compress(ctx, opts, &receipt, handle)
+        } else {
+            let _ = (ctx, handle);
+            bail!(
+                "producing a {:?} receipt from a Bonsai receipt requires the `prove` feature",
+                opts.receipt_kind
+            )
+        }
+    }
+}
@@ -14,12 +14,12 @@
 
 use std::path::{Path, PathBuf};
 
-use anyhow::{ensure, Result};
+use anyhow::{bail, ensure, Result};
 
-use super::{Executor, Prover, ProverOpts};
+use super::{Executor, ProveHandle, Prover, ProverOpts, ReceiptKind};
 use crate::{
-    compute_image_id, host::api::AssetRequest, sha::Digestible, ApiClient, Asset, ExecutorEnv,
-    Receipt, SessionInfo, VerifierContext,
+    compute_image_id_with_preloads, host::api::AssetRequest, sha::Digestible, ApiClient, Asset,
+    ExecutorEnv, InnerReceipt, Receipt, SessionInfo, VerifierContext,
 };
 
 /// An implementation of a [Prover] that runs proof workloads via an external
@@ -46,13 +46,24 @@ impl Prover for ExternalProver {
         ctx: &VerifierContext,
         elf: &[u8],
         opts: &ProverOpts,
+        handle: &ProveHandle,
     ) -> Result<Receipt> {
         tracing::debug!("Launching {}", &self.r0vm_path.to_string_lossy());
 
-        let image_id = compute_image_id(elf)?;
+        let image_id = compute_image_id_with_preloads(elf, &env.preloads)?;
         let client = ApiClient::new_sub_process(&self.r0vm_path)?;
         let binary = Asset::Inline(elf.to_vec().into());
-        let receipt = client.prove(&env, opts.clone(), binary)?;
+        // Compact receipts are sealed with Poseidon over BN254, which the verifier does not
+        // support. Prove a succinct receipt instead, verify it here, and then ask the server
+        // to compress exactly the claim that was verified.
+        let prove_opts = match opts.receipt_kind {
+            ReceiptKind::Compact => ProverOpts {
+                receipt_kind: ReceiptKind::Succinct,
+                ..opts.clone()
+            },
+            _ => opts.clone(),
+        };
+        let receipt = client.prove(&env, prove_opts, binary, handle)?;
         if opts.prove_guest_errors {
             receipt.verify_integrity_with_context(ctx)?;
             ensure!(
@@ -65,7 +76,27 @@ impl Prover for ExternalProver {
             receipt.verify_with_context(ctx, image_id)?;
         }
 
-        Ok(receipt)
+        if opts.receipt_kind != ReceiptKind::Compact {
+            return Ok(receipt);
+        }
+
+        let InnerReceipt::Succinct(succinct) = receipt.inner else {
+            bail!("expected a succinct receipt to compress");
+        };
+        let claim = succinct.claim.digest();
+        let compact =
+            client.identity_p254(opts.clone(), succinct.try_into()?, AssetRequest::Inline)?;
+        ensure!(
+            compact.claim.digest() == claim,
+            "compact receipt does not match the verified claim: expected {}, found {}",
+            hex::encode(&claim),
+            hex::encode(&compact.claim.digest())
+        );
+
+        Ok(Receipt::new(
+            InnerReceipt::Succinct(compact),
+            receipt.journal.bytes,
+        ))
     }
 
     fn get_name(&self) -> String {
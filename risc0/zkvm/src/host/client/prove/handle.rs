@@ -0,0 +1,133 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use anyhow::{ensure, Result};
+
+/// A phase of proof generation, as reported through a [ProveHandle].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProvePhase {
+    /// Executing the guest to split it into segments.
+    Execute,
+
+    /// Generating the witness of the segment with the given index.
+    Witness {
+        /// The index of the segment.
+        segment: u32,
+    },
+
+    /// Committing to the witness of the segment with the given index.
+    Commit {
+        /// The index of the segment.
+        segment: u32,
+    },
+
+    /// Running the FRI protocol over the committed witness of the segment with
+    /// the given index.
+    Fri {
+        /// The index of the segment.
+        segment: u32,
+    },
+
+    /// Lifting the receipt of the segment with the given index into a
+    /// [SuccinctReceipt](crate::SuccinctReceipt).
+    Lift {
+        /// The index of the segment.
+        segment: u32,
+    },
+
+    /// Joining two [SuccinctReceipt](crate::SuccinctReceipt)s.
+    Join,
+}
+
+/// A handle to observe and cancel proof generation.
+///
+/// The prover calls [ProveHandle::enter] as it starts each [ProvePhase]. This
+/// reports the phase to the progress callback, if any, and returns an error if
+/// the handle has been cancelled, so that proving stops cleanly between
+/// phases. A phase that has already started runs to completion.
+///
+/// Clones of a [ProveHandle] share the same cancellation state, so a clone
+/// can be kept to call [ProveHandle::cancel] from another thread.
+///
+/// ```rust
+/// use risc0_zkvm::{ProveHandle, ProvePhase};
+///
+/// let handle = ProveHandle::new().with_progress(|phase| match phase {
+///     ProvePhase::Witness { segment } => println!("proving segment {segment}"),
+///     _ => (),
+/// });
+/// let canceller = handle.clone();
+/// canceller.cancel();
+/// assert!(handle.enter(ProvePhase::Execute).is_err());
+/// ```
+#[derive(Clone, Default)]
+pub struct ProveHandle {
+    cancelled: Arc<AtomicBool>,
+    progress: Option<Arc<dyn Fn(&ProvePhase) + Send + Sync>>,
+}
+
+impl ProveHandle {
+    /// Construct a [ProveHandle] without a progress callback.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call the given function as each [ProvePhase] starts.
+    ///
+    /// The callback may be called from the threads of a parallel prover, so
+    /// it should return quickly.
+    pub fn with_progress<F>(mut self, progress: F) -> Self
+    where
+        F: Fn(&ProvePhase) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Request that proving stops before the next [ProvePhase].
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Return true if [ProveHandle::cancel] has been called on this handle or
+    /// any of its clones.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Start the given [ProvePhase], failing if proving has been cancelled.
+    ///
+    /// Cancelling from within the progress callback stops proving before the
+    /// reported phase.
+    pub fn enter(&self, phase: ProvePhase) -> Result<()> {
+        ensure!(
+            !self.is_cancelled(),
+            "Proving was cancelled before {phase:?}"
+        );
+        tracing::debug!("prove phase: {phase:?}");
+        if let Some(progress) = &self.progress {
+            progress(&phase);
+        }
+        ensure!(
+            !self.is_cancelled(),
+            "Proving was cancelled before {phase:?}"
+        );
+        Ok(())
+    }
+}
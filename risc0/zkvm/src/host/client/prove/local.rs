@@ -14,11 +14,8 @@
 
 use anyhow::Result;
 
-use super::{Executor, Prover, ProverOpts};
-use crate::{
-    get_prover_server, ExecutorEnv, ExecutorImpl, Receipt, SegmentInfo, SessionInfo,
-    VerifierContext,
-};
+use super::{Executor, ProveHandle, Prover, ProverOpts};
+use crate::{get_prover_server, ExecutorEnv, ExecutorImpl, Receipt, SessionInfo, VerifierContext};
 
 /// A [Prover] implementation that selects a [crate::ProverServer] by calling
 /// [get_prover_server].
@@ -42,8 +39,11 @@ impl Prover for LocalProver {
         ctx: &VerifierContext,
         elf: &[u8],
         opts: &ProverOpts,
+        handle: &ProveHandle,
     ) -> Result<Receipt> {
-        get_prover_server(opts)?.prove_with_ctx(env, ctx, elf)
+        let prover = get_prover_server(opts)?;
+        let receipt = prover.prove_with_ctx(env, ctx, elf, handle)?;
+        prover.compress(ctx, opts, &receipt, handle)
     }
 
     fn get_name(&self) -> String {
@@ -53,20 +53,6 @@ impl Prover for LocalProver {
 
 impl Executor for LocalProver {
     fn execute(&self, env: ExecutorEnv<'_>, elf: &[u8]) -> Result<SessionInfo> {
-        let mut exec = ExecutorImpl::from_elf(env, elf)?;
-        let session = exec.run()?;
-        let mut segments = Vec::new();
-        for segment in session.segments {
-            let segment = segment.resolve()?;
-            segments.push(SegmentInfo {
-                po2: segment.po2,
-                cycles: segment.cycles,
-            })
-        }
-        Ok(SessionInfo {
-            segments,
-            journal: session.journal.unwrap_or_default().into(),
-            exit_code: session.exit_code,
-        })
+        ExecutorImpl::from_elf(env, elf)?.execute_only()
     }
 }
@@ -14,16 +14,17 @@
 
 pub(crate) mod bonsai;
 pub(crate) mod external;
+pub(crate) mod handle;
 #[cfg(feature = "prove")]
 pub(crate) mod local;
 
-use std::{path::PathBuf, rc::Rc};
+use std::{path::PathBuf, rc::Rc, thread};
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use self::{bonsai::BonsaiProver, external::ExternalProver};
-use crate::{is_dev_mode, ExecutorEnv, Receipt, SessionInfo, VerifierContext};
+use self::{bonsai::BonsaiProver, external::ExternalProver, handle::ProveHandle};
+use crate::{is_dev_mode, ExecutorEnv, FriParams, Receipt, SessionInfo, VerifierContext};
 
 /// A Prover can execute a given ELF binary and produce a
 /// [Receipt] that can be used to verify correct computation.
@@ -37,6 +38,7 @@ use crate::{is_dev_mode, ExecutorEnv, Receipt, SessionInfo, VerifierContext};
 /// use risc0_zkvm::{
 ///     default_prover,
 ///     ExecutorEnv,
+///     ProveHandle,
 ///     ProverOpts,
 ///     VerifierContext,
 /// };
@@ -53,7 +55,10 @@ use crate::{is_dev_mode, ExecutorEnv, Receipt, SessionInfo, VerifierContext};
 /// let env = ExecutorEnv::builder().write_slice(&[20]).build().unwrap();
 /// let ctx = VerifierContext::default();
 /// let opts = ProverOpts::default();
-/// let receipt = default_prover().prove_with_ctx(env, &ctx, FIB_ELF, &opts).unwrap();
+/// let handle = ProveHandle::new();
+/// let receipt = default_prover()
+///     .prove_with_ctx(env, &ctx, FIB_ELF, &opts, &handle)
+///     .unwrap();
 /// # }
 /// ```
 pub trait Prover {
@@ -67,17 +72,22 @@ pub trait Prover {
             &VerifierContext::default(),
             elf,
             &ProverOpts::default(),
+            &ProveHandle::default(),
         )
     }
 
     /// Prove zkVM execution starting from the specified ELF binary with the
     /// specified [VerifierContext] and [ProverOpts].
+    ///
+    /// Progress is reported to the given [ProveHandle], which can also be used
+    /// to cancel proving between phases.
     fn prove_with_ctx(
         &self,
         env: ExecutorEnv<'_>,
         ctx: &VerifierContext,
         elf: &[u8],
         opts: &ProverOpts,
+        handle: &ProveHandle,
     ) -> Result<Receipt>;
 }
 
@@ -92,22 +102,122 @@ pub trait Executor {
 /// Options to configure a [Prover].
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ProverOpts {
-    /// The hash function to use.
+    /// The hash function to use for proving segments.
+    ///
+    /// One of `sha-256`, `poseidon` or `poseidon2`. Only segments proven with
+    /// `poseidon` can be lifted into a succinct receipt.
     pub hashfn: String,
     /// When false, only prove execution sessions that end in a successful
     /// [crate::ExitCode] (i.e. `Halted(0)` or `Paused(0)`).
     /// When set to true, any completed execution session will be proven, including indicated
     /// errors (e.g. `Halted(1)`) and sessions ending in `Fault`.
     pub prove_guest_errors: bool,
+    /// The kind of receipt to produce. See [ReceiptKind].
+    #[serde(default)]
+    pub receipt_kind: ReceiptKind,
+    /// The parameters of the FRI protocol used to prove segments.
+    ///
+    /// [FriParams::DEFAULT] is the strongest supported set, and the only one
+    /// whose segments can be lifted into a succinct receipt. See [FriParams::for_security_bits] to select
+    /// parameters for a given level of security. Receipts proven with less
+    /// security than [VerifierContext::min_security_bits] are rejected, so
+    /// lower it on the context passed to [Prover::prove_with_ctx] when
+    /// choosing weaker parameters.
+    #[serde(default)]
+    pub fri_params: FriParams,
+    /// An optional limit, in bytes, on the memory occupied by the buffers of
+    /// the prover. When set, segments are proven in a slower, low-memory mode.
+    ///
+    /// Only supported when proving on the CPU. A segment whose estimated
+    /// memory use exceeds the budget is rejected before it is proven, and
+    /// proving fails before any buffer that would exceed the budget is
+    /// allocated. Each
+    /// prover has its own budget, so provers running concurrently may
+    /// together use more than this.
+    #[serde(default)]
+    pub memory_budget: Option<usize>,
+    /// When set, the segments of a session are proven concurrently, as
+    /// configured by the given [ParallelOpts]. Otherwise segments are proven
+    /// one after the other.
+    #[serde(default)]
+    pub parallel: Option<ParallelOpts>,
+}
+
+/// Options to configure how segments are scheduled when they are proven
+/// concurrently.
+///
+/// See [ProverOpts::parallel].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ParallelOpts {
+    /// The maximum number of segments that are proven at the same time.
+    pub max_workers: usize,
+
+    /// An optional limit, in bytes, on the estimated memory needed by all
+    /// segments that are being proven at the same time.
+    ///
+    /// A segment is only dispatched to a worker if doing so keeps the
+    /// estimate within this limit. A single segment is always allowed to
+    /// proceed, even if its estimate alone exceeds the limit.
+    pub memory_limit: Option<usize>,
+}
+
+impl Default for ParallelOpts {
+    /// Return [ParallelOpts] with one worker per available CPU and no memory
+    /// limit. When proving with a GPU, a single worker is used.
+    fn default() -> Self {
+        let max_workers = if cfg!(any(feature = "cuda", feature = "metal")) {
+            1
+        } else {
+            thread::available_parallelism().map_or(1, |x| x.get())
+        };
+        Self {
+            max_workers,
+            memory_limit: None,
+        }
+    }
+}
+
+/// The kind of [Receipt] produced by a [Prover].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReceiptKind {
+    /// A [CompositeReceipt](crate::CompositeReceipt), made up of one receipt
+    /// per segment and one receipt per assumption. Its size grows with the
+    /// length of the execution.
+    #[default]
+    Composite,
+
+    /// A [SuccinctReceipt](crate::SuccinctReceipt), produced by lifting each
+    /// segment receipt, joining them into a single receipt, and resolving
+    /// any assumptions. Its size is constant regardless of the length of the
+    /// execution.
+    ///
+    /// Requires the `poseidon` hash function and the default
+    /// [FriParams].
+    Succinct,
+
+    /// A [SuccinctReceipt](crate::SuccinctReceipt) that has additionally been
+    /// processed by the identity_p254 recursion program, which uses Poseidon
+    /// over the BN254 base field for FRI.
+    ///
+    /// This is the input to the Groth16 prover. Note that it cannot be
+    /// verified with [Receipt::verify]; the [Prover] verifies the
+    /// intermediate succinct receipt before producing it.
+    Compact,
 }
 
 impl Default for ProverOpts {
-    /// Return [ProverOpts] with the SHA-256 hash function and
-    /// `prove_guest_errors` set to false.
+    /// Return [ProverOpts] with the Poseidon hash function,
+    /// `prove_guest_errors` set to false, a [ReceiptKind::Composite] receipt,
+    /// the default [FriParams], no memory budget and segments proven one after
+    /// the other.
     fn default() -> Self {
         Self {
             hashfn: "poseidon".to_string(),
             prove_guest_errors: false,
+            receipt_kind: ReceiptKind::Composite,
+            fri_params: FriParams::DEFAULT,
+            memory_budget: None,
+            parallel: None,
         }
     }
 }
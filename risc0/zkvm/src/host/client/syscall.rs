@@ -0,0 +1,104 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Host-side handlers for guest system calls.
+
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+use anyhow::Result;
+
+/// A host-side implementation of a system call.
+///
+/// Handlers are registered by name with
+/// [ExecutorEnvBuilder::syscall](crate::ExecutorEnvBuilder::syscall). The
+/// value returned is placed in registers `a0` and `a1` of the guest, and
+/// `to_guest` is the buffer the guest provided for the host to fill.
+pub trait Syscall {
+    /// Invokes the system call.
+    fn syscall(
+        &mut self,
+        syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        to_guest: &mut [u32],
+    ) -> Result<(u32, u32)>;
+}
+
+/// Access to memory and machine state for syscalls.
+///
+/// Guest memory and registers are read-only from the point of view of a
+/// [Syscall]; the only way for a handler to write to the guest is through the
+/// `to_guest` buffer and the returned register values.
+pub trait SyscallContext {
+    /// Returns the current cycle being executed.
+    fn get_cycle(&self) -> usize;
+
+    /// Loads the value of the given register, e.g. REG_A0. Fails if `idx` is
+    /// not a valid register index.
+    fn load_register(&mut self, idx: usize) -> Result<u32>;
+
+    /// Loads bytes from the given region of memory.
+    fn load_region(&mut self, addr: u32, size: u32) -> Result<Vec<u8>> {
+        let mut region = Vec::new();
+        for addr in addr..addr + size {
+            region.push(self.load_u8(addr)?);
+        }
+        Ok(region)
+    }
+
+    /// Loads an individual word from memory.
+    fn load_u32(&mut self, addr: u32) -> Result<u32>;
+
+    /// Loads an individual byte from memory.
+    fn load_u8(&mut self, addr: u32) -> Result<u8>;
+
+    /// Loads a null-terminated string from memory.
+    fn load_string(&mut self, mut addr: u32) -> Result<String> {
+        let mut s: Vec<u8> = Vec::new();
+        loop {
+            let b = self.load_u8(addr)?;
+            if b == 0 {
+                break;
+            }
+            s.push(b);
+            addr += 1;
+        }
+        String::from_utf8(s).map_err(anyhow::Error::msg)
+    }
+}
+
+/// The custom [Syscall] handlers registered with an
+/// [ExecutorEnv](crate::ExecutorEnv).
+#[derive(Clone, Default)]
+pub struct SyscallHandlerTable<'a> {
+    pub(crate) inner: BTreeMap<String, Rc<RefCell<dyn Syscall + 'a>>>,
+}
+
+impl<'a> SyscallHandlerTable<'a> {
+    pub fn with_handler(&mut self, syscall: &str, handler: impl Syscall + 'a) -> &mut Self {
+        self.inner
+            .insert(syscall.to_string(), Rc::new(RefCell::new(handler)));
+        self
+    }
+}
+
+impl<S: Syscall + ?Sized> Syscall for Rc<RefCell<S>> {
+    fn syscall(
+        &mut self,
+        syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        self.borrow_mut().syscall(syscall, ctx, to_guest)
+    }
+}
@@ -16,15 +16,17 @@
 pub(crate) mod api;
 #[cfg(feature = "client")]
 pub(crate) mod client;
-pub(crate) mod control_id;
-pub(crate) mod groth16;
 pub(crate) mod receipt;
+#[cfg(feature = "client")]
+pub(crate) mod receipt_file;
 pub(crate) mod recursion;
 #[cfg(feature = "prove")]
 pub(crate) mod server;
 
+use std::collections::BTreeMap;
+
 use anyhow::Result;
-use risc0_binfmt::{MemoryImage, Program};
+use risc0_binfmt::{MemoryImage, MemoryImageBuilder, Program};
 use risc0_zkvm_platform::{memory::GUEST_MAX_MEM, PAGE_SIZE};
 
 use crate::sha::Digest;
@@ -47,6 +49,7 @@ mod protos {
     }
 }
 
+#[cfg(feature = "prove")]
 const CIRCUIT: risc0_circuit_rv32im::CircuitImpl = risc0_circuit_rv32im::CircuitImpl::new();
 
 /// Compute and return the ImageID of the specified ELF binary.
@@ -55,3 +58,24 @@ pub fn compute_image_id(elf: &[u8]) -> Result<Digest> {
     let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
     image.compute_id()
 }
+
+/// Compute and return the ImageID of the specified ELF binary, with the given
+/// named blobs preloaded as with `ExecutorEnvBuilder::preload`.
+pub fn compute_image_id_with_preloads(
+    elf: &[u8],
+    preloads: &BTreeMap<String, Vec<u8>>,
+) -> Result<Digest> {
+    load_image(elf, preloads)?.compute_id()
+}
+
+/// Load the memory image of the specified ELF binary, with the given named
+/// blobs preloaded.
+pub(crate) fn load_image(elf: &[u8], preloads: &BTreeMap<String, Vec<u8>>) -> Result<MemoryImage> {
+    let mut builder = MemoryImageBuilder::new(PAGE_SIZE as u32);
+    builder.program("guest", elf)?;
+    for (name, data) in preloads.iter() {
+        builder.preload(name, data)?;
+    }
+    let (image, _) = builder.build()?;
+    Ok(image)
+}
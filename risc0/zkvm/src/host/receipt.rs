@@ -13,36 +13,26 @@
 // limitations under the License.
 
 //! Manages the output and cryptographic data for a proven computation.
+//!
+//! The inner receipts and their verification are provided by
+//! [risc0_verifier], and re-exported from here.
 
-use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+use alloc::{vec, vec::Vec};
 use core::fmt::Debug;
 
 use anyhow::Result;
-use risc0_binfmt::SystemState;
-use risc0_circuit_rv32im::layout;
-use risc0_core::field::baby_bear::BabyBear;
-use risc0_zkp::{
-    core::{
-        digest::Digest,
-        hash::{
-            blake2b::Blake2bCpuHashSuite, poseidon::PoseidonHashSuite, sha::Sha256HashSuite,
-            HashSuite,
-        },
-    },
-    layout::Buffer,
-    verify::VerificationError,
-};
-use risc0_zkvm_platform::WORD_SIZE;
+use risc0_zkp::{core::digest::Digest, verify::VerificationError};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use super::control_id::{BLAKE2B_CONTROL_ID, POSEIDON_CONTROL_ID, SHA256_CONTROL_ID};
-// Make succinct receipt available through this `receipt` module.
-pub use super::recursion::SuccinctReceipt;
+pub use risc0_verifier::{
+    AssumptionReceipt, CompositeReceipt, Groth16Receipt, InnerReceipt, SegmentReceipt,
+    SuccinctReceipt, VerifierContext,
+};
+
 use crate::{
-    host::groth16::{Groth16Proof, Groth16Seal},
     serde::{from_slice, Error},
-    sha::{Digestible, Sha256},
-    Assumptions, ExitCode, MaybePruned, Output, ReceiptClaim,
+    sha::Sha256,
+    Assumptions, MaybePruned, ReceiptClaim,
 };
 
 /// A receipt attesting to the execution of a Session.
@@ -148,43 +138,39 @@ impl Receipt {
         ctx: &VerifierContext,
         image_id: impl Into<Digest>,
     ) -> Result<(), VerificationError> {
-        self.inner.verify_integrity_with_context(ctx)?;
+        self.verify_inner_integrity(ctx)?;
 
         // NOTE: Post-state digest and input digest are unconstrained by this method.
         let claim = self.inner.get_claim()?;
-        if claim.pre.digest() != image_id.into() {
-            return Err(VerificationError::ImageVerificationError);
-        }
+        claim.verify_success(image_id.into())?;
 
-        // Check the exit code. This verification method requires execution to be
-        // successful.
-        let (ExitCode::Halted(0) | ExitCode::Paused(0)) = claim.exit_code else {
-            return Err(VerificationError::UnexpectedExitCode);
-        };
+        // It is expected that there are no (unresolved) assumptions.
+        claim.verify_journal(&self.journal.bytes, Assumptions(vec![]).into())
+    }
 
-        // Finally check the output hash in the decoded claim against the expected
-        // output.
-        let expected_output = Output {
-            journal: MaybePruned::Pruned(self.journal.digest()),
-            // It is expected that there are no (unresolved) assumptions.
-            assumptions: Assumptions(vec![]).into(),
-        };
+    /// Verify that this receipt proves a successful execution of the zkVM from
+    /// the given `image_id`, conditioned on the returned [Assumptions].
+    ///
+    /// This performs the same checks as [Receipt::verify_with_context], except
+    /// that the claim is allowed to carry a non-empty list of assumptions.
+    /// Such a conditional receipt is produced when a guest verifies a receipt
+    /// that the host provided as an [Assumption::Unresolved] claim. The
+    /// returned list contains the assumptions that are still open; the receipt
+    /// is only valid if each of them is accepted by the verifier, or if they
+    /// are first discharged by proving [crate::recursion::resolve] with a
+    /// receipt for each assumption.
+    pub fn verify_conditional_with_context(
+        &self,
+        ctx: &VerifierContext,
+        image_id: impl Into<Digest>,
+    ) -> Result<Assumptions, VerificationError> {
+        let assumptions = self.verify_conditional_integrity_with_context(ctx)?;
+        self.inner.get_claim()?.verify_success(image_id.into())?;
 
-        if claim.output.digest() != expected_output.digest() {
-            let empty_output = claim.output.is_none() && self.journal.bytes.is_empty();
-            if !empty_output {
-                tracing::debug!(
-                    "journal: 0x{}, expected output digest: 0x{}, decoded output digest: 0x{}",
-                    hex::encode(&self.journal.bytes),
-                    hex::encode(expected_output.digest()),
-                    hex::encode(claim.output.digest()),
-                );
-                return Err(VerificationError::JournalDigestMismatch);
-            }
-            tracing::debug!("accepting zero digest for output of receipt with empty journal");
+        if !assumptions.is_empty() {
+            tracing::debug!("receipt has {} open assumptions", assumptions.len());
         }
-
-        Ok(())
+        Ok(assumptions)
     }
 
     /// Verify the integrity of this receipt, ensuring the claim and jounral
@@ -198,39 +184,56 @@ impl Receipt {
     /// relevant to their application. If you need to verify a successful
     /// guest execution and access the journal, the `verify` function is
     /// recommended.
+    ///
+    /// The claim is required to carry an empty list of assumptions, so a
+    /// conditional receipt is rejected. Use
+    /// [Receipt::verify_conditional_integrity_with_context] to accept one.
     pub fn verify_integrity_with_context(
         &self,
         ctx: &VerifierContext,
     ) -> Result<(), VerificationError> {
-        self.inner.verify_integrity_with_context(ctx)?;
+        self.verify_inner_integrity(ctx)?;
 
         // Check that self.journal is attested to by the inner receipt.
         let claim = self.inner.get_claim()?;
 
-        let expected_output = claim.exit_code.expects_output().then(|| Output {
-            journal: MaybePruned::Pruned(self.journal.digest()),
-            // TODO(#982): It would be reasonable for this method to allow integrity verification
-            // for receipts that have a non-empty assumptions list, but it is not supported here
-            // because we don't have a enough information to open the assumptions list unless we
-            // require it be empty.
-            assumptions: Assumptions(vec![]).into(),
-        });
-
-        if claim.output.digest() != expected_output.digest() {
-            let empty_output = claim.output.is_none() && self.journal.bytes.is_empty();
-            if !empty_output {
-                tracing::debug!(
-                    "journal: 0x{}, expected output digest: 0x{}, decoded output digest: 0x{}",
-                    hex::encode(&self.journal.bytes),
-                    hex::encode(expected_output.digest()),
-                    hex::encode(claim.output.digest()),
-                );
-                return Err(VerificationError::JournalDigestMismatch);
-            }
-            tracing::debug!("accepting zero digest for output of receipt with empty journal");
-        }
+        // TODO(#982): It would be reasonable for this method to allow integrity verification
+        // for receipts that have a non-empty assumptions list, but it is not supported here
+        // because we don't have a enough information to open the assumptions list unless we
+        // require it be empty.
+        claim.verify_journal(&self.journal.bytes, Assumptions(vec![]).into())
+    }
+
+    /// Verify the integrity of this receipt like
+    /// [Receipt::verify_integrity_with_context], but also accept a
+    /// conditional receipt, returning the [Assumptions] its claim carries.
+    ///
+    /// A conditional receipt proves the execution only if every one of the
+    /// returned assumptions holds. The caller is responsible for checking
+    /// each of them, or for discharging them with
+    /// [crate::recursion::resolve], before relying on the receipt. The
+    /// assumptions list must be opened in the claim to be returned; a pruned,
+    /// non-empty list is rejected with [VerificationError::ReceiptFormatError].
+    pub fn verify_conditional_integrity_with_context(
+        &self,
+        ctx: &VerifierContext,
+    ) -> Result<Assumptions, VerificationError> {
+        self.verify_inner_integrity(ctx)?;
 
-        Ok(())
+        let claim = self.inner.get_claim()?;
+        let assumptions = claim.open_assumptions()?;
+        claim.verify_journal(&self.journal.bytes, assumptions.clone().into())?;
+        Ok(assumptions)
+    }
+
+    /// Verify the integrity of the inner receipt, accepting a fake receipt in
+    /// dev mode.
+    fn verify_inner_integrity(&self, ctx: &VerifierContext) -> Result<(), VerificationError> {
+        #[cfg(feature = "std")]
+        if matches!(self.inner, InnerReceipt::Fake { .. }) && crate::is_dev_mode() {
+            return Ok(());
+        }
+        self.inner.verify_integrity_with_context(ctx)
     }
 
     /// Extract the [ReceiptClaim] from this receipt.
@@ -270,395 +273,6 @@ impl AsRef<[u8]> for Journal {
     }
 }
 
-/// An inner receipt can take the form of a [CompositeReceipt] or a
-/// [SuccinctReceipt].
-#[derive(Clone, Debug, Deserialize, Serialize)]
-#[cfg_attr(test, derive(PartialEq))]
-pub enum InnerReceipt {
-    /// A non-succinct [CompositeReceipt].
-    Composite(CompositeReceipt),
-
-    /// The [SuccinctReceipt].
-    Succinct(SuccinctReceipt),
-
-    /// The [Groth16Receipt].
-    Groth16(Groth16Receipt),
-
-    /// A fake receipt for testing and development.
-    ///
-    /// This receipt is not valid and will fail verification unless the
-    /// environment variable `RISC0_DEV_MODE` is set to `true`, in which case a
-    /// pass-through 'verification' will be performed, but it *does not*
-    /// represent any meaningful attestation of receipt's integrity.
-    ///
-    /// This type solely exists to improve development experience, for further
-    /// information about development-only mode see our [dev-mode
-    /// documentation](https://dev.risczero.com/zkvm/dev-mode).
-    Fake {
-        /// [ReceiptClaim] for this fake receipt.
-        claim: ReceiptClaim,
-    },
-}
-
-impl InnerReceipt {
-    /// Verify the integrity of this receipt, ensuring the claim is attested
-    /// to by the seal.
-    pub fn verify_integrity_with_context(
-        &self,
-        ctx: &VerifierContext,
-    ) -> Result<(), VerificationError> {
-        match self {
-            InnerReceipt::Composite(x) => x.verify_integrity_with_context(ctx),
-            InnerReceipt::Groth16(x) => x.verify_integrity(),
-            InnerReceipt::Succinct(x) => x.verify_integrity_with_context(ctx),
-            InnerReceipt::Fake { .. } => {
-                #[cfg(feature = "std")]
-                if crate::is_dev_mode() {
-                    return Ok(());
-                }
-                Err(VerificationError::InvalidProof)
-            }
-        }
-    }
-
-    /// Returns the [InnerReceipt::Composite] arm.
-    pub fn composite(&self) -> Result<&CompositeReceipt, VerificationError> {
-        if let InnerReceipt::Composite(x) = self {
-            Ok(&x)
-        } else {
-            Err(VerificationError::ReceiptFormatError)
-        }
-    }
-
-    /// Returns the [InnerReceipt::Groth16] arm.
-    pub fn groth16(&self) -> Result<&Groth16Receipt, VerificationError> {
-        if let InnerReceipt::Groth16(x) = self {
-            Ok(&x)
-        } else {
-            Err(VerificationError::ReceiptFormatError)
-        }
-    }
-
-    /// Returns the [InnerReceipt::Succinct] arm.
-    pub fn succinct(&self) -> Result<&SuccinctReceipt, VerificationError> {
-        if let InnerReceipt::Succinct(x) = self {
-            Ok(x)
-        } else {
-            Err(VerificationError::ReceiptFormatError)
-        }
-    }
-
-    /// Extract the [ReceiptClaim] from this receipt.
-    pub fn get_claim(&self) -> Result<ReceiptClaim, VerificationError> {
-        match self {
-            InnerReceipt::Composite(ref receipt) => receipt.get_claim(),
-            InnerReceipt::Groth16(ref groth16_receipt) => Ok(groth16_receipt.claim.clone()),
-            InnerReceipt::Succinct(ref succinct_recipt) => Ok(succinct_recipt.claim.clone()),
-            InnerReceipt::Fake { claim } => Ok(claim.clone()),
-        }
-    }
-}
-
-/// A receipt composed of a Groth16 over the BN_254 curve
-#[derive(Clone, Debug, Deserialize, Serialize)]
-#[cfg_attr(test, derive(PartialEq))]
-pub struct Groth16Receipt {
-    /// A Groth16 proof of a zkVM execution with the associated claim.
-    pub seal: Vec<u8>,
-
-    /// [ReceiptClaim] containing information about the execution that this
-    /// receipt proves.
-    pub claim: ReceiptClaim,
-}
-
-impl Groth16Receipt {
-    /// Verify the integrity of this receipt, ensuring the claim is attested
-    /// to by the seal.
-    pub fn verify_integrity(&self) -> Result<(), VerificationError> {
-        Groth16Proof::from_seal(
-            &Groth16Seal::from_vec(&self.seal).map_err(|_| VerificationError::InvalidProof)?,
-            self.claim.digest().into(),
-        )
-        .map_err(|_| VerificationError::InvalidProof)?
-        .verify()
-        .map_err(|_| VerificationError::InvalidProof)?;
-
-        // Everything passed
-        Ok(())
-    }
-}
-
-/// A receipt composed of one or more [SegmentReceipt] structs proving a single
-/// execution with continuations, and zero or more [Receipt] stucts proving any
-/// assumptions.
-#[derive(Clone, Debug, Deserialize, Serialize)]
-#[cfg_attr(test, derive(PartialEq))]
-pub struct CompositeReceipt {
-    /// Segment receipts forming the proof of a execution with continuations.
-    pub segments: Vec<SegmentReceipt>,
-
-    /// An ordered list of assumptions, either proven or unresolved, made within
-    /// the continuation represented by the segment receipts. If any
-    /// assumptions are unresolved, this receipt is only _conditionally_
-    /// valid.
-    // TODO(#982): Allow for unresolved assumptions in this list.
-    pub assumptions: Vec<InnerReceipt>,
-
-    /// Digest of journal included in the final output of the continuation. Will
-    /// be `None` if the continuation has no output (e.g. it ended in
-    /// `Fault`).
-    // NOTE: This field is needed in order to open the assumptions digest from the output digest.
-    // TODO(1.0): This field can potentially be removed since it can be included in the claim on
-    // the last segment receipt instead.
-    pub journal_digest: Option<Digest>,
-}
-
-impl CompositeReceipt {
-    /// Verify the integrity of this receipt, ensuring the claim is attested
-    /// to by the seal.
-    pub fn verify_integrity_with_context(
-        &self,
-        ctx: &VerifierContext,
-    ) -> Result<(), VerificationError> {
-        // Verify the continuation, by verifying every segment receipt in order.
-        let (final_receipt, receipts) = self
-            .segments
-            .as_slice()
-            .split_last()
-            .ok_or(VerificationError::ReceiptFormatError)?;
-
-        // Verify each segment and its chaining to the next.
-        let mut expected_pre_state_digest = None;
-        for receipt in receipts {
-            receipt.verify_integrity_with_context(ctx)?;
-            tracing::debug!("claim: {:#?}", receipt.claim);
-            if let Some(id) = expected_pre_state_digest {
-                if id != receipt.claim.pre.digest() {
-                    return Err(VerificationError::ImageVerificationError);
-                }
-            }
-            if receipt.claim.exit_code != ExitCode::SystemSplit {
-                return Err(VerificationError::UnexpectedExitCode);
-            }
-            if !receipt.claim.output.is_none() {
-                return Err(VerificationError::ReceiptFormatError);
-            }
-            expected_pre_state_digest = Some({
-                // Post state PC is stored as the "actual" value plus 4. This matches the join
-                // predicate implementation. See [ReceiptClaim] for more detail.
-                let mut post = receipt
-                    .claim
-                    .post
-                    .as_value()
-                    .map_err(|_| VerificationError::ReceiptFormatError)?
-                    .clone();
-                post.pc = post
-                    .pc
-                    .checked_sub(WORD_SIZE as u32)
-                    .ok_or(VerificationError::ReceiptFormatError)?;
-                post.digest()
-            });
-        }
-
-        // Verify the last receipt in the continuation.
-        final_receipt.verify_integrity_with_context(ctx)?;
-        tracing::debug!("final: {:#?}", final_receipt.claim);
-        if let Some(id) = expected_pre_state_digest {
-            if id != final_receipt.claim.pre.digest() {
-                return Err(VerificationError::ImageVerificationError);
-            }
-        }
-
-        // Verify all corroborating receipts attached to this composite receipt.
-        for receipt in self.assumptions.iter() {
-            tracing::debug!("verifying assumption: {:?}", receipt.get_claim()?.digest());
-            receipt.verify_integrity_with_context(ctx)?;
-        }
-
-        // Verify decoded output digest is consistent with the journal_digest and
-        // assumptions.
-        self.verify_output_consistency(&final_receipt.claim)?;
-
-        Ok(())
-    }
-
-    /// Returns the [ReceiptClaim] for this [CompositeReceipt].
-    pub fn get_claim(&self) -> Result<ReceiptClaim, VerificationError> {
-        let first_claim = &self
-            .segments
-            .first()
-            .ok_or(VerificationError::ReceiptFormatError)?
-            .claim;
-        let last_claim = &self
-            .segments
-            .last()
-            .ok_or(VerificationError::ReceiptFormatError)?
-            .claim;
-
-        // After verifying the internally consistency of this receipt, we can use
-        // self.assumptions and self.journal_digest in place of
-        // last_claim.output, which is equal.
-        self.verify_output_consistency(last_claim)?;
-        let output: Option<Output> = last_claim
-            .output
-            .is_some()
-            .then(|| {
-                Ok(Output {
-                    journal: MaybePruned::Pruned(
-                        self.journal_digest
-                            .ok_or(VerificationError::ReceiptFormatError)?,
-                    ),
-                    // TODO(#982): Adjust this if unresolved assumptions are allowed on
-                    // CompositeReceipt.
-                    // NOTE: Proven assumptions are not included in the CompositeReceipt claim.
-                    assumptions: Assumptions(vec![]).into(),
-                })
-            })
-            .transpose()?;
-
-        Ok(ReceiptClaim {
-            pre: first_claim.pre.clone(),
-            post: last_claim.post.clone(),
-            exit_code: last_claim.exit_code,
-            input: first_claim.input.clone(),
-            output: output.into(),
-        })
-    }
-
-    /// Check that the output fields in the given receipt claim are
-    /// consistent with the exit code, and with the journal_digest and
-    /// assumptions encoded on self.
-    fn verify_output_consistency(&self, claim: &ReceiptClaim) -> Result<(), VerificationError> {
-        tracing::debug!("checking output: exit_code = {:?}", claim.exit_code);
-        if claim.exit_code.expects_output() && claim.output.is_some() {
-            let self_output = Output {
-                journal: MaybePruned::Pruned(
-                    self.journal_digest
-                        .ok_or(VerificationError::ReceiptFormatError)?,
-                ),
-                assumptions: self.assumptions_claim()?.into(),
-            };
-
-            // If these digests do not match, this receipt is internally inconsistent.
-            if self_output.digest() != claim.output.digest() {
-                let empty_output = claim.output.is_none()
-                    && self
-                        .journal_digest
-                        .ok_or(VerificationError::ReceiptFormatError)?
-                        == Vec::<u8>::new().digest();
-                if !empty_output {
-                    tracing::debug!(
-                        "output digest does not match: expected {:?}; decoded {:?}",
-                        &self_output,
-                        &claim.output
-                    );
-                    return Err(VerificationError::ReceiptFormatError);
-                }
-            }
-        } else {
-            // Ensure all output fields are empty. If not, this receipt is internally
-            // inconsistent.
-            if claim.output.is_some() {
-                tracing::debug!("unexpected non-empty claim output: {:?}", &claim.output);
-                return Err(VerificationError::ReceiptFormatError);
-            }
-            if !self.assumptions.is_empty() {
-                tracing::debug!(
-                    "unexpected non-empty composite receipt assumptions: {:?}",
-                    &self.assumptions
-                );
-                return Err(VerificationError::ReceiptFormatError);
-            }
-            if self.journal_digest.is_some() {
-                tracing::debug!(
-                    "unexpected non-empty composite receipt journal_digest: {:?}",
-                    &self.journal_digest
-                );
-                return Err(VerificationError::ReceiptFormatError);
-            }
-        }
-        Ok(())
-    }
-
-    fn assumptions_claim(&self) -> Result<Assumptions, VerificationError> {
-        Ok(Assumptions(
-            self.assumptions
-                .iter()
-                .map(|a| Ok(a.get_claim()?.into()))
-                .collect::<Result<Vec<_>, _>>()?,
-        ))
-    }
-}
-
-/// A receipt attesting to the execution of a Segment.
-///
-/// A SegmentReceipt attests that a [crate::Segment] was executed in a manner
-/// consistent with the [ReceiptClaim] included in the receipt.
-#[derive(Clone, Debug, Deserialize, Serialize)]
-#[cfg_attr(test, derive(PartialEq))]
-pub struct SegmentReceipt {
-    /// The cryptographic data attesting to the validity of the code execution.
-    ///
-    /// This data is used by the ZKP Verifier (as called by
-    /// [SegmentReceipt::verify_integrity_with_context]) to cryptographically prove that this
-    /// Segment was faithfully executed. It is largely opaque cryptographic data, but contains a
-    /// non-opaque claim component, which can be conveniently accessed with
-    /// [SegmentReceipt::claim].
-    pub seal: Vec<u32>,
-
-    /// Segment index within the [Receipt]
-    pub index: u32,
-
-    /// Name of the hash function used to create this receipt.
-    pub hashfn: String,
-
-    /// [ReceiptClaim] containing information about the execution that this receipt proves.
-    pub claim: ReceiptClaim,
-}
-
-impl SegmentReceipt {
-    /// Verify the integrity of this receipt, ensuring the claim is attested
-    /// to by the seal.
-    pub fn verify_integrity_with_context(
-        &self,
-        ctx: &VerifierContext,
-    ) -> Result<(), VerificationError> {
-        use hex::FromHex;
-        let check_code = |_, control_id: &Digest| -> Result<(), VerificationError> {
-            POSEIDON_CONTROL_ID
-                .into_iter()
-                .chain(SHA256_CONTROL_ID)
-                .chain(BLAKE2B_CONTROL_ID)
-                .find(|x| Digest::from_hex(x).unwrap() == *control_id)
-                .map(|_| ())
-                .ok_or(VerificationError::ControlVerificationError)
-        };
-        let suite = ctx
-            .suites
-            .get(&self.hashfn)
-            .ok_or(VerificationError::InvalidHashSuite)?;
-        risc0_zkp::verify::verify(&super::CIRCUIT, suite, &self.seal, check_code)?;
-
-        // Receipt is consistent with the claim encoded on the seal. Now check against the
-        // claim on the struct.
-        let decoded_claim = decode_receipt_claim_from_seal(&self.seal)?;
-        if decoded_claim.digest() != self.claim.digest() {
-            tracing::debug!(
-                "decoded segment receipt claim does not match claim field: decoded: {:#?}, expected: {:#?}",
-                decoded_claim,
-                self.claim,
-            );
-            return Err(VerificationError::ReceiptFormatError);
-        }
-        Ok(())
-    }
-
-    /// Return the seal for this receipt, as a vector of bytes.
-    pub fn get_seal_bytes(&self) -> Vec<u8> {
-        self.seal.iter().flat_map(|x| x.to_le_bytes()).collect()
-    }
-}
-
 /// An assumption attached with a guest execution as a result of calling
 /// `env::verify` or `env::verify_integrity`.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -684,16 +298,6 @@ impl Assumption {
             Self::Unresolved(claim) => Ok(claim.clone()),
         }
     }
-
-    #[cfg(feature = "prove")]
-    pub(crate) fn as_receipt(&self) -> Result<&Receipt> {
-        match self {
-            Self::Proven(receipt) => Ok(receipt),
-            Self::Unresolved(_) => Err(anyhow::anyhow!(
-                "no receipt available for unresolved assumption"
-            )),
-        }
-    }
 }
 
 impl From<Receipt> for Assumption {
@@ -717,71 +321,11 @@ impl From<ReceiptClaim> for Assumption {
     }
 }
 
-/// Context available to the verification process.
-pub struct VerifierContext {
-    /// A registry of hash functions to be used by the verification process.
-    pub suites: BTreeMap<String, HashSuite<BabyBear>>,
-}
-
-fn decode_system_state_from_io(
-    io: layout::OutBuffer,
-    sys_state: &layout::SystemState,
-) -> Result<SystemState, VerificationError> {
-    let bytes: Vec<u8> = io
-        .tree(sys_state.image_id)
-        .get_bytes()
-        .or(Err(VerificationError::ReceiptFormatError))?;
-    let pc = io
-        .tree(sys_state.pc)
-        .get_u32()
-        .or(Err(VerificationError::ReceiptFormatError))?;
-    let merkle_root = Digest::try_from(bytes).or(Err(VerificationError::ReceiptFormatError))?;
-    Ok(SystemState { pc, merkle_root })
-}
-
-pub(crate) fn decode_receipt_claim_from_seal(
-    seal: &[u32],
-) -> Result<ReceiptClaim, VerificationError> {
-    let elems = bytemuck::cast_slice(seal);
-    let io = layout::OutBuffer(elems);
-    let body = layout::LAYOUT.mux.body;
-    let pre = decode_system_state_from_io(io, body.global.pre)?;
-    let post = decode_system_state_from_io(io, body.global.post)?;
-
-    let input_bytes: Vec<u8> = io
-        .tree(body.global.input)
-        .get_bytes()
-        .or(Err(VerificationError::ReceiptFormatError))?;
-    let input = Digest::try_from(input_bytes).or(Err(VerificationError::ReceiptFormatError))?;
-
-    let output_bytes: Vec<u8> = io
-        .tree(body.global.output)
-        .get_bytes()
-        .or(Err(VerificationError::ReceiptFormatError))?;
-    let output = Digest::try_from(output_bytes).or(Err(VerificationError::ReceiptFormatError))?;
-
-    let sys_exit = io.get_u64(body.global.sys_exit_code) as u32;
-    let user_exit = io.get_u64(body.global.user_exit_code) as u32;
-    let exit_code =
-        ExitCode::from_pair(sys_exit, user_exit).or(Err(VerificationError::ReceiptFormatError))?;
-
-    Ok(ReceiptClaim {
-        pre: pre.into(),
-        post: post.into(),
-        exit_code,
-        input,
-        output: MaybePruned::Pruned(output),
-    })
-}
-
-impl Default for VerifierContext {
-    fn default() -> Self {
-        Self {
-            suites: BTreeMap::from([
-                ("blake2b".into(), Blake2bCpuHashSuite::new_suite()),
-                ("poseidon".into(), PoseidonHashSuite::new_suite()),
-                ("sha-256".into(), Sha256HashSuite::new_suite()),
-            ]),
+impl From<Assumption> for AssumptionReceipt {
+    fn from(assumption: Assumption) -> Self {
+        match assumption {
+            Assumption::Proven(receipt) => Self::Proven(receipt.inner),
+            Assumption::Unresolved(claim) => Self::Unresolved(claim),
         }
     }
 }
@@ -789,9 +333,10 @@ impl Default for VerifierContext {
 #[cfg(test)]
 mod tests {
     use hex::FromHex;
+    use risc0_binfmt::SystemState;
 
     use super::*;
-    use crate::{receipt_claim::MaybePruned, ExitCode::Halted};
+    use crate::{serde::to_vec, sha::Digestible, ExitCode::Halted, FriParams, Groth16Seal, Output};
 
     const IMAGE_ID: [u32; 8] = [
         3877313773, 4166950669, 1851257837, 1474316178, 3714943358, 2342301681, 2883381307,
@@ -844,4 +389,41 @@ mod tests {
         );
         receipt.verify(IMAGE_ID).unwrap();
     }
+
+    #[test]
+    fn verifier_receipt_wire_compat() {
+        let journal = b"hello world".to_vec();
+        let claim = ReceiptClaim {
+            pre: MaybePruned::Pruned(Digest::from(IMAGE_ID)),
+            post: MaybePruned::Pruned(Digest::ZERO),
+            exit_code: Halted(0),
+            input: Digest::ZERO,
+            output: MaybePruned::Value(Some(Output {
+                journal: MaybePruned::Pruned(journal.digest()),
+                assumptions: MaybePruned::Value(Assumptions(vec![])),
+            })),
+        };
+        let receipt = Receipt::new(
+            InnerReceipt::Composite(CompositeReceipt {
+                segments: vec![SegmentReceipt {
+                    seal: vec![1, 2, 3],
+                    index: 0,
+                    hashfn: "poseidon2".into(),
+                    claim: claim.clone(),
+                    fri_params: FriParams::DEFAULT,
+                }],
+                assumptions: vec![AssumptionReceipt::Unresolved(claim.into())],
+                journal_digest: Some(journal.digest()),
+            }),
+            journal,
+        );
+
+        // The receipt of risc0-verifier only differs in its journal type, and has the
+        // same serialized representation.
+        let light: risc0_verifier::Receipt = from_slice(&to_vec(&receipt).unwrap()).unwrap();
+        assert_eq!(light.inner, receipt.inner);
+        assert_eq!(light.journal.bytes, receipt.journal.bytes);
+        let back: Receipt = from_slice(&to_vec(&light).unwrap()).unwrap();
+        assert_eq!(back, receipt);
+    }
 }
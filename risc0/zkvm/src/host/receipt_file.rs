@@ -0,0 +1,431 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A self-describing, versioned file format for receipts.
+//!
+//! A receipt file starts with the magic bytes `R0RC` and the format version,
+//! followed by a [ReceiptHeader] and the receipt itself. The header can be
+//! decoded on its own, so that a file can be identified even if the receipt
+//! it contains can no longer be decoded by this version of the crate.
+//!
+//! Files without the magic bytes are treated as format version 0, which is a
+//! [Receipt] serialized with `bincode` as written by earlier versions of
+//! `r0vm --receipt`.
+
+use std::{fmt, fs, path::Path};
+
+use anyhow::{bail, ensure, Context, Result};
+use bincode::Options;
+use risc0_zkp::verify::VerificationError;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    sha::{Digest, Digestible},
+    Assumptions, Groth16Receipt, InnerReceipt, Receipt, ReceiptClaim, SuccinctReceipt,
+    VerifierContext, VERSION,
+};
+
+/// Magic bytes at the start of every receipt file.
+const RECEIPT_FILE_MAGIC: &[u8; 4] = b"R0RC";
+
+/// The version of the receipt file format written by this crate.
+pub const RECEIPT_FILE_VERSION: u32 = 1;
+
+/// The `bincode` options used to decode a receipt file. They match the
+/// encoding of [bincode::serialize], but reject trailing bytes.
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+}
+
+/// The kind of receipt stored in a [ReceiptFile].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReceiptFileKind {
+    /// A [Receipt], including the journal.
+    Receipt,
+
+    /// A [SuccinctReceipt].
+    Succinct,
+
+    /// A [Groth16Receipt].
+    Groth16,
+}
+
+impl fmt::Display for ReceiptFileKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Receipt => "receipt",
+            Self::Succinct => "succinct",
+            Self::Groth16 => "groth16",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Describes the receipt stored in a [ReceiptFile].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptHeader {
+    /// The kind of receipt stored in the file.
+    pub kind: ReceiptFileKind,
+
+    /// The name of the hash function used by the seal, if the seal is a
+    /// STARK.
+    pub hashfn: Option<String>,
+
+    /// The version of the zkVM that wrote the file, if known.
+    pub zkvm_version: Option<String>,
+
+    /// The image ID of the guest whose execution the receipt proves.
+    pub image_id: Digest,
+
+    /// The digest of the [ReceiptClaim] proven by the receipt.
+    pub claim_digest: Digest,
+}
+
+/// Any of the receipts that can be stored in a [ReceiptFile].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum AnyReceipt {
+    /// A [Receipt], including the journal.
+    Receipt(Receipt),
+
+    /// A [SuccinctReceipt].
+    Succinct(SuccinctReceipt),
+
+    /// A [Groth16Receipt].
+    Groth16(Groth16Receipt),
+}
+
+impl AnyReceipt {
+    /// The kind of this receipt.
+    pub fn kind(&self) -> ReceiptFileKind {
+        match self {
+            Self::Receipt(_) => ReceiptFileKind::Receipt,
+            Self::Succinct(_) => ReceiptFileKind::Succinct,
+            Self::Groth16(_) => ReceiptFileKind::Groth16,
+        }
+    }
+
+    /// The [ReceiptClaim] proven by this receipt.
+    pub fn get_claim(&self) -> Result<ReceiptClaim, VerificationError> {
+        match self {
+            Self::Receipt(receipt) => receipt.get_claim(),
+            Self::Succinct(receipt) => Ok(receipt.claim.clone()),
+            Self::Groth16(receipt) => Ok(receipt.claim.clone()),
+        }
+    }
+
+    /// The name of the hash function used by the seal, if the seal is a
+    /// STARK.
+    pub fn hashfn(&self) -> Option<String> {
+        match self {
+            Self::Receipt(receipt) => match &receipt.inner {
+                InnerReceipt::Composite(inner) => {
+                    inner.segments.first().map(|segment| segment.hashfn.clone())
+                }
+                InnerReceipt::Succinct(_) => Some("poseidon".into()),
+                InnerReceipt::Groth16(_) | InnerReceipt::Fake { .. } => None,
+            },
+            // All receipts from the recursion circuit use Poseidon.
+            Self::Succinct(_) => Some("poseidon".into()),
+            Self::Groth16(_) => None,
+        }
+    }
+
+    /// Verify that this receipt proves a successful execution of the zkVM from
+    /// the given `image_id`.
+    ///
+    /// A [Receipt] is checked with [Receipt::verify_with_context]. A
+    /// [SuccinctReceipt] or [Groth16Receipt] does not carry a journal, so only
+    /// the seal, the image ID, the exit code and the assumptions are checked.
+    ///
+    /// As with [Receipt::verify_with_context], every kind of receipt must be
+    /// unconditional: a claim with a non-empty list of assumptions is
+    /// rejected, as is a claim whose output is pruned, since its assumptions
+    /// cannot be checked.
+    pub fn verify_with_context(
+        &self,
+        ctx: &VerifierContext,
+        image_id: impl Into<Digest>,
+    ) -> Result<(), VerificationError> {
+        let claim = match self {
+            Self::Receipt(receipt) => return receipt.verify_with_context(ctx, image_id),
+            Self::Succinct(receipt) => {
+                receipt.verify_integrity_with_context(ctx)?;
+                &receipt.claim
+            }
+            Self::Groth16(receipt) => {
+                receipt.verify_integrity()?;
+                &receipt.claim
+            }
+        };
+        claim.verify_success(image_id.into())?;
+
+        // It is expected that there are no (unresolved) assumptions.
+        let output = claim
+            .output
+            .as_value()
+            .map_err(|_| VerificationError::ReceiptFormatError)?;
+        if let Some(output) = output {
+            if output.assumptions.digest() != Assumptions(vec![]).digest() {
+                return Err(VerificationError::ReceiptFormatError);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<Receipt> for AnyReceipt {
+    fn from(receipt: Receipt) -> Self {
+        Self::Receipt(receipt)
+    }
+}
+
+impl From<SuccinctReceipt> for AnyReceipt {
+    fn from(receipt: SuccinctReceipt) -> Self {
+        Self::Succinct(receipt)
+    }
+}
+
+impl From<Groth16Receipt> for AnyReceipt {
+    fn from(receipt: Groth16Receipt) -> Self {
+        Self::Groth16(receipt)
+    }
+}
+
+/// A receipt along with a header describing it, which can be written to and
+/// read from disk.
+///
+/// # Example
+///
+/// ```no_run
+/// use risc0_zkvm::ReceiptFile;
+/// # use risc0_zkvm::Receipt;
+/// # fn receipt() -> Receipt { unimplemented!() }
+///
+/// ReceiptFile::new(receipt()).unwrap().save("guest.receipt").unwrap();
+///
+/// let file = ReceiptFile::load("guest.receipt").unwrap();
+/// println!("{} receipt for image {}", file.header.kind, file.header.image_id);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReceiptFile {
+    /// The version of the format the file was read from, or
+    /// [RECEIPT_FILE_VERSION] for a new file.
+    pub format_version: u32,
+
+    /// Describes the receipt.
+    pub header: ReceiptHeader,
+
+    /// The receipt itself.
+    pub receipt: AnyReceipt,
+}
+
+impl ReceiptFile {
+    /// Construct a new [ReceiptFile] holding the given receipt.
+    pub fn new(receipt: impl Into<AnyReceipt>) -> Result<Self> {
+        let receipt = receipt.into();
+        let header = Self::header_for(&receipt, Some(VERSION.to_string()))?;
+        Ok(Self {
+            format_version: RECEIPT_FILE_VERSION,
+            header,
+            receipt,
+        })
+    }
+
+    fn header_for(receipt: &AnyReceipt, zkvm_version: Option<String>) -> Result<ReceiptHeader> {
+        let claim = receipt.get_claim()?;
+        Ok(ReceiptHeader {
+            kind: receipt.kind(),
+            hashfn: receipt.hashfn(),
+            zkvm_version,
+            image_id: claim.pre.digest(),
+            claim_digest: claim.digest(),
+        })
+    }
+
+    /// Encode this file into its versioned binary representation.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(RECEIPT_FILE_MAGIC);
+        bytes.extend_from_slice(&RECEIPT_FILE_VERSION.to_le_bytes());
+        bytes.extend(bincode::serialize(&self.header)?);
+        bytes.extend(bincode::serialize(&self.receipt)?);
+        Ok(bytes)
+    }
+
+    /// Decode the header of a receipt file without decoding the receipt.
+    ///
+    /// Returns the format version of the file along with the header.
+    pub fn decode_header(bytes: &[u8]) -> Result<(u32, ReceiptHeader)> {
+        match Self::split_version(bytes)? {
+            (0, _) => {
+                let file = Self::decode(bytes)?;
+                Ok((0, file.header))
+            }
+            (1, body) => Ok((1, bincode::deserialize(body)?)),
+            (version, _) => Self::unsupported(version),
+        }
+    }
+
+    /// Decode a receipt file from its versioned binary representation.
+    ///
+    /// Files written by earlier versions of the format are upgraded.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        match Self::split_version(bytes)? {
+            (0, body) => {
+                let receipt: Receipt = bincode_options()
+                    .deserialize(body)
+                    .context("not a receipt file")?;
+                let receipt = AnyReceipt::Receipt(receipt);
+                let header = Self::header_for(&receipt, None)?;
+                Ok(Self {
+                    format_version: 0,
+                    header,
+                    receipt,
+                })
+            }
+            (1, body) => {
+                let mut reader = body;
+                let header: ReceiptHeader = bincode::deserialize_from(&mut reader)?;
+                let receipt: AnyReceipt = bincode_options().deserialize(reader)?;
+                let expected = Self::header_for(&receipt, header.zkvm_version.clone())?;
+                ensure!(
+                    header == expected,
+                    "receipt file header does not match the receipt: header {header:?}, receipt {expected:?}"
+                );
+                Ok(Self {
+                    format_version: 1,
+                    header,
+                    receipt,
+                })
+            }
+            (version, _) => Self::unsupported(version),
+        }
+    }
+
+    /// Splits the format version from the rest of the file.
+    fn split_version(bytes: &[u8]) -> Result<(u32, &[u8])> {
+        if bytes.len() < 8 || &bytes[..4] != RECEIPT_FILE_MAGIC {
+            return Ok((0, bytes));
+        }
+        let version = u32::from_le_bytes(bytes[4..8].try_into()?);
+        Ok((version, &bytes[8..]))
+    }
+
+    fn unsupported<T>(version: u32) -> Result<T> {
+        bail!(
+            "unsupported receipt file version: {version}, expected at most {RECEIPT_FILE_VERSION}"
+        )
+    }
+
+    /// Write this file to `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.encode()?)
+            .with_context(|| format!("failed to write receipt to {}", path.display()))
+    }
+
+    /// Read a receipt file from `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)
+            .with_context(|| format!("failed to read receipt from {}", path.display()))?;
+        Self::decode(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_binfmt::SystemState;
+
+    use super::{AnyReceipt, ReceiptFile, ReceiptFileKind, RECEIPT_FILE_VERSION};
+    use crate::{
+        sha::{Digest, Digestible},
+        ExitCode, InnerReceipt, MaybePruned, Receipt, ReceiptClaim,
+    };
+
+    fn fake_receipt() -> Receipt {
+        let claim = ReceiptClaim {
+            pre: MaybePruned::Value(SystemState {
+                pc: 0x1000,
+                merkle_root: Digest::from([1, 2, 3, 4, 5, 6, 7, 8]),
+            }),
+            post: MaybePruned::Pruned(Digest::ZERO),
+            exit_code: ExitCode::Halted(0),
+            input: Digest::ZERO,
+            output: None.into(),
+        };
+        Receipt::new(InnerReceipt::Fake { claim }, Vec::new())
+    }
+
+    #[test]
+    fn round_trip() {
+        let receipt = fake_receipt();
+        let file = ReceiptFile::new(receipt.clone()).unwrap();
+        let bytes = file.encode().unwrap();
+
+        let (version, header) = ReceiptFile::decode_header(&bytes).unwrap();
+        assert_eq!(version, RECEIPT_FILE_VERSION);
+        assert_eq!(header, file.header);
+        assert_eq!(header.kind, ReceiptFileKind::Receipt);
+        assert_eq!(header.claim_digest, receipt.get_claim().unwrap().digest());
+
+        let decoded = ReceiptFile::decode(&bytes).unwrap();
+        let AnyReceipt::Receipt(decoded) = decoded.receipt else {
+            panic!("unexpected receipt kind");
+        };
+        assert_eq!(decoded, receipt);
+    }
+
+    #[test]
+    fn legacy_receipt() {
+        let receipt = fake_receipt();
+        let bytes = bincode::serialize(&receipt).unwrap();
+        let file = ReceiptFile::decode(&bytes).unwrap();
+        assert_eq!(file.format_version, 0);
+        assert_eq!(file.header.zkvm_version, None);
+        assert_eq!(
+            file.header.image_id,
+            receipt.get_claim().unwrap().pre.digest()
+        );
+    }
+
+    #[test]
+    fn mismatched_header() {
+        let mut file = ReceiptFile::new(fake_receipt()).unwrap();
+        file.header.image_id = Digest::ZERO;
+        let bytes = file.encode().unwrap();
+        assert!(ReceiptFile::decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn trailing_bytes() {
+        let mut bytes = ReceiptFile::new(fake_receipt()).unwrap().encode().unwrap();
+        bytes.push(0);
+        assert!(ReceiptFile::decode(&bytes).is_err());
+
+        let mut bytes = bincode::serialize(&fake_receipt()).unwrap();
+        bytes.push(0);
+        assert!(ReceiptFile::decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut bytes = ReceiptFile::new(fake_receipt()).unwrap().encode().unwrap();
+        bytes[4..8].copy_from_slice(&(RECEIPT_FILE_VERSION + 1).to_le_bytes());
+        let err = ReceiptFile::decode(&bytes).unwrap_err();
+        assert!(err.to_string().contains("unsupported receipt file version"));
+    }
+}
@@ -12,24 +12,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-//! This module implements the verifier for the recursion circuit.
+//! This module implements the prover for the recursion circuit.
 //!
-//! This module implements receipts that are generated from the recursion
-//! circuit as well as verification functions for each type of receipt.
+//! The receipts generated by the recursion circuit, and their verification,
+//! are provided by [risc0_verifier].
 
 #[cfg(feature = "prove")]
 mod prove;
-mod receipt;
 #[cfg(test)]
 #[cfg(feature = "prove")]
 mod tests;
 
 pub use risc0_circuit_recursion::control_id::ALLOWED_IDS_ROOT;
+pub use risc0_verifier::{valid_control_ids, SuccinctReceipt};
 
 #[cfg(feature = "prove")]
 pub use self::prove::{
     identity_p254, join, lift, poseidon_hal_pair, resolve, Program, Prover, ProverOpts,
 };
-pub use self::receipt::{valid_control_ids, SuccinctReceipt};
+#[cfg(feature = "prove")]
+pub(crate) use self::prove::{join_claim, resolve_claim};
 
+#[cfg(feature = "prove")]
 const CIRCUIT: risc0_circuit_recursion::CircuitImpl = risc0_circuit_recursion::CircuitImpl::new();
@@ -21,12 +21,13 @@ pub mod zkr;
 
 use std::{collections::VecDeque, mem::take, rc::Rc};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, ensure, Context, Result};
 use hex::FromHex;
 use merkle::MerkleGroup;
 use risc0_circuit_recursion::{
     cpu::CpuCircuitHal, CircuitImpl, REGISTER_GROUP_ACCUM, REGISTER_GROUP_CODE, REGISTER_GROUP_DATA,
 };
+use risc0_verifier::Merge;
 use risc0_zkp::{
     adapter::{CircuitInfo, CircuitStepContext, TapsProvider},
     core::{
@@ -38,19 +39,19 @@ use risc0_zkp::{
         Elem,
     },
     hal::{cpu::CpuHal, CircuitHal, Hal},
+    params::FriParams,
     prove::adapter::ProveAdapter,
     verify::ReadIOP,
-    MIN_CYCLES_PO2, ZK_CYCLES,
+    MAX_CYCLES_PO2, MIN_CYCLES_PO2, ZK_CYCLES,
 };
 use serde::{Deserialize, Serialize};
 
 pub use self::program::Program;
 use super::CIRCUIT;
 use crate::{
-    receipt_claim::{Merge, Output},
     recursion::{valid_control_ids, SuccinctReceipt},
     sha::Digestible,
-    HalPair, ReceiptClaim, SegmentReceipt, POSEIDON_CONTROL_ID,
+    HalPair, Output, ReceiptClaim, SegmentReceipt, POSEIDON_CONTROL_ID,
 };
 
 // TODO: Automatically generate these constants from the circuit somehow without
@@ -80,6 +81,10 @@ pub struct RecursionReceipt {
 /// used as the input to all other recursion programs (e.g. join, resolve, and identity_p254).
 pub fn lift(segment_receipt: &SegmentReceipt) -> Result<SuccinctReceipt> {
     tracing::debug!("Proving lift: claim = {:#?}", segment_receipt.claim);
+    ensure!(
+        segment_receipt.fri_params == FriParams::DEFAULT,
+        "only segments proven with the default FRI parameters can be lifted"
+    );
     let mut prover = Prover::new_lift(&segment_receipt.seal, ProverOpts::default())?;
     let receipt = prover.run()?;
     let mut out_stream = VecDeque::<u32>::new();
@@ -107,13 +112,7 @@ pub fn join(a: &SuccinctReceipt, b: &SuccinctReceipt) -> Result<SuccinctReceipt>
     out_stream.extend(receipt.output.iter());
 
     // Construct the expected claim that should have result from the join.
-    let ab_claim = ReceiptClaim {
-        pre: a.claim.pre.clone(),
-        post: b.claim.post.clone(),
-        exit_code: b.claim.exit_code,
-        input: a.claim.input.clone(),
-        output: b.claim.output.clone(),
-    };
+    let ab_claim = join_claim(&a.claim, &b.claim);
 
     let claim_decoded = ReceiptClaim::decode(&mut out_stream)?;
     tracing::debug!("Proving join finished: decoded claim = {claim_decoded:#?}");
@@ -142,21 +141,8 @@ pub fn resolve(
         corroborating.claim,
     );
 
-    // Construct the resolved claim by copying the conditional receipt claim and resolving
-    // the head assumption. If this fails, then so would the resolve program.
-    let mut resolved_claim = conditional.claim.clone();
-    resolved_claim
-        .output
-        .as_value_mut()
-        .context("conditional receipt output is pruned")?
-        .as_mut()
-        .ok_or(anyhow!(
-            "conditional receipt has empty output and no assumptions"
-        ))?
-        .assumptions
-        .as_value_mut()
-        .context("conditional receipt assumptions are pruned")?
-        .resolve(&corroborating.claim.digest())?;
+    // If resolving the claim fails, then so would the resolve program.
+    let resolved_claim = resolve_claim(&conditional.claim, &corroborating.claim)?;
 
     let mut prover = Prover::new_resolve(conditional, corroborating, ProverOpts::default())?;
     let receipt = prover.run()?;
@@ -172,6 +158,39 @@ pub fn resolve(
     })
 }
 
+/// Construct the claim proven by joining receipts for the claims `a` and `b`.
+pub(crate) fn join_claim(a: &ReceiptClaim, b: &ReceiptClaim) -> ReceiptClaim {
+    ReceiptClaim {
+        pre: a.pre.clone(),
+        post: b.post.clone(),
+        exit_code: b.exit_code,
+        input: a.input.clone(),
+        output: b.output.clone(),
+    }
+}
+
+/// Construct the claim proven by resolving the head assumption of the
+/// `conditional` claim with a receipt for the `corroborating` claim.
+pub(crate) fn resolve_claim(
+    conditional: &ReceiptClaim,
+    corroborating: &ReceiptClaim,
+) -> Result<ReceiptClaim> {
+    let mut resolved_claim = conditional.clone();
+    resolved_claim
+        .output
+        .as_value_mut()
+        .context("conditional receipt output is pruned")?
+        .as_mut()
+        .ok_or(anyhow!(
+            "conditional receipt has empty output and no assumptions"
+        ))?
+        .assumptions
+        .as_value_mut()
+        .context("conditional receipt assumptions are pruned")?
+        .resolve(&corroborating.digest())?;
+    Ok(resolved_claim)
+}
+
 /// Prove the verification of a recursion receipt using the Poseidon254 hash function for FRI.
 ///
 /// The identity_p254 program is used as the last step in the prover pipeline before running the
@@ -502,8 +521,12 @@ impl Prover {
         let merkle_root = allowed_ids.calc_root(hashfn);
 
         let mut iop = ReadIOP::new(seal, opts.suite.rng.as_ref());
-        iop.read_field_elem_slice::<BabyBearElem>(risc0_circuit_rv32im::CircuitImpl::OUTPUT_SIZE);
-        let po2 = *iop.read_u32s(1).first().unwrap() as usize;
+        iop.read_field_elem_slice::<BabyBearElem>(risc0_circuit_rv32im::CircuitImpl::OUTPUT_SIZE)?;
+        let po2 = iop.read_u32s(1)?[0] as usize;
+        ensure!(
+            (MIN_CYCLES_PO2..=MAX_CYCLES_PO2).contains(&po2),
+            "segment seal has unsupported po2: {po2}"
+        );
 
         let (program, control_id) = zkr::lift(po2)?;
         let mut prover = Prover::new(program, control_id, opts);
@@ -17,6 +17,7 @@ use risc0_zkp::{
     field::baby_bear::{BabyBear, BabyBearElem},
     hal::{cpu::CpuHal, Hal},
     prove::poly_group::PolyGroup,
+    QUERIES,
 };
 
 use super::{RECURSION_CODE_SIZE, RECURSION_PO2};
@@ -79,7 +80,7 @@ impl Program {
         hal.batch_interpolate_ntt(&coeffs, self.code_size);
         hal.zk_shift(&coeffs, self.code_size);
         // Make the poly-group & extract the root
-        let code_group = PolyGroup::new(&hal, coeffs, self.code_size, cycles, "code");
+        let code_group = PolyGroup::new(&hal, coeffs, self.code_size, cycles, QUERIES, "code");
         let root = *code_group.merkle.root();
         tracing::trace!("Computed recursion code: {root:?}");
         root
@@ -17,6 +17,7 @@ use risc0_zkp::{
     adapter::CircuitInfo,
     core::digest::{Digest, DIGEST_WORDS},
     field::baby_bear::BabyBearElem,
+    verify::VerificationError,
 };
 use risc0_zkvm_methods::{
     multi_test::MultiTestSpec, HELLO_COMMIT_ELF, HELLO_COMMIT_ID, MULTI_TEST_ELF, MULTI_TEST_ID,
@@ -29,7 +30,8 @@ use super::{
     Prover, ProverOpts,
 };
 use crate::{
-    get_prover_server, ExecutorEnv, ExecutorImpl, InnerReceipt, Receipt, SegmentReceipt, Session,
+    get_prover_server, sha::Digestible, AnyReceipt, Assumption, AssumptionReceipt, ExecutorEnv,
+    ExecutorImpl, InnerReceipt, ProveHandle, Receipt, ReceiptKind, SegmentReceipt, Session,
     VerifierContext,
 };
 
@@ -144,6 +146,7 @@ fn generate_busy_loop_segments(hashfn: &str) -> (Session, Vec<SegmentReceipt>) {
     let opts = crate::ProverOpts {
         hashfn: hashfn.to_string(),
         prove_guest_errors: false,
+        ..Default::default()
     };
     let prover = get_prover_server(&opts).unwrap();
 
@@ -201,6 +204,7 @@ fn generate_composition_receipt(hashfn: &str) -> Receipt {
     let opts = crate::ProverOpts {
         hashfn: hashfn.to_string(),
         prove_guest_errors: false,
+        ..Default::default()
     };
     let prover = get_prover_server(&opts).unwrap();
 
@@ -239,10 +243,10 @@ fn test_recursion_lift_resolve_e2e() {
     let conditional_segment_receipt = composition_receipt.segments[0].clone();
 
     assert_eq!(composition_receipt.assumptions.len(), 1);
-    let assumption_receipt = composition_receipt.assumptions[0]
-        .composite()
-        .unwrap()
-        .clone();
+    let AssumptionReceipt::Proven(assumption_receipt) = &composition_receipt.assumptions[0] else {
+        panic!("expected a proven assumption");
+    };
+    let assumption_receipt = assumption_receipt.composite().unwrap().clone();
     assert_eq!(assumption_receipt.segments.len(), 1);
     assert_eq!(assumption_receipt.assumptions.len(), 0);
     let assumption_segment_receipt = assumption_receipt.segments[0].clone();
@@ -273,3 +277,131 @@ fn test_recursion_lift_resolve_e2e() {
     let resolved_receipt = Receipt::new(InnerReceipt::Succinct(resolved), receipt.journal.bytes);
     resolved_receipt.verify(MULTI_TEST_ID).unwrap();
 }
+
+#[cfg_attr(
+    not(all(feature = "metal", target_os = "macos", target_arch = "x86_64")),
+    test
+)]
+#[serial]
+fn test_receipt_kind_succinct() {
+    let opts = crate::ProverOpts {
+        receipt_kind: ReceiptKind::Succinct,
+        ..Default::default()
+    };
+    let prover = get_prover_server(&opts).unwrap();
+    let ctx = VerifierContext::default();
+
+    // A session with several segments is compressed using a join tree.
+    let (session, _) = generate_busy_loop_segments("poseidon");
+    let composite = prover
+        .prove_session(&ctx, &session, &ProveHandle::default())
+        .unwrap();
+    let receipt = prover
+        .compress(&ctx, &opts, &composite, &ProveHandle::default())
+        .unwrap();
+    receipt.inner.succinct().unwrap();
+    receipt.verify(MULTI_TEST_ID).unwrap();
+    let light: risc0_verifier::Receipt =
+        crate::serde::from_slice(&crate::serde::to_vec(&receipt).unwrap()).unwrap();
+    light.verify(MULTI_TEST_ID).unwrap();
+    assert_eq!(
+        receipt.get_claim().unwrap().digest(),
+        composite.get_claim().unwrap().digest()
+    );
+
+    // Assumptions attached to the composite receipt are resolved.
+    let composite = generate_composition_receipt("poseidon");
+    let receipt = prover
+        .compress(&ctx, &opts, &composite, &ProveHandle::default())
+        .unwrap();
+    receipt.inner.succinct().unwrap();
+    receipt.verify(MULTI_TEST_ID).unwrap();
+}
+
+#[cfg_attr(
+    not(all(feature = "metal", target_os = "macos", target_arch = "x86_64")),
+    test
+)]
+#[serial]
+fn test_unresolved_assumption_resolve() {
+    let opts = crate::ProverOpts {
+        receipt_kind: ReceiptKind::Succinct,
+        ..Default::default()
+    };
+    let prover = get_prover_server(&opts).unwrap();
+    let ctx = VerifierContext::default();
+
+    let assumption_receipt = prover
+        .prove(ExecutorEnv::default(), HELLO_COMMIT_ELF)
+        .unwrap();
+    let assumption_claim = assumption_receipt.get_claim().unwrap();
+
+    // Execute against an unresolved assumption to produce a conditional receipt.
+    let env = ExecutorEnv::builder()
+        .add_assumption(Assumption::Unresolved(assumption_claim.clone().into()))
+        .write(&MultiTestSpec::SysVerify {
+            image_id: HELLO_COMMIT_ID.into(),
+            journal: b"hello world".to_vec(),
+        })
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let conditional = prover
+        .prove_session(&ctx, &session, &ProveHandle::default())
+        .unwrap();
+
+    let unresolved = conditional
+        .inner
+        .composite()
+        .unwrap()
+        .unresolved_assumptions();
+    assert_eq!(unresolved.len(), 1);
+    assert_eq!(unresolved[0].digest(), assumption_claim.digest());
+    assert!(conditional.verify(MULTI_TEST_ID).is_err());
+    assert!(conditional.verify_integrity_with_context(&ctx).is_err());
+    assert_eq!(
+        conditional
+            .verify_conditional_integrity_with_context(&ctx)
+            .unwrap()
+            .len(),
+        1
+    );
+    let open = conditional
+        .verify_conditional_with_context(&ctx, MULTI_TEST_ID)
+        .unwrap();
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].digest(), assumption_claim.digest());
+
+    // Discharge the assumption by resolving it with the assumption receipt.
+    let conditional = prover
+        .compress(&ctx, &opts, &conditional, &ProveHandle::default())
+        .unwrap();
+    assert!(conditional.verify_integrity_with_context(&ctx).is_err());
+
+    // A receipt file holding only the conditional succinct receipt is rejected,
+    // since the open assumption cannot be checked without the journal.
+    let succinct = AnyReceipt::Succinct(conditional.inner.succinct().unwrap().clone());
+    assert!(matches!(
+        succinct.verify_with_context(&ctx, MULTI_TEST_ID),
+        Err(VerificationError::ReceiptFormatError)
+    ));
+    let corroborating = prover
+        .compress(&ctx, &opts, &assumption_receipt, &ProveHandle::default())
+        .unwrap();
+    let resolved = prover
+        .resolve(
+            conditional.inner.succinct().unwrap(),
+            corroborating.inner.succinct().unwrap(),
+        )
+        .unwrap();
+    let receipt = Receipt::new(InnerReceipt::Succinct(resolved), conditional.journal.bytes);
+    receipt.verify(MULTI_TEST_ID).unwrap();
+    assert!(receipt
+        .verify_conditional_with_context(&ctx, MULTI_TEST_ID)
+        .unwrap()
+        .is_empty());
+}
@@ -14,7 +14,13 @@
 
 //! This module implements the Executor.
 
-use std::{cell::RefCell, fmt::Debug, io::Write, mem, rc::Rc};
+use std::{
+    cell::RefCell,
+    fmt::Debug,
+    io::{Read, Write},
+    mem,
+    rc::Rc,
+};
 
 use addr2line::{
     fallible_iterator::FallibleIterator,
@@ -23,7 +29,7 @@ use addr2line::{
 };
 use anyhow::{anyhow, bail, Context, Result};
 use crypto_bigint::{CheckedMul, Encoding, NonZero, U256, U512};
-use risc0_binfmt::{MemoryImage, Program, SystemState};
+use risc0_binfmt::{MemoryImage, SystemState};
 use risc0_zkp::{
     core::{
         digest::{DIGEST_BYTES, DIGEST_WORDS},
@@ -34,12 +40,13 @@ use risc0_zkp::{
 };
 use risc0_zkvm_platform::{
     fileno,
-    memory::{is_guest_memory, GUEST_MAX_MEM},
+    memory::is_guest_memory,
     syscall::{
-        bigint, ecall, halt,
-        reg_abi::{REG_A0, REG_A1, REG_A2, REG_A3, REG_A4, REG_T0},
+        bigint, cycle_region, ecall, halt,
+        nr::SYS_CYCLE_REGION,
+        reg_abi::{REG_A0, REG_A1, REG_A2, REG_A3, REG_A4, REG_A5, REG_MAX, REG_T0},
     },
-    PAGE_SIZE, WORD_SIZE,
+    WORD_SIZE,
 };
 use rrs_lib::{instruction_executor::InstructionExecutor, HartState};
 use serde::{Deserialize, Serialize};
@@ -47,17 +54,29 @@ use sha2::digest::generic_array::GenericArray;
 use tempfile::tempdir;
 use tracing::{level_filters::LevelFilter, Level};
 
-use super::{monitor::MemoryMonitor, profiler::Profiler, syscall::SyscallTable};
+use super::{
+    gdb::{DebugTarget, GdbStub},
+    monitor::MemoryMonitor,
+    profiler::Profiler,
+    region::RegionTracker,
+    snapshot::ExecutorSnapshot,
+    syscall::{SyscallContext, SyscallTable},
+    transcript::{
+        is_replayed_live, FromGuest, RecordingContext, SyscallEntry, SyscallReplay,
+        SyscallTranscript, SYSCALL_ARG_REGS,
+    },
+};
 use crate::{
     align_up,
     host::{
         client::exec::TraceEvent,
+        load_image,
         receipt::Assumption,
         server::opcode::{MajorType, OpCode},
     },
     sha::Digest,
-    Assumptions, ExecutorEnv, ExitCode, FileSegmentRef, Loader, Output, Segment, SegmentRef,
-    Session,
+    Assumptions, CompressedSegmentRef, ExecutorEnv, ExitCode, Loader, Output, Segment, SegmentInfo,
+    SegmentRef, Session, SessionInfo,
 };
 
 /// The number of cycles required to compress a SHA-256 block.
@@ -123,6 +142,9 @@ pub struct ExecutorImpl<'a> {
     segment_limit: usize,
     segment_cycle: usize,
     segments: Vec<Box<dyn SegmentRef>>,
+    segment_infos: Vec<SegmentInfo>,
+    segment_offset: u32,
+    cycle_offset: usize,
     insn_counter: u32,
     split_insn: Option<u32>,
     const_cycles: usize,
@@ -131,7 +153,14 @@ pub struct ExecutorImpl<'a> {
     exit_code: Option<ExitCode>,
     obj_ctx: Option<ObjectContext>,
     output_digest: Option<Digest>,
+    journal_prefix: Vec<u8>,
+    syscall_record: Option<SyscallTranscript>,
+    syscall_replay: Option<SyscallReplay>,
+    gdb: Option<GdbStub<'a>>,
     profiler: Option<Rc<RefCell<Profiler>>>,
+    regions: RegionTracker,
+    finished_cycles: usize,
+    finished_paging_cycles: usize,
 }
 
 impl<'a> ExecutorImpl<'a> {
@@ -165,6 +194,20 @@ impl<'a> ExecutorImpl<'a> {
         let fini_cycles = loader.fini_cycles();
         let const_cycles = init_cycles + fini_cycles + SHA_CYCLES + ZK_CYCLES;
         let syscall_table = SyscallTable::new(&env);
+        let regions = RegionTracker::new(env.cycle_budgets.clone());
+
+        if env.syscall_record_path.is_some() && env.syscall_replay_path.is_some() {
+            bail!("syscalls cannot be recorded and replayed at the same time");
+        }
+        let syscall_record = env
+            .syscall_record_path
+            .is_some()
+            .then(SyscallTranscript::default);
+        let syscall_replay = env
+            .syscall_replay_path
+            .as_ref()
+            .map(|path| Ok::<_, anyhow::Error>(SyscallReplay::new(SyscallTranscript::load(path)?)))
+            .transpose()?;
 
         Ok(Self {
             env,
@@ -177,6 +220,9 @@ impl<'a> ExecutorImpl<'a> {
             segment_limit: 1 << segment_limit_po2,
             segment_cycle: init_cycles,
             segments: Vec::new(),
+            segment_infos: Vec::new(),
+            segment_offset: 0,
+            cycle_offset: 0,
             insn_counter: 0,
             split_insn: None,
             const_cycles,
@@ -185,10 +231,97 @@ impl<'a> ExecutorImpl<'a> {
             exit_code: None,
             obj_ctx,
             output_digest: None,
+            journal_prefix: Vec::new(),
+            syscall_record,
+            syscall_replay,
+            gdb: None,
             profiler,
+            regions,
+            finished_cycles: 0,
+            finished_paging_cycles: 0,
+        })
+    }
+
+    /// Construct a new [ExecutorImpl] that resumes execution from an
+    /// [ExecutorSnapshot].
+    ///
+    /// The [Segment]s produced by the resumed executor are indexed following
+    /// the segments produced before the snapshot was taken. The profiler is
+    /// not available when resuming from a snapshot.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use risc0_zkvm::{ExecutorEnv, ExecutorImpl, ExecutorSnapshot};
+    ///
+    /// let snapshot = ExecutorSnapshot::load("guest.snapshot").unwrap();
+    /// let env = ExecutorEnv::builder().build().unwrap();
+    /// let mut exec = ExecutorImpl::from_snapshot(env, snapshot).unwrap();
+    /// let session = exec.run().unwrap();
+    /// ```
+    pub fn from_snapshot(env: ExecutorEnv<'a>, snapshot: ExecutorSnapshot) -> Result<Self> {
+        let mut exec = Self::with_details(env, *snapshot.pre_image, None, None)?;
+        exec.exit_code = snapshot.exit_code;
+        exec.segment_offset = snapshot.segment_offset;
+        exec.cycle_offset = snapshot.cycle_offset.try_into()?;
+        exec.pending_syscall = snapshot.pending_syscall;
+        exec.output_digest = snapshot.output_digest;
+        exec.journal_prefix = snapshot.journal;
+        exec.env.assumptions.borrow_mut().accessed = snapshot.assumptions;
+        exec.regions.restore(snapshot.regions);
+        exec.finished_cycles = snapshot.region_cycles.0.try_into()?;
+        exec.finished_paging_cycles = snapshot.region_cycles.1.try_into()?;
+        Ok(exec)
+    }
+
+    /// Capture the state of this executor as an [ExecutorSnapshot].
+    ///
+    /// A snapshot can be taken before the executor is run, or after a run
+    /// has ended with [ExitCode::Paused]. To capture snapshots at segment
+    /// boundaries while running, see
+    /// [ExecutorEnvBuilder::snapshot_path](crate::ExecutorEnvBuilder::snapshot_path).
+    pub fn snapshot(&self) -> Result<ExecutorSnapshot> {
+        let (Some(ExitCode::SystemSplit | ExitCode::Paused(_)) | None) = self.exit_code else {
+            bail!(
+                "cannot snapshot an execution which exited with {:?}",
+                self.exit_code
+            );
+        };
+        self.snapshot_with_journal(&self.journal_prefix)
+    }
+
+    fn snapshot_with_journal(&self, journal: &[u8]) -> Result<ExecutorSnapshot> {
+        let pre_image = self
+            .pre_image
+            .clone()
+            .ok_or_else(|| anyhow!("attempted to snapshot the executor with no pre_image"))?;
+        let (cycles, paging_cycles) = self.region_cycles();
+        Ok(ExecutorSnapshot {
+            pre_image,
+            exit_code: self.exit_code,
+            segment_offset: self.segment_index()?,
+            cycle_offset: (self.session_cycle() - self.segment_cycle).try_into()?,
+            pending_syscall: self.pending_syscall.clone(),
+            output_digest: self.output_digest,
+            journal: journal.to_vec(),
+            assumptions: self.env.assumptions.borrow().accessed.clone(),
+            regions: self.regions.state(),
+            region_cycles: (cycles.try_into()?, paging_cycles.try_into()?),
         })
     }
 
+    fn save_snapshot(&self, journal: &Journal) -> Result<()> {
+        if let Some(path) = self.env.snapshot_path.as_deref() {
+            let snapshot = self.snapshot_with_journal(journal.buf.borrow().as_slice())?;
+            tracing::debug!(
+                "saving snapshot: segment_index: {}, path: {}",
+                snapshot.segment_offset,
+                path.display()
+            );
+            snapshot.save(path)?;
+        }
+        Ok(())
+    }
+
     /// Construct a new [ExecutorImpl] from the ELF binary of the guest program
     /// you want to run and an [ExecutorEnv] containing relevant
     /// environmental configuration details.
@@ -206,8 +339,7 @@ impl<'a> ExecutorImpl<'a> {
     /// let mut exec = ExecutorImpl::from_elf(env, BENCH_ELF).unwrap();
     /// ```
     pub fn from_elf(mut env: ExecutorEnv<'a>, elf: &[u8]) -> Result<Self> {
-        let program = Program::load_elf(elf, GUEST_MAX_MEM as u32)?;
-        let image = MemoryImage::new(&program, PAGE_SIZE as u32)?;
+        let image = load_image(elf, &env.preloads)?;
 
         let obj_ctx = if LevelFilter::current().eq(&Level::TRACE) {
             let file = addr2line::object::read::File::parse(elf)?;
@@ -235,7 +367,25 @@ impl<'a> ExecutorImpl<'a> {
         }
 
         let path = self.env.segment_path.clone().unwrap();
-        self.run_with_callback(|segment| Ok(Box::new(FileSegmentRef::new(&segment, &path)?)))
+        self.run_with_callback(|segment| Ok(Box::new(CompressedSegmentRef::new(&segment, &path)?)))
+    }
+
+    /// Run the executor under the control of a GDB client speaking the remote
+    /// serial protocol over `conn`.
+    ///
+    /// Execution stops before the first instruction and waits for the client
+    /// to set breakpoints, single-step, inspect registers and memory, or
+    /// continue. When execution ends, the exit code is reported to the client.
+    ///
+    /// Modifying registers or memory from the debugger results in a
+    /// [Session] that cannot be proven.
+    pub fn run_with_gdb<C: Read + Write + 'a>(&mut self, conn: C) -> Result<Session> {
+        self.gdb = Some(GdbStub::new(Box::new(conn)));
+        let result = self.run();
+        if let Some(mut gdb) = self.gdb.take() {
+            gdb.report_exit(result.as_ref().ok().map(|session| session.exit_code))?;
+        }
+        result
     }
 
     /// Run the executor until [ExitCode::Halted], [ExitCode::Paused], or
@@ -244,111 +394,74 @@ impl<'a> ExecutorImpl<'a> {
     where
         F: FnMut(Segment) -> Result<Box<dyn SegmentRef>>,
     {
-        let (Some(ExitCode::SystemSplit | ExitCode::Paused(_)) | None) = self.exit_code else {
-            return Err(anyhow!(
-                "cannot resume an execution which exited with {:?}",
-                self.exit_code
-            )
-            .into());
-        };
-
+        let journal = self.begin_run()?;
         let start_time = std::time::Instant::now();
 
-        self.pc = self
-            .pre_image
-            .as_ref()
-            .ok_or_else(|| anyhow!("attempted to run the executor with no pre_image"))?
-            .pc;
-        self.monitor.clear_session()?;
-
-        let journal = Journal::default();
-        self.env
-            .posix_io
-            .borrow_mut()
-            .with_write_fd(fileno::JOURNAL, journal.clone());
-
         let mut run_loop = || -> Result<(ExitCode, Segment, MemoryImage)> {
             loop {
-                if let Some(exit_code) = self.step()? {
-                    let total_cycles = self.total_cycles();
-                    tracing::debug!("exit_code: {exit_code:?}, total_cycles: {total_cycles}");
-                    assert!(total_cycles <= self.segment_limit);
-                    let pre_image = self.pre_image.take().ok_or_else(|| {
-                        anyhow!("attempted to run the executor with no pre_image")
-                    })?;
-                    let post_image = self.monitor.build_image(self.pc)?;
-                    let syscalls = mem::take(&mut self.syscalls);
-                    let faults = mem::take(&mut self.monitor.faults);
-                    let po2 = log2_ceil(total_cycles.next_power_of_two()).try_into()?;
-                    let cycles = self.body_cycles.try_into()?;
-                    let segment = Segment::new(
-                        pre_image,
-                        SystemState::from(&post_image),
-                        // NOTE: On the last segment, the output is added outside this loop.
-                        None,
-                        faults,
-                        syscalls,
-                        exit_code,
-                        self.split_insn,
-                        po2,
-                        self.segments
-                            .len()
-                            .try_into()
-                            .context("Too many segments to fit in u32")?,
-                        cycles,
-                    );
-                    match exit_code {
-                        ExitCode::SystemSplit => {
-                            let segment_ref = callback(segment)?;
-                            self.segments.push(segment_ref);
-                            self.split(Some(post_image.into()))?
-                        }
-                        ExitCode::SessionLimit => {
-                            let segment_ref = callback(segment)?;
-                            self.segments.push(segment_ref);
-                            bail!("Session limit exceeded")
-                        }
-                        ExitCode::Paused(inner) => {
-                            tracing::debug!("Paused({inner}): {}", self.segment_cycle);
-                            // Set the pre_image so that the Executor can be run again to resume.
-                            // Move the pc forward by WORD_SIZE because halt does not.
-                            let mut resume_pre_image = post_image.clone();
-                            resume_pre_image.pc += WORD_SIZE as u32;
-                            self.split(Some(resume_pre_image.into()))?;
-                            return Ok((exit_code, segment, post_image));
-                        }
-                        ExitCode::Halted(inner) => {
-                            tracing::debug!("Halted({inner}): {}", self.segment_cycle);
-                            return Ok((exit_code, segment, post_image));
-                        }
-                        ExitCode::Fault => {
-                            tracing::debug!("Fault: {}", self.segment_cycle);
-                            return Ok((exit_code, segment, post_image));
-                        }
-                    };
+                let exit_code = self.run_segment()?;
+                let pre_image = self
+                    .pre_image
+                    .take()
+                    .ok_or_else(|| anyhow!("attempted to run the executor with no pre_image"))?;
+                let post_image = self.monitor.build_image(self.pc)?;
+                let syscalls = mem::take(&mut self.syscalls);
+                let faults = mem::take(&mut self.monitor.faults);
+                let info = self.segment_info()?;
+                let segment = Segment::new(
+                    pre_image,
+                    SystemState::from(&post_image),
+                    // NOTE: On the last segment, the output is added outside this loop.
+                    None,
+                    faults,
+                    syscalls,
+                    exit_code,
+                    self.split_insn,
+                    info.po2,
+                    self.segment_index()?,
+                    info.cycles,
+                );
+                self.segment_infos.push(info);
+                match exit_code {
+                    ExitCode::SystemSplit => {
+                        let segment_ref = callback(segment)?;
+                        self.segments.push(segment_ref);
+                        self.exit_code = Some(exit_code);
+                        self.split(Some(post_image.into()))?;
+                        self.save_snapshot(&journal)?;
+                    }
+                    ExitCode::SessionLimit => {
+                        let segment_ref = callback(segment)?;
+                        self.segments.push(segment_ref);
+                        bail!("Session limit exceeded")
+                    }
+                    ExitCode::Paused(inner) => {
+                        tracing::debug!("Paused({inner}): {}", self.segment_cycle);
+                        // Set the pre_image so that the Executor can be run again to resume.
+                        // Move the pc forward by WORD_SIZE because halt does not.
+                        let mut resume_pre_image = post_image.clone();
+                        resume_pre_image.pc += WORD_SIZE as u32;
+                        self.split(Some(resume_pre_image.into()))?;
+                        return Ok((exit_code, segment, post_image));
+                    }
+                    ExitCode::Halted(inner) => {
+                        tracing::debug!("Halted({inner}): {}", self.segment_cycle);
+                        return Ok((exit_code, segment, post_image));
+                    }
+                    ExitCode::Fault => {
+                        tracing::debug!("Fault: {}", self.segment_cycle);
+                        return Ok((exit_code, segment, post_image));
+                    }
                 };
             }
         };
 
-        let (exit_code, mut final_segment, post_image) = run_loop()?;
+        // Save the transcript even if execution failed, so that the failure can be reproduced.
+        let result = run_loop();
+        self.save_syscall_transcript()?;
+        let (exit_code, mut final_segment, post_image) = result?;
         let elapsed = start_time.elapsed();
-
-        // Take (clear out) the list of accessed assumptions.
-        // Leave the assumptions cache so it can be used if execution is resumed from pause.
-        let assumptions = mem::take(&mut self.env.assumptions.borrow_mut().accessed);
-
-        // Set the session_journal to the committed data iff the the guest set a non-zero output.
-        let session_journal = self
-            .output_digest
-            .and_then(|output_digest| (output_digest != Digest::ZERO).then(|| journal.buf.take()));
-        if !exit_code.expects_output() && session_journal.is_some() {
-            tracing::debug!(
-                "dropping non-empty journal due to exit code {:?}: 0x{}",
-                exit_code,
-                hex::encode(journal.buf.borrow().as_slice())
-            );
-        };
-        self.exit_code = Some(exit_code);
+        let (session_journal, assumptions) = self.end_run(exit_code, &journal)?;
 
         // Construct the Output struct for the final segment.
         final_segment.output = exit_code
@@ -383,21 +496,237 @@ impl<'a> ExecutorImpl<'a> {
         tracing::info!("segment_count = {}", self.segments.len());
         tracing::info!("execution_time = {:?}", elapsed);
 
-        if let Some(profiler) = self.profiler.take() {
-            let report = profiler.borrow_mut().finalize_to_vec();
-            std::fs::write(self.env.pprof_out.as_ref().unwrap(), report)?;
-        }
+        let (cycles, paging_cycles) = self.region_cycles();
+        let cycle_regions = self.regions.finish(cycles, paging_cycles)?;
 
-        Ok(Session::new(
+        let session = Session::new_with_cycle_regions(
             mem::take(&mut self.segments),
             session_journal,
             exit_code,
             post_image,
             assumptions,
-        ))
+            cycle_regions,
+        );
+        self.reset_session(exit_code)?;
+
+        Ok(session)
+    }
+
+    /// Run the executor until [ExitCode::Halted], [ExitCode::Paused], or
+    /// [ExitCode::Fault] is reached, without producing any [Segment]s.
+    ///
+    /// This is useful when only the journal, the exit code and the cycle
+    /// counts of an execution are needed, e.g. for dry runs or estimating
+    /// costs. The returned [SessionInfo] has the same segment count, user
+    /// cycles and po2 of each segment as the [Session] returned by
+    /// [ExecutorImpl::run]. Paging is still tracked, as it contributes to the
+    /// cycle counts, but the merkle tree of the memory image is only updated
+    /// when the execution is paused. Snapshots are not saved at segment
+    /// boundaries.
+    pub fn execute_only(&mut self) -> Result<SessionInfo> {
+        self.execute_info(false)
+    }
+
+    /// Run the executor as [ExecutorImpl::execute_only] does, except that
+    /// reaching the session limit is not an error: the returned [SessionInfo]
+    /// has an exit code of [ExitCode::SessionLimit] and describes the segments
+    /// executed so far.
+    pub(crate) fn execute_to_limit(&mut self) -> Result<SessionInfo> {
+        self.execute_info(true)
+    }
+
+    fn execute_info(&mut self, stop_at_limit: bool) -> Result<SessionInfo> {
+        let journal = self.begin_run()?;
+        let start_time = std::time::Instant::now();
+
+        let mut run_loop = || -> Result<ExitCode> {
+            loop {
+                let exit_code = self.run_segment()?;
+                let info = self.segment_info()?;
+                self.segment_infos.push(info);
+                self.syscalls.clear();
+                match exit_code {
+                    ExitCode::SystemSplit => {
+                        self.monitor.flush_image(self.pc);
+                        self.exit_code = Some(exit_code);
+                        self.split(None)?;
+                    }
+                    ExitCode::SessionLimit if stop_at_limit => return Ok(exit_code),
+                    ExitCode::SessionLimit => bail!("Session limit exceeded"),
+                    ExitCode::Paused(_) => {
+                        // Set the pre_image so that the Executor can be run again to resume.
+                        // Move the pc forward by WORD_SIZE because halt does not.
+                        let mut resume_pre_image = self.monitor.build_image(self.pc)?;
+                        resume_pre_image.pc += WORD_SIZE as u32;
+                        self.split(Some(resume_pre_image.into()))?;
+                        return Ok(exit_code);
+                    }
+                    ExitCode::Halted(_) | ExitCode::Fault => return Ok(exit_code),
+                }
+            }
+        };
+
+        // Save the transcript even if execution failed, so that the failure can be reproduced.
+        let result = run_loop();
+        self.save_syscall_transcript()?;
+        let exit_code = result?;
+        let elapsed = start_time.elapsed();
+        let (session_journal, _) = self.end_run(exit_code, &journal)?;
+
+        tracing::info!("total_cycles = {}", self.total_cycles());
+        tracing::info!("segment_count = {}", self.segment_infos.len());
+        tracing::info!("execution_time = {:?}", elapsed);
+
+        let (cycles, paging_cycles) = self.region_cycles();
+        let cycle_regions = self.regions.finish(cycles, paging_cycles)?;
+
+        let info = SessionInfo {
+            segments: mem::take(&mut self.segment_infos),
+            journal: crate::Journal::new(session_journal.unwrap_or_default()),
+            exit_code,
+            cycle_regions,
+        };
+        self.reset_session(exit_code)?;
+
+        Ok(info)
+    }
+
+    /// Prepare the executor to run from its pre_image, returning the journal
+    /// that collects the data committed by the guest.
+    fn begin_run(&mut self) -> Result<Journal> {
+        let (Some(ExitCode::SystemSplit | ExitCode::Paused(_)) | None) = self.exit_code else {
+            bail!(
+                "cannot resume an execution which exited with {:?}",
+                self.exit_code
+            );
+        };
+
+        self.pc = self
+            .pre_image
+            .as_ref()
+            .ok_or_else(|| anyhow!("attempted to run the executor with no pre_image"))?
+            .pc;
+        self.monitor.clear_session()?;
+
+        let journal = Journal::default();
+        journal
+            .buf
+            .borrow_mut()
+            .extend(mem::take(&mut self.journal_prefix));
+        self.env
+            .posix_io
+            .borrow_mut()
+            .with_write_fd(fileno::JOURNAL, journal.clone());
+        Ok(journal)
+    }
+
+    /// Run until the current segment ends, returning its [ExitCode].
+    fn run_segment(&mut self) -> Result<ExitCode> {
+        loop {
+            self.debug_hook()?;
+            if let Some(exit_code) = self.step()? {
+                let total_cycles = self.total_cycles();
+                tracing::debug!("exit_code: {exit_code:?}, total_cycles: {total_cycles}");
+                assert!(total_cycles <= self.segment_limit);
+                return Ok(exit_code);
+            }
+        }
+    }
+
+    fn segment_info(&self) -> Result<SegmentInfo> {
+        Ok(SegmentInfo {
+            po2: log2_ceil(self.total_cycles().next_power_of_two()).try_into()?,
+            cycles: self.body_cycles.try_into()?,
+        })
+    }
+
+    /// Collect the results shared by every kind of run once execution has
+    /// ended with `exit_code`, returning the session journal and the
+    /// assumptions accessed by the guest.
+    fn end_run(
+        &mut self,
+        exit_code: ExitCode,
+        journal: &Journal,
+    ) -> Result<(Option<Vec<u8>>, Vec<Assumption>)> {
+        if let Some(replay) = &self.syscall_replay {
+            if !matches!(exit_code, ExitCode::Paused(_)) && replay.remaining() != 0 {
+                bail!(
+                    "syscall replay diverged: execution ended with {exit_code:?} and {} syscalls left in the transcript",
+                    replay.remaining()
+                );
+            }
+        }
+
+        // Take (clear out) the list of accessed assumptions.
+        // Leave the assumptions cache so it can be used if execution is resumed from pause.
+        let assumptions = mem::take(&mut self.env.assumptions.borrow_mut().accessed);
+
+        // Set the session_journal to the committed data iff the the guest set a non-zero output.
+        let session_journal = self
+            .output_digest
+            .and_then(|output_digest| (output_digest != Digest::ZERO).then(|| journal.buf.take()));
+        if !exit_code.expects_output() && session_journal.is_some() {
+            tracing::debug!(
+                "dropping non-empty journal due to exit code {:?}: 0x{}",
+                exit_code,
+                hex::encode(journal.buf.borrow().as_slice())
+            );
+        };
+        self.exit_code = Some(exit_code);
+
+        if let Some(profiler) = self.profiler.take() {
+            let report = profiler
+                .borrow_mut()
+                .finalize_to_format(self.env.profile_format);
+            std::fs::write(self.env.pprof_out.as_ref().unwrap(), report)?;
+        }
+
+        Ok((session_journal, assumptions))
+    }
+
+    fn reset_session(&mut self, exit_code: ExitCode) -> Result<()> {
+        // A resumed execution starts a new session, so segment indices and cycles restart at zero.
+        self.segment_infos.clear();
+        self.segment_offset = 0;
+        self.cycle_offset = 0;
+        self.finished_cycles = 0;
+        self.finished_paging_cycles = 0;
+        if let ExitCode::Paused(_) = exit_code {
+            // NOTE: The journal is not carried across a pause.
+            self.save_snapshot(&Journal::default())?;
+        }
+        Ok(())
+    }
+
+    fn save_syscall_transcript(&self) -> Result<()> {
+        if let (Some(transcript), Some(path)) =
+            (&self.syscall_record, self.env.syscall_record_path.as_ref())
+        {
+            tracing::debug!(
+                "saving syscall transcript: entries: {}, path: {}",
+                transcript.entries.len(),
+                path.display()
+            );
+            transcript.save(path)?;
+        }
+        Ok(())
+    }
+
+    fn debug_hook(&mut self) -> Result<()> {
+        if let Some(mut gdb) = self.gdb.take() {
+            let result = gdb.before_step(self);
+            if !gdb.is_detached() {
+                self.gdb = Some(gdb);
+            }
+            result?;
+        }
+        Ok(())
     }
 
     fn split(&mut self, pre_image: Option<Box<MemoryImage>>) -> Result<()> {
+        let (cycles, paging_cycles) = self.region_cycles();
+        self.finished_cycles = cycles;
+        self.finished_paging_cycles = paging_cycles;
         self.pre_image = pre_image;
         self.body_cycles = 0;
         self.split_insn = None;
@@ -560,7 +889,25 @@ impl<'a> ExecutorImpl<'a> {
     }
 
     fn session_cycle(&self) -> usize {
-        self.segments.len() * self.segment_limit + self.segment_cycle
+        self.cycle_offset + self.segment_infos.len() * self.segment_limit + self.segment_cycle
+    }
+
+    /// Returns the cycles and the paging cycles spent so far in this session,
+    /// as accounted to cycle regions. Unlike `session_cycle`, this does
+    /// not include the fixed overhead of each segment.
+    fn region_cycles(&self) -> (usize, usize) {
+        let paging_cycles = self.monitor.page_read_cycles + self.monitor.page_write_cycles;
+        (
+            self.finished_cycles + self.body_cycles + paging_cycles,
+            self.finished_paging_cycles + paging_cycles,
+        )
+    }
+
+    fn segment_index(&self) -> Result<u32> {
+        u32::try_from(self.segment_infos.len())
+            .ok()
+            .and_then(|len| len.checked_add(self.segment_offset))
+            .context("Too many segments to fit in u32")
     }
 
     fn ecall(&mut self) -> Result<OpCodeResult> {
@@ -712,6 +1059,55 @@ impl<'a> ExecutorImpl<'a> {
         ))
     }
 
+    /// Handles a syscall, returning the response along with the guest memory
+    /// read by the host.
+    fn dispatch_syscall(
+        &mut self,
+        syscall_name: &str,
+        to_guest_words: u32,
+    ) -> Result<(SyscallRecord, FromGuest)> {
+        // Cycle regions are accounted by the executor itself.
+        if syscall_name == SYS_CYCLE_REGION.as_str() {
+            return self.sys_cycle_region(to_guest_words);
+        }
+
+        let mut to_guest = vec![0; to_guest_words as usize];
+        let handler = self
+            .syscall_table
+            .get_syscall(syscall_name)
+            .ok_or(anyhow!("Unknown syscall: {syscall_name:?}"))?;
+        let mut ctx = RecordingContext::new(&mut self.monitor);
+        let (a0, a1) = handler
+            .borrow_mut()
+            .syscall(syscall_name, &mut ctx, &mut to_guest)?;
+        let record = SyscallRecord {
+            to_guest,
+            regs: (a0, a1),
+        };
+        Ok((record, ctx.from_guest))
+    }
+
+    fn sys_cycle_region(&mut self, to_guest_words: u32) -> Result<(SyscallRecord, FromGuest)> {
+        let name_ptr = self.monitor.load_register(REG_A3);
+        let name_len = self.monitor.load_register(REG_A4);
+        let op = self.monitor.load_register(REG_A5);
+        let mut ctx = RecordingContext::new(&mut self.monitor);
+        let name = ctx.load_region(name_ptr, name_len)?;
+        let from_guest = ctx.from_guest;
+        let name = String::from_utf8(name).context("cycle region name is not valid UTF-8")?;
+        let (cycles, paging_cycles) = self.region_cycles();
+        match op {
+            cycle_region::ENTER => self.regions.enter(name, cycles, paging_cycles),
+            cycle_region::EXIT => self.regions.exit(&name, cycles, paging_cycles)?,
+            _ => bail!("Illegal cycle region op: {op}"),
+        }
+        let record = SyscallRecord {
+            to_guest: vec![0; to_guest_words as usize],
+            regs: (0, 0),
+        };
+        Ok((record, from_guest))
+    }
+
     fn ecall_software(&mut self) -> Result<OpCodeResult> {
         let to_guest_ptr = self.monitor.load_register(REG_A0);
         if !is_guest_memory(to_guest_ptr) && to_guest_ptr != 0 {
@@ -730,19 +1126,45 @@ impl<'a> ExecutorImpl<'a> {
             tracing::debug!("Replay syscall: {syscall:?}");
             syscall
         } else {
-            let mut to_guest = vec![0; to_guest_words as usize];
-            let handler = self
-                .syscall_table
-                .get_syscall(&syscall_name)
-                .ok_or(anyhow!("Unknown syscall: {syscall_name:?}"))?;
-            let (a0, a1) =
-                handler
-                    .borrow_mut()
-                    .syscall(&syscall_name, &mut self.monitor, &mut to_guest)?;
-            let syscall = SyscallRecord {
-                to_guest,
-                regs: (a0, a1),
+            let args = SYSCALL_ARG_REGS.map(|idx| self.monitor.load_register(idx));
+            let (syscall, from_guest) = match self.syscall_replay.as_mut() {
+                Some(replay) => {
+                    let entry = replay
+                        .next(
+                            &syscall_name,
+                            &args,
+                            to_guest_words as usize,
+                            &mut self.monitor,
+                        )?
+                        .clone();
+                    if is_replayed_live(&syscall_name, &args) {
+                        let (live, _) = self.dispatch_syscall(&syscall_name, to_guest_words)?;
+                        if live.to_guest != entry.to_guest || live.regs != entry.regs {
+                            bail!(
+                                "syscall replay diverged: {syscall_name:?} returned {:08x?}, recorded {:08x?}",
+                                live.regs,
+                                entry.regs
+                            );
+                        }
+                    }
+                    let record = SyscallRecord {
+                        to_guest: entry.to_guest,
+                        regs: entry.regs,
+                    };
+                    (record, FromGuest::default())
+                }
+                None => self.dispatch_syscall(&syscall_name, to_guest_words)?,
             };
+            if let Some(transcript) = self.syscall_record.as_mut() {
+                transcript.entries.push(SyscallEntry {
+                    name: syscall_name.clone(),
+                    args: args.to_vec(),
+                    from_guest_digest: from_guest.digest(),
+                    from_guest: from_guest.ranges,
+                    to_guest: syscall.to_guest.clone(),
+                    regs: syscall.regs,
+                });
+            }
             self.pending_syscall = Some(syscall.clone());
             syscall
         };
@@ -771,3 +1193,29 @@ impl<'a> ExecutorImpl<'a> {
         ))
     }
 }
+
+impl<'a> DebugTarget for ExecutorImpl<'a> {
+    fn pc(&self) -> u32 {
+        self.pc
+    }
+
+    fn set_pc(&mut self, pc: u32) {
+        self.pc = pc;
+    }
+
+    fn registers(&self) -> [u32; REG_MAX] {
+        self.monitor.load_registers()
+    }
+
+    fn set_register(&mut self, idx: usize, value: u32) {
+        self.monitor.poke_register(idx, value);
+    }
+
+    fn read_memory(&self, addr: u32, len: u32) -> Result<Vec<u8>> {
+        self.monitor.peek_region(addr, len)
+    }
+
+    fn write_memory(&mut self, addr: u32, data: &[u8]) -> Result<()> {
+        self.monitor.poke_region(addr, data)
+    }
+}
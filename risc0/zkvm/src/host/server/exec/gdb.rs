@@ -0,0 +1,343 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A stub implementing the GDB remote serial protocol for guest execution.
+//!
+//! See <https://sourceware.org/gdb/current/onlinedocs/gdb.html/Remote-Protocol.html>.
+
+use std::{
+    collections::BTreeSet,
+    io::{Read, Write},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use risc0_zkvm_platform::syscall::reg_abi::REG_MAX;
+
+use crate::ExitCode;
+
+/// The signal reported to GDB when the guest stops for the debugger.
+const SIGTRAP: u8 = 5;
+
+/// The signal reported to GDB when the guest faults.
+const SIGSEGV: u8 = 11;
+
+/// The index of the PC in the RISC-V register file as seen by GDB.
+const REG_PC: usize = REG_MAX;
+
+/// Access to the machine state of a guest being debugged.
+pub(crate) trait DebugTarget {
+    /// Returns the PC of the next instruction to execute.
+    fn pc(&self) -> u32;
+
+    /// Sets the PC of the next instruction to execute.
+    fn set_pc(&mut self, pc: u32);
+
+    /// Returns the general purpose registers.
+    fn registers(&self) -> [u32; REG_MAX];
+
+    /// Sets a general purpose register.
+    fn set_register(&mut self, idx: usize, value: u32);
+
+    /// Reads a region of guest memory.
+    fn read_memory(&self, addr: u32, len: u32) -> Result<Vec<u8>>;
+
+    /// Writes a region of guest memory.
+    fn write_memory(&mut self, addr: u32, data: &[u8]) -> Result<()>;
+}
+
+/// A bidirectional connection to a GDB client.
+pub(crate) trait Connection: Read + Write {}
+
+impl<T: Read + Write> Connection for T {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RunState {
+    /// Stop before the next instruction.
+    Stopped,
+    /// Execute a single instruction, then stop.
+    Step,
+    /// Run until a breakpoint is hit.
+    Continue,
+    /// The client has detached; run without stopping.
+    Detached,
+}
+
+/// The state of a GDB remote serial protocol session.
+pub(crate) struct GdbStub<'a> {
+    conn: Box<dyn Connection + 'a>,
+    state: RunState,
+    breakpoints: BTreeSet<u32>,
+    /// The PC that execution was resumed from, which does not trigger a stop
+    /// until execution has moved on.
+    resumed_at: Option<u32>,
+    ack: bool,
+}
+
+impl<'a> GdbStub<'a> {
+    pub(crate) fn new(conn: Box<dyn Connection + 'a>) -> Self {
+        Self {
+            conn,
+            state: RunState::Stopped,
+            breakpoints: BTreeSet::new(),
+            resumed_at: None,
+            ack: true,
+        }
+    }
+
+    /// Returns true if the client has detached from this session.
+    pub(crate) fn is_detached(&self) -> bool {
+        self.state == RunState::Detached
+    }
+
+    /// Called before each instruction is executed. Stops and serves requests
+    /// from the client if a breakpoint is hit or a single step completed.
+    pub(crate) fn before_step(&mut self, target: &mut dyn DebugTarget) -> Result<()> {
+        let pc = target.pc();
+        if let Some(resumed_at) = self.resumed_at {
+            if resumed_at == pc {
+                // NOTE: The PC does not advance if the instruction caused a split.
+                return Ok(());
+            }
+            self.resumed_at = None;
+        }
+
+        match self.state {
+            RunState::Detached => return Ok(()),
+            RunState::Continue if !self.breakpoints.contains(&pc) => return Ok(()),
+            // The client expects the target to be stopped when it attaches, and
+            // it asks for the stop reason before sending any other command.
+            RunState::Stopped => {}
+            RunState::Step | RunState::Continue => {
+                tracing::debug!("gdb: stopped at pc: 0x{pc:08x}");
+                self.send_packet(&format!("S{SIGTRAP:02x}"))?;
+            }
+        }
+        self.state = RunState::Stopped;
+        self.serve(target)?;
+        self.resumed_at = Some(target.pc());
+        Ok(())
+    }
+
+    /// Reports the end of execution to the client.
+    pub(crate) fn report_exit(&mut self, exit_code: Option<ExitCode>) -> Result<()> {
+        let reply = match exit_code {
+            Some(ExitCode::Halted(code) | ExitCode::Paused(code)) => format!("W{:02x}", code as u8),
+            Some(ExitCode::Fault) => format!("X{SIGSEGV:02x}"),
+            _ => format!("X{SIGTRAP:02x}"),
+        };
+        self.send_packet(&reply)
+    }
+
+    /// Serve requests until the client resumes execution.
+    fn serve(&mut self, target: &mut dyn DebugTarget) -> Result<()> {
+        while self.state == RunState::Stopped {
+            let packet = self.recv_packet()?;
+            tracing::trace!("gdb: <- {packet}");
+            if packet == "k" {
+                bail!("execution killed by the debugger");
+            }
+            let reply = self.handle_packet(&packet, target).unwrap_or_else(|err| {
+                tracing::debug!("gdb: request {packet:?} failed: {err:?}");
+                Some("E01".to_string())
+            });
+            if let Some(reply) = reply {
+                self.send_packet(&reply)?;
+            }
+            if packet == "QStartNoAckMode" {
+                self.ack = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle a single request, returning the reply to send, if any.
+    fn handle_packet(
+        &mut self,
+        packet: &str,
+        target: &mut dyn DebugTarget,
+    ) -> Result<Option<String>> {
+        let (cmd, args) = packet.split_at(packet.len().min(1));
+        let reply = match cmd {
+            "?" => format!("S{SIGTRAP:02x}"),
+            "g" => {
+                let mut reply = String::new();
+                for value in target.registers().iter().chain([target.pc()].iter()) {
+                    reply.push_str(&hex::encode(value.to_le_bytes()));
+                }
+                reply
+            }
+            "G" => {
+                let bytes = hex::decode(args)?;
+                for (idx, word) in bytes.chunks_exact(4).enumerate().take(REG_PC + 1) {
+                    set_register(target, idx, u32::from_le_bytes(word.try_into()?));
+                }
+                "OK".to_string()
+            }
+            "p" => {
+                let idx = usize::from_str_radix(args, 16)?;
+                let value = match idx {
+                    REG_PC => target.pc(),
+                    idx if idx < REG_MAX => target.registers()[idx],
+                    _ => bail!("unknown register {idx}"),
+                };
+                hex::encode(value.to_le_bytes())
+            }
+            "P" => {
+                let (idx, value) = args.split_once('=').context("malformed P packet")?;
+                let value = u32::from_le_bytes(hex::decode(value)?.as_slice().try_into()?);
+                set_register(target, usize::from_str_radix(idx, 16)?, value);
+                "OK".to_string()
+            }
+            "m" => {
+                let (addr, len) = parse_addr_len(args)?;
+                hex::encode(target.read_memory(addr, len)?)
+            }
+            "M" => {
+                let (range, data) = args.split_once(':').context("malformed M packet")?;
+                let (addr, len) = parse_addr_len(range)?;
+                let data = hex::decode(data)?;
+                if data.len() != len as usize {
+                    bail!("M packet length mismatch");
+                }
+                target.write_memory(addr, &data)?;
+                "OK".to_string()
+            }
+            "Z" | "z" => {
+                let mut fields = args.splitn(3, ',');
+                let kind = fields.next().context("malformed breakpoint packet")?;
+                let addr = u32::from_str_radix(fields.next().unwrap_or_default(), 16)?;
+                // Software and hardware breakpoints are handled the same way.
+                // Watchpoints are not supported.
+                if !matches!(kind, "0" | "1") {
+                    return Ok(Some(String::new()));
+                }
+                if cmd == "Z" {
+                    self.breakpoints.insert(addr);
+                } else {
+                    self.breakpoints.remove(&addr);
+                }
+                "OK".to_string()
+            }
+            "c" | "s" => {
+                if !args.is_empty() {
+                    target.set_pc(u32::from_str_radix(args, 16)?);
+                }
+                self.state = if cmd == "c" {
+                    RunState::Continue
+                } else {
+                    RunState::Step
+                };
+                // The reply is sent when execution stops again.
+                return Ok(None);
+            }
+            "D" => {
+                self.state = RunState::Detached;
+                "OK".to_string()
+            }
+            "H" => "OK".to_string(),
+            "T" => "OK".to_string(),
+            _ => match packet {
+                "qAttached" => "1".to_string(),
+                "qC" => "QC1".to_string(),
+                "qfThreadInfo" => "m1".to_string(),
+                "qsThreadInfo" => "l".to_string(),
+                "QStartNoAckMode" => "OK".to_string(),
+                _ if packet.starts_with("qSupported") => {
+                    "PacketSize=4000;QStartNoAckMode+;swbreak+;hwbreak+".to_string()
+                }
+                // An empty reply indicates an unsupported request.
+                _ => String::new(),
+            },
+        };
+        Ok(Some(reply))
+    }
+
+    fn recv_packet(&mut self) -> Result<String> {
+        loop {
+            // Acknowledgements and interrupt requests while stopped are ignored.
+            if self.read_byte()? != b'$' {
+                continue;
+            }
+
+            let mut data = Vec::new();
+            loop {
+                match self.read_byte()? {
+                    b'#' => break,
+                    byte => data.push(byte),
+                }
+            }
+            let checksum = [self.read_byte()?, self.read_byte()?];
+            let expected = u8::from_str_radix(std::str::from_utf8(&checksum)?, 16)?;
+            if checksum_of(&data) != expected {
+                if self.ack {
+                    self.conn.write_all(b"-")?;
+                    self.conn.flush()?;
+                }
+                continue;
+            }
+            if self.ack {
+                self.conn.write_all(b"+")?;
+                self.conn.flush()?;
+            }
+            return Ok(String::from_utf8(data)?);
+        }
+    }
+
+    fn send_packet(&mut self, data: &str) -> Result<()> {
+        tracing::trace!("gdb: -> {data}");
+        loop {
+            let packet = format!("${data}#{:02x}", checksum_of(data.as_bytes()));
+            self.conn.write_all(packet.as_bytes())?;
+            self.conn.flush()?;
+            if !self.ack {
+                return Ok(());
+            }
+            match self.read_byte()? {
+                b'+' => return Ok(()),
+                b'-' => continue,
+                byte => bail!("unexpected acknowledgement from gdb: 0x{byte:02x}"),
+            }
+        }
+    }
+
+    fn read_byte(&mut self) -> Result<u8> {
+        let mut byte = [0u8];
+        self.conn
+            .read_exact(&mut byte)
+            .map_err(|err| anyhow!("gdb connection closed: {err}"))?;
+        Ok(byte[0])
+    }
+}
+
+fn set_register(target: &mut dyn DebugTarget, idx: usize, value: u32) {
+    match idx {
+        // x0 is hardwired to zero.
+        0 => {}
+        REG_PC => target.set_pc(value),
+        idx if idx < REG_MAX => target.set_register(idx, value),
+        _ => {}
+    }
+}
+
+fn parse_addr_len(args: &str) -> Result<(u32, u32)> {
+    let (addr, len) = args.split_once(',').context("malformed address range")?;
+    Ok((
+        u32::from_str_radix(addr, 16)?,
+        u32::from_str_radix(len, 16)?,
+    ))
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |acc, x| acc.wrapping_add(*x))
+}
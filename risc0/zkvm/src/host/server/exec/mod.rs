@@ -19,8 +19,12 @@
 //! contains an execution trace of the specified program.
 
 pub(crate) mod executor;
+mod gdb;
 mod monitor;
 pub(crate) mod profiler;
+mod region;
+pub(crate) mod snapshot;
 pub(crate) mod syscall;
 #[cfg(test)]
 mod tests;
+pub(crate) mod transcript;
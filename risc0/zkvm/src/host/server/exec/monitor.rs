@@ -12,9 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{array, collections::BTreeSet, mem::take};
+use std::{array, cmp::min, collections::BTreeSet, mem::take, sync::Arc};
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use risc0_binfmt::MemoryImage;
 use risc0_zkp::core::hash::sha::BLOCK_BYTES;
 use risc0_zkvm_platform::{
@@ -48,9 +48,10 @@ enum Action {
     StoreReg(usize, u32),
 }
 
+/// A resident page, shared with the [MemoryImage] until it is written to.
 #[derive(Clone)]
 struct Page {
-    buf: Vec<u8>,
+    buf: risc0_binfmt::Page,
 }
 
 impl Page {
@@ -61,7 +62,7 @@ impl Page {
 
     fn store_bytes(&mut self, addr: u32, bytes: &[u8]) {
         let addr = addr as usize;
-        self.buf[addr..addr + bytes.len()].clone_from_slice(bytes);
+        Arc::make_mut(&mut self.buf)[addr..addr + bytes.len()].clone_from_slice(bytes);
     }
 }
 
@@ -79,6 +80,7 @@ pub struct MemoryMonitor {
     enable_trace: bool,
     pages: Vec<Option<Page>>,
     registers: [u32; REG_MAX],
+    unhashed: BTreeSet<u32>,
 }
 
 impl MemoryMonitor {
@@ -101,6 +103,7 @@ impl MemoryMonitor {
             enable_trace,
             pages,
             registers: [0; REG_MAX],
+            unhashed: BTreeSet::new(),
         }
     }
 
@@ -263,7 +266,7 @@ impl MemoryMonitor {
         let offset = addr % info.page_size;
         self.pages[page_idx as usize]
             .get_or_insert_with(|| Page {
-                buf: self.image.load_page(page_idx),
+                buf: self.image.get_page(page_idx),
             })
             .load_bytes(offset, bytes);
         Ok(())
@@ -385,12 +388,58 @@ impl MemoryMonitor {
         let offset = addr % info.page_size;
         self.pages[page_idx as usize]
             .get_or_insert_with(|| Page {
-                buf: self.image.load_page(page_idx),
+                buf: self.image.get_page(page_idx),
             })
             .store_bytes(offset, bytes);
         Ok(())
     }
 
+    /// Read a region of memory without paging it in.
+    ///
+    /// This is used by the debugger, and does not affect the cycle count.
+    pub fn peek_region(&self, addr: u32, len: u32) -> Result<Vec<u8>> {
+        let info = &self.image.info;
+        let mut region = Vec::with_capacity(len as usize);
+        let end = addr
+            .checked_add(len)
+            .ok_or_else(|| anyhow!("address range overflow at 0x{addr:08x}"))?;
+        let mut addr = addr;
+        while addr < end {
+            let page_idx = self.get_page_index(addr)?;
+            let offset = (addr % info.page_size) as usize;
+            let count = min(info.page_size as usize - offset, (end - addr) as usize);
+            match &self.pages[page_idx as usize] {
+                Some(page) => region.extend_from_slice(&page.buf[offset..offset + count]),
+                None => {
+                    region.extend_from_slice(&self.image.get_page(page_idx)[offset..offset + count])
+                }
+            }
+            addr += count as u32;
+        }
+        Ok(region)
+    }
+
+    /// Write a region of memory on behalf of the debugger.
+    ///
+    /// The write goes through the monitor like a store by the guest, so the
+    /// pages are paged in and marked dirty, and it is committed immediately,
+    /// so it is not undone by a split.
+    pub fn poke_region(&mut self, addr: u32, slice: &[u8]) -> Result<()> {
+        addr.checked_add(u32::try_from(slice.len())?)
+            .ok_or_else(|| anyhow!("address range overflow at 0x{addr:08x}"))?;
+        self.store_region(addr, slice)?;
+        self.pending_actions.clear();
+        Ok(())
+    }
+
+    /// Write a register on behalf of the debugger.
+    ///
+    /// The write is committed immediately, so it is not undone by a split.
+    pub fn poke_register(&mut self, idx: usize, data: u32) {
+        self.store_register(idx, data);
+        self.pending_actions.clear();
+    }
+
     pub fn undo(&mut self) -> Result<()> {
         let pending_actions = take(&mut self.pending_actions);
         for action in pending_actions.iter().rev() {
@@ -453,16 +502,19 @@ impl MemoryMonitor {
         Ok(())
     }
 
-    pub fn build_image(&mut self, pc: u32) -> Result<MemoryImage> {
+    /// Write the dirty pages and the registers of the current segment back to
+    /// the memory image, without updating its merkle tree.
+    ///
+    /// The written pages are hashed by the next call to
+    /// [MemoryMonitor::build_image].
+    pub fn flush_image(&mut self, pc: u32) {
         // self.faults.dump();
 
         // Write all dirty pages back to the memory image.
         for page_idx in self.faults.writes.iter() {
             if let Some(page) = self.pages[*page_idx as usize].as_ref() {
                 tracing::debug!("flush page: 0x{page_idx:08x}");
-                let info = &self.image.info;
-                let addr = info.get_page_addr(*page_idx);
-                self.image.store_region_in_page(addr, &page.buf);
+                self.image.store_page(*page_idx, page.buf.clone());
             }
         }
 
@@ -475,10 +527,16 @@ impl MemoryMonitor {
         let sys_addr = SYSTEM.start() as u32;
         self.image.store_region_in_page(sys_addr, &bytes);
 
-        // Update the merkle tree and PC.
-        self.image
-            .hash_pages_iter(self.faults.writes.iter().cloned())?;
+        self.unhashed.extend(self.faults.writes.iter());
         self.image.pc = pc;
+    }
+
+    pub fn build_image(&mut self, pc: u32) -> Result<MemoryImage> {
+        self.flush_image(pc);
+
+        // Update the merkle tree.
+        self.image
+            .hash_pages_iter(take(&mut self.unhashed).into_iter())?;
         Ok(self.image.clone())
     }
 }
@@ -515,8 +573,11 @@ impl SyscallContext for MemoryMonitor {
         self.session_cycle
     }
 
-    fn load_register(&mut self, idx: usize) -> u32 {
-        self.registers[idx]
+    fn load_register(&mut self, idx: usize) -> Result<u32> {
+        self.registers
+            .get(idx)
+            .copied()
+            .ok_or_else(|| anyhow!("Invalid register: {idx}"))
     }
 
     fn load_u32(&mut self, addr: u32) -> Result<u32> {
@@ -18,6 +18,11 @@
 //! guest.  It does not trace full stack traces, but only provides the
 //! top level stack frame.  (More than one stack frame may show up
 //! in the case of inlined functions).
+//!
+//! The profile can be exported in any [ProfileFormat]: pprof protobuf, as
+//! well as formats that can be read without any external tooling.
+
+mod report;
 
 use std::{
     cell::RefCell,
@@ -40,8 +45,11 @@ use risc0_zkvm_platform::memory::TEXT_START;
 use rrs_lib::instruction_formats::{IType, JType, OPCODE_JAL, OPCODE_JALR};
 use rustc_demangle::demangle;
 
-use self::proto::Line;
-use crate::{host::client::env::TraceCallback, TraceEvent};
+use self::{
+    proto::Line,
+    report::{flamegraph, folded, table, StackSample},
+};
+use crate::{host::client::env::TraceCallback, ProfileFormat, TraceEvent};
 
 mod proto {
     // Generated proto interface.
@@ -161,6 +169,12 @@ pub struct Profiler {
     ctx: ObjectContext,
 
     profile: ProfileBuilder,
+
+    // Name of the main binary, used as the title of reports.
+    bin_name: String,
+
+    // Whether the samples have been added to the profile.
+    finalized: bool,
 }
 
 /// Represents a frame.
@@ -224,6 +238,8 @@ impl Profiler {
             call_stack_path: Vec::new(),
             ctx,
             profile: ProfileBuilder::new(),
+            bin_name: filename.unwrap_or("unknown").to_string(),
+            finalized: false,
         };
 
         // Save the main binary name
@@ -340,23 +356,43 @@ impl Profiler {
         }
     }
 
-    /// Count and save the profiling samples, write the results to `output_path`.
-    #[cfg(test)]
-    pub(crate) fn finalize(mut self) -> ProfileBuilder {
+    /// Count and save the profiling samples, adding them to the profile.
+    fn add_samples(&mut self) {
+        if self.finalized {
+            return;
+        }
+        self.finalized = true;
         let root_ref = Rc::clone(&self.root);
         tracing::debug!("{}", self.root.borrow().fmt(0, &self));
         self.walk_stacks(root_ref, Vec::new());
+    }
+
+    /// Count and save the profiling samples, write the results to `output_path`.
+    #[cfg(test)]
+    pub(crate) fn finalize(mut self) -> ProfileBuilder {
+        self.add_samples();
         self.profile
     }
 
     /// Count and save the profiling samples, consuming the profiler and
     /// returning the compiled profile protobuf, encoded as bytes.
     pub fn finalize_to_vec(&mut self) -> Vec<u8> {
-        let root_ref = Rc::clone(&self.root);
-        tracing::debug!("{}", self.root.borrow().fmt(0, &self));
-        self.walk_stacks(root_ref, Vec::new());
+        self.add_samples();
         self.profile.profile.encode_to_vec()
     }
+
+    /// Count and save the profiling samples, returning the profile rendered in
+    /// the given [ProfileFormat].
+    pub fn finalize_to_format(&mut self, format: ProfileFormat) -> Vec<u8> {
+        self.add_samples();
+        let report = match format {
+            ProfileFormat::Pprof => return self.profile.profile.encode_to_vec(),
+            ProfileFormat::Folded => folded(&self.profile.stack_samples()),
+            ProfileFormat::Flamegraph => flamegraph(&self.profile.stack_samples(), &self.bin_name),
+            ProfileFormat::Table => table(&self.profile.stack_samples()),
+        };
+        report.into_bytes()
+    }
 }
 
 impl TraceCallback for Profiler {
@@ -537,6 +573,34 @@ impl ProfileBuilder {
         self.profile.sample.push(sample)
     }
 
+    fn get_frame(&self, line: &Line) -> Frame {
+        let func = &self.profile.function[line.function_id as usize - 1];
+        Frame {
+            name: self.profile.string_table[func.name as usize].clone(),
+            lineno: line.line,
+            filename: self.profile.string_table[func.filename as usize].clone(),
+        }
+    }
+
+    /// Returns the call stack and cycle count of each sample, with the
+    /// outermost frame first.
+    fn stack_samples(&self) -> Vec<StackSample> {
+        self.profile
+            .sample
+            .iter()
+            .map(|sample| StackSample {
+                frames: sample
+                    .location_id
+                    .iter()
+                    .rev()
+                    .flat_map(|id| &self.profile.location[*id as usize - 1].line)
+                    .map(|line| self.get_frame(line))
+                    .collect(),
+                cycles: sample.value[0] as u64,
+            })
+            .collect()
+    }
+
     /// Dereferences strings, etc. in the protobuf for testing purposes.
     /// Returns a tuple of (frames, program counter, cycles)
     #[cfg(test)]
@@ -545,17 +609,7 @@ impl ProfileBuilder {
             sample.location_id.iter().map(move |id| {
                 let loc = &self.profile.location[*id as usize - 1];
                 (
-                    loc.line
-                        .iter()
-                        .map(|line| {
-                            let func = &self.profile.function[line.function_id as usize - 1];
-                            Frame {
-                                name: self.profile.string_table[func.name as usize].clone(),
-                                lineno: line.line,
-                                filename: self.profile.string_table[func.filename as usize].clone(),
-                            }
-                        })
-                        .collect(),
+                    loc.line.iter().map(|line| self.get_frame(line)).collect(),
                     loc.address as usize,
                     sample.value[0] as usize,
                 )
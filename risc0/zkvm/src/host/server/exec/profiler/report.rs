@@ -0,0 +1,261 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Exporters for profiles in formats that do not need the pprof tool.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write,
+};
+
+use super::Frame;
+
+/// Width of the flamegraph image in pixels.
+const SVG_WIDTH: f64 = 1200.0;
+
+/// Height of a single frame in the flamegraph in pixels.
+const SVG_FRAME_HEIGHT: f64 = 16.0;
+
+/// Vertical space reserved for the title of the flamegraph in pixels.
+const SVG_HEADER: f64 = 32.0;
+
+/// Approximate width of a character of frame labels in pixels.
+const SVG_CHAR_WIDTH: f64 = 7.0;
+
+/// Frames narrower than this, in pixels, are not drawn.
+const SVG_MIN_WIDTH: f64 = 0.1;
+
+/// The cycles spent in a call stack.
+pub(super) struct StackSample {
+    /// The frames of the call stack, outermost first.
+    pub(super) frames: Vec<Frame>,
+
+    /// The number of cycles spent with this call stack.
+    pub(super) cycles: u64,
+}
+
+/// Returns the label of a frame in folded stacks, which use `;` as the
+/// separator between frames.
+fn frame_label(frame: &Frame) -> String {
+    frame.name.replace(';', ",")
+}
+
+/// Renders the samples as collapsed stacks, one stack per line followed by
+/// its cycle count, as read by `inferno` and `flamegraph.pl`.
+pub(super) fn folded(samples: &[StackSample]) -> String {
+    let mut stacks: BTreeMap<String, u64> = BTreeMap::new();
+    for sample in samples {
+        let stack: Vec<_> = sample.frames.iter().map(frame_label).collect();
+        *stacks.entry(stack.join(";")).or_default() += sample.cycles;
+    }
+
+    let mut output = String::new();
+    for (stack, cycles) in stacks {
+        writeln!(output, "{stack} {cycles}").unwrap();
+    }
+    output
+}
+
+/// Renders the cycles spent in each function and on each source line, sorted
+/// from most to least expensive.
+///
+/// The self cycles of a function are spent in the function itself, while the
+/// total cycles also include the functions it calls. Source lines are those of
+/// the innermost frame.
+pub(super) fn table(samples: &[StackSample]) -> String {
+    let total: u64 = samples.iter().map(|sample| sample.cycles).sum();
+    let mut functions: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+    let mut lines: BTreeMap<(&str, i64), u64> = BTreeMap::new();
+    for sample in samples {
+        let Some(leaf) = sample.frames.last() else {
+            continue;
+        };
+        functions.entry(leaf.name.as_str()).or_default().0 += sample.cycles;
+        *lines
+            .entry((leaf.filename.as_str(), leaf.lineno))
+            .or_default() += sample.cycles;
+
+        // Recursive functions are only counted once per stack.
+        let mut seen = HashSet::new();
+        for frame in sample.frames.iter() {
+            if seen.insert(frame.name.as_str()) {
+                functions.entry(frame.name.as_str()).or_default().1 += sample.cycles;
+            }
+        }
+    }
+
+    let percent = |cycles: u64| {
+        if total == 0 {
+            0.0
+        } else {
+            cycles as f64 * 100.0 / total as f64
+        }
+    };
+
+    let mut functions: Vec<_> = functions.into_iter().collect();
+    functions.sort_by(|(name_a, a), (name_b, b)| b.cmp(a).then(name_a.cmp(name_b)));
+    let mut lines: Vec<_> = lines.into_iter().collect();
+    lines.sort_by(|(loc_a, a), (loc_b, b)| b.cmp(a).then(loc_a.cmp(loc_b)));
+
+    let mut output = String::new();
+    writeln!(output, "total cycles: {total}").unwrap();
+    writeln!(output).unwrap();
+    writeln!(
+        output,
+        "{:>12} {:>7} {:>12} {:>7}  function",
+        "self", "self%", "total", "total%"
+    )
+    .unwrap();
+    for (name, (self_cycles, total_cycles)) in functions {
+        writeln!(
+            output,
+            "{self_cycles:>12} {:>6.2}% {total_cycles:>12} {:>6.2}%  {name}",
+            percent(self_cycles),
+            percent(total_cycles)
+        )
+        .unwrap();
+    }
+    writeln!(output).unwrap();
+    writeln!(output, "{:>12} {:>7}  line", "self", "self%").unwrap();
+    for ((filename, lineno), cycles) in lines {
+        writeln!(
+            output,
+            "{cycles:>12} {:>6.2}%  {filename}:{lineno}",
+            percent(cycles)
+        )
+        .unwrap();
+    }
+    output
+}
+
+/// A node of the call tree drawn by [flamegraph].
+#[derive(Default)]
+struct FlameNode {
+    cycles: u64,
+    children: BTreeMap<String, FlameNode>,
+}
+
+impl FlameNode {
+    fn depth(&self) -> usize {
+        1 + self
+            .children
+            .values()
+            .map(FlameNode::depth)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Renders the samples as an SVG flamegraph, with the outermost frames at the
+/// bottom and the width of each frame proportional to its cycles.
+pub(super) fn flamegraph(samples: &[StackSample], title: &str) -> String {
+    let mut root = FlameNode::default();
+    for sample in samples {
+        root.cycles += sample.cycles;
+        let mut node = &mut root;
+        for frame in sample.frames.iter() {
+            node = node.children.entry(frame.name.clone()).or_default();
+            node.cycles += sample.cycles;
+        }
+    }
+
+    let depth = root.depth();
+    let height = SVG_HEADER + depth as f64 * SVG_FRAME_HEIGHT;
+    let scale = if root.cycles == 0 {
+        0.0
+    } else {
+        SVG_WIDTH / root.cycles as f64
+    };
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r##"<?xml version="1.0" standalone="no"?>
+<svg version="1.1" width="{SVG_WIDTH}" height="{height}" viewBox="0 0 {SVG_WIDTH} {height}" xmlns="http://www.w3.org/2000/svg">
+<style>text {{ font-family: monospace; font-size: 12px; }}</style>
+<rect x="0" y="0" width="100%" height="100%" fill="#eeeeee"/>
+<text x="{}" y="20" text-anchor="middle">{}</text>"##,
+        SVG_WIDTH / 2.0,
+        escape_xml(title)
+    )
+    .unwrap();
+    let root_label = format!("all ({} cycles)", root.cycles);
+    draw_frame(&mut svg, &root_label, root.cycles, 0.0, height, scale);
+    draw_children(&mut svg, &root, 0.0, height - SVG_FRAME_HEIGHT, scale);
+    writeln!(svg, "</svg>").unwrap();
+    svg
+}
+
+fn draw_children(svg: &mut String, node: &FlameNode, mut x: f64, bottom: f64, scale: f64) {
+    for (name, child) in node.children.iter() {
+        let width = child.cycles as f64 * scale;
+        if width >= SVG_MIN_WIDTH {
+            draw_frame(svg, name, child.cycles, x, bottom, scale);
+            draw_children(svg, child, x, bottom - SVG_FRAME_HEIGHT, scale);
+        }
+        x += width;
+    }
+}
+
+fn draw_frame(svg: &mut String, name: &str, cycles: u64, x: f64, bottom: f64, scale: f64) {
+    let width = cycles as f64 * scale;
+    let y = bottom - SVG_FRAME_HEIGHT;
+    let max_chars = ((width - 6.0) / SVG_CHAR_WIDTH).floor() as usize;
+    let label = if name.chars().count() <= max_chars {
+        name.to_string()
+    } else if max_chars >= 3 {
+        let truncated: String = name.chars().take(max_chars - 2).collect();
+        format!("{truncated}..")
+    } else {
+        String::new()
+    };
+    writeln!(
+        svg,
+        r#"<g><title>{} ({cycles} cycles)</title><rect x="{x:.2}" y="{y:.2}" width="{width:.2}" height="{:.2}" fill="{}" rx="2"/><text x="{:.2}" y="{:.2}">{}</text></g>"#,
+        escape_xml(name),
+        SVG_FRAME_HEIGHT - 1.0,
+        frame_color(name),
+        x + 3.0,
+        y + SVG_FRAME_HEIGHT - 4.0,
+        escape_xml(&label),
+    )
+    .unwrap();
+}
+
+/// Picks a warm color for a frame, which is stable for a given name so that
+/// flamegraphs of different runs can be compared.
+fn frame_color(name: &str) -> String {
+    // FNV-1a, so that the color does not depend on the hasher of this process.
+    let hash = name.bytes().fold(0x811c9dc5u32, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    });
+    let red = 205 + hash % 50;
+    let green = (hash >> 8) % 230;
+    let blue = (hash >> 16) % 55;
+    format!("rgb({red},{green},{blue})")
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,138 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Accounting of the cycles spent in the cycle regions opened by the guest.
+
+use std::{collections::BTreeMap, mem};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::CycleRegion;
+
+/// A region that the guest has entered and not yet exited.
+#[derive(Clone, Serialize, Deserialize)]
+struct OpenRegion {
+    name: String,
+    cycles: usize,
+    paging_cycles: usize,
+}
+
+/// The regions that are open and the totals of those already exited, as
+/// carried across an [ExecutorSnapshot](super::snapshot::ExecutorSnapshot).
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct RegionState {
+    open: Vec<OpenRegion>,
+    totals: BTreeMap<String, CycleRegion>,
+}
+
+/// Tracks the cycle regions of a session and checks them against their
+/// budgets.
+///
+/// Cycle counts are passed in by the executor and only need to increase
+/// monotonically over a session; the cycles of a region are the difference
+/// between the counts at its exit and at its entry.
+pub(crate) struct RegionTracker {
+    open: Vec<OpenRegion>,
+    totals: BTreeMap<String, CycleRegion>,
+    budgets: BTreeMap<String, u64>,
+}
+
+impl RegionTracker {
+    pub(crate) fn new(budgets: BTreeMap<String, u64>) -> Self {
+        Self {
+            open: Vec::new(),
+            totals: BTreeMap::new(),
+            budgets,
+        }
+    }
+
+    pub(crate) fn state(&self) -> RegionState {
+        RegionState {
+            open: self.open.clone(),
+            totals: self.totals.clone(),
+        }
+    }
+
+    pub(crate) fn restore(&mut self, state: RegionState) {
+        self.open = state.open;
+        self.totals = state.totals;
+    }
+
+    pub(crate) fn enter(&mut self, name: String, cycles: usize, paging_cycles: usize) {
+        tracing::trace!("enter cycle region {name:?} at {cycles}");
+        self.open.push(OpenRegion {
+            name,
+            cycles,
+            paging_cycles,
+        });
+    }
+
+    pub(crate) fn exit(&mut self, name: &str, cycles: usize, paging_cycles: usize) -> Result<()> {
+        let Some(region) = self.open.pop() else {
+            // This happens when the region was entered before execution was
+            // paused.
+            tracing::warn!("ignoring exit from cycle region {name:?} which was not entered");
+            return Ok(());
+        };
+        if region.name != name {
+            bail!(
+                "guest exited cycle region {name:?} while in cycle region {:?}",
+                region.name
+            );
+        }
+        tracing::trace!("exit cycle region {name:?} at {cycles}");
+        self.record(region, cycles, paging_cycles)
+    }
+
+    /// Exits all regions that are still open, such as when the guest halts
+    /// inside a region, and returns the totals of the session.
+    pub(crate) fn finish(
+        &mut self,
+        cycles: usize,
+        paging_cycles: usize,
+    ) -> Result<BTreeMap<String, CycleRegion>> {
+        while let Some(region) = self.open.pop() {
+            tracing::debug!(
+                "cycle region {:?} still open at end of session",
+                region.name
+            );
+            self.record(region, cycles, paging_cycles)?;
+        }
+        Ok(mem::take(&mut self.totals))
+    }
+
+    fn record(&mut self, region: OpenRegion, cycles: usize, paging_cycles: usize) -> Result<()> {
+        let total = self.totals.entry(region.name.clone()).or_default();
+        total.count += 1;
+
+        // Recursive regions are only counted once, by their outermost entry.
+        if self.open.iter().any(|open| open.name == region.name) {
+            return Ok(());
+        }
+        total.cycles += cycles.saturating_sub(region.cycles) as u64;
+        total.paging_cycles += paging_cycles.saturating_sub(region.paging_cycles) as u64;
+
+        if let Some(&budget) = self.budgets.get(&region.name) {
+            if total.cycles > budget {
+                bail!(
+                    "cycle region {:?} exceeded its budget: {} cycles used, {budget} allowed",
+                    region.name,
+                    total.cycles
+                );
+            }
+        }
+        Ok(())
+    }
+}
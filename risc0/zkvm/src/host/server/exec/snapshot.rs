@@ -0,0 +1,127 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Snapshots of the executor state that can be persisted and resumed.
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, ensure, Context, Result};
+use risc0_binfmt::{MemoryImage, SystemState};
+use serde::{Deserialize, Serialize};
+
+use super::{executor::SyscallRecord, region::RegionState};
+use crate::{sha::Digest, Assumption, ExitCode};
+
+/// Magic bytes at the start of every snapshot file.
+const SNAPSHOT_MAGIC: &[u8; 4] = b"R0SN";
+
+/// The version of the snapshot format written by this crate.
+const SNAPSHOT_VERSION: u32 = 2;
+
+/// The state of an [ExecutorImpl](crate::ExecutorImpl) captured when the
+/// guest pauses or at a segment boundary.
+///
+/// A snapshot can be written to disk and used to construct a new
+/// [ExecutorImpl](crate::ExecutorImpl) with
+/// [ExecutorImpl::from_snapshot](crate::ExecutorImpl::from_snapshot),
+/// possibly in a different process or on a different machine. Execution
+/// continues exactly where the snapshot was taken: the first [Segment] of
+/// the resumed [Session] starts from the [SystemState] that the last
+/// [Segment] before the snapshot ended in, so receipts for both chain
+/// together.
+///
+/// The assumptions that the guest has already verified, and the cycle
+/// regions it has entered, are part of the snapshot, so the resumed [Session]
+/// claims the same output and reports the same cycle regions as an
+/// uninterrupted run. The [ExecutorEnv](crate::ExecutorEnv) is not part of
+/// the snapshot. When resuming, the host must provide an environment whose
+/// inputs and assumptions are consistent with what the guest has yet to
+/// read.
+///
+/// [Segment]: crate::Segment
+/// [Session]: crate::Session
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExecutorSnapshot {
+    pub(crate) pre_image: Box<MemoryImage>,
+    pub(crate) exit_code: Option<ExitCode>,
+    pub(crate) segment_offset: u32,
+    pub(crate) cycle_offset: u64,
+    pub(crate) pending_syscall: Option<SyscallRecord>,
+    pub(crate) output_digest: Option<Digest>,
+    pub(crate) journal: Vec<u8>,
+    pub(crate) assumptions: Vec<Assumption>,
+    pub(crate) regions: RegionState,
+    pub(crate) region_cycles: (u64, u64),
+}
+
+impl ExecutorSnapshot {
+    /// The [SystemState] that execution resumes from.
+    pub fn pre_state(&self) -> SystemState {
+        SystemState::from(self.pre_image.as_ref())
+    }
+
+    /// The [ExitCode] of the last segment before the snapshot was taken, if
+    /// any.
+    pub fn exit_code(&self) -> Option<ExitCode> {
+        self.exit_code
+    }
+
+    /// The index that will be assigned to the next [Segment](crate::Segment).
+    pub fn segment_index(&self) -> u32 {
+        self.segment_offset
+    }
+
+    /// Encode this snapshot into its versioned binary representation.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(SNAPSHOT_MAGIC);
+        bytes.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        bytes.extend(bincode::serialize(self)?);
+        Ok(bytes)
+    }
+
+    /// Decode a snapshot from its versioned binary representation.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        ensure!(
+            bytes.len() >= 8 && &bytes[..4] == SNAPSHOT_MAGIC,
+            "not an executor snapshot"
+        );
+        let version = u32::from_le_bytes(bytes[4..8].try_into()?);
+        if version != SNAPSHOT_VERSION {
+            bail!("unsupported executor snapshot version: {version}, expected {SNAPSHOT_VERSION}");
+        }
+        Ok(bincode::deserialize(&bytes[8..])?)
+    }
+
+    /// Write this snapshot to the file at `path`.
+    ///
+    /// The snapshot is first written to a temporary file next to `path` and
+    /// then renamed, so that an existing snapshot is never left truncated.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, self.encode()?)
+            .with_context(|| format!("failed to write snapshot to {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Read a snapshot from the file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)
+            .with_context(|| format!("failed to read snapshot from {}", path.display()))?;
+        Self::decode(&bytes)
+    }
+}
@@ -31,6 +31,7 @@ use risc0_zkvm_platform::{
     WORD_SIZE,
 };
 
+pub use crate::host::client::syscall::{Syscall, SyscallContext};
 use crate::{
     host::client::{
         env::{Assumptions, ExecutorEnv},
@@ -41,55 +42,6 @@ use crate::{
     Assumption, ExitCode, MaybePruned, PrunedValueError, ReceiptClaim,
 };
 
-/// A host-side implementation of a system call.
-pub trait Syscall {
-    /// Invokes the system call.
-    fn syscall(
-        &mut self,
-        syscall: &str,
-        ctx: &mut dyn SyscallContext,
-        to_guest: &mut [u32],
-    ) -> Result<(u32, u32)>;
-}
-
-/// Access to memory and machine state for syscalls.
-pub trait SyscallContext {
-    /// Returns the current cycle being executed.
-    fn get_cycle(&self) -> usize;
-
-    /// Loads the value of the given register, e.g. REG_A0.
-    fn load_register(&mut self, idx: usize) -> u32;
-
-    /// Loads bytes from the given region of memory.
-    fn load_region(&mut self, addr: u32, size: u32) -> Result<Vec<u8>> {
-        let mut region = Vec::new();
-        for addr in addr..addr + size {
-            region.push(self.load_u8(addr)?);
-        }
-        Ok(region)
-    }
-
-    /// Loads an individual word from memory.
-    fn load_u32(&mut self, addr: u32) -> Result<u32>;
-
-    /// Loads an individual byte from memory.
-    fn load_u8(&mut self, addr: u32) -> Result<u8>;
-
-    /// Loads a null-terminated string from memory.
-    fn load_string(&mut self, mut addr: u32) -> Result<String> {
-        let mut s: Vec<u8> = Vec::new();
-        loop {
-            let b = self.load_u8(addr)?;
-            if b == 0 {
-                break;
-            }
-            s.push(b);
-            addr += 1;
-        }
-        String::from_utf8(s).map_err(anyhow::Error::msg)
-    }
-}
-
 #[derive(Clone)]
 pub(crate) struct SyscallTable<'a> {
     pub(crate) inner: HashMap<String, Rc<RefCell<dyn Syscall + 'a>>>,
@@ -121,6 +73,11 @@ impl<'a> SyscallTable<'a> {
             this.inner
                 .insert(syscall.clone(), Rc::new(RefCell::new(handler)));
         }
+        // Handlers registered with the environment take precedence over the
+        // built-in ones.
+        for (syscall, handler) in env.syscalls.borrow().inner.iter() {
+            this.inner.insert(syscall.clone(), handler.clone());
+        }
 
         this
     }
@@ -160,8 +117,8 @@ impl Syscall for SysGetenv {
         ctx: &mut dyn SyscallContext,
         to_guest: &mut [u32],
     ) -> Result<(u32, u32)> {
-        let buf_ptr = ctx.load_register(REG_A3);
-        let buf_len = ctx.load_register(REG_A4);
+        let buf_ptr = ctx.load_register(REG_A3)?;
+        let buf_len = ctx.load_register(REG_A4)?;
         let from_guest = ctx.load_region(buf_ptr, buf_len)?;
         let msg = from_utf8(&from_guest)?;
 
@@ -185,8 +142,8 @@ impl Syscall for SysPanic {
         ctx: &mut dyn SyscallContext,
         _to_guest: &mut [u32],
     ) -> Result<(u32, u32)> {
-        let buf_ptr = ctx.load_register(REG_A3);
-        let buf_len = ctx.load_register(REG_A4);
+        let buf_ptr = ctx.load_register(REG_A3)?;
+        let buf_len = ctx.load_register(REG_A4)?;
         let from_guest = ctx.load_region(buf_ptr, buf_len)?;
         let msg = from_utf8(&from_guest)?;
         bail!("Guest panicked: {msg}");
@@ -368,8 +325,8 @@ impl Syscall for SysVerify {
         ctx: &mut dyn SyscallContext,
         to_guest: &mut [u32],
     ) -> Result<(u32, u32)> {
-        let from_guest_ptr = ctx.load_register(REG_A3);
-        let from_guest_len = ctx.load_register(REG_A4);
+        let from_guest_ptr = ctx.load_register(REG_A3)?;
+        let from_guest_len = ctx.load_register(REG_A4)?;
         let from_guest: Vec<u8> = ctx.load_region(from_guest_ptr, from_guest_len)?;
 
         if syscall == SYS_VERIFY.as_str() {
@@ -396,7 +353,7 @@ impl Syscall for Args {
             Ok((self.0.len().try_into()?, 0))
         } else if syscall == SYS_ARGV.as_str() {
             // Get the arg or return an error if out of bounds.
-            let arg_index = ctx.load_register(REG_A3);
+            let arg_index = ctx.load_register(REG_A3)?;
             let arg_val = self.0.get(arg_index as usize).ok_or_else(|| {
                 anyhow!(
                     "guest requested index {arg_index} from argv of len {}",
@@ -444,8 +401,8 @@ impl<'a> Syscall for SysSliceIo<'a> {
         to_guest: &mut [u32],
     ) -> Result<(u32, u32)> {
         let mut stored_result = self.stored_result.borrow_mut();
-        let buf_ptr = ctx.load_register(REG_A3);
-        let buf_len = ctx.load_register(REG_A4);
+        let buf_ptr = ctx.load_register(REG_A3)?;
+        let buf_len = ctx.load_register(REG_A4)?;
         let from_guest = ctx.load_region(buf_ptr, buf_len)?;
         Ok(match stored_result.take() {
             None => {
@@ -508,7 +465,7 @@ impl<'a> Syscall for Rc<RefCell<PosixIo<'a>>> {
 impl<'a> PosixIo<'a> {
     fn sys_read_avail(&mut self, ctx: &mut dyn SyscallContext) -> Result<(u32, u32)> {
         tracing::debug!("sys_read_avail");
-        let fd = ctx.load_register(REG_A3);
+        let fd = ctx.load_register(REG_A3)?;
         let reader = self
             .read_fds
             .get_mut(&fd)
@@ -523,8 +480,8 @@ impl<'a> PosixIo<'a> {
         ctx: &mut dyn SyscallContext,
         to_guest: &mut [u32],
     ) -> Result<(u32, u32)> {
-        let fd = ctx.load_register(REG_A3);
-        let nbytes = ctx.load_register(REG_A4) as usize;
+        let fd = ctx.load_register(REG_A3)?;
+        let nbytes = ctx.load_register(REG_A4)? as usize;
 
         tracing::debug!(
             "sys_read, attempting to read {nbytes} bytes from fd {fd}, to_guest: {}",
@@ -585,9 +542,9 @@ impl<'a> PosixIo<'a> {
     }
 
     fn sys_write(&mut self, ctx: &mut dyn SyscallContext) -> Result<(u32, u32)> {
-        let fd = ctx.load_register(REG_A3);
-        let buf_ptr = ctx.load_register(REG_A4);
-        let buf_len = ctx.load_register(REG_A5);
+        let fd = ctx.load_register(REG_A3)?;
+        let buf_ptr = ctx.load_register(REG_A4)?;
+        let buf_len = ctx.load_register(REG_A5)?;
         let from_guest_bytes = ctx.load_region(buf_ptr, buf_len)?;
         let writer = self
             .write_fds
@@ -601,8 +558,8 @@ impl<'a> PosixIo<'a> {
     }
 
     fn sys_log(&mut self, ctx: &mut dyn SyscallContext) -> Result<(u32, u32)> {
-        let buf_ptr = ctx.load_register(REG_A3);
-        let buf_len = ctx.load_register(REG_A4);
+        let buf_ptr = ctx.load_register(REG_A3)?;
+        let buf_len = ctx.load_register(REG_A4)?;
         let from_guest = ctx.load_region(buf_ptr, buf_len)?;
         // write to stdout, but be sure to point it to where the file descriptor is pointing
         let writer = self
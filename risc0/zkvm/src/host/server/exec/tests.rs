@@ -13,20 +13,31 @@
 // limitations under the License.
 
 use std::{
-    collections::{BTreeMap, HashSet},
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
     io::Cursor,
+    rc::Rc,
     str::from_utf8,
     sync::Mutex,
 };
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use bytes::Bytes;
 use risc0_binfmt::{MemoryImage, Program};
 use risc0_zkvm_methods::{
-    multi_test::{MultiTestSpec, SYS_MULTI_TEST},
-    HELLO_COMMIT_ELF, MULTI_TEST_ELF, RAND_ELF, SLICE_IO_ELF, STANDARD_LIB_ELF,
+    multi_test::{MultiTestSpec, SQUARE_REQUEST_SCHEMA, SYS_MULTI_TEST},
+    HELLO_COMMIT_ELF, HELLO_COMMIT_ID, MULTI_TEST_ELF, MULTI_TEST_ID, RAND_ELF, SLICE_IO_ELF,
+    STANDARD_LIB_ELF,
 };
-use risc0_zkvm_platform::{fileno, syscall::nr::SYS_RANDOM, PAGE_SIZE, WORD_SIZE};
+use risc0_zkvm_platform::{
+    fileno,
+    syscall::{
+        nr::{SYS_GETENV, SYS_RANDOM},
+        reg_abi::{REG_A3, REG_A4},
+    },
+    PAGE_SIZE, WORD_SIZE,
+};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest as _, Sha256};
 use test_log::test;
 
@@ -38,9 +49,11 @@ use crate::{
         },
         testutils,
     },
+    rpc::RpcRequest,
     serde::to_vec,
     sha::{Digest, Digestible},
-    ExecutorEnv, ExecutorImpl, ExitCode,
+    CompressedSegmentRef, ExecutorEnv, ExecutorImpl, ExecutorSnapshot, ExitCode, ProfileFormat,
+    Segment, SegmentRef, SyscallTranscript,
 };
 
 fn run_test(spec: MultiTestSpec) {
@@ -100,6 +113,54 @@ fn basic() {
     assert_eq!(segments[0].index, 0);
 }
 
+#[test]
+fn segment_file_roundtrip() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let segment = session.resolve().unwrap().pop().unwrap();
+
+    let decoded = Segment::decode(&segment.encode().unwrap()).unwrap();
+    assert_eq!(decoded.get_claim().unwrap(), segment.get_claim().unwrap());
+    assert_eq!(
+        decoded.pre_image.compute_id().unwrap(),
+        segment.pre_image.compute_id().unwrap()
+    );
+    assert!(decoded.pre_image.pages().len() < segment.pre_image.pages().len());
+    for (&idx, page) in decoded.pre_image.pages() {
+        assert_eq!(Some(page), segment.pre_image.page(idx));
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let segment_ref = CompressedSegmentRef::new(&segment, dir.path()).unwrap();
+    let opened = CompressedSegmentRef::open(segment_ref.path()).unwrap();
+    assert_eq!(opened.header(), segment_ref.header());
+    assert_eq!(opened.header().index, segment.index);
+    assert_eq!(opened.header().exit_code, segment.exit_code);
+    let resolved = opened.resolve().unwrap();
+    assert_eq!(resolved.get_claim().unwrap(), segment.get_claim().unwrap());
+
+    let mut corrupt = segment.encode().unwrap();
+    corrupt[4] = 0xff;
+    assert!(Segment::decode(&corrupt).is_err());
+
+    // A corrupt header length is rejected before the header is read.
+    let mut corrupt = segment.encode().unwrap();
+    corrupt[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(Segment::decode(&corrupt).is_err());
+    let path = dir.path().join("corrupt.segment");
+    std::fs::write(&path, &corrupt).unwrap();
+    assert!(CompressedSegmentRef::open(&path).is_err());
+    std::fs::write(&path, b"not a segment file").unwrap();
+    assert!(CompressedSegmentRef::open(&path).is_err());
+}
+
 #[test]
 fn system_split() {
     let entry = 0x4000;
@@ -141,6 +202,328 @@ fn system_split() {
     assert_eq!(segments[1].index, 1);
 }
 
+#[test]
+fn execute_only() {
+    let spec = MultiTestSpec::BusyLoop { cycles: 1 << 16 };
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(14)
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let segments = session.resolve().unwrap();
+    assert!(segments.len() > 2);
+
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(14)
+        .build()
+        .unwrap();
+    let info = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .execute_only()
+        .unwrap();
+    assert_eq!(info.exit_code, session.exit_code);
+    assert_eq!(info.journal, session.journal.unwrap_or_default());
+    assert_eq!(info.segments.len(), segments.len());
+    for (info, segment) in info.segments.iter().zip(segments.iter()) {
+        assert_eq!(info.po2, segment.po2);
+        assert_eq!(info.cycles, segment.cycles);
+    }
+
+    // Reaching the session limit is an error, as it is for `run`, unless the
+    // execution is run to the limit.
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(14)
+        .session_limit(Some(1 << 15))
+        .build()
+        .unwrap();
+    let err = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .execute_only()
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("Session limit exceeded"));
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(14)
+        .session_limit(Some(1 << 15))
+        .build()
+        .unwrap();
+    let info = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .execute_to_limit()
+        .unwrap();
+    assert_eq!(info.exit_code, ExitCode::SessionLimit);
+    assert!(info.segments.len() < segments.len());
+}
+
+#[test]
+fn execute_only_resume_pause() {
+    let new_exec = || {
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::PauseContinue(0))
+            .unwrap()
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap()
+    };
+    let mut expected = new_exec();
+    assert_eq!(expected.run().unwrap().exit_code, ExitCode::Paused(0));
+    let mut exec = new_exec();
+    assert_eq!(exec.execute_only().unwrap().exit_code, ExitCode::Paused(0));
+
+    // The execution can be resumed from an up to date pre_image.
+    assert_eq!(
+        exec.snapshot().unwrap().pre_image.compute_id().unwrap(),
+        expected.snapshot().unwrap().pre_image.compute_id().unwrap()
+    );
+    let resumed = exec.run().unwrap();
+    assert_eq!(resumed.exit_code, ExitCode::Halted(0));
+    assert_eq!(
+        resumed.get_claim().unwrap().digest(),
+        expected.run().unwrap().get_claim().unwrap().digest()
+    );
+}
+
+#[test]
+fn snapshot_resume_segment_boundary() {
+    let tmp = tempfile::tempdir().unwrap();
+    let snapshot_path = tmp.path().join("guest.snapshot");
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1 << 16 })
+        .unwrap()
+        .segment_limit_po2(14)
+        .snapshot_path(&snapshot_path)
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    let segments = session.resolve().unwrap();
+    assert!(segments.len() > 2);
+    let last = segments.last().unwrap();
+
+    // The latest snapshot was taken at the boundary before the last segment.
+    let snapshot = ExecutorSnapshot::load(&snapshot_path).unwrap();
+    assert_eq!(snapshot.exit_code(), Some(ExitCode::SystemSplit));
+    assert_eq!(snapshot.segment_index(), last.index);
+
+    let env = ExecutorEnv::builder().build().unwrap();
+    let resumed = ExecutorImpl::from_snapshot(env, snapshot)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(resumed.exit_code, ExitCode::Halted(0));
+    assert_eq!(resumed.journal, session.journal);
+    let resumed_segments = resumed.resolve().unwrap();
+    assert_eq!(resumed_segments.len(), 1);
+    assert_eq!(resumed_segments[0].index, last.index);
+    assert_eq!(
+        resumed_segments[0].pre_image.compute_id().unwrap(),
+        segments[segments.len() - 2].post_state.digest()
+    );
+    assert_eq!(
+        resumed_segments[0].post_state.digest(),
+        last.post_state.digest()
+    );
+}
+
+#[test]
+fn snapshot_resume_claim() {
+    let hello_commit = ExecutorImpl::from_elf(ExecutorEnv::default(), HELLO_COMMIT_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let tmp = tempfile::tempdir().unwrap();
+    let snapshot_path = tmp.path().join("guest.snapshot");
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::SysVerifyInRegion {
+            image_id: HELLO_COMMIT_ID.into(),
+            journal: hello_commit.journal.clone().unwrap().bytes,
+            cycles: 1 << 16,
+        })
+        .unwrap()
+        .add_assumption(hello_commit.get_claim().unwrap().into())
+        .segment_limit_po2(14)
+        .snapshot_path(&snapshot_path)
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert_eq!(session.assumptions.len(), 1);
+    assert_eq!(session.cycle_regions["busy"].count, 1);
+
+    // The latest snapshot was taken inside the cycle region, after the
+    // assumption was verified, so the resumed environment provides neither.
+    let snapshot = ExecutorSnapshot::load(&snapshot_path).unwrap();
+    assert!(snapshot.segment_index() > 0);
+    let env = ExecutorEnv::builder().build().unwrap();
+    let resumed = ExecutorImpl::from_snapshot(env, snapshot)
+        .unwrap()
+        .run()
+        .unwrap();
+
+    // The resumed session claims the same output and post state as the full run.
+    let claim = session.get_claim().unwrap();
+    let resumed_claim = resumed.get_claim().unwrap();
+    assert_eq!(resumed_claim.exit_code, claim.exit_code);
+    assert_eq!(resumed_claim.post.digest(), claim.post.digest());
+    assert_eq!(resumed_claim.output.digest(), claim.output.digest());
+    assert_eq!(resumed.cycle_regions, session.cycle_regions);
+}
+
+#[test]
+fn snapshot_resume_pause() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::PauseContinue(0))
+        .unwrap()
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+    let session = exec.run().unwrap();
+    assert_eq!(session.exit_code, ExitCode::Paused(0));
+
+    let snapshot = ExecutorSnapshot::decode(&exec.snapshot().unwrap().encode().unwrap()).unwrap();
+    assert_eq!(snapshot.exit_code(), Some(ExitCode::Paused(0)));
+    assert_eq!(snapshot.segment_index(), 0);
+
+    // Resuming from the snapshot matches resuming the original executor.
+    let env = ExecutorEnv::builder().build().unwrap();
+    let resumed = ExecutorImpl::from_snapshot(env, snapshot)
+        .unwrap()
+        .run()
+        .unwrap();
+    let expected = exec.run().unwrap();
+    assert_eq!(resumed.exit_code, ExitCode::Halted(0));
+    assert_eq!(
+        resumed.get_claim().unwrap().digest(),
+        expected.get_claim().unwrap().digest()
+    );
+    assert!(exec.snapshot().is_err());
+}
+
+mod gdb {
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use risc0_zkvm_methods::HELLO_COMMIT_ELF;
+
+    use crate::{ExecutorEnv, ExecutorImpl, ExitCode};
+
+    struct Client(TcpStream);
+
+    impl Client {
+        fn request(&mut self, packet: &str) -> String {
+            let checksum = packet.bytes().fold(0u8, |acc, x| acc.wrapping_add(x));
+            write!(self.0, "${packet}#{checksum:02x}").unwrap();
+            assert_eq!(self.read_byte(), b'+');
+            while self.read_byte() != b'$' {}
+            let mut reply = Vec::new();
+            loop {
+                match self.read_byte() {
+                    b'#' => break,
+                    byte => reply.push(byte),
+                }
+            }
+            self.read_byte();
+            self.read_byte();
+            self.0.write_all(b"+").unwrap();
+            String::from_utf8(reply).unwrap()
+        }
+
+        fn read_byte(&mut self) -> u8 {
+            let mut byte = [0u8];
+            self.0.read_exact(&mut byte).unwrap();
+            byte[0]
+        }
+
+        fn pc(&mut self) -> u32 {
+            let regs = hex::decode(self.request("g")).unwrap();
+            assert_eq!(regs.len(), 33 * 4);
+            u32::from_le_bytes(regs[32 * 4..].try_into().unwrap())
+        }
+    }
+
+    fn debug<F>(script: F) -> Vec<u32>
+    where
+        F: FnOnce(&mut Client) -> Vec<u32> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = thread::spawn(move || script(&mut Client(TcpStream::connect(addr).unwrap())));
+        let (stream, _) = listener.accept().unwrap();
+        let env = ExecutorEnv::builder().build().unwrap();
+        let session = ExecutorImpl::from_elf(env, HELLO_COMMIT_ELF)
+            .unwrap()
+            .run_with_gdb(stream)
+            .unwrap();
+        assert_eq!(session.exit_code, ExitCode::Halted(0));
+        client.join().unwrap()
+    }
+
+    #[test]
+    fn step_and_breakpoint() {
+        // Single-step through the first instructions of the guest.
+        let pcs = debug(|client| {
+            assert_eq!(client.request("?"), "S05");
+            let mut pcs = vec![client.pc()];
+            for _ in 0..10 {
+                assert_eq!(client.request("s"), "S05");
+                pcs.push(client.pc());
+            }
+            assert_eq!(client.request("c"), "W00");
+            pcs
+        });
+        assert_ne!(pcs[0], pcs[1]);
+
+        // A breakpoint stops execution at the same address.
+        let target = pcs[10];
+        let pcs = debug(move |client| {
+            let entry = client.pc();
+            let insn = client.request(&format!("m{entry:x},4"));
+            assert_eq!(insn.len(), 8);
+            assert_eq!(client.request(&format!("Z0,{target:x},4")), "OK");
+            assert_eq!(client.request("c"), "S05");
+            let pc = client.pc();
+            assert_eq!(client.request(&format!("z0,{target:x},4")), "OK");
+            assert_eq!(client.request("c"), "W00");
+            vec![pc]
+        });
+        assert_eq!(pcs, vec![target]);
+    }
+
+    #[test]
+    fn watchpoint_and_memory_write() {
+        debug(|client| {
+            let entry = client.pc();
+            // Watchpoints are unsupported, which is signalled by an empty reply.
+            assert_eq!(client.request(&format!("Z2,{entry:x},4")), "");
+            let insn = client.request(&format!("m{entry:x},4"));
+            assert_eq!(client.request(&format!("M{entry:x},4:{insn}")), "OK");
+            assert_eq!(client.request(&format!("m{entry:x},4")), insn);
+            assert_eq!(client.request("c"), "W00");
+            vec![]
+        });
+    }
+}
+
 #[test]
 fn libm_build() {
     run_test(MultiTestSpec::LibM);
@@ -178,6 +561,366 @@ fn host_syscall() {
     assert_eq!(*actual.lock().unwrap(), expected[..expected.len() - 1]);
 }
 
+#[test]
+fn syscall_record_replay() {
+    let tmp = tempfile::tempdir().unwrap();
+    let transcript_path = tmp.path().join("syscalls.bin");
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::Syscall { count: 5 })
+        .unwrap()
+        .io_callback(SYS_MULTI_TEST, |buf| {
+            let mut reply = buf.to_vec();
+            reply.push(b'!');
+            Ok(reply.into())
+        })
+        .record_syscalls(&transcript_path)
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+
+    let transcript = SyscallTranscript::load(&transcript_path).unwrap();
+    let multi_test_calls = transcript
+        .entries
+        .iter()
+        .filter(|entry| entry.name == SYS_MULTI_TEST.as_str())
+        .count();
+    assert_eq!(multi_test_calls, 10);
+
+    // Replaying needs neither the input nor the callback.
+    let env = ExecutorEnv::builder()
+        .replay_syscalls(&transcript_path)
+        .build()
+        .unwrap();
+    let replayed = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(
+        replayed.get_claim().unwrap().digest(),
+        session.get_claim().unwrap().digest()
+    );
+
+    // Replaying a transcript that does not match the execution fails.
+    let mut truncated = transcript.clone();
+    truncated.entries.pop();
+    truncated.save(&transcript_path).unwrap();
+    let env = ExecutorEnv::builder()
+        .replay_syscalls(&transcript_path)
+        .build()
+        .unwrap();
+    let err = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("diverged"), "{err}");
+}
+
+/// A syscall that replies to each message from the guest with the message
+/// followed by `!`, using the same two-call protocol as `env::send_recv_slice`.
+#[derive(Default)]
+struct ExclaimSyscall {
+    calls: usize,
+    reply: Option<Vec<u8>>,
+}
+
+impl Syscall for ExclaimSyscall {
+    fn syscall(
+        &mut self,
+        _syscall: &str,
+        ctx: &mut dyn SyscallContext,
+        to_guest: &mut [u32],
+    ) -> Result<(u32, u32)> {
+        self.calls += 1;
+        match self.reply.take() {
+            None => {
+                let buf_ptr = ctx.load_register(REG_A3)?;
+                let buf_len = ctx.load_register(REG_A4)?;
+                let mut reply = ctx.load_region(buf_ptr, buf_len)?;
+                reply.push(b'!');
+                let len = reply.len() as u32;
+                self.reply = Some(reply);
+                Ok((len, 0))
+            }
+            Some(reply) => {
+                let to_guest_bytes: &mut [u8] = bytemuck::cast_slice_mut(to_guest);
+                to_guest_bytes[..reply.len()].copy_from_slice(&reply);
+                Ok((0, 0))
+            }
+        }
+    }
+}
+
+#[test]
+fn custom_syscall() {
+    let handler = Rc::new(RefCell::new(ExclaimSyscall::default()));
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::Syscall { count: 5 })
+        .unwrap()
+        .syscall(SYS_MULTI_TEST, handler.clone())
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(session.exit_code, ExitCode::Halted(0));
+    assert_eq!(handler.borrow().calls, 10);
+}
+
+#[test]
+fn override_builtin_syscall() {
+    struct Getenv(HashMap<&'static str, &'static str>);
+
+    impl Syscall for Getenv {
+        fn syscall(
+            &mut self,
+            _syscall: &str,
+            ctx: &mut dyn SyscallContext,
+            to_guest: &mut [u32],
+        ) -> Result<(u32, u32)> {
+            let buf_ptr = ctx.load_register(REG_A3)?;
+            let buf_len = ctx.load_register(REG_A4)?;
+            let name = String::from_utf8(ctx.load_region(buf_ptr, buf_len)?)?;
+            let Some(val) = self.0.get(name.as_str()) else {
+                return Ok((u32::MAX, 0));
+            };
+            let to_guest_bytes: &mut [u8] = bytemuck::cast_slice_mut(to_guest);
+            let nbytes = to_guest_bytes.len().min(val.len());
+            to_guest_bytes[..nbytes].copy_from_slice(&val.as_bytes()[..nbytes]);
+            Ok((val.len() as u32, 0))
+        }
+    }
+
+    // The variables set on the builder are hidden by the override.
+    let env = ExecutorEnv::builder()
+        .env_var("ENV_VAR2", "val2")
+        .syscall(
+            SYS_GETENV,
+            Getenv(HashMap::from([
+                ("TEST_MODE", "ENV_VARS"),
+                ("ENV_VAR1", "overridden"),
+            ])),
+        )
+        .read_fd(fileno::STDIN, Cursor::new("ENV_VAR1\nENV_VAR2"))
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, STANDARD_LIB_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let actual = &session.journal.as_ref().unwrap().bytes;
+    assert_eq!(
+        from_utf8(actual).unwrap(),
+        "ENV_VAR1=overridden\n!ENV_VAR2\n"
+    );
+}
+
+// The guest's `SquareRequest` implements the `RpcRequest` of its own copy of
+// this crate, so the host side is declared again here.
+#[derive(Serialize, Deserialize)]
+struct SquareRequest(u32);
+
+impl RpcRequest for SquareRequest {
+    type Response = u64;
+
+    const SCHEMA: &'static str = SQUARE_REQUEST_SCHEMA;
+}
+
+#[derive(Serialize, Deserialize)]
+struct WideSquareRequest(u64);
+
+impl RpcRequest for WideSquareRequest {
+    type Response = u64;
+
+    const SCHEMA: &'static str = "multi_test::SquareRequest/2";
+}
+
+#[test]
+fn rpc_call() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::RpcCall {
+            values: vec![3, 0, 7],
+        })
+        .unwrap()
+        .rpc_handler(SYS_MULTI_TEST, |SquareRequest(x)| {
+            ensure!(x != 0, "zero is not allowed");
+            Ok(x as u64 * x as u64)
+        })
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let results: Vec<Result<u64, String>> = session.journal.unwrap().decode().unwrap();
+    assert_eq!(
+        results,
+        vec![
+            Ok(9),
+            Err("RPC handler failed: zero is not allowed".to_string()),
+            Ok(49)
+        ]
+    );
+
+    // A handler with a different request schema rejects the calls.
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::RpcCall { values: vec![3] })
+        .unwrap()
+        .rpc_handler(SYS_MULTI_TEST, |WideSquareRequest(x)| Ok(x * x))
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let results: Vec<Result<u64, String>> = session.journal.unwrap().decode().unwrap();
+    assert_eq!(
+        results,
+        vec![Err("RPC request schema does not match".to_string())]
+    );
+}
+
+#[test]
+fn syscall_replay_payload() {
+    let tmp = tempfile::tempdir().unwrap();
+    let record = |value: u32| {
+        let transcript_path = tmp.path().join(format!("syscalls-{value}.bin"));
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::RpcCall {
+                values: vec![value],
+            })
+            .unwrap()
+            .rpc_handler(SYS_MULTI_TEST, |SquareRequest(x)| Ok(x as u64 * x as u64))
+            .record_syscalls(&transcript_path)
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        SyscallTranscript::load(&transcript_path).unwrap()
+    };
+    let three = record(3);
+    let four = record(4);
+
+    // Splice the RPC calls made with 3 into the transcript of the run with 4,
+    // so that the guest makes the same calls with a different payload.
+    let mut spliced = four.clone();
+    let mut payloads = 0;
+    for (entry, recorded) in spliced.entries.iter_mut().zip(three.entries.iter()) {
+        if entry.name == SYS_MULTI_TEST.as_str() {
+            assert_eq!(entry.args, recorded.args);
+            assert_eq!(entry.from_guest, recorded.from_guest);
+            if entry.from_guest_digest != recorded.from_guest_digest {
+                payloads += 1;
+            }
+            *entry = recorded.clone();
+        }
+    }
+    assert_eq!(payloads, 1);
+
+    let transcript_path = tmp.path().join("spliced.bin");
+    spliced.save(&transcript_path).unwrap();
+    let env = ExecutorEnv::builder()
+        .replay_syscalls(&transcript_path)
+        .build()
+        .unwrap();
+    let err = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("guest memory"), "{err}");
+}
+
+#[test]
+fn read_borrowed() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::ReadBorrowed)
+        .unwrap()
+        .write_frame(&("hello world", 5u32))
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let text: String = session.journal.unwrap().decode().unwrap();
+    assert_eq!(text, "hello");
+}
+
+#[test]
+fn cycle_regions() {
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::CycleRegions { iterations: 3 })
+        .unwrap()
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let outer = session.cycle_regions["outer"];
+    let sha = session.cycle_regions["sha"];
+    assert_eq!(outer.count, 3);
+    assert_eq!(sha.count, 3);
+    assert!(sha.cycles > 0);
+    assert!(outer.cycles > sha.cycles);
+    assert!(outer.paging_cycles <= outer.cycles);
+
+    // The same run fails once a region exceeds its budget.
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::CycleRegions { iterations: 3 })
+        .unwrap()
+        .cycle_budget("sha", sha.cycles - 1)
+        .build()
+        .unwrap();
+    let err = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .err()
+        .unwrap();
+    assert!(err
+        .to_string()
+        .contains("cycle region \"sha\" exceeded its budget"));
+}
+
+#[test]
+fn preloaded_blobs() {
+    let run = |name: &str| -> Option<Vec<u8>> {
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::Preloaded {
+                name: name.to_string(),
+            })
+            .unwrap()
+            .preload("book", b"e4 e5 Nf3")
+            .preload("weights", &[1, 2, 3, 4, 5])
+            .build()
+            .unwrap();
+        let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        session.journal.unwrap().decode().unwrap()
+    };
+    assert_eq!(run("book"), Some(b"e4 e5 Nf3".to_vec()));
+    assert_eq!(run("weights"), Some(vec![1, 2, 3, 4, 5]));
+    assert_eq!(run("missing"), None);
+
+    // Preloaded blobs are covered by the image ID.
+    let preloads = BTreeMap::from([("book".to_string(), b"e4 e5 Nf3".to_vec())]);
+    let image_id = crate::compute_image_id_with_preloads(MULTI_TEST_ELF, &preloads).unwrap();
+    assert_ne!(image_id, Digest::from(MULTI_TEST_ID));
+    let image = crate::host::load_image(MULTI_TEST_ELF, &BTreeMap::new()).unwrap();
+    assert_eq!(image.compute_id().unwrap(), Digest::from(MULTI_TEST_ID));
+}
+
 // Make sure panics in the callback get propagated correctly.
 #[test]
 #[should_panic(expected = "I am panicking from here!")]
@@ -953,6 +1696,48 @@ fn profiler() {
     assert!(check(&fr, addr), "{fr:#?} {addr}");
 }
 
+#[test]
+fn profiler_formats() {
+    let tmp = tempfile::tempdir().unwrap();
+    let run = |format: ProfileFormat| {
+        let path = tmp.path().join(format.as_str());
+        let env = ExecutorEnv::builder()
+            .write(&MultiTestSpec::Profiler)
+            .unwrap()
+            .enable_profiler(&path)
+            .profile_format(format)
+            .build()
+            .unwrap();
+        ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+            .unwrap()
+            .run()
+            .unwrap();
+        String::from_utf8(std::fs::read(&path).unwrap()).unwrap()
+    };
+
+    let folded = run(ProfileFormat::Folded);
+    assert!(
+        folded
+            .lines()
+            .any(|line| line.contains("profile_test_func1;profile_test_func2 ")),
+        "{folded}"
+    );
+    for line in folded.lines() {
+        let (_, cycles) = line.rsplit_once(' ').unwrap();
+        cycles.parse::<u64>().unwrap();
+    }
+
+    let svg = run(ProfileFormat::Flamegraph);
+    assert!(svg.starts_with("<?xml"));
+    assert!(svg.contains("profile_test_func2"));
+    assert!(svg.trim_end().ends_with("</svg>"));
+
+    let table = run(ProfileFormat::Table);
+    assert!(table.starts_with("total cycles: "), "{table}");
+    assert!(table.contains("profile_test_func2"), "{table}");
+    assert!(table.contains("multi_test.rs:"), "{table}");
+}
+
 #[test]
 fn oom() {
     let env = ExecutorEnv::builder()
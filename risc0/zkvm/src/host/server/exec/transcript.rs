@@ -0,0 +1,250 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recording and replaying of the host responses to guest syscalls.
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, ensure, Context, Result};
+use risc0_zkvm_platform::{
+    fileno,
+    syscall::{
+        nr::{SYS_CYCLE_REGION, SYS_VERIFY, SYS_VERIFY_INTEGRITY, SYS_WRITE},
+        reg_abi::{REG_A3, REG_A4, REG_A5},
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    sha::{self, Digest, Sha256},
+    SyscallContext,
+};
+
+/// Magic bytes at the start of every syscall transcript file.
+const TRANSCRIPT_MAGIC: &[u8; 4] = b"R0ST";
+
+/// The version of the syscall transcript format written by this crate.
+const TRANSCRIPT_VERSION: u32 = 2;
+
+/// The registers holding the guest arguments of a syscall.
+pub(crate) const SYSCALL_ARG_REGS: [usize; 3] = [REG_A3, REG_A4, REG_A5];
+
+/// A single syscall made by the guest and the response of the host.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyscallEntry {
+    /// The name of the syscall.
+    pub name: String,
+
+    /// The guest arguments of the syscall, taken from registers `a3` to `a5`.
+    pub args: Vec<u32>,
+
+    /// The ranges of guest memory read by the host, as `(addr, len)` pairs
+    /// in the order they were read.
+    pub from_guest: Vec<(u32, u32)>,
+
+    /// The SHA-256 digest of the bytes read by the host from `from_guest`.
+    pub from_guest_digest: Digest,
+
+    /// The words written by the host to guest memory.
+    pub to_guest: Vec<u32>,
+
+    /// The values returned by the host in registers `a0` and `a1`.
+    pub regs: (u32, u32),
+}
+
+/// The ordered list of syscalls made by a guest during execution.
+///
+/// A transcript is written by an executor configured with
+/// [ExecutorEnvBuilder::record_syscalls](crate::ExecutorEnvBuilder::record_syscalls)
+/// and can be fed back to an executor configured with
+/// [ExecutorEnvBuilder::replay_syscalls](crate::ExecutorEnvBuilder::replay_syscalls).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyscallTranscript {
+    /// The syscalls in the order they were made by the guest.
+    pub entries: Vec<SyscallEntry>,
+}
+
+impl SyscallTranscript {
+    /// Encode this transcript into its versioned binary representation.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(TRANSCRIPT_MAGIC);
+        bytes.extend_from_slice(&TRANSCRIPT_VERSION.to_le_bytes());
+        bytes.extend(bincode::serialize(self)?);
+        Ok(bytes)
+    }
+
+    /// Decode a transcript from its versioned binary representation.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        ensure!(
+            bytes.len() >= 8 && &bytes[..4] == TRANSCRIPT_MAGIC,
+            "not a syscall transcript"
+        );
+        let version = u32::from_le_bytes(bytes[4..8].try_into()?);
+        if version != TRANSCRIPT_VERSION {
+            bail!(
+                "unsupported syscall transcript version: {version}, expected {TRANSCRIPT_VERSION}"
+            );
+        }
+        Ok(bincode::deserialize(&bytes[8..])?)
+    }
+
+    /// Write this transcript to the file at `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.encode()?)
+            .with_context(|| format!("failed to write syscall transcript to {}", path.display()))
+    }
+
+    /// Read a transcript from the file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).with_context(|| {
+            format!("failed to read syscall transcript from {}", path.display())
+        })?;
+        Self::decode(&bytes)
+    }
+}
+
+/// Feeds the entries of a [SyscallTranscript] back to the executor.
+pub(crate) struct SyscallReplay {
+    transcript: SyscallTranscript,
+    next: usize,
+}
+
+impl SyscallReplay {
+    pub(crate) fn new(transcript: SyscallTranscript) -> Self {
+        Self {
+            transcript,
+            next: 0,
+        }
+    }
+
+    /// Returns the next recorded entry, checking that the guest is making the
+    /// same syscall, with the same data in guest memory, that was recorded.
+    pub(crate) fn next(
+        &mut self,
+        name: &str,
+        args: &[u32],
+        to_guest_words: usize,
+        ctx: &mut dyn SyscallContext,
+    ) -> Result<&SyscallEntry> {
+        let idx = self.next;
+        let Some(entry) = self.transcript.entries.get(idx) else {
+            bail!("syscall replay diverged at entry {idx}: guest called {name:?} after the end of the transcript");
+        };
+        if entry.name != name {
+            bail!(
+                "syscall replay diverged at entry {idx}: guest called {name:?}, recorded {:?}",
+                entry.name
+            );
+        }
+        if entry.args != args || entry.to_guest.len() != to_guest_words {
+            bail!(
+                "syscall replay diverged at entry {idx} ({name:?}): guest args {args:08x?} requesting {to_guest_words} words, recorded args {:08x?} with {} words",
+                entry.args,
+                entry.to_guest.len()
+            );
+        }
+        let mut from_guest = FromGuest::default();
+        for &(addr, len) in entry.from_guest.iter() {
+            from_guest.push(addr, &ctx.load_region(addr, len)?);
+        }
+        if from_guest.digest() != entry.from_guest_digest {
+            bail!("syscall replay diverged at entry {idx} ({name:?}): guest memory read by the host differs from the recording");
+        }
+        self.next += 1;
+        Ok(entry)
+    }
+
+    /// The number of entries that have not been replayed.
+    pub(crate) fn remaining(&self) -> usize {
+        self.transcript.entries.len() - self.next
+    }
+}
+
+/// The guest memory read by the host while handling a syscall.
+#[derive(Default)]
+pub(crate) struct FromGuest {
+    pub(crate) ranges: Vec<(u32, u32)>,
+    bytes: Vec<u8>,
+}
+
+impl FromGuest {
+    fn push(&mut self, addr: u32, bytes: &[u8]) {
+        let len = bytes.len() as u32;
+        match self.ranges.last_mut() {
+            Some(last) if last.0.wrapping_add(last.1) == addr => last.1 += len,
+            _ => self.ranges.push((addr, len)),
+        }
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub(crate) fn digest(&self) -> Digest {
+        *sha::Impl::hash_bytes(&self.bytes)
+    }
+}
+
+/// A [SyscallContext] that records the guest memory read through it.
+pub(crate) struct RecordingContext<'a> {
+    inner: &'a mut dyn SyscallContext,
+    pub(crate) from_guest: FromGuest,
+}
+
+impl<'a> RecordingContext<'a> {
+    pub(crate) fn new(inner: &'a mut dyn SyscallContext) -> Self {
+        Self {
+            inner,
+            from_guest: FromGuest::default(),
+        }
+    }
+}
+
+impl SyscallContext for RecordingContext<'_> {
+    fn get_cycle(&self) -> usize {
+        self.inner.get_cycle()
+    }
+
+    fn load_register(&mut self, idx: usize) -> Result<u32> {
+        self.inner.load_register(idx)
+    }
+
+    fn load_u32(&mut self, addr: u32) -> Result<u32> {
+        let word = self.inner.load_u32(addr)?;
+        self.from_guest.push(addr, &word.to_le_bytes());
+        Ok(word)
+    }
+
+    fn load_u8(&mut self, addr: u32) -> Result<u8> {
+        let byte = self.inner.load_u8(addr)?;
+        self.from_guest.push(addr, &[byte]);
+        Ok(byte)
+    }
+}
+
+/// Returns true if the syscall must still be handled by the executor when
+/// replaying a transcript.
+///
+/// Writes to the journal, assumption lookups and cycle regions update the
+/// state of the [Session](crate::Session) itself, so they are dispatched to
+/// the live handlers and their responses are checked against the transcript
+/// instead.
+pub(crate) fn is_replayed_live(name: &str, args: &[u32]) -> bool {
+    if name == SYS_WRITE.as_str() {
+        return args.first() == Some(&fileno::JOURNAL);
+    }
+    name == SYS_VERIFY.as_str()
+        || name == SYS_VERIFY_INTEGRITY.as_str()
+        || name == SYS_CYCLE_REGION.as_str()
+}
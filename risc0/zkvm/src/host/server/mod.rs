@@ -16,6 +16,7 @@ pub(crate) mod exec;
 pub(crate) mod opcode;
 #[cfg(feature = "prove")]
 pub(crate) mod prove;
+pub(crate) mod segment_file;
 pub(crate) mod session;
 #[cfg(test)]
 mod testutils;
@@ -0,0 +1,260 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Estimate the cost of proving a guest program without proving it.
+//!
+//! An estimate either executes the guest to completion, or stops after a
+//! number of cycles and extrapolates. Sampling parts of an execution is not
+//! supported: the state at the start of any segment is only known after
+//! executing every cycle before it, so sampling would cost as much as
+//! executing up to the last sample.
+
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{ExecutorEnv, ExecutorImpl, ExitCode, SessionInfo};
+
+/// A model of the time needed to prove a segment, and of the size of its seal,
+/// for a given hash function and HAL.
+///
+/// Proving times depend on the machine, so there are no built-in models.
+/// Calibrate a model on the target machine with [ProvingModel::calibrate],
+/// e.g. by running the `risc0-benchmark` crate with `--model`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProvingModel {
+    /// The hash function used by the prover, e.g. `poseidon` or `sha-256`.
+    pub hashfn: String,
+
+    /// The HAL used by the prover, e.g. `cpu`, `metal` or `cuda`.
+    pub hal: String,
+
+    /// The fixed number of seconds spent proving each segment.
+    pub segment_secs: f64,
+
+    /// The number of seconds spent proving each cycle of a segment, including
+    /// padding up to the next power of 2.
+    pub cycle_secs: f64,
+
+    /// The fixed number of bytes of each segment seal.
+    pub seal_bytes: f64,
+
+    /// The number of bytes added to a segment seal for each power of 2 cycles.
+    pub seal_bytes_per_po2: f64,
+}
+
+/// A measurement of proving a session, used to calibrate a [ProvingModel].
+#[derive(Clone, Debug)]
+pub struct CalibrationSample {
+    /// The po2 of each segment of the session.
+    pub segment_po2s: Vec<u32>,
+
+    /// The time spent proving the session.
+    pub proving_time: Duration,
+
+    /// The total size of the seals of the session.
+    pub seal_bytes: u64,
+}
+
+impl ProvingModel {
+    /// Fit a [ProvingModel] to measurements of proving sessions.
+    ///
+    /// The proving time is fit against the number of segments and the total
+    /// number of cycles, and the seal size against the number of segments and
+    /// the sum of their po2s, using least squares.
+    pub fn calibrate(hashfn: &str, hal: &str, samples: &[CalibrationSample]) -> Result<Self> {
+        ensure!(
+            samples.len() >= 2,
+            "At least 2 samples are needed to calibrate a proving model"
+        );
+        let time_samples: Vec<_> = samples
+            .iter()
+            .map(|sample| {
+                let cycles: u64 = sample.segment_po2s.iter().map(|po2| 1u64 << po2).sum();
+                (
+                    sample.segment_po2s.len() as f64,
+                    cycles as f64,
+                    sample.proving_time.as_secs_f64(),
+                )
+            })
+            .collect();
+        let seal_samples: Vec<_> = samples
+            .iter()
+            .map(|sample| {
+                let po2s: u32 = sample.segment_po2s.iter().sum();
+                (
+                    sample.segment_po2s.len() as f64,
+                    po2s as f64,
+                    sample.seal_bytes as f64,
+                )
+            })
+            .collect();
+        let (segment_secs, cycle_secs) = least_squares(&time_samples)?;
+        let (seal_bytes, seal_bytes_per_po2) = least_squares(&seal_samples)?;
+        Ok(Self {
+            hashfn: hashfn.to_string(),
+            hal: hal.to_string(),
+            segment_secs,
+            cycle_secs,
+            seal_bytes,
+            seal_bytes_per_po2,
+        })
+    }
+
+    /// Estimate the time needed to prove a segment of `2^po2` cycles.
+    pub fn segment_time(&self, po2: u32) -> Duration {
+        Duration::from_secs_f64(self.segment_secs + self.cycle_secs * (1u64 << po2) as f64)
+    }
+
+    /// Estimate the size in bytes of the seal of a segment of `2^po2` cycles.
+    pub fn segment_seal_bytes(&self, po2: u32) -> u64 {
+        (self.seal_bytes + self.seal_bytes_per_po2 * po2 as f64) as u64
+    }
+}
+
+// Fit `z = a * x + b * y` to the given `(x, y, z)` samples, with `a` and `b`
+// clamped to be non-negative.
+fn least_squares(samples: &[(f64, f64, f64)]) -> Result<(f64, f64)> {
+    let (mut xx, mut xy, mut yy, mut xz, mut yz) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for (x, y, z) in samples {
+        xx += x * x;
+        xy += x * y;
+        yy += y * y;
+        xz += x * z;
+        yz += y * z;
+    }
+    let det = xx * yy - xy * xy;
+    if det.abs() <= f64::EPSILON * xx * yy {
+        bail!("Calibration samples are degenerate; use sessions of different sizes");
+    }
+    let a = (xz * yy - yz * xy) / det;
+    let b = (yz * xx - xz * xy) / det;
+    Ok((a.max(0.0), b.max(0.0)))
+}
+
+/// Options for [estimate].
+#[derive(Clone, Debug)]
+pub struct EstimateOpts {
+    /// Stop executing after this many cycles and extrapolate the cost of the
+    /// whole execution from the segments executed so far.
+    ///
+    /// When `None`, the guest is executed to completion.
+    pub cycle_limit: Option<u64>,
+
+    /// The total number of user cycles expected, used to extrapolate when
+    /// execution is stopped by `cycle_limit`.
+    ///
+    /// When `None`, the estimate only covers the cycles executed.
+    pub expected_user_cycles: Option<u64>,
+
+    /// The [ProvingModel] used to estimate the proving time and proof size.
+    ///
+    /// When `None`, only the segments and cycles are estimated.
+    pub model: Option<ProvingModel>,
+}
+
+/// An estimate of the cost of proving an execution.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Estimate {
+    /// The number of segments.
+    pub segments: u64,
+
+    /// The number of user cycles without any overhead for continuations or
+    /// po2 padding.
+    pub user_cycles: u64,
+
+    /// The total number of cycles that a prover experiences, including
+    /// overhead and padding up to the nearest power of 2.
+    pub total_cycles: u64,
+
+    /// The total size in bytes of the segment seals, if estimated with a
+    /// [ProvingModel].
+    pub proof_bytes: Option<u64>,
+
+    /// The estimated time needed to prove all segments, if estimated with a
+    /// [ProvingModel].
+    pub proving_time: Option<Duration>,
+
+    /// Whether the execution ran to completion. When false, the other fields
+    /// are extrapolated or only cover part of the execution.
+    pub complete: bool,
+}
+
+impl Estimate {
+    /// Construct an [Estimate] from the [SessionInfo] of an execution.
+    pub fn from_session_info(info: &SessionInfo, model: Option<&ProvingModel>) -> Self {
+        let segments = &info.segments;
+        Self {
+            segments: segments.len() as u64,
+            user_cycles: segments.iter().map(|segment| segment.cycles as u64).sum(),
+            total_cycles: segments.iter().map(|segment| 1u64 << segment.po2).sum(),
+            proof_bytes: model.map(|model| {
+                segments
+                    .iter()
+                    .map(|segment| model.segment_seal_bytes(segment.po2))
+                    .sum()
+            }),
+            proving_time: model.map(|model| {
+                segments
+                    .iter()
+                    .map(|segment| model.segment_time(segment.po2))
+                    .sum()
+            }),
+            complete: info.exit_code != ExitCode::SessionLimit,
+        }
+    }
+
+    /// Extrapolate this estimate to an execution of `user_cycles` user cycles,
+    /// assuming that the rest of the execution has the same cost per user
+    /// cycle as the part that was estimated.
+    pub fn extrapolate(&self, user_cycles: u64) -> Self {
+        if self.user_cycles == 0 {
+            return self.clone();
+        }
+        let scale = user_cycles as f64 / self.user_cycles as f64;
+        Self {
+            segments: (self.segments as f64 * scale).ceil() as u64,
+            user_cycles,
+            total_cycles: (self.total_cycles as f64 * scale).ceil() as u64,
+            proof_bytes: self
+                .proof_bytes
+                .map(|bytes| (bytes as f64 * scale).ceil() as u64),
+            proving_time: self.proving_time.map(|time| time.mul_f64(scale)),
+            complete: false,
+        }
+    }
+}
+
+/// Estimate the cost of proving the execution of the specified ELF binary.
+///
+/// The guest is run as with [ExecutorImpl::execute_only], so no segments are
+/// built. If execution is stopped by [EstimateOpts::cycle_limit], the
+/// estimate is extrapolated to [EstimateOpts::expected_user_cycles] if it is
+/// set. Otherwise, the estimate only covers the segments executed before the
+/// limit, and [Estimate::complete] is false.
+pub fn estimate(mut env: ExecutorEnv<'_>, elf: &[u8], opts: &EstimateOpts) -> Result<Estimate> {
+    if let Some(cycle_limit) = opts.cycle_limit {
+        env.session_limit = Some(match env.session_limit {
+            Some(limit) => limit.min(cycle_limit),
+            None => cycle_limit,
+        });
+    }
+    let info = ExecutorImpl::from_elf(env, elf)?.execute_to_limit()?;
+    let estimate = Estimate::from_session_info(&info, opts.model.as_ref());
+    Ok(match opts.expected_user_cycles {
+        Some(user_cycles) if !estimate.complete => estimate.extrapolate(user_cycles),
+        _ => estimate,
+    })
+}
@@ -29,7 +29,7 @@ use risc0_zkp::{
     },
     hal::Hal,
     prove::poly_group::PolyGroup,
-    MAX_CYCLES_PO2, MIN_CYCLES_PO2, ZK_CYCLES,
+    MAX_CYCLES_PO2, MIN_CYCLES_PO2, QUERIES, ZK_CYCLES,
 };
 use risc0_zkvm_platform::{memory, WORD_SIZE};
 use tracing::{debug, trace};
@@ -442,7 +442,7 @@ impl Loader {
             hal.batch_interpolate_ntt(&coeffs, code_size);
             hal.zk_shift(&coeffs, code_size);
             // Make the poly-group & extract the root
-            let code_group = PolyGroup::new(hal, coeffs, code_size, cycles, "code");
+            let code_group = PolyGroup::new(hal, coeffs, code_size, cycles, QUERIES, "code");
             table.push(*code_group.merkle.root());
         }
 
@@ -14,9 +14,11 @@
 
 //! Run the zkVM guest and prove its results.
 
+mod cost;
 mod dev_mode;
 mod exec;
 pub(crate) mod loader;
+mod parallel;
 mod plonk;
 mod prover_impl;
 #[cfg(test)]
@@ -24,7 +26,7 @@ mod tests;
 
 use std::rc::Rc;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, ensure, Result};
 use cfg_if::cfg_if;
 use risc0_circuit_rv32im::CircuitImpl;
 use risc0_core::field::{
@@ -38,10 +40,17 @@ use risc0_zkp::{
 };
 use risc0_zkvm_platform::WORD_SIZE;
 
+pub use self::{
+    cost::{estimate, CalibrationSample, Estimate, EstimateOpts, ProvingModel},
+    parallel::{estimate_segment_memory, ParallelProverImpl},
+};
 use self::{dev_mode::DevModeProver, prover_impl::ProverImpl};
 use crate::{
-    host::receipt::{SegmentReceipt, SuccinctReceipt},
-    is_dev_mode, ExecutorEnv, ExecutorImpl, ProverOpts, Receipt, Segment, Session, VerifierContext,
+    host::receipt::{
+        AssumptionReceipt, CompositeReceipt, InnerReceipt, SegmentReceipt, SuccinctReceipt,
+    },
+    is_dev_mode, ExecutorEnv, ExecutorImpl, FriParams, ParallelOpts, ProveHandle, ProvePhase,
+    ProverOpts, Receipt, ReceiptKind, Segment, Session, VerifierContext,
 };
 
 /// A ProverServer can execute a given ELF binary and produce a [Receipt]
@@ -49,27 +58,61 @@ use crate::{
 pub trait ProverServer {
     /// Prove the specified ELF binary.
     fn prove(&self, env: ExecutorEnv<'_>, elf: &[u8]) -> Result<Receipt> {
-        self.prove_with_ctx(env, &VerifierContext::default(), elf)
+        self.prove_with_ctx(
+            env,
+            &VerifierContext::default(),
+            elf,
+            &ProveHandle::default(),
+        )
     }
 
-    /// Prove the specified ELF binary using the specified [VerifierContext].
+    /// Prove the specified ELF binary using the specified [VerifierContext],
+    /// reporting progress to the given [ProveHandle].
     fn prove_with_ctx(
         &self,
         env: ExecutorEnv<'_>,
         ctx: &VerifierContext,
         elf: &[u8],
+        handle: &ProveHandle,
     ) -> Result<Receipt> {
+        handle.enter(ProvePhase::Execute)?;
         let mut exec = ExecutorImpl::from_elf(env, elf)?;
         let session = exec.run()?;
-        self.prove_session(ctx, &session)
+        self.prove_session(ctx, &session, handle)
     }
 
-    /// Prove the specified [Session].
-    fn prove_session(&self, ctx: &VerifierContext, session: &Session) -> Result<Receipt>;
+    /// Prove the specified [Session], reporting progress to the given
+    /// [ProveHandle].
+    ///
+    /// Proving stops with an error before the next phase once the handle is
+    /// cancelled.
+    fn prove_session(
+        &self,
+        ctx: &VerifierContext,
+        session: &Session,
+        handle: &ProveHandle,
+    ) -> Result<Receipt>;
 
     /// Prove the specified [Segment].
     fn prove_segment(&self, ctx: &VerifierContext, segment: &Segment) -> Result<SegmentReceipt>;
 
+    /// Prove the specified [Segment], reporting progress to the given
+    /// [ProveHandle].
+    ///
+    /// The default implementation only reports the [ProvePhase::Witness]
+    /// phase before calling [ProverServer::prove_segment].
+    fn prove_segment_with_handle(
+        &self,
+        ctx: &VerifierContext,
+        segment: &Segment,
+        handle: &ProveHandle,
+    ) -> Result<SegmentReceipt> {
+        handle.enter(ProvePhase::Witness {
+            segment: segment.index,
+        })?;
+        self.prove_segment(ctx, segment)
+    }
+
     /// Return the peak memory usage that this [ProverServer] has experienced.
     fn get_peak_memory_usage(&self) -> usize;
 
@@ -79,8 +122,126 @@ pub trait ProverServer {
     /// Join two [SuccinctReceipt] into a [SuccinctReceipt]
     fn join(&self, a: &SuccinctReceipt, b: &SuccinctReceipt) -> Result<SuccinctReceipt>;
 
+    /// Resolve an assumption from a conditional [SuccinctReceipt] by providing
+    /// a [SuccinctReceipt] proving the validity of the assumption.
+    fn resolve(
+        &self,
+        conditional: &SuccinctReceipt,
+        corroborating: &SuccinctReceipt,
+    ) -> Result<SuccinctReceipt>;
+
     /// Convert a [SuccinctReceipt] with a poseidon hash function that uses a 254-bit field
     fn identity_p254(&self, a: &SuccinctReceipt) -> Result<SuccinctReceipt>;
+
+    /// Compress a [CompositeReceipt] into a single [SuccinctReceipt].
+    ///
+    /// Each segment receipt is lifted and the results are joined pairwise in
+    /// a balanced tree. Each proven assumption is then compressed, if needed,
+    /// and resolved in order.
+    ///
+    /// Unresolved assumptions are left on the claim of the returned receipt,
+    /// which is then conditional. They can be discharged later by calling
+    /// [ProverServer::resolve] with a receipt for each of them, in order.
+    /// Because assumptions are resolved from the head of the list, a proven
+    /// assumption cannot follow an unresolved one.
+    fn composite_to_succinct(
+        &self,
+        receipt: &CompositeReceipt,
+        handle: &ProveHandle,
+    ) -> Result<SuccinctReceipt> {
+        let mut layer = receipt
+            .segments
+            .iter()
+            .map(|segment| {
+                ensure!(
+                    segment.hashfn == "poseidon",
+                    "succinct receipts require segments proven with poseidon, not {}",
+                    segment.hashfn
+                );
+                ensure!(
+                    segment.fri_params == FriParams::DEFAULT,
+                    "succinct receipts require segments proven with the default FRI parameters, not {:?}",
+                    segment.fri_params
+                );
+                handle.enter(ProvePhase::Lift {
+                    segment: segment.index,
+                })?;
+                self.lift(segment)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        while layer.len() > 1 {
+            layer = layer
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => {
+                        handle.enter(ProvePhase::Join)?;
+                        self.join(a, b)
+                    }
+                    [a] => Ok(a.clone()),
+                    _ => unreachable!(),
+                })
+                .collect::<Result<Vec<_>>>()?;
+        }
+        let mut conditional = layer
+            .pop()
+            .ok_or_else(|| anyhow!("composite receipt has no segments"))?;
+
+        let mut unresolved = false;
+        for assumption in receipt.assumptions.iter() {
+            let corroborating = match assumption {
+                AssumptionReceipt::Unresolved(_) => {
+                    unresolved = true;
+                    continue;
+                }
+                _ if unresolved => {
+                    bail!("a proven assumption cannot be resolved after an unresolved assumption")
+                }
+                AssumptionReceipt::Proven(InnerReceipt::Composite(inner)) => {
+                    self.composite_to_succinct(inner, handle)?
+                }
+                AssumptionReceipt::Proven(InnerReceipt::Succinct(inner)) => inner.clone(),
+                _ => bail!("only composite and succinct assumption receipts can be resolved"),
+            };
+            conditional = self.resolve(&conditional, &corroborating)?;
+        }
+        Ok(conditional)
+    }
+
+    /// Convert the given [Receipt] into the [ReceiptKind] selected by the
+    /// specified [ProverOpts].
+    ///
+    /// Receipts that are already of the selected kind, as well as fake
+    /// receipts produced in dev mode, are returned unchanged. Any intermediate
+    /// [SuccinctReceipt] is verified using the specified [VerifierContext].
+    /// Progress is reported to the given [ProveHandle].
+    fn compress(
+        &self,
+        ctx: &VerifierContext,
+        opts: &ProverOpts,
+        receipt: &Receipt,
+        handle: &ProveHandle,
+    ) -> Result<Receipt> {
+        let succinct = match (&receipt.inner, opts.receipt_kind) {
+            (_, ReceiptKind::Composite) | (InnerReceipt::Fake { .. }, _) => {
+                return Ok(receipt.clone())
+            }
+            (InnerReceipt::Succinct(_), ReceiptKind::Succinct) => return Ok(receipt.clone()),
+            (InnerReceipt::Composite(inner), _) => {
+                let succinct = self.composite_to_succinct(inner, handle)?;
+                succinct.verify_integrity_with_context(ctx)?;
+                succinct
+            }
+            (InnerReceipt::Succinct(inner), _) => inner.clone(),
+            (InnerReceipt::Groth16(_), kind) => {
+                bail!("a Groth16 receipt cannot be converted into a {kind:?} receipt")
+            }
+        };
+        let inner = match opts.receipt_kind {
+            ReceiptKind::Compact => InnerReceipt::Succinct(self.identity_p254(&succinct)?),
+            _ => InnerReceipt::Succinct(succinct),
+        };
+        Ok(Receipt::new(inner, receipt.journal.bytes.clone()))
+    }
 }
 
 /// A pair of [Hal] and [CircuitHal].
@@ -101,7 +262,7 @@ impl Session {
     /// For each segment, call [Segment::prove] and collect the receipts.
     pub fn prove(&self) -> Result<Receipt> {
         let prover = get_prover_server(&ProverOpts::default())?;
-        prover.prove_session(&VerifierContext::default(), self)
+        prover.prove_session(&VerifierContext::default(), self, &ProveHandle::default())
     }
 }
 
@@ -149,8 +310,10 @@ mod cuda {
     use std::rc::Rc;
 
     use anyhow::{bail, Result};
-    use risc0_circuit_rv32im::cuda::{CudaCircuitHalPoseidon, CudaCircuitHalSha256};
-    use risc0_zkp::hal::cuda::{CudaHalPoseidon, CudaHalSha256};
+    use risc0_circuit_rv32im::cuda::{
+        CudaCircuitHalPoseidon, CudaCircuitHalPoseidon2, CudaCircuitHalSha256,
+    };
+    use risc0_zkp::hal::cuda::{CudaHalPoseidon, CudaHalPoseidon2, CudaHalSha256};
 
     use super::{HalPair, ProverImpl, ProverServer};
     use crate::ProverOpts;
@@ -160,18 +323,26 @@ mod cuda {
             "sha-256" => {
                 let hal = Rc::new(CudaHalSha256::new());
                 let circuit_hal = Rc::new(CudaCircuitHalSha256::new(hal.clone()));
-                Ok(Rc::new(ProverImpl::new(
-                    "cuda",
-                    HalPair { hal, circuit_hal },
-                )))
+                Ok(Rc::new(
+                    ProverImpl::new("cuda", HalPair { hal, circuit_hal })
+                        .with_fri_params(opts.fri_params),
+                ))
             }
             "poseidon" => {
                 let hal = Rc::new(CudaHalPoseidon::new());
                 let circuit_hal = Rc::new(CudaCircuitHalPoseidon::new(hal.clone()));
-                Ok(Rc::new(ProverImpl::new(
-                    "cuda",
-                    HalPair { hal, circuit_hal },
-                )))
+                Ok(Rc::new(
+                    ProverImpl::new("cuda", HalPair { hal, circuit_hal })
+                        .with_fri_params(opts.fri_params),
+                ))
+            }
+            "poseidon2" => {
+                let hal = Rc::new(CudaHalPoseidon2::new());
+                let circuit_hal = Rc::new(CudaCircuitHalPoseidon2::new(hal.clone()));
+                Ok(Rc::new(
+                    ProverImpl::new("cuda", HalPair { hal, circuit_hal })
+                        .with_fri_params(opts.fri_params),
+                ))
             }
             _ => bail!("Unsupported hashfn: {}", opts.hashfn),
         }
@@ -185,7 +356,8 @@ mod metal {
     use anyhow::{bail, Result};
     use risc0_circuit_rv32im::metal::MetalCircuitHal;
     use risc0_zkp::hal::metal::{
-        MetalHalPoseidon, MetalHalSha256, MetalHashPoseidon, MetalHashSha256,
+        MetalHalPoseidon, MetalHalPoseidon2, MetalHalSha256, MetalHashPoseidon, MetalHashPoseidon2,
+        MetalHashSha256,
     };
 
     use super::{HalPair, ProverImpl, ProverServer};
@@ -196,18 +368,26 @@ mod metal {
             "sha-256" => {
                 let hal = Rc::new(MetalHalSha256::new());
                 let circuit_hal = Rc::new(MetalCircuitHal::<MetalHashSha256>::new(hal.clone()));
-                Ok(Rc::new(ProverImpl::new(
-                    "metal",
-                    HalPair { hal, circuit_hal },
-                )))
+                Ok(Rc::new(
+                    ProverImpl::new("metal", HalPair { hal, circuit_hal })
+                        .with_fri_params(opts.fri_params),
+                ))
             }
             "poseidon" => {
                 let hal = Rc::new(MetalHalPoseidon::new());
                 let circuit_hal = Rc::new(MetalCircuitHal::<MetalHashPoseidon>::new(hal.clone()));
-                Ok(Rc::new(ProverImpl::new(
-                    "metal",
-                    HalPair { hal, circuit_hal },
-                )))
+                Ok(Rc::new(
+                    ProverImpl::new("metal", HalPair { hal, circuit_hal })
+                        .with_fri_params(opts.fri_params),
+                ))
+            }
+            "poseidon2" => {
+                let hal = Rc::new(MetalHalPoseidon2::new());
+                let circuit_hal = Rc::new(MetalCircuitHal::<MetalHashPoseidon2>::new(hal.clone()));
+                Ok(Rc::new(
+                    ProverImpl::new("metal", HalPair { hal, circuit_hal })
+                        .with_fri_params(opts.fri_params),
+                ))
             }
             _ => bail!("Unsupported hashfn: {}", opts.hashfn),
         }
@@ -221,7 +401,9 @@ mod cpu {
     use anyhow::{bail, Result};
     use risc0_circuit_rv32im::cpu::CpuCircuitHal;
     use risc0_zkp::{
-        core::hash::{poseidon::PoseidonHashSuite, sha::Sha256HashSuite},
+        core::hash::{
+            poseidon::PoseidonHashSuite, poseidon2::Poseidon2HashSuite, sha::Sha256HashSuite,
+        },
         hal::cpu::CpuHal,
     };
 
@@ -232,23 +414,51 @@ mod cpu {
         let suite = match opts.hashfn.as_str() {
             "sha-256" => Sha256HashSuite::new_suite(),
             "poseidon" => PoseidonHashSuite::new_suite(),
+            "poseidon2" => Poseidon2HashSuite::new_suite(),
             _ => bail!("Unsupported hashfn: {}", opts.hashfn),
         };
-        let hal = Rc::new(CpuHal::new(suite));
+        let hal = match opts.memory_budget {
+            Some(budget) => CpuHal::new(suite).with_memory_budget(budget),
+            None => CpuHal::new(suite),
+        };
+        let hal = Rc::new(hal);
         let circuit_hal = Rc::new(CpuCircuitHal::new(&CIRCUIT));
         let hal_pair = HalPair { hal, circuit_hal };
-        Ok(Rc::new(ProverImpl::new("cpu", hal_pair)))
+        Ok(Rc::new(
+            ProverImpl::new("cpu", hal_pair).with_fri_params(opts.fri_params),
+        ))
     }
 }
 
 /// Select a [ProverServer] based on the specified [ProverOpts] and currently
 /// compiled features.
+///
+/// When [ProverOpts::parallel] is set, the selected [ProverServer] proves the
+/// segments of a [Session] concurrently; see [ParallelProverImpl].
 pub fn get_prover_server(opts: &ProverOpts) -> Result<Rc<dyn ProverServer>> {
     if is_dev_mode() {
         eprintln!("WARNING: proving in dev mode. This will not generate valid, secure proofs.");
         return Ok(Rc::new(DevModeProver));
     }
+    ensure!(
+        opts.fri_params.is_supported(),
+        "Unsupported FRI parameters: {:?}",
+        opts.fri_params
+    );
+    ensure!(
+        opts.memory_budget.is_none() || cfg!(not(any(feature = "cuda", feature = "metal"))),
+        "A memory budget is only supported when proving on the CPU"
+    );
+
+    match &opts.parallel {
+        Some(parallel) => Ok(Rc::new(ParallelProverImpl::new(opts, parallel)?)),
+        None => get_segment_prover_server(opts),
+    }
+}
 
+/// Select a [ProverServer] that proves segments one after the other, based on
+/// the specified [ProverOpts] and currently compiled features.
+fn get_segment_prover_server(opts: &ProverOpts) -> Result<Rc<dyn ProverServer>> {
     cfg_if! {
         if #[cfg(feature = "cuda")] {
             cuda::get_prover_server(opts)
@@ -259,3 +469,18 @@ pub fn get_prover_server(opts: &ProverOpts) -> Result<Rc<dyn ProverServer>> {
         }
     }
 }
+
+/// Select a [ProverServer] that proves the segments of a [Session]
+/// concurrently, based on the specified [ProverOpts] and [ParallelOpts].
+///
+/// This is the same as calling [get_prover_server] with
+/// [ProverOpts::parallel] set to `parallel`.
+pub fn get_parallel_prover_server(
+    opts: &ProverOpts,
+    parallel: &ParallelOpts,
+) -> Result<Rc<dyn ProverServer>> {
+    get_prover_server(&ProverOpts {
+        parallel: Some(parallel.clone()),
+        ..opts.clone()
+    })
+}
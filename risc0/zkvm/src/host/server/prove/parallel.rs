@@ -0,0 +1,280 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Prove the segments of a [Session] concurrently.
+
+use std::{
+    collections::BTreeMap,
+    rc::Rc,
+    sync::{mpsc, Mutex},
+    thread,
+};
+
+use anyhow::{anyhow, bail, Result};
+use risc0_core::field::{
+    baby_bear::{Elem, ExtElem},
+    ExtElem as _,
+};
+use risc0_zkp::{adapter::TapsProvider, core::digest::Digest, INV_RATE};
+
+use super::{get_segment_prover_server, prover_impl::compose_session_receipt, ProverServer};
+use crate::{
+    host::{
+        receipt::{SegmentReceipt, SuccinctReceipt},
+        CIRCUIT,
+    },
+    ParallelOpts, ProveHandle, ProverOpts, Receipt, Segment, Session, VerifierContext,
+};
+
+/// Estimate the number of bytes needed to prove a segment of size `2^po2`.
+///
+/// This is the size of the prover's buffers that are alive together once the
+/// check polynomial has been committed: the coefficients and evaluations of
+/// each register group of the rv32im circuit and of the check polynomial, the
+/// combined polynomials that are mixed for FRI, and the nodes of the Merkle
+/// tree being built. In low-memory mode, the peak is lower, as the
+/// evaluations are only held while the check polynomial is evaluated.
+pub fn estimate_segment_memory(po2: u32) -> usize {
+    let size = 1 << po2;
+    let domain = size * INV_RATE;
+    let taps = CIRCUIT.get_taps();
+    let check_size = INV_RATE * ExtElem::EXT_SIZE;
+    let columns = (0..taps.num_groups())
+        .map(|group| taps.group_size(group))
+        .sum::<usize>()
+        + check_size;
+    // The combos, along with the final polynomial that they are summed into.
+    let combos = taps.combos_size() + 2;
+    columns * (size + domain) * std::mem::size_of::<Elem>()
+        + combos * size * std::mem::size_of::<ExtElem>()
+        + 2 * domain * std::mem::size_of::<Digest>()
+}
+
+/// A [ProverServer] that proves the segments of a [Session] concurrently.
+///
+/// Each worker thread constructs its own [ProverServer] from the
+/// [ProverOpts] given at construction. Segments are resolved and dispatched
+/// to workers in order, and the resulting [SegmentReceipt]s are collected in
+/// segment order so that the produced
+/// [CompositeReceipt](crate::CompositeReceipt) has the same layout and claim
+/// as one produced by proving each segment sequentially.
+///
+/// The [SessionEvents](crate::SessionEvents) hooks of the [Session] are run
+/// on the calling thread: `on_pre_prove_segment` is fired in segment order as
+/// each segment is dispatched and `on_post_prove_segment` is fired in segment
+/// order as the receipts are collected. The phases of each segment are
+/// reported to the [ProveHandle] from the worker that proves it.
+///
+/// A [VerifierContext] cannot be shared between threads, so each worker
+/// checks its receipts against the default hash suites and the
+/// [VerifierContext::min_security_bits] of the caller's context. The
+/// [CompositeReceipt](crate::CompositeReceipt) of all of them is then verified
+/// against the caller's context once.
+pub struct ParallelProverImpl {
+    opts: ProverOpts,
+    parallel: ParallelOpts,
+    prover: Rc<dyn ProverServer>,
+}
+
+impl ParallelProverImpl {
+    /// Construct a [ParallelProverImpl] with the given [ProverOpts] and
+    /// [ParallelOpts].
+    ///
+    /// The [ProverOpts::parallel] field of `opts` is ignored.
+    pub fn new(opts: &ProverOpts, parallel: &ParallelOpts) -> Result<Self> {
+        if parallel.max_workers == 0 {
+            bail!("ParallelOpts::max_workers must be at least 1");
+        }
+        let opts = ProverOpts {
+            parallel: None,
+            ..opts.clone()
+        };
+        Ok(Self {
+            prover: get_segment_prover_server(&opts)?,
+            opts,
+            parallel: parallel.clone(),
+        })
+    }
+
+    fn prove_segments(
+        &self,
+        ctx: &VerifierContext,
+        session: &Session,
+        handle: &ProveHandle,
+    ) -> Result<Vec<SegmentReceipt>> {
+        let (job_tx, job_rx) = mpsc::channel::<(usize, Segment)>();
+        let (result_tx, result_rx) = mpsc::channel::<(usize, Segment, Result<SegmentReceipt>)>();
+        let job_rx = Mutex::new(job_rx);
+        let num_workers = self.parallel.max_workers.min(session.segments.len());
+
+        thread::scope(|scope| {
+            for _ in 0..num_workers {
+                let result_tx = result_tx.clone();
+                let job_rx = &job_rx;
+                let opts = &self.opts;
+                let min_security_bits = ctx.min_security_bits;
+                scope.spawn(move || worker(opts, min_security_bits, handle, job_rx, result_tx));
+            }
+            drop(result_tx);
+
+            let result = self.schedule(session, job_tx, &result_rx);
+            // Dropping the receiver lets any worker that is still busy exit
+            // as soon as it finishes its current segment.
+            drop(result_rx);
+            result
+        })
+    }
+
+    fn schedule(
+        &self,
+        session: &Session,
+        job_tx: mpsc::Sender<(usize, Segment)>,
+        result_rx: &mpsc::Receiver<(usize, Segment, Result<SegmentReceipt>)>,
+    ) -> Result<Vec<SegmentReceipt>> {
+        let num_segments = session.segments.len();
+        let mut receipts = Vec::with_capacity(num_segments);
+        let mut completed = BTreeMap::new();
+        let mut staged: Option<Segment> = None;
+        let mut next_dispatch = 0;
+        let mut in_flight = 0;
+        let mut in_flight_memory = 0;
+
+        while receipts.len() < num_segments {
+            while next_dispatch < num_segments && in_flight < self.parallel.max_workers {
+                let segment = match staged.take() {
+                    Some(segment) => segment,
+                    None => session.segments[next_dispatch].resolve()?,
+                };
+                let memory = estimate_segment_memory(segment.po2);
+                if let Some(limit) = self.parallel.memory_limit {
+                    if in_flight > 0 && in_flight_memory + memory > limit {
+                        staged = Some(segment);
+                        break;
+                    }
+                }
+                for hook in &session.hooks {
+                    hook.on_pre_prove_segment(&segment);
+                }
+                tracing::debug!(
+                    "dispatch segment[{next_dispatch}]: po2: {}, in_flight: {in_flight}",
+                    segment.po2
+                );
+                job_tx
+                    .send((next_dispatch, segment))
+                    .map_err(|_| anyhow!("all prover workers have exited"))?;
+                next_dispatch += 1;
+                in_flight += 1;
+                in_flight_memory += memory;
+            }
+
+            let (idx, segment, result) = result_rx
+                .recv()
+                .map_err(|_| anyhow!("all prover workers have exited"))?;
+            in_flight -= 1;
+            in_flight_memory -= estimate_segment_memory(segment.po2);
+            completed.insert(idx, (segment, result?));
+
+            while let Some((segment, receipt)) = completed.remove(&receipts.len()) {
+                for hook in &session.hooks {
+                    hook.on_post_prove_segment(&segment);
+                }
+                receipts.push(receipt);
+            }
+        }
+
+        Ok(receipts)
+    }
+}
+
+fn worker(
+    opts: &ProverOpts,
+    min_security_bits: usize,
+    handle: &ProveHandle,
+    job_rx: &Mutex<mpsc::Receiver<(usize, Segment)>>,
+    result_tx: mpsc::Sender<(usize, Segment, Result<SegmentReceipt>)>,
+) {
+    let prover = get_segment_prover_server(opts);
+    let ctx = VerifierContext {
+        min_security_bits,
+        ..Default::default()
+    };
+    loop {
+        let job = job_rx.lock().unwrap().recv();
+        let Ok((idx, segment)) = job else {
+            return;
+        };
+        let result = match &prover {
+            Ok(prover) => prover.prove_segment_with_handle(&ctx, &segment, handle),
+            Err(err) => Err(anyhow!("failed to construct prover: {err}")),
+        };
+        if result_tx.send((idx, segment, result)).is_err() {
+            return;
+        }
+    }
+}
+
+impl ProverServer for ParallelProverImpl {
+    fn prove_session(
+        &self,
+        ctx: &VerifierContext,
+        session: &Session,
+        handle: &ProveHandle,
+    ) -> Result<Receipt> {
+        tracing::info!(
+            "prove_session: parallel, workers = {}, exit_code = {:?}, journal = {:?}",
+            self.parallel.max_workers,
+            session.exit_code,
+            session.journal.as_ref().map(|x| hex::encode(x))
+        );
+        let segments = self.prove_segments(ctx, session, handle)?;
+        compose_session_receipt(ctx, session, segments)
+    }
+
+    fn prove_segment(&self, ctx: &VerifierContext, segment: &Segment) -> Result<SegmentReceipt> {
+        self.prover.prove_segment(ctx, segment)
+    }
+
+    fn prove_segment_with_handle(
+        &self,
+        ctx: &VerifierContext,
+        segment: &Segment,
+        handle: &ProveHandle,
+    ) -> Result<SegmentReceipt> {
+        self.prover.prove_segment_with_handle(ctx, segment, handle)
+    }
+
+    fn get_peak_memory_usage(&self) -> usize {
+        self.prover.get_peak_memory_usage()
+    }
+
+    fn lift(&self, receipt: &SegmentReceipt) -> Result<SuccinctReceipt> {
+        self.prover.lift(receipt)
+    }
+
+    fn join(&self, a: &SuccinctReceipt, b: &SuccinctReceipt) -> Result<SuccinctReceipt> {
+        self.prover.join(a, b)
+    }
+
+    fn resolve(
+        &self,
+        conditional: &SuccinctReceipt,
+        corroborating: &SuccinctReceipt,
+    ) -> Result<SuccinctReceipt> {
+        self.prover.resolve(conditional, corroborating)
+    }
+
+    fn identity_p254(&self, a: &SuccinctReceipt) -> Result<SuccinctReceipt> {
+        self.prover.identity_p254(a)
+    }
+}
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::{bail, Result};
+use anyhow::{bail, ensure, Result};
 use risc0_circuit_rv32im::{
     layout::{OutBuffer, LAYOUT},
     REGISTER_GROUP_ACCUM, REGISTER_GROUP_CODE, REGISTER_GROUP_DATA,
@@ -22,18 +22,21 @@ use risc0_zkp::{
     adapter::TapsProvider,
     hal::{CircuitHal, Hal},
     layout::Buffer,
+    params::FriParams,
     prove::adapter::ProveAdapter,
 };
 
-use super::{exec::MachineContext, HalPair, ProverServer};
+use super::{estimate_segment_memory, exec::MachineContext, HalPair, ProverServer};
 use crate::{
     host::{
-        receipt::{CompositeReceipt, InnerReceipt, SegmentReceipt, SuccinctReceipt},
-        recursion::{identity_p254, join, lift},
+        receipt::{
+            AssumptionReceipt, CompositeReceipt, InnerReceipt, SegmentReceipt, SuccinctReceipt,
+        },
+        recursion::{identity_p254, join, lift, resolve},
         CIRCUIT,
     },
     sha::Digestible,
-    Loader, Receipt, Segment, Session, VerifierContext,
+    Loader, ProveHandle, ProvePhase, Receipt, Segment, Session, VerifierContext,
 };
 
 /// An implementation of a Prover that runs locally.
@@ -44,6 +47,7 @@ where
 {
     name: String,
     hal_pair: HalPair<H, C>,
+    fri_params: FriParams,
 }
 
 impl<H, C> ProverImpl<H, C>
@@ -56,8 +60,15 @@ where
         Self {
             name: name.to_string(),
             hal_pair,
+            fri_params: FriParams::DEFAULT,
         }
     }
+
+    /// Prove segments with the given [FriParams] instead of the default.
+    pub fn with_fri_params(mut self, fri_params: FriParams) -> Self {
+        self.fri_params = fri_params;
+        self
+    }
 }
 
 impl<H, C> ProverServer for ProverImpl<H, C>
@@ -65,7 +76,12 @@ where
     H: Hal<Field = BabyBear, Elem = Elem, ExtElem = ExtElem>,
     C: CircuitHal<H>,
 {
-    fn prove_session(&self, ctx: &VerifierContext, session: &Session) -> Result<Receipt> {
+    fn prove_session(
+        &self,
+        ctx: &VerifierContext,
+        session: &Session,
+        handle: &ProveHandle,
+    ) -> Result<Receipt> {
         tracing::info!(
             "prove_session: {}, exit_code = {:?}, journal = {:?}",
             self.name,
@@ -78,38 +94,24 @@ where
             for hook in &session.hooks {
                 hook.on_pre_prove_segment(&segment);
             }
-            segments.push(self.prove_segment(ctx, &segment)?);
+            segments.push(self.prove_segment_with_handle(ctx, &segment, handle)?);
             for hook in &session.hooks {
                 hook.on_post_prove_segment(&segment);
             }
         }
-        // TODO(#982): Support unresolved assumptions here.
-        let inner = InnerReceipt::Composite(CompositeReceipt {
-            segments,
-            assumptions: session
-                .assumptions
-                .iter()
-                .map(|a| Ok(a.as_receipt()?.inner.clone()))
-                .collect::<Result<Vec<_>>>()?,
-            journal_digest: session.journal.as_ref().map(|journal| journal.digest()),
-        });
-        let receipt = Receipt::new(inner, session.journal.clone().unwrap_or_default().bytes);
-
-        receipt.verify_integrity_with_context(ctx)?;
-        if receipt.get_claim()?.digest() != session.get_claim()?.digest() {
-            tracing::debug!("receipt and session claim do not match");
-            tracing::debug!("receipt claim: {:#?}", receipt.get_claim()?);
-            tracing::debug!("session claim: {:#?}", session.get_claim()?);
-            bail!(
-                "session and receipt claim do not match: session {}, receipt {}",
-                hex::encode(&session.get_claim()?.digest()),
-                hex::encode(&receipt.get_claim()?.digest())
-            );
-        }
-        Ok(receipt)
+        compose_session_receipt(ctx, session, segments)
     }
 
     fn prove_segment(&self, ctx: &VerifierContext, segment: &Segment) -> Result<SegmentReceipt> {
+        self.prove_segment_with_handle(ctx, segment, &ProveHandle::default())
+    }
+
+    fn prove_segment_with_handle(
+        &self,
+        ctx: &VerifierContext,
+        segment: &Segment,
+        handle: &ProveHandle,
+    ) -> Result<SegmentReceipt> {
         use risc0_zkp::prove::executor::Executor;
 
         tracing::debug!(
@@ -120,7 +122,17 @@ where
         );
         let (hal, circuit_hal) = (self.hal_pair.hal.as_ref(), &self.hal_pair.circuit_hal);
         let hashfn = &hal.get_hash_suite().name;
+        if let Some(budget) = hal.get_memory_budget() {
+            let estimate = estimate_segment_memory(segment.po2);
+            ensure!(
+                estimate <= budget,
+                "Segment needs ~{estimate} bytes, over the memory budget of {budget} bytes"
+            );
+        }
 
+        handle.enter(ProvePhase::Witness {
+            segment: segment.index,
+        })?;
         let io = segment.prepare_globals()?;
         let machine = MachineContext::new(segment);
         let po2 = segment.po2 as usize;
@@ -131,39 +143,54 @@ where
         executor.finalize();
 
         let mut adapter = ProveAdapter::new(&mut executor);
-        let mut prover = risc0_zkp::prove::Prover::new(hal, CIRCUIT.get_taps());
+        let mut prover =
+            risc0_zkp::prove::Prover::new_with_params(hal, CIRCUIT.get_taps(), self.fri_params);
 
         adapter.execute(prover.iop());
 
         prover.set_po2(adapter.po2() as usize);
 
-        prover.commit_group(
+        handle.enter(ProvePhase::Commit {
+            segment: segment.index,
+        })?;
+        prover.try_commit_group(
             REGISTER_GROUP_CODE,
             hal.copy_from_elem("code", &adapter.get_code().as_slice()),
-        );
-        prover.commit_group(
+        )?;
+        prover.try_commit_group(
             REGISTER_GROUP_DATA,
             hal.copy_from_elem("data", &adapter.get_data().as_slice()),
-        );
+        )?;
         adapter.accumulate(prover.iop());
-        prover.commit_group(
+        prover.try_commit_group(
             REGISTER_GROUP_ACCUM,
             hal.copy_from_elem("accum", &adapter.get_accum().as_slice()),
-        );
+        )?;
 
         let mix = hal.copy_from_elem("mix", &adapter.get_mix().as_slice());
-        let out_slice = &adapter.get_io().as_slice();
-
-        tracing::debug!("Globals: {:?}", OutBuffer(out_slice).tree(&LAYOUT));
+        {
+            let out_slice = &adapter.get_io().as_slice();
+            tracing::debug!("Globals: {:?}", OutBuffer(out_slice).tree(&LAYOUT));
+        }
         let out = hal.copy_from_elem("out", &adapter.get_io().as_slice());
 
-        let seal = prover.finalize(&[&mix, &out], circuit_hal.as_ref());
+        // Every group of the witness has been committed, so release the
+        // executor's copy of it before the prover allocates the check
+        // polynomial.
+        drop(adapter);
+        drop(executor);
+
+        handle.enter(ProvePhase::Fri {
+            segment: segment.index,
+        })?;
+        let seal = prover.try_finalize(&[&mix, &out], circuit_hal.as_ref())?;
 
         let receipt = SegmentReceipt {
             seal,
             index: segment.index,
             hashfn: hashfn.clone(),
             claim: segment.get_claim()?,
+            fri_params: self.fri_params,
         };
         receipt.verify_integrity_with_context(ctx)?;
 
@@ -182,7 +209,52 @@ where
         join(a, b)
     }
 
+    fn resolve(
+        &self,
+        conditional: &SuccinctReceipt,
+        corroborating: &SuccinctReceipt,
+    ) -> Result<SuccinctReceipt> {
+        resolve(conditional, corroborating)
+    }
+
     fn identity_p254(&self, a: &SuccinctReceipt) -> Result<SuccinctReceipt> {
         identity_p254(a)
     }
 }
+
+/// Assemble the [CompositeReceipt] for a [Session] from its proven segments.
+///
+/// The `segments` must be ordered by segment index. The resulting [Receipt]
+/// is verified against `ctx` and checked to match the claim of the [Session].
+pub(crate) fn compose_session_receipt(
+    ctx: &VerifierContext,
+    session: &Session,
+    segments: Vec<SegmentReceipt>,
+) -> Result<Receipt> {
+    // Unresolved assumptions are carried over as claims, making the receipt conditional.
+    let inner = InnerReceipt::Composite(CompositeReceipt {
+        segments,
+        assumptions: session
+            .assumptions
+            .iter()
+            .cloned()
+            .map(AssumptionReceipt::from)
+            .collect(),
+        journal_digest: session.journal.as_ref().map(|journal| journal.digest()),
+    });
+    let receipt = Receipt::new(inner, session.journal.clone().unwrap_or_default().bytes);
+
+    // The claim is matched against the session below, which pins the assumptions it carries.
+    receipt.verify_conditional_integrity_with_context(ctx)?;
+    if receipt.get_claim()?.digest() != session.get_claim()?.digest() {
+        tracing::debug!("receipt and session claim do not match");
+        tracing::debug!("receipt claim: {:#?}", receipt.get_claim()?);
+        tracing::debug!("session claim: {:#?}", session.get_claim()?);
+        bail!(
+            "session and receipt claim do not match: session {}, receipt {}",
+            hex::encode(&session.get_claim()?.digest()),
+            hex::encode(&receipt.get_claim()?.digest())
+        );
+    }
+    Ok(receipt)
+}
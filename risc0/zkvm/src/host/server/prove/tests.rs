@@ -15,8 +15,9 @@
 use std::rc::Rc;
 
 use anyhow::Result;
-use risc0_circuit_rv32im::cpu::CpuCircuitHal;
+use risc0_circuit_rv32im::{cpu::CpuCircuitHal, CircuitImpl};
 use risc0_zkp::{
+    adapter::CircuitInfo,
     core::{digest::Digest, hash::blake2b::Blake2bCpuHashSuite},
     hal::cpu::CpuHal,
     verify::VerificationError,
@@ -26,25 +27,33 @@ use risc0_zkvm_platform::{memory, WORD_SIZE};
 use serial_test::serial;
 use test_log::test;
 
-use super::{get_prover_server, HalPair, ProverImpl};
+use super::{
+    estimate, estimate_segment_memory, get_parallel_prover_server, get_prover_server,
+    CalibrationSample, EstimateOpts, HalPair, ProverImpl, ProvingModel,
+};
 use crate::{
     host::{server::testutils, CIRCUIT},
     serde::{from_slice, to_vec},
-    ExecutorEnv, ExecutorImpl, ExitCode, ProverOpts, ProverServer, Receipt, Session,
-    VerifierContext,
+    ExecutorEnv, ExecutorImpl, ExitCode, FriParams, ParallelOpts, ProveHandle, ProvePhase,
+    ProverOpts, ProverServer, Receipt, Session, VerifierContext,
 };
 
 fn prover_opts_fast() -> ProverOpts {
     ProverOpts {
         hashfn: "sha-256".to_string(),
         prove_guest_errors: false,
+        ..Default::default()
     }
 }
 
 fn prove_session_fast(session: &Session) -> Receipt {
     let prover = get_prover_server(&prover_opts_fast()).unwrap();
     prover
-        .prove_session(&VerifierContext::default(), session)
+        .prove_session(
+            &VerifierContext::default(),
+            session,
+            &ProveHandle::default(),
+        )
         .unwrap()
 }
 
@@ -57,6 +66,7 @@ fn prove_nothing(hashfn: &str) -> Result<Receipt> {
     let opts = ProverOpts {
         hashfn: hashfn.to_string(),
         prove_guest_errors: false,
+        ..Default::default()
     };
     get_prover_server(&opts).unwrap().prove(env, MULTI_TEST_ELF)
 }
@@ -67,6 +77,101 @@ fn hashfn_poseidon() {
     prove_nothing("poseidon").unwrap();
 }
 
+#[test]
+#[cfg_attr(feature = "cuda", serial)]
+fn hashfn_poseidon2() {
+    let receipt = prove_nothing("poseidon2").unwrap();
+    let segments = &receipt.inner.composite().unwrap().segments;
+    assert!(segments.iter().all(|segment| segment.hashfn == "poseidon2"));
+    receipt.verify(MULTI_TEST_ID).unwrap();
+
+    // The receipt must verify with the CPU verifier, whichever HAL proved it.
+    let light: risc0_verifier::Receipt = from_slice(&to_vec(&receipt).unwrap()).unwrap();
+    light.verify(MULTI_TEST_ID).unwrap();
+}
+
+#[test]
+#[cfg_attr(feature = "cuda", serial)]
+fn fri_params() {
+    let params = FriParams::for_security_bits(60).unwrap();
+    let opts = ProverOpts {
+        fri_params: params,
+        ..prover_opts_fast()
+    };
+    let ctx = VerifierContext {
+        min_security_bits: 60,
+        ..Default::default()
+    };
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::DoNothing)
+        .unwrap()
+        .build()
+        .unwrap();
+    let receipt = get_prover_server(&opts)
+        .unwrap()
+        .prove_with_ctx(env, &ctx, MULTI_TEST_ELF, &ProveHandle::default())
+        .unwrap();
+    let segments = &receipt.inner.composite().unwrap().segments;
+    assert!(segments.iter().all(|segment| segment.fri_params == params));
+    receipt.verify_with_context(&ctx, MULTI_TEST_ID).unwrap();
+
+    // The default context requires more security than these parameters give.
+    assert_eq!(
+        receipt.verify(MULTI_TEST_ID).unwrap_err(),
+        VerificationError::InvalidFriParams
+    );
+
+    // The seal must be verified with the parameters it was proven with.
+    let mut segment = segments[0].clone();
+    segment.fri_params = FriParams::DEFAULT;
+    assert!(segment.verify_integrity_with_context(&ctx).is_err());
+
+    let opts = ProverOpts {
+        fri_params: FriParams::new(0),
+        ..prover_opts_fast()
+    };
+    assert!(get_prover_server(&opts).is_err());
+}
+
+#[test]
+#[cfg(not(any(feature = "cuda", feature = "metal")))]
+fn memory_budget() {
+    let env = || {
+        ExecutorEnv::builder()
+            .write(&MultiTestSpec::DoNothing)
+            .unwrap()
+            .build()
+            .unwrap()
+    };
+
+    // The estimate of a segment is enough to prove it in low-memory mode, and
+    // bounds the peak of the buffers measured while proving it.
+    let estimate = estimate_segment_memory(13);
+    let opts = ProverOpts {
+        memory_budget: Some(estimate),
+        ..prover_opts_fast()
+    };
+    let prover = get_prover_server(&opts).unwrap();
+    let receipt = prover.prove(env(), MULTI_TEST_ELF).unwrap();
+    receipt.verify(MULTI_TEST_ID).unwrap();
+    let peak = prover.get_peak_memory_usage();
+    assert!(
+        peak <= estimate && peak > estimate / 2,
+        "peak: {peak}, estimate: {estimate}"
+    );
+
+    // A segment that does not fit in the budget is rejected before proving.
+    let opts = ProverOpts {
+        memory_budget: Some(estimate_segment_memory(13) - 1),
+        ..prover_opts_fast()
+    };
+    let err = get_prover_server(&opts)
+        .unwrap()
+        .prove(env(), MULTI_TEST_ELF)
+        .unwrap_err();
+    assert!(err.to_string().contains("memory budget"));
+}
+
 #[test]
 fn hashfn_blake2b() {
     let hal_pair = HalPair {
@@ -106,6 +211,64 @@ fn check_image_id() {
     );
 }
 
+#[test]
+#[cfg_attr(feature = "cuda", serial)]
+fn malformed_seal() {
+    let receipt = prove_nothing("sha-256").unwrap();
+    let segment = &receipt.inner.composite().unwrap().segments[0];
+    let seal = &segment.seal;
+
+    let mut seals = vec![
+        vec![],
+        seal[..1].to_vec(),
+        seal[..CircuitImpl::OUTPUT_SIZE].to_vec(),
+        seal[..seal.len() / 2].to_vec(),
+        seal[..seal.len() - 1].to_vec(),
+        [seal.as_slice(), &[0]].concat(),
+    ];
+    // A global that is not a canonical field element.
+    let mut unreduced = seal.clone();
+    unreduced[0] = u32::MAX;
+    seals.push(unreduced);
+    // A po2 that is out of range.
+    let mut po2 = seal.clone();
+    po2[CircuitImpl::OUTPUT_SIZE] = u32::MAX;
+    seals.push(po2);
+
+    let ctx = VerifierContext::default();
+    for seal in seals {
+        let mut malformed = segment.clone();
+        malformed.seal = seal;
+        assert!(malformed.verify_integrity_with_context(&ctx).is_err());
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "cuda", serial)]
+fn light_verifier() {
+    use risc0_zkvm_methods::{HELLO_COMMIT_ELF, HELLO_COMMIT_ID};
+
+    let env = ExecutorEnv::builder().build().unwrap();
+    let receipt = get_prover_server(&prover_opts_fast())
+        .unwrap()
+        .prove(env, HELLO_COMMIT_ELF)
+        .unwrap();
+
+    // The receipt of risc0-verifier has the same serialized representation.
+    let mut light: risc0_verifier::Receipt = from_slice(&to_vec(&receipt).unwrap()).unwrap();
+    light.verify(HELLO_COMMIT_ID).unwrap();
+    assert_eq!(
+        light.verify(MULTI_TEST_ID).unwrap_err(),
+        VerificationError::ImageVerificationError
+    );
+
+    light.journal.bytes.push(0);
+    assert_eq!(
+        light.verify(HELLO_COMMIT_ID).unwrap_err(),
+        VerificationError::JournalDigestMismatch
+    );
+}
+
 #[test]
 #[serial]
 fn sha_basics() {
@@ -279,10 +442,268 @@ fn session_events() {
     assert_eq!(on_post_prove_segment_flag.take(), true);
 }
 
+#[test]
+fn prove_handle() {
+    use std::sync::{Arc, Mutex};
+
+    let env = || {
+        ExecutorEnv::builder()
+            .write(&MultiTestSpec::DoNothing)
+            .unwrap()
+            .build()
+            .unwrap()
+    };
+    let prover = get_prover_server(&prover_opts_fast()).unwrap();
+    let ctx = VerifierContext::default();
+
+    let phases = Arc::new(Mutex::new(Vec::new()));
+    let handle = ProveHandle::new().with_progress({
+        let phases = phases.clone();
+        move |phase| phases.lock().unwrap().push(*phase)
+    });
+    prover
+        .prove_with_ctx(env(), &ctx, MULTI_TEST_ELF, &handle)
+        .unwrap();
+    assert_eq!(
+        phases.lock().unwrap().as_slice(),
+        &[
+            ProvePhase::Execute,
+            ProvePhase::Witness { segment: 0 },
+            ProvePhase::Commit { segment: 0 },
+            ProvePhase::Fri { segment: 0 },
+        ]
+    );
+
+    // Cancelling from the callback stops proving before the reported phase.
+    let handle = ProveHandle::new();
+    let canceller = handle.clone();
+    let handle = handle.with_progress(move |phase| {
+        if let ProvePhase::Commit { .. } = phase {
+            canceller.cancel();
+        }
+    });
+    let err = prover
+        .prove_with_ctx(env(), &ctx, MULTI_TEST_ELF, &handle)
+        .unwrap_err();
+    assert!(err.to_string().contains("cancelled"));
+    assert!(handle.is_cancelled());
+}
+
+#[test]
+#[cfg_attr(feature = "cuda", serial)]
+fn parallel_prove_session() {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::{sha::Digestible, Segment, SessionEvents};
+
+    struct Recorder {
+        events: Rc<RefCell<Vec<(&'static str, u32)>>>,
+    }
+
+    impl SessionEvents for Recorder {
+        fn on_pre_prove_segment(&self, segment: &Segment) {
+            self.events.borrow_mut().push(("pre", segment.index));
+        }
+
+        fn on_post_prove_segment(&self, segment: &Segment) {
+            self.events.borrow_mut().push(("post", segment.index));
+        }
+    }
+
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1 << 15 })
+        .unwrap()
+        .segment_limit_po2(13)
+        .build()
+        .unwrap();
+    let mut exec = ExecutorImpl::from_elf(env, MULTI_TEST_ELF).unwrap();
+    let mut session = exec.run().unwrap();
+    let num_segments = session.segments.len() as u32;
+    assert!(num_segments > 1);
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    session.add_hook(Recorder {
+        events: events.clone(),
+    });
+
+    let parallel = ParallelOpts {
+        max_workers: 2,
+        memory_limit: Some(estimate_segment_memory(13)),
+    };
+    let prover = get_parallel_prover_server(&prover_opts_fast(), &parallel).unwrap();
+    let receipt = prover
+        .prove_session(
+            &VerifierContext::default(),
+            &session,
+            &ProveHandle::default(),
+        )
+        .unwrap();
+
+    let segments = &receipt.inner.composite().unwrap().segments;
+    let indices: Vec<u32> = segments.iter().map(|x| x.index).collect();
+    assert_eq!(indices, (0..num_segments).collect::<Vec<_>>());
+    assert_eq!(
+        receipt.get_claim().unwrap().digest(),
+        session.get_claim().unwrap().digest()
+    );
+
+    let events = events.take();
+    let pre: Vec<u32> = events
+        .iter()
+        .filter(|(kind, _)| *kind == "pre")
+        .map(|(_, idx)| *idx)
+        .collect();
+    let post: Vec<u32> = events
+        .iter()
+        .filter(|(kind, _)| *kind == "post")
+        .map(|(_, idx)| *idx)
+        .collect();
+    assert_eq!(pre, indices);
+    assert_eq!(post, indices);
+}
+
+#[test]
+#[cfg_attr(feature = "cuda", serial)]
+fn parallel_prove_with_ctx() {
+    // The parallel prover is selected through the ProverOpts, and must accept
+    // segments that only verify with the caller's context.
+    let params = FriParams::for_security_bits(60).unwrap();
+    let opts = ProverOpts {
+        fri_params: params,
+        parallel: Some(ParallelOpts {
+            max_workers: 2,
+            memory_limit: None,
+        }),
+        ..prover_opts_fast()
+    };
+    let ctx = VerifierContext {
+        min_security_bits: 60,
+        ..Default::default()
+    };
+    let env = ExecutorEnv::builder()
+        .write(&MultiTestSpec::BusyLoop { cycles: 1 << 15 })
+        .unwrap()
+        .segment_limit_po2(13)
+        .build()
+        .unwrap();
+    let receipt = get_prover_server(&opts)
+        .unwrap()
+        .prove_with_ctx(env, &ctx, MULTI_TEST_ELF, &ProveHandle::default())
+        .unwrap();
+    let segments = &receipt.inner.composite().unwrap().segments;
+    assert!(segments.len() > 1);
+    assert!(segments.iter().all(|segment| segment.fri_params == params));
+    receipt.verify_with_context(&ctx, MULTI_TEST_ID).unwrap();
+}
+
 // These tests come from:
 // https://github.com/riscv-software-src/riscv-tests
 // They were built using the toolchain from:
 // https://github.com/risc0/toolchain/releases/tag/2022.03.25
+#[test]
+fn estimate_matches_execution() {
+    let spec = MultiTestSpec::BusyLoop { cycles: 1 << 16 };
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(14)
+        .build()
+        .unwrap();
+    let session = ExecutorImpl::from_elf(env, MULTI_TEST_ELF)
+        .unwrap()
+        .run()
+        .unwrap();
+    let (total_cycles, user_cycles) = session.get_cycles().unwrap();
+
+    let model = ProvingModel {
+        hashfn: "poseidon".to_string(),
+        hal: "cpu".to_string(),
+        segment_secs: 0.5,
+        cycle_secs: 1e-4,
+        seal_bytes: 100_000.0,
+        seal_bytes_per_po2: 5_000.0,
+    };
+    let mut opts = EstimateOpts {
+        cycle_limit: None,
+        expected_user_cycles: None,
+        model: Some(model.clone()),
+    };
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(14)
+        .build()
+        .unwrap();
+    let full = estimate(env, MULTI_TEST_ELF, &opts).unwrap();
+    assert!(full.complete);
+    assert_eq!(full.segments, session.segments.len() as u64);
+    assert_eq!(full.total_cycles, total_cycles);
+    assert_eq!(full.user_cycles, user_cycles);
+    assert!(full.proof_bytes.unwrap() <= full.segments * model.segment_seal_bytes(14));
+    assert!(full.proving_time.unwrap() <= model.segment_time(14) * full.segments as u32);
+
+    // Stopping early and extrapolating gives a similar estimate.
+    opts.cycle_limit = Some(total_cycles / 2);
+    opts.expected_user_cycles = Some(user_cycles);
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(14)
+        .build()
+        .unwrap();
+    let partial = estimate(env, MULTI_TEST_ELF, &opts).unwrap();
+    assert!(!partial.complete);
+    assert_eq!(partial.user_cycles, user_cycles);
+    assert!(partial.segments >= full.segments / 2);
+    assert!(partial.segments <= full.segments * 2);
+
+    // Without a model, only the segments and cycles are estimated.
+    opts.model = None;
+    let env = ExecutorEnv::builder()
+        .write(&spec)
+        .unwrap()
+        .segment_limit_po2(14)
+        .build()
+        .unwrap();
+    let partial = estimate(env, MULTI_TEST_ELF, &opts).unwrap();
+    assert_eq!(partial.user_cycles, user_cycles);
+    assert_eq!(partial.proof_bytes, None);
+    assert_eq!(partial.proving_time, None);
+}
+
+#[test]
+fn calibrate_proving_model() {
+    let expected = ProvingModel {
+        hashfn: "poseidon".to_string(),
+        hal: "cpu".to_string(),
+        segment_secs: 0.25,
+        cycle_secs: 1e-5,
+        seal_bytes: 90_000.0,
+        seal_bytes_per_po2: 6_000.0,
+    };
+    let samples: Vec<_> = [vec![16], vec![20], vec![20, 20, 18], vec![14, 14]]
+        .into_iter()
+        .map(|segment_po2s| CalibrationSample {
+            proving_time: segment_po2s
+                .iter()
+                .map(|po2| expected.segment_time(*po2))
+                .sum(),
+            seal_bytes: segment_po2s
+                .iter()
+                .map(|po2| expected.segment_seal_bytes(*po2))
+                .sum(),
+            segment_po2s,
+        })
+        .collect();
+    let model = ProvingModel::calibrate("poseidon", "cpu", &samples).unwrap();
+    assert!((model.segment_secs - expected.segment_secs).abs() < 1e-6);
+    assert!((model.cycle_secs - expected.cycle_secs).abs() < 1e-9);
+    assert!((model.seal_bytes - expected.seal_bytes).abs() < 1.0);
+    assert!((model.seal_bytes_per_po2 - expected.seal_bytes_per_po2).abs() < 1.0);
+
+    assert!(ProvingModel::calibrate("poseidon", "cpu", &samples[..1]).is_err());
+}
+
 mod riscv {
     use super::prove_session_fast;
     use crate::{ExecutorEnv, ExecutorImpl};
@@ -471,6 +892,7 @@ mod sys_verify {
         let opts = ProverOpts {
             hashfn: "sha-256".to_string(),
             prove_guest_errors: true,
+            ..Default::default()
         };
 
         let env = ExecutorEnvBuilder::default()
@@ -497,6 +919,7 @@ mod sys_verify {
         let opts = ProverOpts {
             hashfn: "sha-256".to_string(),
             prove_guest_errors: true,
+            ..Default::default()
         };
 
         let env = ExecutorEnvBuilder::default()
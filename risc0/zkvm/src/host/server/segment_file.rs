@@ -0,0 +1,243 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A stable, compressed on-disk format for [Segment]s.
+//!
+//! A segment file starts with the magic bytes `R0SG` and a little-endian `u32`
+//! version, followed by a little-endian `u32` length and the bincode encoded
+//! [SegmentFileHeader] of that length. The rest of the file is the zlib
+//! compressed bincode encoding of the segment itself, which only holds the
+//! pages of the pre-image that the segment reads or writes.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use risc0_binfmt::{MemoryImage, Page, PageTableInfo, SystemState};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    exec::executor::SyscallRecord,
+    session::{PageFaults, Segment, SegmentRef},
+};
+use crate::{ExitCode, Output};
+
+/// Magic bytes at the start of every segment file.
+const SEGMENT_MAGIC: &[u8; 4] = b"R0SG";
+
+/// The version of the segment file format written by this crate.
+pub const SEGMENT_FILE_VERSION: u32 = 1;
+
+/// The length of the magic bytes, version and header length at the start of
+/// every segment file.
+const PREFIX_LEN: usize = 12;
+
+/// The largest [SegmentFileHeader] accepted when decoding, well above the
+/// size of any encoded header, so that a corrupt length cannot cause a large
+/// allocation.
+const MAX_HEADER_LEN: usize = 1024;
+
+/// A summary of a [Segment], stored uncompressed at the start of a segment
+/// file so that it can be read without decoding the whole segment.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SegmentFileHeader {
+    /// The index of the segment within its session.
+    pub index: u32,
+
+    /// The number of cycles of the segment in powers of 2.
+    pub po2: u32,
+
+    /// The number of user cycles of the segment.
+    pub cycles: u32,
+
+    /// The [ExitCode] of the segment.
+    pub exit_code: ExitCode,
+
+    /// The number of pre-image pages stored in the file.
+    pub num_pages: u32,
+}
+
+/// The compressed body of a segment file.
+#[derive(Serialize, Deserialize)]
+struct SegmentBody {
+    info: PageTableInfo,
+    pc: u32,
+    pages: BTreeMap<u32, Page>,
+    post_state: SystemState,
+    output: Option<Output>,
+    faults: PageFaults,
+    syscalls: Vec<SyscallRecord>,
+    split_insn: Option<u32>,
+}
+
+impl Segment {
+    /// Encode this segment into the versioned, compressed segment file format.
+    ///
+    /// Only the pages of the pre-image that are read or written by the
+    /// segment are kept, which is all that is needed to prove it.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let pages: BTreeMap<u32, Page> = self
+            .faults
+            .reads
+            .union(&self.faults.writes)
+            .filter_map(|page_idx| {
+                let page = self.pre_image.page(*page_idx)?;
+                Some((*page_idx, page.clone()))
+            })
+            .collect();
+        let header = SegmentFileHeader {
+            index: self.index,
+            po2: self.po2,
+            cycles: self.cycles,
+            exit_code: self.exit_code,
+            num_pages: pages.len() as u32,
+        };
+        let body = SegmentBody {
+            info: self.pre_image.info.clone(),
+            pc: self.pre_image.pc,
+            pages,
+            post_state: self.post_state.clone(),
+            output: self.output.clone(),
+            faults: self.faults.clone(),
+            syscalls: self.syscalls.clone(),
+            split_insn: self.split_insn,
+        };
+
+        let header = bincode::serialize(&header)?;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(SEGMENT_MAGIC);
+        bytes.extend_from_slice(&SEGMENT_FILE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        bytes.extend(header);
+        let mut encoder = ZlibEncoder::new(bytes, Compression::default());
+        bincode::serialize_into(&mut encoder, &body)?;
+        Ok(encoder.finish()?)
+    }
+
+    /// Decode a segment from the versioned, compressed segment file format.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let (header, body) = decode_header(bytes)?;
+        let body: SegmentBody = bincode::deserialize_from(ZlibDecoder::new(body))?;
+        ensure!(
+            body.pages.len() == header.num_pages as usize,
+            "segment file holds {} pages, expected {}",
+            body.pages.len(),
+            header.num_pages
+        );
+        let pre_image = MemoryImage::from_pages(body.info, body.pc, body.pages)?;
+        Ok(Segment::new(
+            Box::new(pre_image),
+            body.post_state,
+            body.output,
+            body.faults,
+            body.syscalls,
+            header.exit_code,
+            body.split_insn,
+            header.po2,
+            header.index,
+            header.cycles,
+        ))
+    }
+}
+
+/// Check the magic bytes and version at the start of a segment file, and
+/// return the length of its header.
+fn decode_prefix(bytes: &[u8]) -> Result<usize> {
+    ensure!(
+        bytes.len() >= PREFIX_LEN && &bytes[..4] == SEGMENT_MAGIC,
+        "not a segment file"
+    );
+    let version = u32::from_le_bytes(bytes[4..8].try_into()?);
+    if version != SEGMENT_FILE_VERSION {
+        bail!("unsupported segment file version: {version}, expected {SEGMENT_FILE_VERSION}");
+    }
+    let header_len = u32::from_le_bytes(bytes[8..PREFIX_LEN].try_into()?) as usize;
+    ensure!(
+        header_len <= MAX_HEADER_LEN,
+        "segment file header is too large: {header_len} bytes"
+    );
+    Ok(header_len)
+}
+
+/// Split a segment file into its header and its compressed body.
+fn decode_header(bytes: &[u8]) -> Result<(SegmentFileHeader, &[u8])> {
+    let header_end = PREFIX_LEN + decode_prefix(bytes)?;
+    let header = bytes
+        .get(PREFIX_LEN..header_end)
+        .context("truncated segment file header")?;
+    Ok((bincode::deserialize(header)?, &bytes[header_end..]))
+}
+
+/// An implementation of a [SegmentRef] that stores the segment in a file using
+/// the compressed segment file format.
+///
+/// Only the [SegmentFileHeader] is read when the reference is opened; the
+/// segment itself is read and decompressed when the reference is resolved.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CompressedSegmentRef {
+    path: PathBuf,
+    header: SegmentFileHeader,
+}
+
+#[typetag::serde]
+impl SegmentRef for CompressedSegmentRef {
+    fn resolve(&self) -> Result<Segment> {
+        let bytes = fs::read(&self.path)
+            .with_context(|| format!("failed to read segment from {}", self.path.display()))?;
+        Segment::decode(&bytes)
+    }
+}
+
+impl CompressedSegmentRef {
+    /// Construct a [CompressedSegmentRef] that stores `segment` in a file in
+    /// the directory at `path`.
+    pub fn new(segment: &Segment, path: &Path) -> Result<Self> {
+        let path = path.join(format!("{}.segment", segment.index));
+        fs::write(&path, segment.encode()?)
+            .with_context(|| format!("failed to write segment to {}", path.display()))?;
+        Self::open(path)
+    }
+
+    /// Open the segment file at `path`, reading only its header.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut prefix = [0_u8; PREFIX_LEN];
+        let mut file = File::open(&path)
+            .with_context(|| format!("failed to open segment file {}", path.display()))?;
+        file.read_exact(&mut prefix)
+            .context("truncated segment file")?;
+        let header_len = decode_prefix(&prefix)?;
+        let mut bytes = prefix.to_vec();
+        bytes.resize(PREFIX_LEN + header_len, 0);
+        file.read_exact(&mut bytes[PREFIX_LEN..])
+            .context("truncated segment file header")?;
+        let (header, _) = decode_header(&bytes)?;
+        Ok(Self { path, header })
+    }
+
+    /// The header of the referenced segment file.
+    pub fn header(&self) -> &SegmentFileHeader {
+        &self.header
+    }
+
+    /// The path of the referenced segment file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
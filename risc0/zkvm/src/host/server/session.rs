@@ -15,7 +15,7 @@
 //! This module defines [Session] and [Segment] which provides a way to share
 //! execution traces between the execution phase and the proving phase.
 
-use alloc::collections::BTreeSet;
+use alloc::collections::{BTreeMap, BTreeSet};
 use std::{
     borrow::Borrow,
     fs::File,
@@ -29,8 +29,8 @@ use risc0_zkvm_platform::WORD_SIZE;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    host::server::exec::executor::SyscallRecord, sha::Digest, Assumption, Assumptions, ExitCode,
-    Journal, Output, ReceiptClaim,
+    host::server::exec::executor::SyscallRecord, sha::Digest, Assumption, Assumptions, CycleRegion,
+    ExitCode, Journal, Output, ReceiptClaim,
 };
 
 #[derive(Clone, Default, Serialize, Deserialize, Debug)]
@@ -65,6 +65,9 @@ pub struct Session {
     /// The list of assumptions made by the guest and resolved by the host.
     pub assumptions: Vec<Assumption>,
 
+    /// The cycles spent in each cycle region opened by the guest, by name.
+    pub cycle_regions: BTreeMap<String, CycleRegion>,
+
     /// The hooks to be called during the proving phase.
     #[serde(skip)]
     pub hooks: Vec<Box<dyn SessionEvents>>,
@@ -113,6 +116,9 @@ pub struct Segment {
 }
 
 /// The Events of [Session]
+///
+/// See [ProveHandle](crate::ProveHandle) to follow each phase of proving and
+/// to cancel it.
 pub trait SessionEvents {
     /// Fired before the proving of a segment starts.
     #[allow(unused)]
@@ -131,6 +137,26 @@ impl Session {
         exit_code: ExitCode,
         post_image: MemoryImage,
         assumptions: Vec<Assumption>,
+    ) -> Self {
+        Self::new_with_cycle_regions(
+            segments,
+            journal,
+            exit_code,
+            post_image,
+            assumptions,
+            BTreeMap::new(),
+        )
+    }
+
+    /// Construct a new [Session] from its constituent components, including
+    /// the totals of the cycle regions entered by the guest.
+    pub fn new_with_cycle_regions(
+        segments: Vec<Box<dyn SegmentRef>>,
+        journal: Option<Vec<u8>>,
+        exit_code: ExitCode,
+        post_image: MemoryImage,
+        assumptions: Vec<Assumption>,
+        cycle_regions: BTreeMap<String, CycleRegion>,
     ) -> Self {
         Self {
             segments,
@@ -138,6 +164,7 @@ impl Session {
             exit_code,
             post_image,
             assumptions,
+            cycle_regions,
             hooks: Vec::new(),
         }
     }
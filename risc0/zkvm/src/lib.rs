@@ -25,7 +25,7 @@ mod fault_monitor;
 pub mod guest;
 #[cfg(not(target_os = "zkvm"))]
 mod host;
-mod receipt_claim;
+pub mod rpc;
 pub mod serde;
 pub mod sha;
 
@@ -42,8 +42,15 @@ pub use anyhow::Result;
 #[cfg(any(feature = "client", feature = "prove"))]
 pub use bytes::Bytes;
 pub use risc0_binfmt::SystemState;
+#[cfg(not(target_os = "zkvm"))]
+pub use risc0_binfmt::{MemoryImage, RegionProof};
+pub use risc0_verifier::{
+    Assumptions, ExitCode, InvalidExitCodeError, MaybePruned, Output, PrunedValueError,
+    ReceiptClaim,
+};
 pub use risc0_zkvm_platform::{declare_syscall, memory::GUEST_MAX_MEM, PAGE_SIZE};
 
+pub use self::fault_ids::{FAULT_CHECKER_ELF, FAULT_CHECKER_ID};
 #[cfg(feature = "fault-proof")]
 pub use self::fault_monitor::FaultCheckMonitor;
 #[cfg(all(not(target_os = "zkvm"), feature = "prove"))]
@@ -51,8 +58,17 @@ pub use self::host::{
     api::server::Server as ApiServer,
     client::prove::local::LocalProver,
     server::{
-        exec::executor::ExecutorImpl,
-        prove::{get_prover_server, loader::Loader, HalPair, ProverServer},
+        exec::{
+            executor::ExecutorImpl,
+            snapshot::ExecutorSnapshot,
+            transcript::{SyscallEntry, SyscallTranscript},
+        },
+        prove::{
+            estimate, estimate_segment_memory, get_parallel_prover_server, get_prover_server,
+            loader::Loader, CalibrationSample, Estimate, EstimateOpts, HalPair, ParallelProverImpl,
+            ProverServer, ProvingModel,
+        },
+        segment_file::{CompressedSegmentRef, SegmentFileHeader, SEGMENT_FILE_VERSION},
         session::{FileSegmentRef, Segment, SegmentRef, Session, SessionEvents, SimpleSegmentRef},
     },
 };
@@ -60,31 +76,32 @@ pub use self::host::{
 pub use self::host::{
     api::{client::Client as ApiClient, Asset, AssetRequest, Connector, SegmentInfo, SessionInfo},
     client::{
-        env::{ExecutorEnv, ExecutorEnvBuilder},
-        exec::TraceEvent,
+        env::{ExecutorEnv, ExecutorEnvBuilder, ProfileFormat},
+        exec::{CycleRegion, TraceEvent},
         prove::{
-            bonsai::BonsaiProver, default_executor, default_prover, external::ExternalProver,
-            Executor, Prover, ProverOpts,
+            bonsai::BonsaiProver,
+            default_executor, default_prover,
+            external::ExternalProver,
+            handle::{ProveHandle, ProvePhase},
+            Executor, ParallelOpts, Prover, ProverOpts, ReceiptKind,
         },
+        syscall::{Syscall, SyscallContext},
     },
+    receipt_file::{AnyReceipt, ReceiptFile, ReceiptFileKind, ReceiptHeader, RECEIPT_FILE_VERSION},
 };
 #[cfg(not(target_os = "zkvm"))]
 pub use self::host::{
-    compute_image_id,
-    control_id::POSEIDON_CONTROL_ID,
-    groth16::{Groth16Proof, Groth16Seal},
+    compute_image_id, compute_image_id_with_preloads,
     receipt::{
-        Assumption, CompositeReceipt, Groth16Receipt, InnerReceipt, Journal, Receipt,
-        SegmentReceipt, SuccinctReceipt, VerifierContext,
+        Assumption, AssumptionReceipt, CompositeReceipt, Groth16Receipt, InnerReceipt, Journal,
+        Receipt, SegmentReceipt, SuccinctReceipt, VerifierContext,
     },
     recursion::ALLOWED_IDS_ROOT,
 };
-pub use self::{
-    fault_ids::{FAULT_CHECKER_ELF, FAULT_CHECKER_ID},
-    receipt_claim::{
-        Assumptions, ExitCode, InvalidExitCodeError, MaybePruned, Output, PrunedValueError,
-        ReceiptClaim,
-    },
+#[cfg(not(target_os = "zkvm"))]
+pub use risc0_verifier::{
+    groth16::{Groth16Proof, Groth16Seal},
+    FriParams, POSEIDON_CONTROL_ID,
 };
 
 /// Reports the current version of this crate.
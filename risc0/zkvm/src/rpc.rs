@@ -0,0 +1,262 @@
+// Copyright 2023 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed request/response calls from the guest to the host.
+//!
+//! A guest calls the host with `env::call` on a named channel, for which the
+//! host has registered a handler with `ExecutorEnvBuilder::rpc_handler`. The
+//! request, an [RpcRequest], and its response are encoded with
+//! [crate::serde].
+//!
+//! Every request starts with the [RPC_VERSION] of the guest and the
+//! [schema_id] of the request, and every reply starts with the [RPC_VERSION]
+//! of the host. A mismatch in either is reported to the guest as an
+//! [RpcError] instead of being decoded as the wrong type.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    serde::{from_slice, to_vec},
+    sha::{Digest, Impl, Sha256},
+};
+
+/// The version of the RPC framing used by this crate.
+pub const RPC_VERSION: u32 = 1;
+
+/// A request made by the guest with `env::call`.
+///
+/// ```rust
+/// use risc0_zkvm::rpc::RpcRequest;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct GetPrice {
+///     symbol: String,
+/// }
+///
+/// impl RpcRequest for GetPrice {
+///     type Response = u64;
+///
+///     const SCHEMA: &'static str = "price_oracle::GetPrice/1";
+/// }
+/// ```
+pub trait RpcRequest: Serialize + DeserializeOwned {
+    /// The response returned by the host.
+    type Response: Serialize + DeserializeOwned;
+
+    /// Names the encoding of this request and its response.
+    ///
+    /// The guest and the host must agree on the schema of a call, so it
+    /// should be changed whenever either type changes in a way that the other
+    /// side cannot decode, e.g. by including a version number.
+    const SCHEMA: &'static str;
+}
+
+/// Returns the identifier of the [RpcRequest::SCHEMA] of a request.
+pub fn schema_id<Req: RpcRequest>() -> Digest {
+    *Impl::hash_bytes(Req::SCHEMA.as_bytes())
+}
+
+/// Error returned to the guest by a failed RPC call.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum RpcError {
+    /// The guest and the host use different versions of the RPC framing.
+    VersionMismatch {
+        /// The [RPC_VERSION] of the guest.
+        guest: u32,
+        /// The [RPC_VERSION] of the host.
+        host: u32,
+    },
+
+    /// The guest and the host disagree on the [RpcRequest::SCHEMA] of the
+    /// channel.
+    SchemaMismatch,
+
+    /// The request or the response could not be encoded or decoded.
+    Serde(String),
+
+    /// The host handler returned an error.
+    Handler(String),
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::VersionMismatch { guest, host } => {
+                write!(f, "RPC version mismatch: guest {guest}, host {host}")
+            }
+            Self::SchemaMismatch => write!(f, "RPC request schema does not match"),
+            Self::Serde(msg) => write!(f, "RPC serialization failed: {msg}"),
+            Self::Handler(msg) => write!(f, "RPC handler failed: {msg}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RpcError {}
+
+impl From<crate::serde::Error> for RpcError {
+    fn from(err: crate::serde::Error) -> Self {
+        Self::Serde(err.to_string())
+    }
+}
+
+/// Encodes a request made by the guest.
+pub(crate) fn encode_request<Req: RpcRequest>(req: &Req) -> Result<Vec<u32>, RpcError> {
+    let mut words = Vec::new();
+    words.push(RPC_VERSION);
+    words.extend_from_slice(schema_id::<Req>().as_words());
+    words.extend(to_vec(req)?);
+    Ok(words)
+}
+
+/// Decodes the reply to a request made by the guest.
+pub(crate) fn decode_reply<Resp: DeserializeOwned>(words: &[u32]) -> Result<Resp, RpcError> {
+    let Some((&version, body)) = words.split_first() else {
+        return Err(RpcError::Serde("empty RPC reply".to_string()));
+    };
+    if version != RPC_VERSION {
+        return Err(RpcError::VersionMismatch {
+            guest: RPC_VERSION,
+            host: version,
+        });
+    }
+    from_slice::<Result<Resp, RpcError>, _>(body)?
+}
+
+/// Decodes a request from the guest, calls `handler` with it, and returns the
+/// encoded reply.
+///
+/// Errors are encoded into the reply so that they are returned to the guest.
+#[cfg(not(target_os = "zkvm"))]
+pub(crate) fn handle_request<Req, E>(
+    words: &[u32],
+    handler: impl FnOnce(Req) -> Result<Req::Response, E>,
+) -> Vec<u32>
+where
+    Req: RpcRequest,
+    E: fmt::Display,
+{
+    let result = decode_request::<Req>(words)
+        .and_then(|req| handler(req).map_err(|err| RpcError::Handler(err.to_string())));
+    let mut reply = Vec::new();
+    reply.push(RPC_VERSION);
+    match to_vec(&result) {
+        Ok(body) => reply.extend(body),
+        // The response itself could not be encoded, so report that instead.
+        Err(err) => reply.extend(to_vec(&Result::<(), _>::Err(RpcError::from(err))).unwrap()),
+    }
+    reply
+}
+
+#[cfg(not(target_os = "zkvm"))]
+fn decode_request<Req: RpcRequest>(words: &[u32]) -> Result<Req, RpcError> {
+    use crate::sha::DIGEST_WORDS;
+
+    if words.len() < 1 + DIGEST_WORDS {
+        return Err(RpcError::Serde("truncated RPC request".to_string()));
+    }
+    let (header, body) = words.split_at(1 + DIGEST_WORDS);
+    if header[0] != RPC_VERSION {
+        return Err(RpcError::VersionMismatch {
+            guest: header[0],
+            host: RPC_VERSION,
+        });
+    }
+    if header[1..] != *schema_id::<Req>().as_words() {
+        return Err(RpcError::SchemaMismatch);
+    }
+    Ok(from_slice(body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec, vec::Vec};
+
+    use serde::{Deserialize, Serialize};
+
+    use super::{decode_reply, encode_request, handle_request, RpcError, RpcRequest, RPC_VERSION};
+
+    #[derive(Serialize, Deserialize)]
+    struct Repeat(u32, String);
+
+    impl RpcRequest for Repeat {
+        type Response = Vec<u32>;
+
+        const SCHEMA: &'static str = "tests::Repeat/1";
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Square(u32);
+
+    impl RpcRequest for Square {
+        type Response = u32;
+
+        const SCHEMA: &'static str = "tests::Square/1";
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct SquareV2(u64);
+
+    impl RpcRequest for SquareV2 {
+        type Response = u64;
+
+        const SCHEMA: &'static str = "tests::Square/2";
+    }
+
+    #[test]
+    fn round_trip() {
+        let request = encode_request(&Repeat(3, String::from("abc"))).unwrap();
+        let reply = handle_request(&request, |Repeat(n, s)| Ok::<_, String>(vec![n; s.len()]));
+        assert_eq!(decode_reply::<Vec<u32>>(&reply), Ok(vec![3, 3, 3]));
+    }
+
+    #[test]
+    fn handler_error() {
+        let request = encode_request(&Square(7)).unwrap();
+        let reply = handle_request(&request, |_: Square| Err::<u32, _>("nope"));
+        assert_eq!(
+            decode_reply::<u32>(&reply),
+            Err(RpcError::Handler("nope".into()))
+        );
+    }
+
+    #[test]
+    fn schema_mismatch() {
+        let request = encode_request(&Square(7)).unwrap();
+        let reply = handle_request(&request, |SquareV2(x)| Ok::<_, String>(x * x));
+        assert_eq!(decode_reply::<u32>(&reply), Err(RpcError::SchemaMismatch));
+    }
+
+    #[test]
+    fn version_mismatch() {
+        let mut request = encode_request(&Square(7)).unwrap();
+        request[0] = RPC_VERSION + 1;
+        let reply = handle_request(&request, |Square(x)| Ok::<_, String>(x));
+        assert_eq!(
+            decode_reply::<u32>(&reply),
+            Err(RpcError::VersionMismatch {
+                guest: RPC_VERSION + 1,
+                host: RPC_VERSION
+            })
+        );
+    }
+}
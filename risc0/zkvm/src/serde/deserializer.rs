@@ -16,7 +16,7 @@ use alloc::{string::String, vec};
 
 use bytemuck::Pod;
 use risc0_zkvm_platform::WORD_SIZE;
-use serde::de::{DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::de::{Deserialize, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
 
 use super::err::{Error, Result};
 use crate::align_up;
@@ -31,6 +31,20 @@ pub trait WordRead {
     /// padding up to the next word boundary.  Returns an error if EOF was
     /// encountered.
     fn read_padded_bytes(&mut self, bytes: &mut [u8]) -> Result<()>;
+
+    /// Return the next `len` bytes of input without copying them, and discard
+    /// the padding up to the next word boundary. Returns an error if EOF was
+    /// encountered.
+    ///
+    /// Readers that are not backed by a buffer which outlives them return
+    /// `Ok(None)` without consuming any input, in which case the bytes are
+    /// read with [WordRead::read_padded_bytes] instead.
+    fn borrow_padded_bytes<'de>(&mut self, _len: usize) -> Result<Option<&'de [u8]>>
+    where
+        Self: 'de,
+    {
+        Ok(None)
+    }
 }
 
 // Allow borrowed WordReads to work transparently
@@ -42,6 +56,13 @@ impl<R: WordRead + ?Sized> WordRead for &mut R {
     fn read_padded_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
         (**self).read_padded_bytes(bytes)
     }
+
+    fn borrow_padded_bytes<'de>(&mut self, len: usize) -> Result<Option<&'de [u8]>>
+    where
+        Self: 'de,
+    {
+        (**self).borrow_padded_bytes(len)
+    }
 }
 
 impl WordRead for &[u32] {
@@ -65,6 +86,19 @@ impl WordRead for &[u32] {
             Ok(())
         }
     }
+
+    fn borrow_padded_bytes<'de>(&mut self, len: usize) -> Result<Option<&'de [u8]>>
+    where
+        Self: 'de,
+    {
+        let bytes: &[u8] = bytemuck::cast_slice(*self);
+        if len > bytes.len() {
+            Err(Error::DeserializeUnexpectedEnd)
+        } else {
+            (_, *self) = self.split_at(align_up(len, WORD_SIZE) / WORD_SIZE);
+            Ok(Some(&bytes[..len]))
+        }
+    }
 }
 
 /// Deserialize a slice into the specified type.
@@ -88,6 +122,32 @@ pub fn from_slice<T: DeserializeOwned, P: Pod>(slice: &[P]) -> Result<T> {
     }
 }
 
+/// Deserialize a slice of words into the specified type, borrowing from it.
+///
+/// Unlike [from_slice], `&'de str` and `&'de [u8]` fields of `T` point
+/// directly into `words` instead of being copied into newly allocated memory,
+/// which saves cycles when deserializing large inputs in the guest. Returns
+/// an `Err` if deserialization isn't possible, such as if `words` is not the
+/// serialized form of an object of type `T`.
+///
+/// Note that serde serializes `&[u8]` and `Vec<u8>` as sequences of
+/// individual bytes, each taking up a word. To borrow a `&'de [u8]`, it must
+/// have been serialized with `Serializer::serialize_bytes`, as done by
+/// wrappers such as `serde_bytes`.
+///
+/// ```rust
+/// use risc0_zkvm::serde::{from_slice_borrowed, to_vec};
+///
+/// let words = to_vec(&("hello", 42u32)).unwrap();
+/// let (greeting, value): (&str, u32) = from_slice_borrowed(&words).unwrap();
+/// assert_eq!(greeting, "hello");
+/// assert_eq!(value, 42);
+/// ```
+pub fn from_slice_borrowed<'de, T: Deserialize<'de>>(words: &'de [u32]) -> Result<T> {
+    let mut deserializer = Deserializer::new(words);
+    T::deserialize(&mut deserializer)
+}
+
 /// Enables deserializing from a WordRead
 pub struct Deserializer<'de, R: WordRead + 'de> {
     reader: R,
@@ -337,6 +397,10 @@ impl<'de, 'a, R: WordRead + 'de> serde::Deserializer<'de> for &'a mut Deserializ
         V: Visitor<'de>,
     {
         let len_bytes = self.try_take_word()? as usize;
+        if let Some(bytes) = self.reader.borrow_padded_bytes(len_bytes)? {
+            let str = core::str::from_utf8(bytes).map_err(|_| Error::DeserializeBadChar)?;
+            return visitor.visit_borrowed_str(str);
+        }
         // TODO: Can we use MaybeUninit here instead of zeroing out?
         // The documentation for sys::io::Read implies that it's not
         // safe; is there another way to not do double writes here?
@@ -357,6 +421,9 @@ impl<'de, 'a, R: WordRead + 'de> serde::Deserializer<'de> for &'a mut Deserializ
         V: Visitor<'de>,
     {
         let len_bytes = self.try_take_word()? as usize;
+        if let Some(bytes) = self.reader.borrow_padded_bytes(len_bytes)? {
+            return visitor.visit_borrowed_bytes(bytes);
+        }
         // TODO: Can we use MaybeUninit here instead of zeroing out?
         // The documentation for sys::io::Read implies that it's not
         // safe; is there another way to not do double writes here?
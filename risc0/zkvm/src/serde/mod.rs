@@ -42,7 +42,7 @@ mod deserializer;
 mod err;
 mod serializer;
 
-pub use deserializer::{from_slice, Deserializer, WordRead};
+pub use deserializer::{from_slice, from_slice_borrowed, Deserializer, WordRead};
 pub use err::{Error, Result};
 pub use serializer::{to_vec, to_vec_with_capacity, Serializer, WordWrite};
 
@@ -50,7 +50,9 @@ pub use serializer::{to_vec, to_vec_with_capacity, Serializer, WordWrite};
 mod tests {
     use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
 
-    use crate::serde::{from_slice, to_vec};
+    use serde::{Deserialize, Serialize};
+
+    use crate::serde::{from_slice, from_slice_borrowed, to_vec, Error};
 
     #[test]
     fn test_vec_round_trip() {
@@ -76,4 +78,55 @@ mod tests {
         let output: (u32, u64) = from_slice(data.as_slice()).unwrap();
         assert_eq!(input, output);
     }
+
+    /// Serializes as bytes rather than as a sequence of `u8`.
+    struct RawBytes<'a>(&'a [u8]);
+
+    impl Serialize for RawBytes<'_> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Borrowed<'a> {
+        name: &'a str,
+        data: &'a [u8],
+        trailer: u32,
+    }
+
+    #[test]
+    fn test_borrowed() {
+        let data = to_vec(&("hello", RawBytes(&[1, 2, 3, 4, 5]), 7u32)).unwrap();
+        let output: Borrowed = from_slice_borrowed(&data).unwrap();
+        assert_eq!(
+            output,
+            Borrowed {
+                name: "hello",
+                data: &[1, 2, 3, 4, 5],
+                trailer: 7,
+            }
+        );
+
+        // The borrowed fields point into the input words.
+        let words = data.as_ptr_range();
+        let words = words.start as usize..words.end as usize;
+        assert!(words.contains(&(output.name.as_ptr() as usize)));
+        assert!(words.contains(&(output.data.as_ptr() as usize)));
+    }
+
+    #[test]
+    fn test_borrowed_into_owned() {
+        let input: BTreeMap<String, u32> = BTreeMap::from([("foo".into(), 1), ("bar".into(), 2)]);
+        let data = to_vec(&input).unwrap();
+        let output: BTreeMap<String, u32> = from_slice_borrowed(&data).unwrap();
+        assert_eq!(input, output);
+    }
+
+    #[test]
+    fn test_borrowed_truncated() {
+        let data = to_vec(&"hello").unwrap();
+        let result: Result<&str, _> = from_slice_borrowed(&data[..data.len() - 1]);
+        assert_eq!(result, Err(Error::DeserializeUnexpectedEnd));
+    }
 }
@@ -18,7 +18,10 @@ use clap::Parser;
 use risc0_zkp::{
     core::{
         digest::Digest,
-        hash::{blake2b::Blake2bCpuHashSuite, poseidon::PoseidonHashSuite, sha::Sha256HashSuite},
+        hash::{
+            blake2b::Blake2bCpuHashSuite, poseidon::PoseidonHashSuite,
+            poseidon2::Poseidon2HashSuite, sha::Sha256HashSuite,
+        },
     },
     field::baby_bear::BabyBear,
     hal::cpu::CpuHal,
@@ -36,7 +39,7 @@ pub struct Bootstrap {
     skip_recursion: bool,
 }
 
-const CONTROL_ID_PATH_RV32IM: &str = "risc0/zkvm/src/host/control_id.rs";
+const CONTROL_ID_PATH_RV32IM: &str = "risc0/verifier/src/control_id.rs";
 const CONTROL_ID_PATH_RECURSION: &str = "risc0/circuit/recursion/src/control_id.rs";
 
 impl Bootstrap {
@@ -60,6 +63,9 @@ impl Bootstrap {
         tracing::info!("computing control IDs with Blake2b");
         let control_id_blake2b =
             loader.compute_control_id(&CpuHal::new(Blake2bCpuHashSuite::new_suite()));
+        tracing::info!("computing control IDs with Poseidon2");
+        let control_id_poseidon2 =
+            loader.compute_control_id(&CpuHal::new(Poseidon2HashSuite::new_suite()));
 
         let contents = format!(
             include_str!("templates/control_id_rv32im.rs"),
@@ -96,6 +102,17 @@ impl Bootstrap {
             control_id_blake2b[8],
             control_id_blake2b[9],
             control_id_blake2b[10],
+            control_id_poseidon2[0],
+            control_id_poseidon2[1],
+            control_id_poseidon2[2],
+            control_id_poseidon2[3],
+            control_id_poseidon2[4],
+            control_id_poseidon2[5],
+            control_id_poseidon2[6],
+            control_id_poseidon2[7],
+            control_id_poseidon2[8],
+            control_id_poseidon2[9],
+            control_id_poseidon2[10],
         );
         tracing::debug!("contents of rv32im control_id.rs:\n{contents}");
 
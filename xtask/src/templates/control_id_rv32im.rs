@@ -14,6 +14,8 @@
 
 const CONTROL_ID_ENTRIES: usize = risc0_zkp::MAX_CYCLES_PO2 - risc0_zkp::MIN_CYCLES_PO2;
 
+/// The control IDs of the rv32im circuit, one for each supported segment
+/// size.
 pub type RawControlId = [&'static str; CONTROL_ID_ENTRIES];
 
 /// Control ID for SHA-256
@@ -60,3 +62,18 @@ pub const BLAKE2B_CONTROL_ID: RawControlId = [
     "{}", //
     "{}", //
 ];
+
+/// Control ID for Poseidon2
+pub const POSEIDON2_CONTROL_ID: RawControlId = [
+    "{}", //
+    "{}", //
+    "{}", //
+    "{}", //
+    "{}", //
+    "{}", //
+    "{}", //
+    "{}", //
+    "{}", //
+    "{}", //
+    "{}", //
+];